@@ -0,0 +1,266 @@
+use ciborium::Value;
+use coset::{
+    iana::{self, EnumI64},
+    CoseKey,
+};
+use p256::{
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+    SecretKey,
+};
+use passkey_types::{
+    ctap2::{AuthenticatorData, Ctap2Error},
+    webauthn::{AttestationStatementFormatIdentifiers, AuthenticatorTransport},
+    Bytes,
+};
+
+#[cfg(doc)]
+use crate::Authenticator;
+use crate::{ec2_public_key_from_cose_key, public_key_algorithm, CredentialSigningKey};
+
+/// The information available to an [`AttestationStatementProvider`] when generating the
+/// attestation statement of a newly created credential.
+///
+/// <https://w3c.github.io/webauthn/#sctn-attestation>
+#[non_exhaustive]
+pub struct AttestationInput<'a> {
+    /// The authenticator data of the new credential, including its attested credential data.
+    pub auth_data: &'a AuthenticatorData,
+
+    /// Hash of the serialized client data given to the authenticator.
+    pub client_data_hash: &'a [u8],
+
+    /// The private key of the newly created credential, usable for self attestation.
+    pub credential_key: &'a CoseKey,
+
+    /// The attestation statement formats preferred by the client, from most to least preferred.
+    pub formats_preference: Option<&'a [AttestationStatementFormatIdentifiers]>,
+
+    /// The transports of the authenticator, which are also reported to the relying party with
+    /// the new credential, for the statement formats that attest them.
+    pub transports: &'a [AuthenticatorTransport],
+
+    /// Whether an enterprise attestation was requested and granted for the relying party, in
+    /// which case the statement may include information uniquely identifying the authenticator,
+    /// such as the serial number of its certificate.
+    pub enterprise_attestation: bool,
+}
+
+/// Pluggable trait for the [`Authenticator`] to generate attestation statements.
+///
+/// Implement this trait to produce attestation statement formats that are not provided by this
+/// crate. The [`Authenticator`] uses [`NoneAttestation`] unless configured otherwise and never
+/// calls the provider when the client only accepts the `none` format.
+#[async_trait::async_trait]
+pub trait AttestationStatementProvider {
+    /// Generate the attestation statement for a new credential, returning the attestation
+    /// statement format identifier along with the statement itself.
+    async fn attestation_statement(
+        &self,
+        input: AttestationInput<'_>,
+    ) -> Result<(String, Value), Ctap2Error>;
+
+    /// Whether the statements are self attestations, signed with the credential's own key. The
+    /// [`Authenticator`] then reports an all-zero AAGUID in the attested credential data, as
+    /// required for self attestation.
+    fn is_self_attestation(&self) -> bool {
+        false
+    }
+}
+
+/// Provides the `none` attestation statement, which is an empty map.
+///
+/// <https://w3c.github.io/webauthn/#sctn-none-attestation>
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoneAttestation;
+
+impl NoneAttestation {
+    /// The `none` attestation statement format identifier and its empty statement.
+    pub fn statement() -> (String, Value) {
+        ("none".into(), Value::Map(Vec::new()))
+    }
+}
+
+#[async_trait::async_trait]
+impl AttestationStatementProvider for NoneAttestation {
+    async fn attestation_statement(
+        &self,
+        _input: AttestationInput<'_>,
+    ) -> Result<(String, Value), Ctap2Error> {
+        Ok(Self::statement())
+    }
+}
+
+/// Provides the `packed` attestation statement without a certificate, which is self attestation:
+/// the authenticator data and the client data hash are signed with the private key of the new
+/// credential itself.
+///
+/// Set it with [`Authenticator::set_attestation_statement_provider`] to prove that the relying party
+/// received the public key the credential signs with, without identifying the authenticator.
+///
+/// <https://w3c.github.io/webauthn/#sctn-packed-attestation>
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SelfAttestation;
+
+#[async_trait::async_trait]
+impl AttestationStatementProvider for SelfAttestation {
+    async fn attestation_statement(
+        &self,
+        input: AttestationInput<'_>,
+    ) -> Result<(String, Value), Ctap2Error> {
+        let coset::RegisteredLabelWithPrivate::Assigned(algorithm) = input
+            .credential_key
+            .alg
+            .clone()
+            .ok_or(Ctap2Error::UnsupportedAlgorithm)?
+        else {
+            return Err(Ctap2Error::UnsupportedAlgorithm);
+        };
+
+        let mut signed_data = input.auth_data.to_vec();
+        signed_data.extend_from_slice(input.client_data_hash);
+        let signature =
+            CredentialSigningKey::from_cose_key(input.credential_key)?.sign(&signed_data);
+
+        let statement = Value::Map(vec![
+            (Value::Text("alg".into()), Value::from(algorithm.to_i64())),
+            (Value::Text("sig".into()), Value::Bytes(signature.to_vec())),
+        ]);
+        Ok(("packed".into(), statement))
+    }
+
+    fn is_self_attestation(&self) -> bool {
+        true
+    }
+}
+
+/// Provides the `packed` attestation statement with a batch attestation certificate, which is
+/// full attestation: the authenticator data and the client data hash are signed with the batch
+/// private key, and the statement includes its certificate chain.
+///
+/// The leaf certificate must hold the public key matching the batch private key and should carry
+/// the `id-fido-gen-ce-aaguid` extension with the AAGUID of the [`Authenticator`]. The client only
+/// lets the authenticator attest credentials when the relying party asked for attestation, such
+/// as with [`AttestationConveyancePreference::Direct`].
+///
+/// <https://w3c.github.io/webauthn/#sctn-packed-attestation>
+///
+/// [`AttestationConveyancePreference::Direct`]: passkey_types::webauthn::AttestationConveyancePreference::Direct
+pub struct PackedAttestation {
+    cert_chain: Vec<Bytes>,
+    signing_key: SigningKey,
+}
+
+impl PackedAttestation {
+    /// Attest credentials with the P-256 batch `private_key`, whose DER encoded certificate comes
+    /// first in `cert_chain`, followed by the certificates that issued it.
+    pub fn new(cert_chain: Vec<Bytes>, private_key: SecretKey) -> Self {
+        Self {
+            cert_chain,
+            signing_key: SigningKey::from(private_key),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttestationStatementProvider for PackedAttestation {
+    async fn attestation_statement(
+        &self,
+        input: AttestationInput<'_>,
+    ) -> Result<(String, Value), Ctap2Error> {
+        let mut signed_data = input.auth_data.to_vec();
+        signed_data.extend_from_slice(input.client_data_hash);
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        let statement = Value::Map(vec![
+            (
+                Value::Text("alg".into()),
+                Value::from(iana::Algorithm::ES256.to_i64()),
+            ),
+            (
+                Value::Text("sig".into()),
+                Value::Bytes(signature.to_der().as_bytes().to_vec()),
+            ),
+            (
+                Value::Text("x5c".into()),
+                Value::Array(
+                    self.cert_chain
+                        .iter()
+                        .map(|cert| Value::Bytes(cert.to_vec()))
+                        .collect(),
+                ),
+            ),
+        ]);
+        Ok(("packed".into(), statement))
+    }
+}
+
+/// Provides the `fido-u2f` attestation statement with a batch attestation certificate, for relying
+/// parties that only verify the attestation of FIDO U2F authenticators.
+///
+/// The statement is only produced for `ES256` credentials, creating a credential with an other
+/// algorithm fails with [`Ctap2Error::UnsupportedAlgorithm`]. When the client lists the formats it
+/// prefers without `fido-u2f`, the `none` statement is returned instead.
+///
+/// <https://w3c.github.io/webauthn/#sctn-fido-u2f-attestation>
+pub struct FidoU2fAttestation {
+    certificate: Bytes,
+    signing_key: SigningKey,
+}
+
+impl FidoU2fAttestation {
+    /// Attest credentials with the P-256 batch `private_key`, whose DER encoded certificate is
+    /// `certificate`. Unlike the `packed` format, `fido-u2f` has room for a single certificate.
+    pub fn new(certificate: Bytes, private_key: SecretKey) -> Self {
+        Self {
+            certificate,
+            signing_key: SigningKey::from(private_key),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttestationStatementProvider for FidoU2fAttestation {
+    async fn attestation_statement(
+        &self,
+        input: AttestationInput<'_>,
+    ) -> Result<(String, Value), Ctap2Error> {
+        if input.formats_preference.is_some_and(|formats| {
+            !formats.contains(&AttestationStatementFormatIdentifiers::FidoU2f)
+        }) {
+            return Ok(NoneAttestation::statement());
+        }
+
+        let acd = input
+            .auth_data
+            .attested_credential_data
+            .as_ref()
+            .ok_or(Ctap2Error::InvalidCredential)?;
+        if public_key_algorithm(&acd.key) != Some(iana::Algorithm::ES256) {
+            return Err(Ctap2Error::UnsupportedAlgorithm);
+        }
+        let public_key = ec2_public_key_from_cose_key(&acd.key)?.to_encoded_point(false);
+
+        // The registration data signed by U2F authenticators: a reserved zero byte, the
+        // application parameter, the challenge parameter, the key handle and the uncompressed
+        // public key.
+        let mut signed_data = vec![0x00];
+        signed_data.extend_from_slice(input.auth_data.rp_id_hash());
+        signed_data.extend_from_slice(input.client_data_hash);
+        signed_data.extend_from_slice(acd.credential_id());
+        signed_data.extend_from_slice(public_key.as_bytes());
+        let signature: Signature = self.signing_key.sign(&signed_data);
+
+        let statement = Value::Map(vec![
+            (
+                Value::Text("sig".into()),
+                Value::Bytes(signature.to_der().as_bytes().to_vec()),
+            ),
+            (
+                Value::Text("x5c".into()),
+                Value::Array(vec![Value::Bytes(self.certificate.to_vec())]),
+            ),
+        ]);
+        Ok(("fido-u2f".into(), statement))
+    }
+}
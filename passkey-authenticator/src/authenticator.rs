@@ -1,14 +1,111 @@
-use coset::iana;
+use std::collections::{BTreeMap, HashMap};
+
+use ciborium::Value;
+use coset::{iana, CoseKey};
 use passkey_types::{
-    ctap2::{Aaguid, Ctap2Error, Flags},
+    ctap2::{Aaguid, AuthenticatorData, Ctap2Error, Flags},
     webauthn,
 };
 
-use crate::{user_validation, CredentialStore, UserValidationMethod};
+use self::{
+    client_pin::{ClientPin, DEFAULT_MIN_PIN_LENGTH},
+    credential_management::PendingEnumeration,
+    get_assertion::PendingAssertions,
+    large_blobs::{PendingLargeBlob, MIN_SERIALIZED_LARGE_BLOB_ARRAY},
+    make_credential::CREDENTIAL_ID_LENGTH,
+};
+use crate::{
+    pin_protocol::KeyAgreementKey, user_validation, AttestationStatementProvider, CredentialStore,
+    NoneAttestation, UserCheck, UserValidationMethod, VerificationMethod,
+};
 
+mod builder;
+mod client_pin;
+mod credential_management;
+mod device_public_key;
 mod get_assertion;
 mod get_info;
+mod large_blobs;
 mod make_credential;
+mod reset;
+#[cfg(any(feature = "tokio", test))]
+mod shared;
+mod signals;
+
+pub use self::builder::AuthenticatorBuilder;
+pub use self::client_pin::MAX_PIN_RETRIES;
+#[cfg(any(feature = "tokio", test))]
+pub use self::shared::SharedAuthenticator;
+
+/// The `KEY_PROTECTION_SOFTWARE` value of the FIDO Registry of Predefined Values, reported in the
+/// `uvm` extension since the credential keys are held by the [`CredentialStore`].
+const KEY_PROTECTION_SOFTWARE: u32 = 0x0001;
+
+/// The `MATCHER_PROTECTION_SOFTWARE` value of the FIDO Registry of Predefined Values, reported in
+/// the `uvm` extension since the user is matched by the [`UserValidationMethod`].
+const MATCHER_PROTECTION_SOFTWARE: u32 = 0x0001;
+
+/// How an [`Authenticator`] implements the signature counter of its credentials, which lets
+/// relying parties detect cloned credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CounterMode {
+    /// A single counter, shared by all the credentials, is incremented by every assertion. It is
+    /// only kept in memory by the authenticator and starts at zero.
+    Global,
+
+    /// Every credential has its own counter, which is stored in [`Passkey::counter`](passkey_types::Passkey::counter) and
+    /// incremented by the assertions made with it. New credentials only get a counter when
+    /// [`Authenticator::set_make_credentials_with_signature_counter`] is enabled, the ones without
+    /// one always report zero.
+    #[default]
+    PerCredential,
+
+    /// The counter is always reported as zero, which is recommended for credentials that are
+    /// synced between devices and keeps assertions from being correlated. The counters of stored
+    /// credentials are left untouched.
+    Disabled,
+}
+
+/// Whether [`Authenticator::make_credential_exporting_key`] also saves the credential it returns
+/// in the [`CredentialStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExport {
+    /// The credential is saved like [`Authenticator::make_credential`] does, the caller gets a
+    /// copy of it.
+    AlsoStore,
+
+    /// The credential is only returned to the caller, who is responsible for persisting it. The
+    /// authenticator cannot assert it until it is put back in a store.
+    ExportOnly,
+}
+
+/// The backup flags an [`Authenticator`] reports in the authenticator data of its credentials,
+/// which tell relying parties whether the credentials survive the loss of the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupState {
+    /// The credentials are bound to this device and never backed up, so neither
+    /// [`Flags::BE`] nor [`Flags::BS`] is set.
+    SingleDevice,
+
+    /// The credentials can be backed up but currently are not, only [`Flags::BE`] is set.
+    Eligible,
+
+    /// The credentials are backed up, for example because the store syncs them between devices,
+    /// so both [`Flags::BE`] and [`Flags::BS`] are set. This is the default.
+    #[default]
+    BackedUp,
+}
+
+impl BackupState {
+    /// The flags of the authenticator data that report this state.
+    fn flags(self) -> Flags {
+        match self {
+            BackupState::SingleDevice => Flags::empty(),
+            BackupState::Eligible => Flags::BE,
+            BackupState::BackedUp => Flags::BE | Flags::BS,
+        }
+    }
+}
 
 /// A virtual authenticator with all the necessary state and information.
 pub struct Authenticator<S, U> {
@@ -34,6 +131,107 @@ pub struct Authenticator<S, U> {
     /// NOTE: Using a counter with a credential that will sync is not recommended and can cause friction
     /// with the distributed nature of synced keys. It can also cause issues with backup and restore functionality.
     make_credentials_with_signature_counter: bool,
+
+    /// The signature counter given to new credentials when they are saved with a signature counter.
+    /// The default value is `0`.
+    initial_signature_counter: u32,
+
+    /// How the signature counter is implemented, [`CounterMode::PerCredential`] by default.
+    counter_mode: CounterMode,
+
+    /// The signature counter shared by all the credentials in [`CounterMode::Global`].
+    global_signature_counter: u32,
+
+    /// Whether assertions are refused when the stored signature counter of a credential is lower
+    /// than the last one the authenticator emitted for it, which is disabled by default.
+    counter_rollback_detection: bool,
+
+    /// The last signature counter emitted for each credential, by credential ID, when
+    /// [`Self::set_counter_rollback_detection`] is enabled.
+    emitted_signature_counters: HashMap<Vec<u8>, u32>,
+
+    /// The private keys of this device used by the `devicePubKey` extension, by credential ID.
+    /// They are only kept in memory, see [`Self::set_device_key`].
+    device_keys: HashMap<Vec<u8>, CoseKey>,
+
+    /// The backup flags reported for the credentials, [`BackupState::BackedUp`] by default.
+    backup_state: BackupState,
+
+    /// Value to control whether the authenticator verifies the user in every ceremony, even when
+    /// user verification was not requested. The default value is `false`.
+    always_uv: bool,
+
+    /// Value to control whether credentials can be created without user verification when it was
+    /// not requested. The default value is `true`.
+    make_credential_uv_not_required: bool,
+
+    /// The maximum number of discoverable credentials the authenticator can hold, there is no
+    /// limit by default.
+    max_discoverable_credentials: Option<u32>,
+
+    /// The maximum length of the credential IDs reported in [`Self::get_info`], which is not
+    /// reported by default.
+    max_credential_id_length: Option<usize>,
+
+    /// The user verification modality reported in [`Self::get_info`], which is not reported by
+    /// default.
+    uv_modality: Option<u32>,
+
+    /// The certifications reported in [`Self::get_info`], there are none by default.
+    certifications: BTreeMap<String, u64>,
+
+    /// The minimum length of new PINs, which is reported in [`Self::get_info`] and defaults to 4.
+    min_pin_length: u32,
+
+    /// The relying parties to which [`Self::make_credential`] discloses the minimum PIN length
+    /// through the `minPinLength` extension, there are none by default.
+    min_pin_length_rp_ids: Vec<String>,
+
+    /// The relying parties to which [`Self::make_credential`] releases vendor facilitated
+    /// enterprise attestation, enterprise attestation is not supported when `None`, the default.
+    enterprise_attestation_rp_ids: Option<Vec<String>>,
+
+    /// The protection policy of new credentials when none is requested through the `credProtect`
+    /// extension. There is no default policy by default.
+    default_credential_protection_policy: Option<webauthn::CredentialProtectionPolicy>,
+
+    /// Whether the `hmac-secret` extension is supported, which is the default.
+    hmac_secret: bool,
+
+    /// Whether the public keys of new credentials include their `alg` parameter, which is the
+    /// default.
+    public_key_alg_included: bool,
+
+    /// The key agreement key used to establish secrets shared with the platform, regenerated
+    /// every time the authenticator is created.
+    key_agreement: KeyAgreementKey,
+
+    /// The PIN of the authenticator and its retries counter, set through [`Self::client_pin`].
+    client_pin: ClientPin,
+
+    /// The maximum size of the serialized large-blob array reported in [`Self::get_info`] when the
+    /// [`CredentialStore`] can hold large blobs. The default value is `1024`, the minimum allowed.
+    max_serialized_large_blob_array: usize,
+
+    /// The fragments of the large-blob array written so far, until the write is complete.
+    pending_large_blob: Option<PendingLargeBlob>,
+
+    /// The credentials of the last assertion that are left for [`Self::get_next_assertion`].
+    pending_assertions: Option<PendingAssertions>,
+
+    /// The relying parties or credentials left by the last enumeration of
+    /// [`Self::credential_management`].
+    pending_enumeration: Option<PendingEnumeration>,
+
+    /// The options applied by the last ceremony, see [`Self::effective_options`].
+    effective_options: Option<passkey_types::ctap2::make_credential::Options>,
+
+    /// Generates the attestation statement of new credentials, defaults to [`NoneAttestation`].
+    attestation: Box<dyn AttestationStatementProvider + Send + Sync>,
+
+    /// The source of the random credential IDs, which is the one used to generate keys when
+    /// `None`, the default.
+    credential_id_rng: Option<Box<dyn rand::RngCore + Send + Sync>>,
 }
 
 impl<S, U> Authenticator<S, U>
@@ -47,7 +245,11 @@ where
             aaguid,
             store,
             // TODO: Change this to a method on the cryptographic backend
-            algs: vec![iana::Algorithm::ES256],
+            algs: vec![
+                iana::Algorithm::ES256,
+                iana::Algorithm::EdDSA,
+                iana::Algorithm::RS256,
+            ],
             transports: vec![
                 webauthn::AuthenticatorTransport::Internal,
                 webauthn::AuthenticatorTransport::Hybrid,
@@ -55,6 +257,34 @@ where
             user_validation: user,
             display_name: None,
             make_credentials_with_signature_counter: false,
+            initial_signature_counter: 0,
+            counter_mode: CounterMode::default(),
+            global_signature_counter: 0,
+            counter_rollback_detection: false,
+            emitted_signature_counters: HashMap::new(),
+            device_keys: HashMap::new(),
+            backup_state: BackupState::default(),
+            always_uv: false,
+            make_credential_uv_not_required: true,
+            max_discoverable_credentials: None,
+            max_credential_id_length: None,
+            uv_modality: None,
+            certifications: BTreeMap::new(),
+            min_pin_length: DEFAULT_MIN_PIN_LENGTH,
+            min_pin_length_rp_ids: Vec::new(),
+            enterprise_attestation_rp_ids: None,
+            default_credential_protection_policy: None,
+            hmac_secret: true,
+            public_key_alg_included: true,
+            key_agreement: KeyAgreementKey::generate(),
+            client_pin: ClientPin::new(),
+            max_serialized_large_blob_array: MIN_SERIALIZED_LARGE_BLOB_ARRAY,
+            pending_large_blob: None,
+            pending_assertions: None,
+            pending_enumeration: None,
+            effective_options: None,
+            attestation: Box::new(NoneAttestation),
+            credential_id_rng: None,
         }
     }
 
@@ -81,6 +311,267 @@ where
         self.make_credentials_with_signature_counter
     }
 
+    /// Set the signature counter that new credentials start with, which is only used when
+    /// [`Self::set_make_credentials_with_signature_counter`] is enabled. The first assertion made
+    /// with a new credential reports this value incremented by one, and the counter stops at
+    /// [`u32::MAX`].
+    pub fn set_initial_signature_counter(&mut self, value: u32) {
+        self.initial_signature_counter = value;
+    }
+
+    /// Get the signature counter that new credentials start with.
+    pub fn initial_signature_counter(&self) -> u32 {
+        self.initial_signature_counter
+    }
+
+    /// Set how the authenticator implements the signature counter of its credentials.
+    pub fn set_counter_mode(&mut self, value: CounterMode) {
+        self.counter_mode = value;
+    }
+
+    /// Get how the authenticator implements the signature counter of its credentials.
+    pub fn counter_mode(&self) -> CounterMode {
+        self.counter_mode
+    }
+
+    /// Set whether the authenticator refuses to make an assertion with a credential whose stored
+    /// signature counter is lower than the last one it emitted for it, in
+    /// [`CounterMode::PerCredential`]. Such a counter means the store was rolled back or the
+    /// credential was copied from another store, for example by a sync conflict, and the
+    /// assertion fails with [`Ctap2Error::InvalidCredential`].
+    ///
+    /// The emitted counters are only tracked in memory, from the moment this is enabled.
+    pub fn set_counter_rollback_detection(&mut self, value: bool) {
+        self.counter_rollback_detection = value;
+        if !value {
+            self.emitted_signature_counters.clear();
+        }
+    }
+
+    /// Get whether the authenticator refuses assertions with rolled back signature counters.
+    pub fn counter_rollback_detection(&self) -> bool {
+        self.counter_rollback_detection
+    }
+
+    /// Set the backup flags the authenticator reports in the authenticator data of new
+    /// credentials and assertions.
+    pub fn set_backup_state(&mut self, value: BackupState) {
+        self.backup_state = value;
+    }
+
+    /// Get the backup flags the authenticator reports for its credentials.
+    pub fn backup_state(&self) -> BackupState {
+        self.backup_state
+    }
+
+    /// Set whether the authenticator should verify the user in every ceremony, regardless of the
+    /// "uv" option of the request. This is reported as `alwaysUv` in [`Self::get_info`].
+    pub fn set_always_uv(&mut self, value: bool) {
+        self.always_uv = value;
+    }
+
+    /// Get whether the authenticator verifies the user in every ceremony.
+    pub fn always_uv(&self) -> bool {
+        self.always_uv
+    }
+
+    /// Get the options applied by the last [`Self::make_credential`] or [`Self::get_assertion`]
+    /// when it checked the user, once `alwaysUv` and the protection policies of the located
+    /// credentials were taken into account. The "uv" option is also set when the user was
+    /// verified with a PIN.
+    ///
+    /// This is `None` when the last ceremony failed before checking the user.
+    pub fn effective_options(&self) -> Option<&passkey_types::ctap2::make_credential::Options> {
+        self.effective_options.as_ref()
+    }
+
+    /// Set whether the authenticator allows creating credentials without user verification when
+    /// it was not requested. When disabled, [`Self::make_credential`] requests without the "uv"
+    /// option fail with [`Ctap2Error::PuatRequired`] if the authenticator can verify the user. This
+    /// is reported as `makeCredUvNotRqd` in [`Self::get_info`].
+    pub fn set_make_credential_uv_not_required(&mut self, value: bool) {
+        self.make_credential_uv_not_required = value;
+    }
+
+    /// Get whether the authenticator allows creating credentials without user verification.
+    pub fn make_credential_uv_not_required(&self) -> bool {
+        self.make_credential_uv_not_required
+    }
+
+    /// Set the maximum number of discoverable credentials the authenticator can hold. Once it is
+    /// reached, [`Self::make_credential`] fails with [`Ctap2Error::KeyStoreFull`] for new
+    /// discoverable credentials, and the remaining capacity is reported as
+    /// `remainingDiscoverableCredentials` in [`Self::get_info`] when the [`CredentialStore`] can
+    /// count its discoverable credentials.
+    pub fn set_max_discoverable_credentials(&mut self, value: Option<u32>) {
+        self.max_discoverable_credentials = value;
+    }
+
+    /// Get the maximum number of discoverable credentials the authenticator can hold.
+    pub fn max_discoverable_credentials(&self) -> Option<u32> {
+        self.max_discoverable_credentials
+    }
+
+    /// Set the maximum length of the credential IDs the authenticator accepts, reported as
+    /// `maxCredentialIdLength` in [`Self::get_info`]. Clients leave the longer credentials out of
+    /// the allow list of their assertions, so this must cover the IDs of every stored credential.
+    /// It is raised to the length of the IDs generated by [`Self::make_credential`] when lower.
+    pub fn set_max_credential_id_length(&mut self, value: Option<usize>) {
+        self.max_credential_id_length = value.map(|max| max.max(CREDENTIAL_ID_LENGTH));
+    }
+
+    /// Get the maximum length of the credential IDs the authenticator accepts.
+    pub fn max_credential_id_length(&self) -> Option<usize> {
+        self.max_credential_id_length
+    }
+
+    /// Set the user verification modality reported as `uvModality` in [`Self::get_info`], a
+    /// combination of the `USER_VERIFY` flags of the FIDO Registry of Predefined Values. It only
+    /// describes the [`UserValidationMethod`], which verifies the user regardless of it.
+    pub fn set_uv_modality(&mut self, value: Option<u32>) {
+        self.uv_modality = value;
+    }
+
+    /// Get the user verification modality reported by the authenticator.
+    pub fn uv_modality(&self) -> Option<u32> {
+        self.uv_modality
+    }
+
+    /// Set the `level` of the authenticator in the certification `program`, such as `FIDO` or
+    /// `FIPS-CMVP-2`, which are reported as `certifications` in [`Self::get_info`]. A `None`
+    /// level removes the certification.
+    ///
+    /// This is meant to emulate a certified authenticator, the certifications are not verified.
+    pub fn set_certification(&mut self, program: impl Into<String>, level: Option<u64>) {
+        let program = program.into();
+        match level {
+            Some(level) => self.certifications.insert(program, level),
+            None => self.certifications.remove(&program),
+        };
+    }
+
+    /// Get the certifications reported by the authenticator, by program.
+    pub fn certifications(&self) -> &BTreeMap<String, u64> {
+        &self.certifications
+    }
+
+    /// Set the minimum length of the PINs set or changed through [`Self::client_pin`], in Unicode
+    /// code points, which is reported as `minPINLength` in [`Self::get_info`]. It is raised to 4,
+    /// the smallest minimum allowed, when lower. PINs that were set before are kept.
+    pub fn set_min_pin_length(&mut self, value: u32) {
+        self.min_pin_length = value.max(DEFAULT_MIN_PIN_LENGTH);
+    }
+
+    /// Get the minimum length of new PINs.
+    pub fn min_pin_length(&self) -> u32 {
+        self.min_pin_length
+    }
+
+    /// Set the RP IDs of the relying parties allowed to learn the minimum PIN length, which
+    /// [`Self::make_credential`] reports in the extensions of the authenticator data when they
+    /// request the `minPinLength` extension.
+    pub fn set_min_pin_length_rp_ids(&mut self, rp_ids: Vec<String>) {
+        self.min_pin_length_rp_ids = rp_ids;
+    }
+
+    /// Get the RP IDs of the relying parties allowed to learn the minimum PIN length.
+    pub fn min_pin_length_rp_ids(&self) -> &[String] {
+        &self.min_pin_length_rp_ids
+    }
+
+    /// Enable enterprise attestation for the relying parties with the given RP IDs, or disable it
+    /// with `None`. [`Self::make_credential`] then produces an enterprise attestation, which may
+    /// uniquely identify the authenticator, when it is requested by a managed platform or for one
+    /// of these relying parties.
+    pub fn set_enterprise_attestation_rp_ids(&mut self, rp_ids: Option<Vec<String>>) {
+        self.enterprise_attestation_rp_ids = rp_ids;
+    }
+
+    /// Get the RP IDs of the relying parties enterprise attestation is enabled for, if it is
+    /// enabled.
+    pub fn enterprise_attestation_rp_ids(&self) -> Option<&[String]> {
+        self.enterprise_attestation_rp_ids.as_deref()
+    }
+
+    /// Set the protection policy given to new credentials when the request does not include the
+    /// `credProtect` extension. The `passkey-client` never lowers this default,
+    /// it only requests a policy when the relying party asks for a more protective one.
+    pub fn set_default_credential_protection_policy(
+        &mut self,
+        value: Option<webauthn::CredentialProtectionPolicy>,
+    ) {
+        self.default_credential_protection_policy = value;
+    }
+
+    /// Get the protection policy given to new credentials when none is requested.
+    pub fn default_credential_protection_policy(
+        &self,
+    ) -> Option<webauthn::CredentialProtectionPolicy> {
+        self.default_credential_protection_policy
+    }
+
+    /// Set whether the `hmac-secret` extension is supported. When it is not, the extension is
+    /// ignored by [`Self::make_credential`] and [`Self::get_assertion`] and it is no longer
+    /// reported in [`Self::get_info`].
+    pub fn set_hmac_secret_enabled(&mut self, value: bool) {
+        self.hmac_secret = value;
+    }
+
+    /// Get whether the `hmac-secret` extension is supported.
+    pub fn hmac_secret_enabled(&self) -> bool {
+        self.hmac_secret
+    }
+
+    /// Set whether the COSE public keys of new credentials, embedded in their attested credential
+    /// data, include the `alg` parameter. It is optional in COSE, but some relying parties require
+    /// it while others reject keys that have it. The stored private keys always include it.
+    pub fn set_public_key_alg_included(&mut self, value: bool) {
+        self.public_key_alg_included = value;
+    }
+
+    /// Get whether the public keys of new credentials include their `alg` parameter.
+    pub fn public_key_alg_included(&self) -> bool {
+        self.public_key_alg_included
+    }
+
+    /// Set the random number generator that credential IDs are drawn from, separately from the
+    /// one used to generate the keys of credentials. Without one, credential IDs come from the
+    /// same source as the keys.
+    pub fn set_credential_id_rng(
+        &mut self,
+        rng: impl rand::RngCore + rand::CryptoRng + Send + Sync + 'static,
+    ) {
+        self.credential_id_rng = Some(Box::new(rng));
+    }
+
+    /// Get the public key agreement key of the authenticator, which the platform uses along with
+    /// its own key agreement key to compute the secret shared with the authenticator through PIN/UV
+    /// auth protocol one, such as to encrypt the salts of the `hmac-secret` extension.
+    pub fn key_agreement_key(&self) -> CoseKey {
+        self.key_agreement.public_key()
+    }
+
+    /// Set the maximum size of the serialized large-blob array, which is raised to `1024` bytes
+    /// when lower. Writes of a larger array through [`Self::large_blobs`] fail with
+    /// [`Ctap2Error::LargeBlobStorageFull`].
+    pub fn set_max_serialized_large_blob_array(&mut self, value: usize) {
+        self.max_serialized_large_blob_array = value.max(MIN_SERIALIZED_LARGE_BLOB_ARRAY);
+    }
+
+    /// Get the maximum size of the serialized large-blob array.
+    pub fn max_serialized_large_blob_array(&self) -> usize {
+        self.max_serialized_large_blob_array
+    }
+
+    /// Set the [`AttestationStatementProvider`] used to generate the attestation statement of new
+    /// credentials. The default provider is [`NoneAttestation`].
+    pub fn set_attestation_statement_provider(
+        &mut self,
+        provider: impl AttestationStatementProvider + Send + Sync + 'static,
+    ) {
+        self.attestation = Box::new(provider);
+    }
+
     /// Access the [`CredentialStore`] to look into what is stored.
     pub fn store(&self) -> &S {
         &self.store
@@ -127,6 +618,27 @@ where
         Self { transports, ..self }
     }
 
+    /// Create the authenticator data of a ceremony of `rp_id`, which reports the backup flags of
+    /// [`Self::backup_state`].
+    fn authenticator_data(&self, rp_id: &str, counter: Option<u32>) -> AuthenticatorData {
+        let mut auth_data = AuthenticatorData::new(rp_id, counter);
+        auth_data.flags = self.backup_state.flags();
+        auth_data
+    }
+
+    /// Remember the options with which the user of the current ceremony is checked, for
+    /// [`Self::effective_options`].
+    fn set_effective_options(
+        &mut self,
+        options: &passkey_types::ctap2::make_credential::Options,
+        pin_verified: bool,
+    ) {
+        self.effective_options = Some(passkey_types::ctap2::make_credential::Options {
+            uv: options.uv || pin_verified,
+            ..options.clone()
+        });
+    }
+
     /// Collect user consent if required. This step MUST happen before the following steps due
     ///    to privacy reasons (i.e., authenticator cannot disclose existence of a credential
     ///    until the user interacted with the device):
@@ -173,6 +685,45 @@ where
     }
 }
 
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore,
+    U: UserValidationMethod + Sync,
+{
+    /// The output of the `uvm` extension for a ceremony whose user check resulted in `flags`,
+    /// which holds the single method the user was checked with, if it is known.
+    async fn uvm_extension_output(&self, flags: Flags, pin_verified: bool) -> Option<Value> {
+        let method = if pin_verified {
+            // The PIN is verified by the authenticator itself.
+            VerificationMethod::PasscodeInternal
+        } else {
+            self.user_validation
+                .verification_method(UserCheck {
+                    presence: flags.contains(Flags::UP),
+                    verification: flags.contains(Flags::UV),
+                })
+                .await?
+        };
+        Some(Value::Array(vec![Value::Array(vec![
+            Value::from(method.value()),
+            Value::from(KEY_PROTECTION_SOFTWARE),
+            Value::from(MATCHER_PROTECTION_SOFTWARE),
+        ])]))
+    }
+}
+
+/// The extensions of authenticator data, with their outputs keyed in the CTAP2 canonical order:
+/// shorter extension identifiers come first, and identifiers of the same length are sorted
+/// lexically.
+fn extensions_in_canonical_order(mut outputs: Vec<(Value, Value)>) -> Value {
+    outputs.sort_by(|(a, _), (b, _)| {
+        let a = a.as_text().unwrap_or_default();
+        let b = b.as_text().unwrap_or_default();
+        a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+    });
+    Value::Map(outputs)
+}
+
 #[cfg(test)]
 mod tests {
     use passkey_types::ctap2::{Aaguid, Flags};
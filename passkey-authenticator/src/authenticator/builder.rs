@@ -0,0 +1,270 @@
+use coset::iana;
+use passkey_types::ctap2::Aaguid;
+
+use super::client_pin::DEFAULT_MIN_PIN_LENGTH;
+use crate::{Authenticator, BackupState, CounterMode, CredentialStore, UserValidationMethod};
+
+/// Configures an [`Authenticator`] before creating it, see [`Authenticator::builder`].
+///
+/// The settings that are not given keep the defaults of [`Authenticator::new`]. The store and the
+/// user validation method must be given before the authenticator can be built.
+pub struct AuthenticatorBuilder<S, U> {
+    aaguid: Aaguid,
+    store: S,
+    user_validation: U,
+    algorithms: Option<Vec<iana::Algorithm>>,
+    counter_mode: CounterMode,
+    backup_state: BackupState,
+    hmac_secret: bool,
+    min_pin_length: u32,
+}
+
+impl Authenticator<(), ()> {
+    /// Start configuring an [`Authenticator`], which is an alternative to [`Authenticator::new`]
+    /// followed by its setters.
+    pub fn builder() -> AuthenticatorBuilder<(), ()> {
+        AuthenticatorBuilder {
+            aaguid: Aaguid::new_empty(),
+            store: (),
+            user_validation: (),
+            algorithms: None,
+            counter_mode: CounterMode::default(),
+            backup_state: BackupState::default(),
+            hmac_secret: true,
+            min_pin_length: DEFAULT_MIN_PIN_LENGTH,
+        }
+    }
+}
+
+impl<S, U> AuthenticatorBuilder<S, U> {
+    /// Set the AAGUID of the authenticator, which is empty by default.
+    pub fn aaguid(self, aaguid: Aaguid) -> Self {
+        Self { aaguid, ..self }
+    }
+
+    /// Set the signature algorithms new credentials can be created with, in order of preference
+    /// when the relying party accepts several of them. This must be a subset of `ES256`, `EdDSA`
+    /// and `RS256`, which are all supported by default.
+    pub fn algorithms(self, algorithms: Vec<iana::Algorithm>) -> Self {
+        Self {
+            algorithms: Some(algorithms),
+            ..self
+        }
+    }
+
+    /// Set how the authenticator implements the signature counter of its credentials, see
+    /// [`Authenticator::set_counter_mode`].
+    pub fn counter_mode(self, counter_mode: CounterMode) -> Self {
+        Self {
+            counter_mode,
+            ..self
+        }
+    }
+
+    /// Set the backup flags reported for the credentials, which are those of synced credentials
+    /// by default, see [`Authenticator::set_backup_state`].
+    pub fn backup_state(self, backup_state: BackupState) -> Self {
+        Self {
+            backup_state,
+            ..self
+        }
+    }
+
+    /// Set whether the `hmac-secret` extension is supported, see
+    /// [`Authenticator::set_hmac_secret_enabled`].
+    pub fn enable_hmac_secret(self, enabled: bool) -> Self {
+        Self {
+            hmac_secret: enabled,
+            ..self
+        }
+    }
+
+    /// Set the minimum length of new PINs, which is 4 by default, see
+    /// [`Authenticator::set_min_pin_length`].
+    pub fn min_pin_length(self, min_pin_length: u32) -> Self {
+        Self {
+            min_pin_length,
+            ..self
+        }
+    }
+
+    /// Set the storage of the authenticator's credentials.
+    pub fn store<T: CredentialStore>(self, store: T) -> AuthenticatorBuilder<T, U> {
+        AuthenticatorBuilder {
+            aaguid: self.aaguid,
+            store,
+            user_validation: self.user_validation,
+            algorithms: self.algorithms,
+            counter_mode: self.counter_mode,
+            backup_state: self.backup_state,
+            hmac_secret: self.hmac_secret,
+            min_pin_length: self.min_pin_length,
+        }
+    }
+
+    /// Set how the authenticator checks the user's presence and verifies them.
+    pub fn user_validation<T: UserValidationMethod>(
+        self,
+        user_validation: T,
+    ) -> AuthenticatorBuilder<S, T> {
+        AuthenticatorBuilder {
+            aaguid: self.aaguid,
+            store: self.store,
+            user_validation,
+            algorithms: self.algorithms,
+            counter_mode: self.counter_mode,
+            backup_state: self.backup_state,
+            hmac_secret: self.hmac_secret,
+            min_pin_length: self.min_pin_length,
+        }
+    }
+}
+
+impl<S, U> AuthenticatorBuilder<S, U>
+where
+    S: CredentialStore,
+    U: UserValidationMethod,
+{
+    /// Create the configured authenticator.
+    pub fn build(self) -> Authenticator<S, U> {
+        let mut authenticator = Authenticator::new(self.aaguid, self.store, self.user_validation);
+        if let Some(algorithms) = self.algorithms {
+            authenticator.algs = algorithms;
+        }
+        authenticator.set_counter_mode(self.counter_mode);
+        authenticator.set_backup_state(self.backup_state);
+        authenticator.set_hmac_secret_enabled(self.hmac_secret);
+        authenticator.set_min_pin_length(self.min_pin_length);
+        authenticator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        ctap2::{get_assertion, make_credential, Aaguid, Ctap2Error},
+        rand::random_vec,
+        webauthn,
+    };
+
+    use crate::{Authenticator, BackupState, CounterMode, MemoryStore, MockUserValidationMethod};
+
+    fn make_credential_request() -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: random_vec(32).into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: "future.1password.com".into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: random_vec(16).into(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: true,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn builder_configures_the_authenticator() {
+        // Arrange
+        let aaguid = Aaguid::from([7; 16]);
+        let mut authenticator = Authenticator::builder()
+            .aaguid(aaguid)
+            .algorithms(vec![iana::Algorithm::EdDSA])
+            .counter_mode(CounterMode::Global)
+            .enable_hmac_secret(false)
+            .min_pin_length(6)
+            .store(MemoryStore::new())
+            .user_validation(MockUserValidationMethod::verified_user(0))
+            .build();
+
+        // Act
+        let info = authenticator.get_info().await;
+        let es256_only = authenticator
+            .make_credential(make_credential_request())
+            .await;
+
+        // Assert
+        assert_eq!(info.aaguid, aaguid);
+        assert_eq!(
+            info.algorithms,
+            Some(vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::EdDSA,
+            }])
+        );
+        assert!(!info.extensions.unwrap().contains(&"hmac-secret".into()));
+        assert_eq!(info.min_pin_length, Some(6));
+        assert_eq!(authenticator.counter_mode(), CounterMode::Global);
+        assert_eq!(
+            es256_only.unwrap_err(),
+            Ctap2Error::UnsupportedAlgorithm.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_flags_are_reported_in_credentials_and_assertions() {
+        // Arrange
+        let mut synced = Authenticator::builder()
+            .store(MemoryStore::new())
+            .user_validation(MockUserValidationMethod::verified_user(2))
+            .build();
+        let mut eligible = Authenticator::builder()
+            .backup_state(BackupState::Eligible)
+            .store(MemoryStore::new())
+            .user_validation(MockUserValidationMethod::verified_user(2))
+            .build();
+        let assertion_request = || get_assertion::Request {
+            rp_id: "future.1password.com".into(),
+            client_data_hash: random_vec(32).into(),
+            allow_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: false,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+        };
+
+        // Act
+        let synced_credential = synced.make_credential(make_credential_request()).await;
+        let synced_assertion = synced.get_assertion(assertion_request()).await;
+        let eligible_credential = eligible.make_credential(make_credential_request()).await;
+        let eligible_assertion = eligible.get_assertion(assertion_request()).await;
+
+        // Assert
+        assert_eq!(synced.backup_state(), BackupState::BackedUp);
+        for auth_data in [
+            synced_credential.unwrap().auth_data,
+            synced_assertion.unwrap().auth_data,
+        ] {
+            assert!(auth_data.is_backup_eligible());
+            assert!(auth_data.is_backed_up());
+        }
+        for auth_data in [
+            eligible_credential.unwrap().auth_data,
+            eligible_assertion.unwrap().auth_data,
+        ] {
+            assert!(auth_data.is_backup_eligible());
+            assert!(!auth_data.is_backed_up());
+        }
+    }
+}
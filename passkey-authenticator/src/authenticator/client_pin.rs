@@ -0,0 +1,721 @@
+use passkey_types::{
+    crypto::sha256,
+    ctap2::{
+        client_pin::{Request, Response, SubCommand},
+        Ctap2Error, StatusCode, U2FError,
+    },
+    Bytes,
+};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    pin_protocol::{KeyAgreementKey, PinToken, SharedSecret, PROTOCOL_ONE},
+    Authenticator,
+};
+
+/// The number of consecutive wrong PINs after which the PIN is blocked, until the authenticator is
+/// reset.
+pub const MAX_PIN_RETRIES: u8 = 8;
+
+/// The number of consecutive wrong PINs after which the PIN cannot be tried again until the
+/// authenticator is powered up again.
+const MAX_CONSECUTIVE_PIN_MISMATCHES: u8 = 3;
+
+/// The default and smallest minimum length of a PIN, in Unicode code points.
+pub(super) const DEFAULT_MIN_PIN_LENGTH: u32 = 4;
+
+/// The maximum length of a PIN, in bytes of its UTF-8 encoding.
+const MAX_PIN_BYTES: usize = 63;
+
+/// The minimum length of a padded PIN, which hides the length of the PIN itself.
+const MIN_PADDED_PIN_LENGTH: usize = 64;
+
+/// The state of the `clientPIN` feature of an authenticator.
+pub(super) struct ClientPin {
+    /// The first 16 bytes of the SHA-256 hash of the PIN, once one was set.
+    pin_hash: Option<[u8; 16]>,
+
+    /// The number of PIN attempts left before the PIN is blocked.
+    retries: u8,
+
+    /// The number of wrong PINs given since the authenticator was powered up.
+    consecutive_mismatches: u8,
+
+    /// The `pinToken` given to the platform in exchange of the PIN.
+    token: PinToken,
+}
+
+impl ClientPin {
+    /// The state of an authenticator without a PIN.
+    pub(super) fn new() -> Self {
+        Self {
+            pin_hash: None,
+            retries: MAX_PIN_RETRIES,
+            consecutive_mismatches: 0,
+            token: PinToken::generate(),
+        }
+    }
+
+    /// Forget the state that does not survive a power cycle, which is everything but the PIN and
+    /// its retries counter.
+    pub(super) fn power_cycle(&mut self) {
+        self.consecutive_mismatches = 0;
+        self.token = PinToken::generate();
+    }
+}
+
+impl<S, U> Authenticator<S, U> {
+    /// This method is used by the host to configure the PIN of the authenticator and to exchange it
+    /// for a `pinToken` through [PIN/UV auth protocol one], which then authenticates the
+    /// `pinAuth` parameter of [`Self::make_credential`] and [`Self::get_assertion`] as well as the
+    /// `pinUvAuthParam` of [`Self::large_blobs`].
+    ///
+    /// After [`MAX_PIN_RETRIES`] consecutive wrong PINs, the PIN is blocked until the authenticator
+    /// is reset. After 3 consecutive wrong PINs, the PIN cannot be tried again until
+    /// [`Self::power_cycle`].
+    ///
+    /// [PIN/UV auth protocol one]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#pinProto1
+    pub fn client_pin(&mut self, input: Request) -> Result<Response, StatusCode> {
+        if input.pin_protocol != PROTOCOL_ONE {
+            return Err(U2FError::InvalidParameter.into());
+        }
+
+        match input.sub_command {
+            SubCommand::GetPinRetries => Ok(Response {
+                retries: Some(self.client_pin.retries),
+                ..Default::default()
+            }),
+            SubCommand::GetKeyAgreement => Ok(Response {
+                key_agreement: Some(self.key_agreement.public_key()),
+                ..Default::default()
+            }),
+            SubCommand::SetPin => {
+                let (secret, pin_auth, new_pin_enc) = self.shared_secret_and(
+                    &input,
+                    input.pin_auth.as_ref(),
+                    input.new_pin_enc.as_ref(),
+                )?;
+                if self.client_pin.pin_hash.is_some() {
+                    return Err(Ctap2Error::PinAuthInvalid.into());
+                }
+                secret.verify(new_pin_enc, pin_auth)?;
+                self.client_pin.pin_hash = Some(decrypt_new_pin_hash(
+                    &secret,
+                    new_pin_enc,
+                    self.min_pin_length,
+                )?);
+                self.client_pin.retries = MAX_PIN_RETRIES;
+                Ok(Response::default())
+            }
+            SubCommand::ChangePin => {
+                let (secret, pin_auth, new_pin_enc) = self.shared_secret_and(
+                    &input,
+                    input.pin_auth.as_ref(),
+                    input.new_pin_enc.as_ref(),
+                )?;
+                let pin_hash_enc = input
+                    .pin_hash_enc
+                    .as_ref()
+                    .ok_or(Ctap2Error::MissingParameter)?;
+                self.check_pin_can_be_tried()?;
+                let mut message = new_pin_enc.to_vec();
+                message.extend_from_slice(pin_hash_enc);
+                secret.verify(&message, pin_auth)?;
+                self.check_pin_hash(&secret, pin_hash_enc)?;
+                self.client_pin.pin_hash = Some(decrypt_new_pin_hash(
+                    &secret,
+                    new_pin_enc,
+                    self.min_pin_length,
+                )?);
+                self.client_pin.token = PinToken::generate();
+                Ok(Response::default())
+            }
+            SubCommand::GetPinToken => {
+                let key_agreement = input
+                    .key_agreement
+                    .as_ref()
+                    .ok_or(Ctap2Error::MissingParameter)?;
+                let pin_hash_enc = input
+                    .pin_hash_enc
+                    .as_ref()
+                    .ok_or(Ctap2Error::MissingParameter)?;
+                let secret = self.key_agreement.shared_secret(key_agreement)?;
+                self.check_pin_can_be_tried()?;
+                self.check_pin_hash(&secret, pin_hash_enc)?;
+                // A new token invalidates the ones given before.
+                self.client_pin.token = PinToken::generate();
+                Ok(Response {
+                    pin_token: Some(self.client_pin.token.encrypt(&secret)?.into()),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// Get the number of PIN attempts left before the PIN is blocked.
+    pub fn pin_retries(&self) -> u8 {
+        self.client_pin.retries
+    }
+
+    /// Whether a PIN was set through [`Self::client_pin`].
+    pub fn is_pin_set(&self) -> bool {
+        self.client_pin.pin_hash.is_some()
+    }
+
+    /// Simulate the authenticator being powered up again, which regenerates the key agreement key
+    /// and the `pinToken`, and allows the PIN to be tried again after 3 consecutive wrong PINs.
+    /// The PIN, its retries counter and the credentials are kept.
+    pub fn power_cycle(&mut self) {
+        self.key_agreement = KeyAgreementKey::generate();
+        self.client_pin.power_cycle();
+        self.pending_assertions = None;
+        self.pending_large_blob = None;
+        self.pending_enumeration = None;
+    }
+
+    /// Verify the `pinAuth` parameter of a request, which authenticates `message` with the
+    /// `pinToken`. Returns whether the user was verified through their PIN, which happens when the
+    /// parameter is present.
+    pub(super) fn verify_pin_auth(
+        &self,
+        pin_auth: Option<&Bytes>,
+        pin_protocol: Option<u8>,
+        message: &[u8],
+    ) -> Result<bool, StatusCode> {
+        let Some(pin_auth) = pin_auth else {
+            return Ok(false);
+        };
+        // NB: An empty pinAuth is sent by platforms to find out whether a PIN is set, which a
+        // physical authenticator only answers after the user touched it.
+        if pin_auth.is_empty() || !self.is_pin_set() {
+            return Err(match self.is_pin_set() {
+                true => Ctap2Error::PinInvalid.into(),
+                false => Ctap2Error::PinNotSet.into(),
+            });
+        }
+        if pin_protocol != Some(PROTOCOL_ONE) {
+            return Err(Ctap2Error::PinAuthInvalid.into());
+        }
+        self.client_pin.token.verify(message, pin_auth)?;
+        Ok(true)
+    }
+
+    /// Compute the secret shared with the platform and get the two given parameters, which are
+    /// all required.
+    fn shared_secret_and<'a>(
+        &self,
+        input: &Request,
+        first: Option<&'a Bytes>,
+        second: Option<&'a Bytes>,
+    ) -> Result<(SharedSecret, &'a Bytes, &'a Bytes), StatusCode> {
+        let (Some(key_agreement), Some(first), Some(second)) =
+            (input.key_agreement.as_ref(), first, second)
+        else {
+            return Err(Ctap2Error::MissingParameter.into());
+        };
+        Ok((
+            self.key_agreement.shared_secret(key_agreement)?,
+            first,
+            second,
+        ))
+    }
+
+    fn check_pin_can_be_tried(&self) -> Result<(), StatusCode> {
+        if self.client_pin.pin_hash.is_none() {
+            return Err(Ctap2Error::PinNotSet.into());
+        }
+        if self.client_pin.retries == 0 {
+            return Err(Ctap2Error::PinBlocked.into());
+        }
+        if self.client_pin.consecutive_mismatches >= MAX_CONSECUTIVE_PIN_MISMATCHES {
+            return Err(Ctap2Error::PinAuthBlocked.into());
+        }
+        Ok(())
+    }
+
+    /// Check the encrypted hash of the PIN given by the platform, which uses up one of the PIN
+    /// retries unless it matches.
+    fn check_pin_hash(
+        &mut self,
+        secret: &SharedSecret,
+        pin_hash_enc: &[u8],
+    ) -> Result<(), StatusCode> {
+        self.client_pin.retries -= 1;
+        let pin_hash = secret.decrypt(pin_hash_enc)?;
+        // The hashes are compared in constant time so that the timing does not reveal how much of
+        // the PIN hash was guessed.
+        let matches = self
+            .client_pin
+            .pin_hash
+            .as_ref()
+            .is_some_and(|hash| bool::from(hash.as_slice().ct_eq(&pin_hash)));
+        if !matches {
+            // A new key agreement key forces the platform to compute a new shared secret.
+            self.key_agreement = KeyAgreementKey::generate();
+            self.client_pin.consecutive_mismatches += 1;
+            return Err(if self.client_pin.retries == 0 {
+                Ctap2Error::PinBlocked.into()
+            } else if self.client_pin.consecutive_mismatches >= MAX_CONSECUTIVE_PIN_MISMATCHES {
+                Ctap2Error::PinAuthBlocked.into()
+            } else {
+                Ctap2Error::PinInvalid.into()
+            });
+        }
+        self.client_pin.retries = MAX_PIN_RETRIES;
+        self.client_pin.consecutive_mismatches = 0;
+        Ok(())
+    }
+}
+
+/// Decrypt a new PIN padded with zeros, check that it follows the PIN policy and hash it.
+fn decrypt_new_pin_hash(
+    secret: &SharedSecret,
+    new_pin_enc: &[u8],
+    min_pin_length: u32,
+) -> Result<[u8; 16], StatusCode> {
+    if new_pin_enc.len() < MIN_PADDED_PIN_LENGTH {
+        return Err(Ctap2Error::PinPolicyViolation.into());
+    }
+    let padded = secret.decrypt(new_pin_enc)?;
+    let length = padded
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |last| last + 1);
+    let pin = std::str::from_utf8(&padded[..length]).map_err(|_| Ctap2Error::PinPolicyViolation)?;
+    let min_pin_length = usize::try_from(min_pin_length).unwrap_or(usize::MAX);
+    if pin.len() > MAX_PIN_BYTES || pin.chars().count() < min_pin_length {
+        return Err(Ctap2Error::PinPolicyViolation.into());
+    }
+
+    let mut pin_hash = [0; 16];
+    pin_hash.copy_from_slice(&sha256(pin.as_bytes())[..16]);
+    Ok(pin_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::{iana, CoseKey};
+    use hmac::{Hmac, Mac};
+    use passkey_types::{
+        crypto::sha256,
+        ctap2::{
+            client_pin::{Request, SubCommand},
+            credential_management, get_assertion, large_blobs, make_credential, Aaguid, Ctap2Error,
+            Flags, StatusCode,
+        },
+        rand::random_vec,
+        webauthn, Bytes,
+    };
+    use sha2::Sha256;
+
+    use crate::{
+        pin_protocol::{KeyAgreementKey, SharedSecret},
+        Authenticator, MemoryStore, MockUserValidationMethod, UserCheck,
+    };
+
+    type TestAuthenticator = Authenticator<MemoryStore, MockUserValidationMethod>;
+
+    /// A user who is present but not verified by the authenticator, which relies on the PIN.
+    fn present_user(times: usize) -> MockUserValidationMethod {
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true))
+            .times(..);
+        user_mock
+            .expect_is_presence_enabled()
+            .returning(|| true)
+            .times(..);
+        user_mock
+            .expect_check_user()
+            .with(
+                mockall::predicate::always(),
+                mockall::predicate::eq(true),
+                mockall::predicate::eq(false),
+            )
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: true,
+                    verification: false,
+                })
+            })
+            .times(times);
+        user_mock
+    }
+
+    /// Do the key agreement on the platform side.
+    fn key_agreement(authenticator: &TestAuthenticator) -> (CoseKey, SharedSecret) {
+        let platform = KeyAgreementKey::generate();
+        let secret = platform
+            .shared_secret(&authenticator.key_agreement_key())
+            .unwrap();
+        (platform.public_key(), secret)
+    }
+
+    fn encrypted_new_pin(secret: &SharedSecret, pin: &str) -> Bytes {
+        let mut padded = pin.as_bytes().to_vec();
+        padded.resize(64, 0);
+        secret.encrypt(&padded).unwrap().into()
+    }
+
+    fn encrypted_pin_hash(secret: &SharedSecret, pin: &str) -> Bytes {
+        secret
+            .encrypt(&sha256(pin.as_bytes())[..16])
+            .unwrap()
+            .into()
+    }
+
+    fn set_pin(authenticator: &TestAuthenticator, pin: &str) -> Request {
+        let (key_agreement, secret) = key_agreement(authenticator);
+        let new_pin_enc = encrypted_new_pin(&secret, pin);
+        Request {
+            pin_protocol: 1,
+            sub_command: SubCommand::SetPin,
+            key_agreement: Some(key_agreement),
+            pin_auth: Some(secret.authenticate(&new_pin_enc).to_vec().into()),
+            new_pin_enc: Some(new_pin_enc),
+            pin_hash_enc: None,
+        }
+    }
+
+    /// Exchange the PIN for the decrypted `pinToken`.
+    fn get_pin_token(
+        authenticator: &mut TestAuthenticator,
+        pin: &str,
+    ) -> Result<Vec<u8>, StatusCode> {
+        let (key_agreement, secret) = key_agreement(authenticator);
+        let response = authenticator.client_pin(Request {
+            pin_protocol: 1,
+            sub_command: SubCommand::GetPinToken,
+            key_agreement: Some(key_agreement),
+            pin_auth: None,
+            new_pin_enc: None,
+            pin_hash_enc: Some(encrypted_pin_hash(&secret, pin)),
+        })?;
+        Ok(secret.decrypt(&response.pin_token.unwrap()).unwrap())
+    }
+
+    /// Authenticate the message with the `pinToken` like the platform does.
+    fn pin_auth(pin_token: &[u8], message: &[u8]) -> Bytes {
+        let mut mac = Hmac::<Sha256>::new_from_slice(pin_token).unwrap();
+        mac.update(message);
+        mac.finalize().into_bytes()[..16].to_vec().into()
+    }
+
+    fn make_credential_request(pin_auth: Option<Bytes>) -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: vec![0; 32].into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: "future.1password.com".into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: random_vec(16).into(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: true,
+                up: true,
+                uv: false,
+            },
+            pin_protocol: pin_auth.is_some().then_some(1),
+            pin_auth,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn pin_token_verifies_the_user() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(2));
+        let info_without_pin = authenticator.get_info().await;
+
+        // Act
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let pin_token = get_pin_token(&mut authenticator, "1234").unwrap();
+        let created = authenticator
+            .make_credential(make_credential_request(Some(pin_auth(
+                &pin_token, &[0; 32],
+            ))))
+            .await
+            .unwrap();
+        let asserted = authenticator
+            .get_assertion(get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: vec![1; 32].into(),
+                allow_list: None,
+                extensions: None,
+                options: get_assertion::Options {
+                    rk: false,
+                    up: true,
+                    uv: false,
+                },
+                pin_auth: Some(pin_auth(&pin_token, &[1; 32])),
+                pin_protocol: Some(1),
+            })
+            .await
+            .unwrap();
+        let info = authenticator.get_info().await;
+
+        // Assert
+        assert!(created.auth_data.flags.contains(Flags::UP | Flags::UV));
+        assert!(asserted.auth_data.flags.contains(Flags::UP | Flags::UV));
+        assert_eq!(info_without_pin.options.unwrap().client_pin, Some(false));
+        assert_eq!(info.options.unwrap().client_pin, Some(true));
+        assert_eq!(info.pin_protocols, Some(vec![1]));
+        assert_eq!(authenticator.pin_retries(), 8);
+    }
+
+    #[tokio::test]
+    async fn requests_must_be_authenticated_once_a_pin_is_set() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(0));
+        let too_short = authenticator.client_pin(set_pin(&authenticator, "123"));
+        let without_pin = authenticator
+            .make_credential(make_credential_request(Some(vec![0; 16].into())))
+            .await;
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+
+        // Act
+        let set_again = authenticator.client_pin(set_pin(&authenticator, "5678"));
+        let unauthenticated = authenticator
+            .make_credential(make_credential_request(None))
+            .await;
+        let wrongly_authenticated = authenticator
+            .make_credential(make_credential_request(Some(vec![0; 16].into())))
+            .await;
+        let probing = authenticator
+            .make_credential(make_credential_request(Some(Bytes::from(Vec::new()))))
+            .await;
+
+        // Assert
+        assert_eq!(too_short, Err(Ctap2Error::PinPolicyViolation.into()));
+        assert_eq!(without_pin.unwrap_err(), Ctap2Error::PinNotSet.into());
+        assert_eq!(set_again, Err(Ctap2Error::PinAuthInvalid.into()));
+        assert_eq!(
+            unauthenticated.unwrap_err(),
+            Ctap2Error::PuatRequired.into()
+        );
+        assert_eq!(
+            wrongly_authenticated.unwrap_err(),
+            Ctap2Error::PinAuthInvalid.into()
+        );
+        assert_eq!(probing.unwrap_err(), Ctap2Error::PinInvalid.into());
+    }
+
+    #[tokio::test]
+    async fn assertions_must_be_authenticated_once_a_pin_is_set() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(1));
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let pin_token = get_pin_token(&mut authenticator, "1234").unwrap();
+        authenticator
+            .make_credential(make_credential_request(Some(pin_auth(
+                &pin_token, &[0; 32],
+            ))))
+            .await
+            .unwrap();
+
+        // Act
+        let unauthenticated = authenticator
+            .get_assertion(get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: vec![1; 32].into(),
+                allow_list: None,
+                extensions: None,
+                options: get_assertion::Options {
+                    rk: false,
+                    up: true,
+                    uv: false,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await;
+
+        // Assert
+        assert_eq!(
+            unauthenticated.unwrap_err(),
+            Ctap2Error::PuatRequired.into()
+        );
+    }
+
+    #[tokio::test]
+    async fn pin_is_blocked_after_too_many_wrong_attempts() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(0));
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let mut attempts = Vec::new();
+
+        // Act
+        for _ in 0..4 {
+            attempts.push(get_pin_token(&mut authenticator, "0000").unwrap_err());
+        }
+        let retries_before_power_cycle = authenticator.pin_retries();
+        for _ in 0..2 {
+            authenticator.power_cycle();
+            for _ in 0..3 {
+                attempts.push(get_pin_token(&mut authenticator, "0000").unwrap_err());
+            }
+        }
+        let right_pin = get_pin_token(&mut authenticator, "1234");
+        authenticator.reset().await.unwrap();
+
+        // Assert
+        use Ctap2Error::{PinAuthBlocked, PinBlocked, PinInvalid};
+        assert_eq!(
+            attempts,
+            [
+                PinInvalid,
+                PinInvalid,
+                PinAuthBlocked,
+                PinAuthBlocked,
+                PinInvalid,
+                PinInvalid,
+                PinAuthBlocked,
+                PinInvalid,
+                PinBlocked,
+                PinBlocked,
+            ]
+            .map(StatusCode::from)
+        );
+        assert_eq!(retries_before_power_cycle, 5);
+        assert_eq!(right_pin, Err(PinBlocked.into()));
+        assert!(!authenticator.is_pin_set());
+        assert_eq!(authenticator.pin_retries(), 8);
+    }
+
+    #[tokio::test]
+    async fn pin_is_changed_knowing_the_current_one() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(0));
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let old_token = get_pin_token(&mut authenticator, "1234").unwrap();
+        let (key_agreement, secret) = key_agreement(&authenticator);
+        let new_pin_enc = encrypted_new_pin(&secret, "56789");
+        let pin_hash_enc = encrypted_pin_hash(&secret, "1234");
+        let message = [new_pin_enc.as_slice(), pin_hash_enc.as_slice()].concat();
+
+        // Act
+        authenticator
+            .client_pin(Request {
+                pin_protocol: 1,
+                sub_command: SubCommand::ChangePin,
+                key_agreement: Some(key_agreement),
+                pin_auth: Some(secret.authenticate(&message).to_vec().into()),
+                new_pin_enc: Some(new_pin_enc),
+                pin_hash_enc: Some(pin_hash_enc),
+            })
+            .unwrap();
+        let with_old_token = authenticator
+            .make_credential(make_credential_request(Some(pin_auth(
+                &old_token, &[0; 32],
+            ))))
+            .await;
+
+        // Assert
+        assert_eq!(
+            with_old_token.unwrap_err(),
+            Ctap2Error::PinAuthInvalid.into()
+        );
+        assert_eq!(
+            get_pin_token(&mut authenticator, "1234"),
+            Err(Ctap2Error::PinInvalid.into())
+        );
+        assert!(get_pin_token(&mut authenticator, "56789").is_ok());
+    }
+
+    #[tokio::test]
+    async fn large_blob_writes_are_authorized_with_the_pin_token() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(0));
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let pin_token = get_pin_token(&mut authenticator, "1234").unwrap();
+        let array = large_blobs::INITIAL_SERIALIZED_ARRAY.to_vec();
+        let mut message = vec![0xff; 32];
+        message.extend_from_slice(&[0x0c, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        message.extend_from_slice(&sha256(&array));
+        let request = |pin_uv_auth_param: Option<Bytes>| large_blobs::Request {
+            get: None,
+            set: Some(array.clone().into()),
+            offset: 0,
+            length: Some(array.len()),
+            pin_uv_auth_protocol: pin_uv_auth_param.is_some().then_some(1),
+            pin_uv_auth_param,
+        };
+
+        // Act
+        let unauthorized = authenticator.large_blobs(request(None)).await;
+        let authorized = authenticator
+            .large_blobs(request(Some(pin_auth(&pin_token, &message))))
+            .await;
+
+        // Assert
+        assert_eq!(unauthorized, Err(Ctap2Error::PuatRequired.into()));
+        assert!(authorized.is_ok());
+    }
+
+    #[tokio::test]
+    async fn credential_management_is_authorized_with_the_pin_token() {
+        // Arrange
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), present_user(0));
+        authenticator
+            .client_pin(set_pin(&authenticator, "1234"))
+            .unwrap();
+        let pin_token = get_pin_token(&mut authenticator, "1234").unwrap();
+        let mut request = credential_management::Request {
+            sub_command: credential_management::SubCommand::GetCredsMetadata,
+            sub_command_params: None,
+            pin_uv_auth_protocol: None,
+            pin_uv_auth_param: None,
+        };
+
+        // Act
+        let unauthorized = authenticator.credential_management(request.clone()).await;
+        let wrongly_authorized = authenticator
+            .credential_management(credential_management::Request {
+                pin_uv_auth_protocol: Some(1),
+                pin_uv_auth_param: Some(pin_auth(&pin_token, &[0x02])),
+                ..request.clone()
+            })
+            .await;
+        request.pin_uv_auth_protocol = Some(1);
+        request.pin_uv_auth_param = Some(pin_auth(&pin_token, &request.pin_uv_auth_message()));
+        let authorized = authenticator.credential_management(request).await;
+
+        // Assert
+        assert_eq!(unauthorized, Err(Ctap2Error::PuatRequired.into()));
+        assert_eq!(wrongly_authorized, Err(Ctap2Error::PinAuthInvalid.into()));
+        assert_eq!(
+            authorized.map(|response| response.existing_resident_credentials_count),
+            Ok(Some(0))
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use coset::{iana, iana::EnumI64, CoseKey, Label, RegisteredLabel};
+use passkey_types::{
+    crypto::sha256,
+    ctap2::{
+        credential_management::{Request, Response, SubCommand},
+        make_credential::{PublicKeyCredentialRpEntity, PublicKeyCredentialUserEntity},
+        Ctap2Error, StatusCode, U2FError,
+    },
+    webauthn::{PublicKeyCredentialDescriptor, PublicKeyCredentialType},
+    CredentialExtensions, Passkey,
+};
+
+use crate::{pin_protocol::PROTOCOL_ONE, Authenticator, CredentialStore, UserValidationMethod};
+
+/// The relying parties or credentials of an enumeration that are left for its `GetNext`
+/// subcommand.
+pub(super) enum PendingEnumeration {
+    Rps(VecDeque<Response>),
+    Credentials(VecDeque<Response>),
+}
+
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    /// This method is used by the host to manage the discoverable credentials of the
+    /// authenticator: count them, enumerate their relying parties and credentials, delete them
+    /// and update the details of their users. The credentials are the ones given by
+    /// [`CredentialStore::list_credentials`], so a store that cannot enumerate its credentials
+    /// fails with [`Ctap2Error::NotAllowed`].
+    ///
+    /// Once a PIN is set, every subcommand except the ones continuing an enumeration must be
+    /// authorized with the `pinToken`, otherwise this fails with [`Ctap2Error::PuatRequired`].
+    pub async fn credential_management(&mut self, input: Request) -> Result<Response, StatusCode> {
+        if input.sub_command.requires_pin_uv_auth() {
+            // Starting any other subcommand ends the current enumeration.
+            self.pending_enumeration = None;
+            match &input.pin_uv_auth_param {
+                Some(pin_uv_auth_param) => {
+                    if input.pin_uv_auth_protocol != Some(PROTOCOL_ONE) {
+                        return Err(U2FError::InvalidParameter.into());
+                    }
+                    self.verify_pin_auth(
+                        Some(pin_uv_auth_param),
+                        input.pin_uv_auth_protocol,
+                        &input.pin_uv_auth_message(),
+                    )?;
+                }
+                None if self.is_pin_set() => return Err(Ctap2Error::PuatRequired.into()),
+                None => {}
+            }
+        }
+
+        let params = input.sub_command_params.unwrap_or_default();
+        match input.sub_command {
+            SubCommand::GetCredsMetadata => {
+                let existing = match self.store().discoverable_credentials_count().await {
+                    Some(count) => count,
+                    None => self.discoverable_credentials(None).await?.len(),
+                };
+                let existing = u32::try_from(existing).unwrap_or(u32::MAX);
+                // Without a maximum, the remaining capacity is only bounded by the count itself.
+                let remaining = match self.max_discoverable_credentials {
+                    Some(max) => max.saturating_sub(existing),
+                    None => u32::MAX - existing,
+                };
+                Ok(Response {
+                    existing_resident_credentials_count: Some(existing),
+                    max_possible_remaining_resident_credentials_count: Some(remaining),
+                    ..Default::default()
+                })
+            }
+            SubCommand::EnumerateRpsBegin => {
+                let rp_ids: BTreeSet<String> = self
+                    .discoverable_credentials(None)
+                    .await?
+                    .into_iter()
+                    .map(|(credential, _)| credential.rp_id)
+                    .collect();
+                let rps = rp_ids
+                    .into_iter()
+                    .map(|id| Response {
+                        rp_id_hash: Some(sha256(id.as_bytes()).to_vec().into()),
+                        rp: Some(PublicKeyCredentialRpEntity { id, name: None }),
+                        ..Default::default()
+                    })
+                    .collect();
+                self.begin_enumeration(rps, PendingEnumeration::Rps, |first, total| {
+                    first.total_rps = Some(total);
+                })
+            }
+            SubCommand::EnumerateCredentialsBegin => {
+                let rp_id_hash = params.rp_id_hash.ok_or(Ctap2Error::MissingParameter)?;
+                let mut credentials = Vec::new();
+                for (credential, extensions) in self.discoverable_credentials(None).await? {
+                    if sha256(credential.rp_id.as_bytes())[..] != *rp_id_hash {
+                        continue;
+                    }
+                    credentials.push(self.describe_credential(credential, extensions).await);
+                }
+                self.begin_enumeration(
+                    credentials,
+                    PendingEnumeration::Credentials,
+                    |first, total| first.total_credentials = Some(total),
+                )
+            }
+            SubCommand::EnumerateRpsGetNextRp => match &mut self.pending_enumeration {
+                Some(PendingEnumeration::Rps(rps)) => {
+                    rps.pop_front().ok_or(Ctap2Error::NotAllowed.into())
+                }
+                _ => Err(Ctap2Error::NotAllowed.into()),
+            },
+            SubCommand::EnumerateCredentialsGetNextCredential => {
+                match &mut self.pending_enumeration {
+                    Some(PendingEnumeration::Credentials(credentials)) => {
+                        credentials.pop_front().ok_or(Ctap2Error::NotAllowed.into())
+                    }
+                    _ => Err(Ctap2Error::NotAllowed.into()),
+                }
+            }
+            SubCommand::DeleteCredential => {
+                let descriptor = params.credential_id.ok_or(Ctap2Error::MissingParameter)?;
+                self.find_discoverable_credential(&descriptor).await?;
+                self.store_mut().delete_credential(&descriptor.id).await?;
+                self.emitted_signature_counters.remove(&*descriptor.id);
+                self.device_keys.remove(&*descriptor.id);
+                Ok(Response::default())
+            }
+            SubCommand::UpdateUserInformation => {
+                let (Some(descriptor), Some(user)) = (params.credential_id, params.user) else {
+                    return Err(Ctap2Error::MissingParameter.into());
+                };
+                let credential = self.find_discoverable_credential(&descriptor).await?;
+                // The user ID of a credential cannot be changed.
+                if credential.user_handle.as_ref() != Some(&user.id) {
+                    return Err(U2FError::InvalidParameter.into());
+                }
+                self.store_mut()
+                    .update_user(&credential.rp_id, user)
+                    .await?;
+                Ok(Response::default())
+            }
+        }
+    }
+
+    /// Return the first of the enumerated items, completed by `with_total`, and keep the others
+    /// for the `GetNext` subcommand. Fails with [`Ctap2Error::NoCredentials`] when there are none.
+    fn begin_enumeration(
+        &mut self,
+        items: Vec<Response>,
+        pending: fn(VecDeque<Response>) -> PendingEnumeration,
+        with_total: impl FnOnce(&mut Response, u32),
+    ) -> Result<Response, StatusCode> {
+        let total = u32::try_from(items.len()).unwrap_or(u32::MAX);
+        let mut items = VecDeque::from(items);
+        let Some(mut first) = items.pop_front() else {
+            return Err(Ctap2Error::NoCredentials.into());
+        };
+        with_total(&mut first, total);
+        if !items.is_empty() {
+            self.pending_enumeration = Some(pending(items));
+        }
+        Ok(first)
+    }
+
+    /// The discoverable credentials of `rp_id`, or of every relying party, along with the state
+    /// of their extensions.
+    async fn discoverable_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<(Passkey, CredentialExtensions)>, StatusCode> {
+        let mut credentials = Vec::new();
+        for item in self.store().list_credentials(rp_id).await? {
+            let extensions = self.store().credential_extensions(&item).await;
+            if let Ok(credential) = Passkey::try_from(item) {
+                credentials.push((credential, extensions));
+            }
+        }
+        Ok(credentials)
+    }
+
+    /// Look up the discoverable credential given to a subcommand, failing with
+    /// [`Ctap2Error::NoCredentials`] when there is none.
+    async fn find_discoverable_credential(
+        &self,
+        descriptor: &PublicKeyCredentialDescriptor,
+    ) -> Result<Passkey, StatusCode> {
+        self.discoverable_credentials(None)
+            .await?
+            .into_iter()
+            .map(|(credential, _)| credential)
+            .find(|credential| credential.credential_id == descriptor.id)
+            .ok_or(Ctap2Error::NoCredentials.into())
+    }
+
+    /// The enumerated description of a credential. Only the ID of its user is known when the
+    /// store does not keep the details of users.
+    async fn describe_credential(
+        &self,
+        credential: Passkey,
+        extensions: CredentialExtensions,
+    ) -> Response {
+        let user_id = credential.user_handle.clone().unwrap_or_default();
+        let user = match self.store().get_user(&credential.rp_id, &user_id).await {
+            Some(user) => user,
+            None => PublicKeyCredentialUserEntity {
+                id: user_id,
+                name: None,
+                display_name: None,
+                icon_url: None,
+            },
+        };
+        Response {
+            user: Some(user),
+            credential_id: Some(PublicKeyCredentialDescriptor {
+                ty: PublicKeyCredentialType::PublicKey,
+                id: credential.credential_id,
+                transports: None,
+            }),
+            public_key: Some(public_key_of(&credential.key)),
+            cred_protect: extensions.cred_protect,
+            large_blob_key: extensions.large_blob_key,
+            ..Default::default()
+        }
+    }
+}
+
+/// The public key of a credential, which is its private COSE key without the private parameters.
+fn public_key_of(private: &CoseKey) -> CoseKey {
+    let is_public_parameter: fn(i64) -> bool = match private.kty {
+        RegisteredLabel::Assigned(iana::KeyType::RSA) => |label| {
+            label == iana::RsaKeyParameter::N.to_i64() || label == iana::RsaKeyParameter::E.to_i64()
+        },
+        // The private key `d` has the same label for EC2 and OKP keys.
+        _ => |label| label != iana::Ec2KeyParameter::D.to_i64(),
+    };
+    let mut public = private.clone();
+    public
+        .params
+        .retain(|(label, _)| matches!(label, Label::Int(label) if is_public_parameter(*label)));
+    public
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        crypto::sha256,
+        ctap2::{
+            credential_management::{Request, Response, SubCommand, SubCommandParams},
+            make_credential, Aaguid, Ctap2Error,
+        },
+        rand::random_vec,
+        webauthn, Bytes,
+    };
+
+    use crate::{Authenticator, MemoryStore, MockUserValidationMethod};
+
+    fn make_credential_request(rp_id: &str) -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: random_vec(32).into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: rp_id.into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: random_vec(16).into(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: true,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    fn request(sub_command: SubCommand, params: Option<SubCommandParams>) -> Request {
+        Request {
+            sub_command,
+            sub_command_params: params,
+            pin_uv_auth_protocol: None,
+            pin_uv_auth_param: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn credentials_are_enumerated_by_relying_party_and_deleted() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(3),
+        );
+        authenticator.set_max_discoverable_credentials(Some(10));
+        let mut credential_ids = Vec::new();
+        for rp_id in [
+            "1password.com",
+            "future.1password.com",
+            "future.1password.com",
+        ] {
+            let response = authenticator
+                .make_credential(make_credential_request(rp_id))
+                .await
+                .expect("failed to create a credential");
+            let credential_id = response
+                .auth_data
+                .attested_credential_data
+                .expect("missing attested credential data")
+                .credential_id()
+                .to_vec();
+            credential_ids.push(Bytes::from(credential_id));
+        }
+
+        // Act
+        let metadata = authenticator
+            .credential_management(request(SubCommand::GetCredsMetadata, None))
+            .await
+            .expect("failed to get the metadata");
+        let first_rp = authenticator
+            .credential_management(request(SubCommand::EnumerateRpsBegin, None))
+            .await
+            .expect("failed to begin the enumeration of relying parties");
+        let second_rp = authenticator
+            .credential_management(request(SubCommand::EnumerateRpsGetNextRp, None))
+            .await
+            .expect("failed to get the next relying party");
+        let no_more_rps = authenticator
+            .credential_management(request(SubCommand::EnumerateRpsGetNextRp, None))
+            .await;
+        let credentials_params = SubCommandParams {
+            rp_id_hash: Some(sha256(b"future.1password.com").to_vec().into()),
+            ..Default::default()
+        };
+        let first_credential = authenticator
+            .credential_management(request(
+                SubCommand::EnumerateCredentialsBegin,
+                Some(credentials_params.clone()),
+            ))
+            .await
+            .expect("failed to begin the enumeration of credentials");
+        let second_credential = authenticator
+            .credential_management(request(
+                SubCommand::EnumerateCredentialsGetNextCredential,
+                None,
+            ))
+            .await
+            .expect("failed to get the next credential");
+        let deleted_id = first_credential.credential_id.clone().unwrap();
+        authenticator
+            .credential_management(request(
+                SubCommand::DeleteCredential,
+                Some(SubCommandParams {
+                    credential_id: Some(deleted_id.clone()),
+                    ..Default::default()
+                }),
+            ))
+            .await
+            .expect("failed to delete the credential");
+        let remaining_credentials = authenticator
+            .credential_management(request(
+                SubCommand::EnumerateCredentialsBegin,
+                Some(credentials_params),
+            ))
+            .await
+            .expect("failed to enumerate the remaining credentials");
+        let metadata_after_delete = authenticator
+            .credential_management(request(SubCommand::GetCredsMetadata, None))
+            .await
+            .expect("failed to get the metadata");
+
+        // Assert
+        let options = authenticator.get_info().await.options.unwrap();
+        assert_eq!(options.cred_mgmt, Some(true));
+        assert_eq!(metadata.existing_resident_credentials_count, Some(3));
+        assert_eq!(
+            metadata.max_possible_remaining_resident_credentials_count,
+            Some(7)
+        );
+        assert_eq!(first_rp.total_rps, Some(2));
+        assert_eq!(first_rp.rp.unwrap().id, "1password.com");
+        assert_eq!(
+            first_rp.rp_id_hash.as_deref().map(Vec::as_slice),
+            Some(&sha256(b"1password.com")[..])
+        );
+        assert_eq!(second_rp.total_rps, None);
+        assert_eq!(second_rp.rp.unwrap().id, "future.1password.com");
+        assert_eq!(no_more_rps, Err(Ctap2Error::NotAllowed.into()));
+
+        assert_eq!(first_credential.total_credentials, Some(2));
+        assert!(first_credential.public_key.is_some());
+        let user = first_credential.user.unwrap();
+        assert_eq!(user.display_name.as_deref(), Some("wendy"));
+        let enumerated = [
+            deleted_id.id.clone(),
+            second_credential.credential_id.unwrap().id,
+        ];
+        assert!(enumerated.contains(&credential_ids[1]));
+        assert!(enumerated.contains(&credential_ids[2]));
+
+        assert!(!authenticator.store().contains_key(&*deleted_id.id));
+        assert_eq!(remaining_credentials.total_credentials, Some(1));
+        assert_ne!(remaining_credentials.credential_id, Some(deleted_id));
+        assert_eq!(
+            metadata_after_delete,
+            Response {
+                existing_resident_credentials_count: Some(2),
+                max_possible_remaining_resident_credentials_count: Some(8),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use ciborium::Value;
+use coset::{iana, CborSerializable, CoseKey};
+use passkey_types::{
+    ctap2::{Aaguid, AttestedCredentialData, AuthenticatorData, Ctap2Error},
+    webauthn::{
+        AttestationConveyancePreference, AttestationStatementFormatIdentifiers,
+        AuthenticationExtensionsDevicePublicKeyInputs,
+    },
+};
+
+use crate::{
+    private_key_from_cose_key, AttestationInput, Authenticator, CoseKeyPair, CredentialSigningKey,
+    CredentialStore, NoneAttestation, UserValidationMethod,
+};
+
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore,
+    U: UserValidationMethod,
+{
+    /// Set the private key of this device for the credential with the given ID, used by the
+    /// `devicePubKey` extension. This lets the keys generated by the authenticator, read with
+    /// [`Self::device_key`], be restored from a storage that is local to this device.
+    ///
+    /// The device keys are only kept in memory and are never saved along with the credentials,
+    /// which may be synced to other devices.
+    pub fn set_device_key(&mut self, credential_id: &[u8], key: CoseKey) {
+        self.device_keys.insert(credential_id.to_vec(), key);
+    }
+
+    /// Get the private key of this device for the credential with the given ID, if the
+    /// `devicePubKey` extension was processed with it.
+    pub fn device_key(&self, credential_id: &[u8]) -> Option<&CoseKey> {
+        self.device_keys.get(credential_id)
+    }
+
+    /// The output of the `devicePubKey` extension for a ceremony of `rp_id` with the credential
+    /// with the given ID, which generates the key of this device for the credential on first use.
+    ///
+    /// The device key is attested like a new credential, by the configured
+    /// [`AttestationStatementProvider`](crate::AttestationStatementProvider), unless the `none`
+    /// attestation was requested. The statement is then made over authenticator data of `rp_id`
+    /// with a zero counter, whose attested credential data holds the device public key under the
+    /// credential ID, followed by the client data hash.
+    pub(super) async fn device_pub_key_extension_output(
+        &mut self,
+        rp_id: &str,
+        credential_id: &[u8],
+        client_data_hash: &[u8],
+        inputs: &AuthenticationExtensionsDevicePublicKeyInputs,
+    ) -> Result<Value, Ctap2Error> {
+        let private_key = self
+            .device_keys
+            .entry(credential_id.to_vec())
+            .or_insert_with(|| CoseKeyPair::generate(iana::Algorithm::ES256).private)
+            .clone();
+        let public_key = CoseKeyPair::from_secret_key(
+            &private_key_from_cose_key(&private_key)?,
+            iana::Algorithm::ES256,
+        )
+        .public;
+        let dpk = public_key
+            .clone()
+            .to_vec()
+            .map_err(|_| Ctap2Error::InvalidCbor)?;
+
+        let mut signed_data = client_data_hash.to_vec();
+        signed_data.extend_from_slice(credential_id);
+        let sig = CredentialSigningKey::from_cose_key(&private_key)?.sign(&signed_data);
+
+        // Like the attestation of new credentials, no statement is generated when only the `none`
+        // format is accepted.
+        let none_only = inputs.attestation == AttestationConveyancePreference::None
+            || inputs.attestation_formats == [AttestationStatementFormatIdentifiers::None];
+        // Self attestation must not identify the authenticator by its AAGUID.
+        let aaguid = if !none_only && self.attestation.is_self_attestation() {
+            Aaguid::new_empty()
+        } else {
+            self.aaguid
+        };
+        let (fmt, att_stmt) = if none_only {
+            NoneAttestation::statement()
+        } else {
+            let acd = AttestedCredentialData::new(aaguid, credential_id.to_vec(), public_key)
+                .map_err(|_| Ctap2Error::InvalidCredential)?;
+            let auth_data = AuthenticatorData::new(rp_id, None).set_attested_credential_data(acd);
+            self.attestation
+                .attestation_statement(AttestationInput {
+                    auth_data: &auth_data,
+                    client_data_hash,
+                    credential_key: &private_key,
+                    formats_preference: Some(&inputs.attestation_formats)
+                        .filter(|formats| !formats.is_empty())
+                        .map(Vec::as_slice),
+                    transports: &self.transports,
+                    enterprise_attestation: false,
+                })
+                .await?
+        };
+
+        let attestation = Value::Map(vec![
+            (Value::Text("sig".into()), Value::Bytes(sig.into())),
+            (
+                Value::Text("aaguid".into()),
+                Value::Bytes(aaguid.0.to_vec()),
+            ),
+            (Value::Text("dpk".into()), Value::Bytes(dpk)),
+            // The key is scoped to the whole device rather than to the RP ID.
+            (Value::Text("scope".into()), Value::from(0)),
+            (Value::Text("nonce".into()), Value::Bytes(Vec::new())),
+            (Value::Text("fmt".into()), Value::Text(fmt)),
+            (Value::Text("attStmt".into()), att_stmt),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut encoded)
+            .map_err(|_| Ctap2Error::InvalidCbor)?;
+        Ok(Value::Bytes(encoded))
+    }
+}
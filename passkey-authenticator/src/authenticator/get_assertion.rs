@@ -1,16 +1,24 @@
-use p256::ecdsa::{signature::SignerMut, SigningKey};
+use ciborium::Value;
+use hmac::{Hmac, Mac};
 use passkey_types::{
     ctap2::{
-        get_assertion::{Request, Response},
-        AuthenticatorData, Ctap2Error, StatusCode,
+        get_assertion::{HmacSecretInput, Request, Response, UnsignedExtensionOutputs},
+        Ctap2Error, Flags, StatusCode, U2FError,
     },
-    webauthn::PublicKeyCredentialUserEntity,
-    Passkey,
+    webauthn::{
+        AuthenticationExtensionsPrfValues, CredentialProtectionPolicy,
+        PublicKeyCredentialDescriptor, PublicKeyCredentialType, PublicKeyCredentialUserEntity,
+    },
+    Bytes, CredRandom, Passkey,
 };
+use sha2::Sha256;
+use std::{collections::VecDeque, time::SystemTime};
 
+use super::extensions_in_canonical_order;
 use crate::{
-    private_key_from_cose_key, user_validation::UIHint, Authenticator, CredentialStore,
-    UserValidationMethod,
+    pin_protocol::{KeyAgreementKey, PROTOCOL_ONE},
+    user_validation::UIHint,
+    Authenticator, CounterMode, CredentialSigningKey, CredentialStore, UserValidationMethod,
 };
 
 impl<S: CredentialStore + Sync, U> Authenticator<S, U>
@@ -22,27 +30,87 @@ where
     /// This method is used by a host to request cryptographic proof of user authentication as well
     /// as user consent to a given transaction, using a previously generated credential that is
     /// bound to the authenticator and relying party identifier.
-    pub async fn get_assertion(&mut self, input: Request) -> Result<Response, StatusCode> {
+    pub async fn get_assertion(&mut self, mut input: Request) -> Result<Response, StatusCode> {
+        // A new assertion forgets the credentials and the options left by the previous one.
+        self.pending_assertions = None;
+        self.effective_options = None;
+
+        // The `alwaysUv` option verifies the user even when it was not requested.
+        if self.always_uv {
+            input.options.uv = true;
+        }
+
+        // NB: The pinAuth parameter is verified before locating the credentials of step 1, since
+        // the user verification decides which ones are eligible. See step 2.
+        let pin_verified = self.verify_pin_auth(
+            input.pin_auth.as_ref(),
+            input.pin_protocol,
+            &input.client_data_hash,
+        )?;
+        if pin_verified {
+            input.options.uv = false;
+        } else if self.is_pin_set() && !input.options.uv {
+            return Err(Ctap2Error::PuatRequired.into());
+        }
+
         // 1. Locate all credentials that are eligible for retrieval under the specified criteria:
         //     1. If an allowList is present and is non-empty, locate all denoted credentials
         //        present on this authenticator and bound to the specified rpId.
         //     2. If an allowList is not present, locate all credentials that are present on this
         //        authenticator and bound to the specified rpId.
         //     3. Let numberOfCredentials be the number of credentials found.
-        //        --> Seeing as we handle 1 credential per account for an RP, returning the number
-        //            of credentials leaks the number of accounts that is stored. This is not ideal,
-        //            therefore we will never populate this field.
-        let maybe_credential = self
-            .store()
-            .find_credentials(
-                input
-                    .allow_list
-                    .as_deref()
-                    .filter(|inner| !inner.is_empty()),
-                &input.rp_id,
-            )
-            .await
-            .and_then(|c| c.into_iter().next().ok_or(Ctap2Error::NoCredentials.into()));
+        //        --> Returning the number of discoverable credentials leaks the number of accounts
+        //            that is stored, therefore it is only populated for the credentials of an allow
+        //            list, whose IDs the relying party already knows. See step 10.
+        //     4. Remove the credentials whose credProtect policy is not satisfied when the user is
+        //        not verified: `userVerificationOptionalWithCredentialIDList` credentials must be
+        //        listed in the allowList, and `userVerificationRequired` credentials are removed.
+        let allow_list = input
+            .allow_list
+            .as_deref()
+            .filter(|inner| !inner.is_empty());
+        // NB: Without an allow list, every discoverable credential of the RP is a candidate.
+        let found = match allow_list {
+            Some(_) => {
+                self.store()
+                    .find_credentials(allow_list, &input.rp_id)
+                    .await
+            }
+            None => self.store().list_credentials(Some(&input.rp_id)).await,
+        };
+        // NB: Credentials that were disabled in the store are not eligible, they are skipped
+        // rather than removed so that they can be enabled again later.
+        let mut next_credentials = Vec::new();
+        let maybe_eligible = match found {
+            Ok(credentials) => {
+                let mut eligible = Vec::new();
+                for credential in credentials {
+                    if self.store().is_credential_disabled(&credential).await {
+                        log::info!("Skipping a disabled credential");
+                        continue;
+                    }
+                    let policy = self
+                        .store()
+                        .credential_extensions(&credential)
+                        .await
+                        .cred_protect;
+                    if !is_protection_satisfied(
+                        policy,
+                        allow_list.is_some(),
+                        input.options.uv || pin_verified,
+                    ) {
+                        log::info!("Skipping a credential protected by credProtect");
+                        continue;
+                    }
+                    eligible.push(credential);
+                }
+                if allow_list.is_some() && eligible.len() > 1 {
+                    next_credentials = eligible.split_off(1);
+                }
+                Ok(eligible)
+            }
+            Err(err) => Err(err),
+        };
 
         // 2. If pinAuth parameter is present and pinProtocol is 1, verify it by matching it against
         //    first 16 bytes of HMAC-SHA-256 of clientDataHash parameter using
@@ -53,9 +121,8 @@ where
         //    return CTAP2_ERR_PIN_AUTH_INVALID.
         // 4. If pinAuth parameter is not present and clientPin has been set on the authenticator,
         //    set the "uv" bit to 0 in the response.
-        if input.pin_auth.is_some() {
-            return Err(Ctap2Error::PinAuthInvalid.into());
-        }
+        // --> The pinAuth parameter was verified at the beginning, the "uv" bit is set along with
+        //     the user consent of step 7.
 
         // 5. If the options parameter is present, process all the options.
         //     1. If the option is known but not supported, terminate this procedure and
@@ -72,21 +139,43 @@ where
             return Err(Ctap2Error::UnsupportedOption.into());
         }
 
-        // 6. TODO, if the extensions parameter is present, process any extensions that this
+        // 6. If the extensions parameter is present, process any extensions that this
         //    authenticator supports. Authenticator extension outputs generated by the authenticator
         //    extension processing are returned in the authenticator data.
+        // NB: The `prf` inputs can only be evaluated once the credential and the user verification
+        // are known, see after step 8. The `largeBlobKey` extension is only valid with value `true`.
+        let large_blob_key = input.extensions.as_ref().and_then(|ext| ext.large_blob_key);
+        if large_blob_key == Some(false) {
+            return Err(Ctap2Error::InvalidOption.into());
+        }
 
         // 7. Collect user consent if required. This step MUST happen before the following steps due
         //    to privacy reasons (i.e., authenticator cannot disclose existence of a credential
         //    until the user interacted with the device):
-        let hint = match &maybe_credential {
-            Ok(credential) => UIHint::RequestExistingCredential(credential),
-            Err(_) => UIHint::InformNoCredentialsFound,
+        // NB: When several discoverable credentials were found, the user consents with the first
+        // one before selecting the credential to assert with in step 11.
+        let hint = match maybe_eligible.as_deref() {
+            Ok([credential, ..]) => UIHint::RequestExistingCredential(credential),
+            _ => UIHint::InformNoCredentialsFound,
+        };
+        self.set_effective_options(&input.options, pin_verified);
+        let mut flags = self.check_user(hint, &input.options).await?;
+        if pin_verified {
+            flags |= Flags::UV;
+        }
+        let uvm = if input.extensions.as_ref().and_then(|ext| ext.uvm) == Some(true) {
+            self.uvm_extension_output(flags, pin_verified).await
+        } else {
+            None
         };
-        let flags = self.check_user(hint, &input.options).await?;
 
         // 8. If no credentials were located in step 1, return CTAP2_ERR_NO_CREDENTIALS.
-        let mut credential = maybe_credential?
+        // NB: The credential is selected here, see step 11, since the extensions below are
+        // evaluated for the credential that is asserted.
+        let credential = self
+            .select_eligible_credential(maybe_eligible?, allow_list.is_none())
+            .await?
+            .ok_or(Ctap2Error::NoCredentials)?
             .try_into()
             .ok()
             .ok_or(Ctap2Error::NoCredentials)?;
@@ -108,6 +197,8 @@ where
         //        information and numberOfCredentials. User identifiable information (name,
         //        DisplayName, icon) inside publicKeyCredentialUserEntity MUST not be returned if
         //        user verification is not done by the authenticator.
+        // NB: The other credentials of the allow list are remembered for `get_next_assertion`,
+        // without a timer: they are only forgotten by the next `get_assertion` or once exhausted.
 
         // 11. If authenticator has a display:
         //     1. Display all these credentials to the user, using their friendly name along with
@@ -117,16 +208,194 @@ where
         //     3. If the user declines to select a credential or takes too long (as determined by
         //        the authenticator), terminate this procedure and return the
         //        CTAP2_ERR_OPERATION_DENIED error.
+        // NB: The discoverable credentials found without an allow list were already presented
+        // to the user through `UserValidationMethod::select_credential` in step 8, once the user
+        // consented in step 7.
+        let mut response = self
+            .assert_with_credential(&input, flags, uvm.clone(), credential)
+            .await?;
+
+        // NB: `numberOfCredentials` is a single byte, which bounds the credentials that are
+        // asserted from one allow list.
+        let credential_ids: VecDeque<Bytes> = next_credentials
+            .into_iter()
+            .filter_map(|credential| credential.try_into().ok())
+            .map(|credential: Passkey| credential.credential_id)
+            .take(usize::from(u8::MAX - 1))
+            .collect();
+        if !credential_ids.is_empty() {
+            response.number_of_credentials =
+                Some(u8::try_from(credential_ids.len() + 1).unwrap_or(u8::MAX));
+            self.pending_assertions = Some(PendingAssertions {
+                request: input,
+                flags,
+                uvm,
+                credential_ids,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// This method is used by the host to get the assertion of the next credential located by the
+    /// preceding [`Self::get_assertion`], when its response reported more than one credential in
+    /// `numberOfCredentials`. The user is not checked again: the assertion carries the flags of
+    /// the first one.
+    ///
+    /// Fails with [`Ctap2Error::NotAllowed`] when there is no such credential left.
+    pub async fn get_next_assertion(&mut self) -> Result<Response, StatusCode> {
+        // 1. If authenticator does not remember any authenticatorGetAssertion parameters, return
+        //    CTAP2_ERR_NOT_ALLOWED.
+        // 2. If the credentialCounter is equal to or greater than numberOfCredentials, return
+        //    CTAP2_ERR_NOT_ALLOWED.
+        let Some(mut pending) = self.pending_assertions.take() else {
+            return Err(Ctap2Error::NotAllowed.into());
+        };
+        let Some(credential_id) = pending.credential_ids.pop_front() else {
+            return Err(Ctap2Error::NotAllowed.into());
+        };
+
+        // 3. If timer since the last call to authenticatorGetAssertion/authenticatorGetNextAssertion
+        //    is greater than 30 seconds, discard the current authenticatorGetAssertion state and
+        //    return CTAP2_ERR_NOT_ALLOWED. This step is optional if transport is done over NFC.
+        // NB: No timer is kept, see step 10 of `get_assertion`.
+
+        // 4. Select the credential indexed by credentialCounter.
+        // NB: The credential is located again so that it reflects any update made meanwhile, such
+        // as the signature counter of the previous assertion in `CounterMode::Global`.
+        let descriptor = PublicKeyCredentialDescriptor {
+            ty: PublicKeyCredentialType::PublicKey,
+            id: credential_id,
+            transports: None,
+        };
+        let credential: Passkey = self
+            .store()
+            .find_credentials(Some(&[descriptor]), &pending.request.rp_id)
+            .await?
+            .into_iter()
+            .next()
+            .and_then(|credential| credential.try_into().ok())
+            .ok_or(Ctap2Error::NoCredentials)?;
+
+        // 5. Update the response to include the selected credential’s
+        //    publicKeyCredentialUserEntity information.
+        // 6. Sign the clientDataHash along with authData with the selected credential.
+        let response = self
+            .assert_with_credential(
+                &pending.request,
+                pending.flags,
+                pending.uvm.clone(),
+                credential,
+            )
+            .await?;
+
+        // 7. Reset the timer and increment credentialCounter.
+        if !pending.credential_ids.is_empty() {
+            self.pending_assertions = Some(pending);
+        }
+
+        Ok(response)
+    }
+
+    /// Build the assertion of the given credential, once the user was checked with the resulting
+    /// `flags` and the `uvm` extension output, updating the credential's signature counter and
+    /// time of use.
+    async fn assert_with_credential(
+        &mut self,
+        input: &Request,
+        flags: Flags,
+        uvm: Option<Value>,
+        mut credential: Passkey,
+    ) -> Result<Response, StatusCode> {
+        // 6. The `prf` salts selected for this credential are evaluated with the secret matching
+        //    whether the user was verified. Credentials created without `hmac-secret` give no results,
+        //    and neither do any credentials when the extension is disabled.
+        let prf_results = match (
+            input.extensions.as_ref().and_then(|ext| ext.prf.as_ref()),
+            credential
+                .extensions
+                .cred_random
+                .as_ref()
+                .filter(|_| self.hmac_secret),
+        ) {
+            (Some(prf), Some(cred_random)) => prf
+                .values_for_credential(&credential.credential_id)
+                .map(|salts| evaluate_hmac_secret(cred_random, flags, salts))
+                .transpose()?,
+            _ => None,
+        };
+        let hmac_secret_output = match (
+            input
+                .extensions
+                .as_ref()
+                .and_then(|ext| ext.hmac_secret.as_ref()),
+            credential
+                .extensions
+                .cred_random
+                .as_ref()
+                .filter(|_| self.hmac_secret),
+        ) {
+            (Some(hmac_secret), Some(cred_random)) => Some(evaluate_encrypted_hmac_secret(
+                &self.key_agreement,
+                cred_random,
+                flags,
+                hmac_secret,
+            )?),
+            _ => None,
+        };
+
+        // NB: Fail before updating the signature counter when this build cannot sign with the
+        // credential's algorithm, or when its stored key is malformed, so that the stored
+        // credential is left untouched.
+        let mut signing_key =
+            CredentialSigningKey::from_cose_key(&credential.key).inspect_err(|error| {
+                log::warn!("Cannot sign with the stored key of the credential: {error:?}");
+            })?;
+
+        // NB: A stored counter lower than the last one emitted means that the stored credential
+        // was rolled back, which is refused before it is updated when detection is enabled.
+        if self.counter_rollback_detection && self.counter_mode == CounterMode::PerCredential {
+            if let (Some(counter), Some(&emitted)) = (
+                credential.counter,
+                self.emitted_signature_counters
+                    .get(&*credential.credential_id),
+            ) {
+                if counter < emitted {
+                    log::warn!("Refusing a credential whose signature counter was rolled back");
+                    return Err(Ctap2Error::InvalidCredential.into());
+                }
+            }
+        }
 
         // [WebAuthn-9]. Increment the credential associated signature counter or the global signature
         //               counter value, depending on which approach is implemented by the authenticator,
         //               by some positive value. If the authenticator does not implement a signature
         //               counter, let the signature counter value remain constant at zero.
-        if let Some(counter) = credential.counter {
-            credential.counter = Some(counter + 1);
-            self.store_mut()
-                .update_credential(credential.clone())
-                .await?;
+        // NB: The counters stop at their maximum value rather than wrapping around to zero. The
+        // time of use is recorded along with the counter, so the credential is updated even when it
+        // has no counter.
+        let counter = match self.counter_mode {
+            CounterMode::Global => {
+                self.global_signature_counter = self.global_signature_counter.saturating_add(1);
+                Some(self.global_signature_counter)
+            }
+            CounterMode::PerCredential => {
+                if let Some(counter) = credential.counter {
+                    credential.counter = Some(counter.saturating_add(1));
+                }
+                credential.counter
+            }
+            CounterMode::Disabled => None,
+        };
+        credential.last_used_at = Some(SystemTime::now());
+        self.store_mut()
+            .update_credential(credential.clone())
+            .await?;
+        if self.counter_rollback_detection && self.counter_mode == CounterMode::PerCredential {
+            if let Some(counter) = credential.counter {
+                self.emitted_signature_counters
+                    .insert(credential.credential_id.to_vec(), counter);
+            }
         }
 
         // 12. Sign the clientDataHash along with authData with the selected credential.
@@ -135,18 +404,45 @@ where
         //      concatenation is safe to use here because the authenticator data describes its own
         //      length. The hash of the serialized client data (which potentially has a variable
         //      length) is always the last element.
-        let auth_data = AuthenticatorData::new(&input.rp_id, credential.counter).set_flags(flags);
+        let mut auth_data = self
+            .authenticator_data(&input.rp_id, counter)
+            .set_flags(flags);
+        let mut extension_outputs = Vec::new();
+        if let Some(output) = hmac_secret_output {
+            extension_outputs.push((Value::Text("hmac-secret".into()), Value::Bytes(output)));
+        }
+        if let Some(uvm) = uvm {
+            extension_outputs.push((Value::Text("uvm".into()), uvm));
+        }
+        if let Some(device_pub_key) = input
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.device_pub_key.as_ref())
+        {
+            let output = self
+                .device_pub_key_extension_output(
+                    &input.rp_id,
+                    &credential.credential_id,
+                    &input.client_data_hash,
+                    device_pub_key,
+                )
+                .await?;
+            extension_outputs.push((Value::Text("devicePubKey".into()), output));
+        }
+        if !extension_outputs.is_empty() {
+            auth_data = auth_data.set_extensions(extensions_in_canonical_order(extension_outputs));
+        }
         let mut signature_target = auth_data.to_vec();
-        signature_target.extend(input.client_data_hash);
-
-        let secret_key = private_key_from_cose_key(&credential.key)?;
-
-        let mut private_key = SigningKey::from(secret_key);
+        signature_target.extend_from_slice(&input.client_data_hash);
 
-        let signature: p256::ecdsa::Signature = private_key.sign(&signature_target);
-        let signature_bytes = signature.to_der().to_bytes().to_vec().into();
+        let signature_bytes = signing_key.sign(&signature_target);
 
         let user_handle = credential.user_handle.clone();
+        let large_blob_key = input
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.large_blob_key)
+            .and(credential.extensions.large_blob_key.clone());
 
         Ok(Response {
             credential: Some(credential.into()),
@@ -160,22 +456,168 @@ where
                 name: "".into(),
             }),
             number_of_credentials: None,
+            large_blob_key,
+            unsigned_extension_outputs: prf_results
+                .map(|results| UnsignedExtensionOutputs { prf: Some(results) }),
         })
     }
+
+    /// Record that the credential with the given ID was used, by updating its
+    /// [`Passkey::last_used_at`] without signing anything nor incrementing its signature counter.
+    /// This lets an application record that the user viewed a credential separately from the
+    /// assertions made with it.
+    ///
+    /// Fails with [`Ctap2Error::NoCredentials`] when the credential is not stored for `rp_id`.
+    pub async fn touch_credential(
+        &mut self,
+        rp_id: &str,
+        credential_id: &[u8],
+    ) -> Result<(), StatusCode> {
+        let descriptor = PublicKeyCredentialDescriptor {
+            ty: PublicKeyCredentialType::PublicKey,
+            id: credential_id.to_vec().into(),
+            transports: None,
+        };
+        let mut credential: Passkey = self
+            .store()
+            .find_credentials(Some(&[descriptor]), rp_id)
+            .await?
+            .into_iter()
+            .next()
+            .and_then(|credential| credential.try_into().ok())
+            .ok_or(Ctap2Error::NoCredentials)?;
+
+        credential.last_used_at = Some(SystemTime::now());
+        self.store_mut().update_credential(credential).await
+    }
+
+    /// Pick the credential to assert with among the eligible ones, letting the user select it when
+    /// several discoverable credentials were found. Returns `None` when none are eligible, and
+    /// fails when the user declines to select one.
+    async fn select_eligible_credential(
+        &self,
+        mut eligible: Vec<S::PasskeyItem>,
+        discoverable: bool,
+    ) -> Result<Option<S::PasskeyItem>, Ctap2Error> {
+        let index = if discoverable && eligible.len() > 1 {
+            self.user_validation.select_credential(&eligible).await?
+        } else {
+            0
+        };
+        Ok((index < eligible.len()).then(|| eligible.swap_remove(index)))
+    }
+}
+
+/// The assertion parameters and the remaining credentials of an allow list, which are asserted one
+/// by one by [`Authenticator::get_next_assertion`].
+pub(super) struct PendingAssertions {
+    request: Request,
+    flags: Flags,
+    uvm: Option<Value>,
+    credential_ids: VecDeque<Bytes>,
+}
+
+/// Whether a credential with the given `credProtect` policy can be used by an assertion, knowing
+/// whether its ID was given in a non-empty allow list and whether the user is verified.
+fn is_protection_satisfied(
+    policy: Option<CredentialProtectionPolicy>,
+    in_allow_list: bool,
+    user_verified: bool,
+) -> bool {
+    match policy {
+        None | Some(CredentialProtectionPolicy::UserVerificationOptional) => true,
+        Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList) => {
+            in_allow_list || user_verified
+        }
+        Some(CredentialProtectionPolicy::UserVerificationRequired) => user_verified,
+    }
+}
+
+/// Evaluate the `hmac-secret` extension with salts encrypted by the platform, returning the
+/// results encrypted with the same shared secret.
+fn evaluate_encrypted_hmac_secret(
+    key_agreement: &KeyAgreementKey,
+    cred_random: &CredRandom,
+    flags: Flags,
+    input: &HmacSecretInput,
+) -> Result<Vec<u8>, StatusCode> {
+    if input.pin_uv_auth_protocol.unwrap_or(PROTOCOL_ONE) != PROTOCOL_ONE {
+        return Err(U2FError::InvalidParameter.into());
+    }
+    let shared_secret = key_agreement.shared_secret(&input.key_agreement)?;
+    shared_secret.verify(&input.salt_enc, &input.salt_auth)?;
+
+    let salts = shared_secret.decrypt(&input.salt_enc)?;
+    let salts = match salts.len() {
+        32 => AuthenticationExtensionsPrfValues {
+            first: salts.into(),
+            second: None,
+        },
+        64 => AuthenticationExtensionsPrfValues {
+            first: salts[..32].to_vec().into(),
+            second: Some(salts[32..].to_vec().into()),
+        },
+        _ => return Err(U2FError::InvalidLength.into()),
+    };
+    let outputs = evaluate_hmac_secret(cred_random, flags, &salts)?;
+
+    let mut plaintext = outputs.first.to_vec();
+    if let Some(second) = outputs.second {
+        plaintext.extend_from_slice(&second);
+    }
+    shared_secret.encrypt(&plaintext)
+}
+
+/// Compute `HMAC-SHA-256(CredRandom, salt)` for each of the 32 byte salts, using the secret
+/// matching whether the user was verified.
+fn evaluate_hmac_secret(
+    cred_random: &CredRandom,
+    flags: Flags,
+    salts: &AuthenticationExtensionsPrfValues,
+) -> Result<AuthenticationExtensionsPrfValues, StatusCode> {
+    let secret = if flags.contains(Flags::UV) {
+        &cred_random.with_uv
+    } else {
+        &cred_random.without_uv
+    };
+    let hmac = |salt: &Bytes| -> Result<Bytes, StatusCode> {
+        if salt.len() != 32 {
+            return Err(U2FError::InvalidLength.into());
+        }
+        // SAFETY: HMAC accepts keys of any length.
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(salt);
+        Ok(mac.finalize().into_bytes().to_vec().into())
+    };
+
+    Ok(AuthenticationExtensionsPrfValues {
+        first: hmac(&salts.first)?,
+        second: salts.second.as_ref().map(hmac).transpose()?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use coset::{CborSerializable, CoseKey};
+    use coset::{iana, iana::EnumI64, CborSerializable, CoseKey, CoseKeyBuilder};
     use passkey_types::{
         ctap2::{
-            get_assertion::{Options, Request},
-            Aaguid, Ctap2Error,
+            get_assertion::{ExtensionInputs, HmacSecretInput, Options, Request, Response},
+            Aaguid, AuthenticatorData, Ctap2Error, DevicePublicKeyAttestation, Flags, U2FError,
+        },
+        webauthn::{
+            AuthenticationExtensionsPrfValues, CredentialProtectionPolicy,
+            PublicKeyCredentialDescriptor, PublicKeyCredentialType,
         },
-        Passkey,
+        Bytes, CredRandom, CredentialExtensions, Passkey,
     };
 
-    use crate::{user_validation::MockUIHint, Authenticator, MockUserValidationMethod};
+    use super::evaluate_hmac_secret;
+    use crate::{
+        pin_protocol::{KeyAgreementKey, SharedSecret},
+        user_validation::MockUIHint,
+        Authenticator, CounterMode, CredentialStore, MemoryStore, MockUserValidationMethod,
+        RecordingUserValidation, UserCheck, UserValidationCall,
+    };
 
     fn create_passkey() -> Passkey {
         Passkey {
@@ -184,6 +626,10 @@ mod tests {
             rp_id: "example.com".into(),
             user_handle: None,
             counter: None,
+            disabled: false,
+            transports: None,
+            extensions: Default::default(),
+            last_used_at: None,
         }
     }
 
@@ -270,4 +716,933 @@ mod tests {
             9001
         );
     }
+
+    #[tokio::test]
+    async fn per_credential_counters_increment_with_consecutive_assertions() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                counter: Some(0),
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(2),
+        );
+
+        // Act
+        let first = authenticator.get_assertion(good_request()).await.unwrap();
+        let second = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        assert_eq!(authenticator.counter_mode(), CounterMode::PerCredential);
+        assert_eq!(first.auth_data.counter, Some(1));
+        assert_eq!(second.auth_data.counter, Some(2));
+        assert_eq!(authenticator.store().as_ref().unwrap().counter, Some(2));
+    }
+
+    #[tokio::test]
+    async fn assertions_are_refused_once_the_stored_counter_is_rolled_back() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                counter: Some(10),
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(3),
+        );
+        authenticator.set_counter_rollback_detection(true);
+        let first = authenticator.get_assertion(good_request()).await.unwrap();
+        // Restore the credential as it was stored before the assertion, as a stale copy would.
+        authenticator.store_mut().as_mut().unwrap().counter = Some(10);
+
+        // Act
+        let rolled_back = authenticator.get_assertion(good_request()).await;
+        authenticator.set_counter_rollback_detection(false);
+        let undetected = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(first.auth_data.counter, Some(11));
+        assert_eq!(
+            rolled_back.unwrap_err(),
+            Ctap2Error::InvalidCredential.into()
+        );
+        assert_eq!(authenticator.store().as_ref().unwrap().counter, Some(11));
+        assert_eq!(undetected.unwrap().auth_data.counter, Some(11));
+    }
+
+    #[tokio::test]
+    async fn global_counter_is_shared_by_every_credential() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                counter: Some(9000),
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(3),
+        );
+        authenticator.set_counter_mode(CounterMode::Global);
+
+        // Act
+        let first = authenticator.get_assertion(good_request()).await.unwrap();
+        let second = authenticator.get_assertion(good_request()).await.unwrap();
+        *authenticator.store_mut() = Some(Passkey {
+            credential_id: vec![1; 16].into(),
+            ..create_passkey()
+        });
+        let other_credential = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        assert_eq!(first.auth_data.counter, Some(1));
+        assert_eq!(second.auth_data.counter, Some(2));
+        assert_eq!(other_credential.auth_data.counter, Some(3));
+        assert_eq!(authenticator.store().as_ref().unwrap().counter, None);
+    }
+
+    #[tokio::test]
+    async fn disabled_counter_is_always_zero() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                counter: Some(9000),
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(2),
+        );
+        authenticator.set_counter_mode(CounterMode::Disabled);
+
+        // Act
+        let first = authenticator.get_assertion(good_request()).await.unwrap();
+        let second = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        assert_eq!(first.auth_data.counter, None);
+        assert_eq!(second.auth_data.counter, None);
+        assert_eq!(&second.auth_data.to_vec()[33..37], &[0; 4]);
+        assert_eq!(authenticator.store().as_ref().unwrap().counter, Some(9000));
+    }
+
+    #[tokio::test]
+    async fn discoverable_credentials_of_the_rp_are_presented_for_selection() {
+        // Arrange
+        let discoverable = |id: u8, rp_id: &str| Passkey {
+            credential_id: vec![id; 16].into(),
+            rp_id: rp_id.into(),
+            user_handle: Some(vec![id].into()),
+            ..create_passkey()
+        };
+        let store = MemoryStore::from(std::collections::HashMap::from_iter(
+            [
+                discoverable(1, "example.com"),
+                discoverable(2, "example.com"),
+                discoverable(3, "other.example.com"),
+                Passkey {
+                    credential_id: vec![4; 16].into(),
+                    ..create_passkey()
+                },
+            ]
+            .map(|pk| (pk.credential_id.to_vec(), pk)),
+        ));
+        let mut user_mock = MockUserValidationMethod::verified_user(1);
+        user_mock
+            .expect_select_credential()
+            .withf(|credentials| credentials.len() == 2)
+            .returning(|credentials| {
+                Ok(credentials
+                    .iter()
+                    .position(|pk| pk.credential_id.as_slice() == [2; 16])
+                    .unwrap())
+            })
+            .once();
+        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_mock);
+
+        // Act
+        let listed = authenticator.store().list_credentials(None).await.unwrap();
+        let response = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        assert_eq!(listed.len(), 3);
+        assert_eq!(response.credential.unwrap().id.as_slice(), [2; 16]);
+    }
+
+    #[tokio::test]
+    async fn get_assertion_is_built_from_the_selected_credential() {
+        // Arrange
+        let resident = |id: u8| Passkey {
+            credential_id: vec![id; 16].into(),
+            user_handle: Some(vec![id].into()),
+            ..create_passkey()
+        };
+        let store = MemoryStore::from(std::collections::HashMap::from_iter(
+            [resident(1), resident(2)].map(|pk| (pk.credential_id.to_vec(), pk)),
+        ));
+        let selected = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let mut user_mock = MockUserValidationMethod::verified_user(1);
+        user_mock.expect_select_credential().once().returning({
+            let selected = selected.clone();
+            move |credentials| {
+                *selected.lock().unwrap() = Some(credentials[1].clone());
+                Ok(1)
+            }
+        });
+        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_mock);
+
+        // Act
+        let response = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        let selected: Passkey = selected.lock().unwrap().take().unwrap();
+        assert_eq!(response.credential.unwrap().id, selected.credential_id);
+        assert_eq!(response.user.unwrap().id, selected.user_handle.unwrap());
+        let used: Vec<_> = authenticator
+            .store()
+            .values()
+            .filter(|pk| pk.last_used_at.is_some())
+            .map(|pk| pk.credential_id.clone())
+            .collect();
+        assert_eq!(used, vec![selected.credential_id]);
+    }
+
+    #[tokio::test]
+    async fn get_assertion_fails_when_the_user_declines_to_select_a_credential() {
+        // Arrange
+        let store = MemoryStore::from(std::collections::HashMap::from_iter([1, 2].map(|id| {
+            let pk = Passkey {
+                credential_id: vec![id; 16].into(),
+                user_handle: Some(vec![id].into()),
+                ..create_passkey()
+            };
+            (pk.credential_id.to_vec(), pk)
+        })));
+        let mut user_mock = MockUserValidationMethod::verified_user(1);
+        user_mock
+            .expect_select_credential()
+            .once()
+            .returning(|_| Err(Ctap2Error::OperationDenied));
+        let user_validation = RecordingUserValidation::new(user_mock);
+        let calls = user_validation.calls();
+        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_validation);
+
+        // Act
+        let result = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(result.unwrap_err(), Ctap2Error::OperationDenied.into());
+        // The user consents before being shown the credentials to select from.
+        let prompts: Vec<_> = calls
+            .to_vec()
+            .into_iter()
+            .filter_map(|call| match call {
+                UserValidationCall::CheckUser { hint, .. } => Some(hint),
+                UserValidationCall::SelectCredential { .. } => Some("SelectCredential"),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(prompts, ["RequestExistingCredential", "SelectCredential"]);
+    }
+
+    #[tokio::test]
+    async fn next_assertions_walk_through_every_credential_of_the_allow_list() {
+        // Arrange
+        let ids: Vec<Bytes> = (1..=3).map(|id| vec![id; 16].into()).collect();
+        let store = MemoryStore::from(std::collections::HashMap::from_iter(ids.iter().map(|id| {
+            let pk = Passkey {
+                credential_id: id.clone(),
+                counter: Some(0),
+                ..create_passkey()
+            };
+            (id.to_vec(), pk)
+        })));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            store,
+            MockUserValidationMethod::verified_user(1),
+        );
+        let request = Request {
+            allow_list: Some(
+                ids.iter()
+                    .map(|id| PublicKeyCredentialDescriptor {
+                        ty: PublicKeyCredentialType::PublicKey,
+                        id: id.clone(),
+                        transports: None,
+                    })
+                    .collect(),
+            ),
+            ..good_request()
+        };
+
+        // Act
+        let first = authenticator.get_assertion(request).await.unwrap();
+        let second = authenticator.get_next_assertion().await.unwrap();
+        let third = authenticator.get_next_assertion().await.unwrap();
+        let exhausted = authenticator.get_next_assertion().await;
+
+        // Assert
+        assert_eq!(first.number_of_credentials, Some(3));
+        assert_eq!(second.number_of_credentials, None);
+        assert_eq!(third.number_of_credentials, None);
+        let asserted: Vec<Bytes> = [first, second, third]
+            .into_iter()
+            .map(|response| {
+                assert!(response.auth_data.flags.contains(Flags::UV));
+                assert_eq!(response.auth_data.counter, Some(1));
+                response.credential.unwrap().id
+            })
+            .collect();
+        assert_eq!(asserted, ids);
+        assert_eq!(exhausted.unwrap_err(), Ctap2Error::NotAllowed.into());
+    }
+
+    #[tokio::test]
+    async fn next_assertions_are_forgotten_by_a_new_assertion() {
+        // Arrange
+        let ids: Vec<Bytes> = (1..=2).map(|id| vec![id; 16].into()).collect();
+        let store = MemoryStore::from(std::collections::HashMap::from_iter(ids.iter().map(|id| {
+            let pk = Passkey {
+                credential_id: id.clone(),
+                ..create_passkey()
+            };
+            (id.to_vec(), pk)
+        })));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            store,
+            MockUserValidationMethod::verified_user(2),
+        );
+        let allow_list = |ids: &[Bytes]| Request {
+            allow_list: Some(
+                ids.iter()
+                    .map(|id| PublicKeyCredentialDescriptor {
+                        ty: PublicKeyCredentialType::PublicKey,
+                        id: id.clone(),
+                        transports: None,
+                    })
+                    .collect(),
+            ),
+            ..good_request()
+        };
+
+        // Act
+        let before = authenticator.get_next_assertion().await;
+        let first = authenticator.get_assertion(allow_list(&ids)).await.unwrap();
+        let single = authenticator
+            .get_assertion(allow_list(&ids[..1]))
+            .await
+            .unwrap();
+        let after = authenticator.get_next_assertion().await;
+
+        // Assert
+        assert_eq!(before.unwrap_err(), Ctap2Error::NotAllowed.into());
+        assert_eq!(first.number_of_credentials, Some(2));
+        assert_eq!(single.number_of_credentials, None);
+        assert_eq!(after.unwrap_err(), Ctap2Error::NotAllowed.into());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_records_when_the_credential_was_used() {
+        // Arrange
+        let passkey = create_passkey();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey.clone()),
+            MockUserValidationMethod::verified_user_with_hint(
+                1,
+                MockUIHint::RequestExistingCredential(passkey),
+            ),
+        );
+        let before = std::time::SystemTime::now();
+
+        // Act
+        authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        let stored = authenticator.store().as_ref().unwrap();
+        assert!(stored.last_used_at.is_some_and(|used| used >= before));
+        assert_eq!(stored.counter, None);
+    }
+
+    #[tokio::test]
+    async fn touch_credential_updates_last_used_at_but_not_the_counter() {
+        // Arrange
+        let passkey = Passkey {
+            credential_id: vec![1; 16].into(),
+            counter: Some(9000),
+            ..create_passkey()
+        };
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey),
+            MockUserValidationMethod::verified_user(0),
+        );
+        let before = std::time::SystemTime::now();
+
+        // Act
+        let touched = authenticator
+            .touch_credential("example.com", &[1; 16])
+            .await;
+        let other_rp = authenticator
+            .touch_credential("other.example.com", &[1; 16])
+            .await;
+
+        // Assert
+        assert_eq!(touched, Ok(()));
+        assert_eq!(other_rp, Err(Ctap2Error::NoCredentials.into()));
+        let stored = authenticator.store().as_ref().unwrap();
+        assert!(stored.last_used_at.is_some_and(|used| used >= before));
+        assert_eq!(stored.counter, Some(9000));
+    }
+
+    fn protected_passkey(policy: CredentialProtectionPolicy) -> Passkey {
+        Passkey {
+            credential_id: vec![1; 16].into(),
+            user_handle: Some(vec![2; 16].into()),
+            extensions: CredentialExtensions {
+                cred_protect: Some(policy),
+                ..Default::default()
+            },
+            ..create_passkey()
+        }
+    }
+
+    /// A user who is only verified when it is requested.
+    fn user_mock_verified_on_request(times: usize) -> MockUserValidationMethod {
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true));
+        user_mock.expect_is_presence_enabled().returning(|| true);
+        user_mock
+            .expect_check_user()
+            .returning(|_, presence, verification| {
+                Ok(UserCheck {
+                    presence,
+                    verification,
+                })
+            })
+            .times(times);
+        user_mock
+    }
+
+    fn request_without_uv(allow_list: bool) -> Request {
+        let mut request = good_request();
+        request.options.uv = false;
+        if allow_list {
+            request.allow_list = Some(vec![protected_passkey(
+                CredentialProtectionPolicy::UserVerificationOptional,
+            )
+            .into()]);
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn get_assertion_omits_user_verification_required_credentials_without_uv() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(protected_passkey(
+                CredentialProtectionPolicy::UserVerificationRequired,
+            )),
+            user_mock_verified_on_request(3),
+        );
+
+        // Act
+        let discovered = authenticator.get_assertion(request_without_uv(false)).await;
+        let allowed = authenticator.get_assertion(request_without_uv(true)).await;
+        let verified = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(discovered.err(), Some(Ctap2Error::NoCredentials.into()));
+        assert_eq!(allowed.err(), Some(Ctap2Error::NoCredentials.into()));
+        assert!(verified.is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_requires_the_allow_list_for_credential_id_list_protection_without_uv() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(protected_passkey(
+                CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList,
+            )),
+            user_mock_verified_on_request(3),
+        );
+
+        // Act
+        let discovered = authenticator.get_assertion(request_without_uv(false)).await;
+        let allowed = authenticator.get_assertion(request_without_uv(true)).await;
+        let verified = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(discovered.err(), Some(Ctap2Error::NoCredentials.into()));
+        assert!(allowed.is_ok());
+        assert!(verified.is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_auth_data_has_no_attested_credential_data() {
+        // Arrange
+        let request = good_request();
+        let passkey = create_passkey();
+        let store = Some(passkey.clone());
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            store,
+            MockUserValidationMethod::verified_user_with_hint(
+                1,
+                MockUIHint::RequestExistingCredential(passkey),
+            ),
+        );
+
+        // Act
+        let response = authenticator.get_assertion(request).await.unwrap();
+
+        // Assert
+        let auth_data = AuthenticatorData::from_slice(&response.auth_data.to_vec())
+            .expect("could not decode the authenticator data");
+        assert!(!auth_data.flags.attested_credential_data_included());
+        assert!(auth_data.attested_credential_data.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_skips_disabled_credentials_until_enabled_again() {
+        // Arrange
+        let passkey = Passkey {
+            disabled: true,
+            ..create_passkey()
+        };
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey),
+            MockUserValidationMethod::verified_user(2),
+        );
+
+        // Act
+        let disabled = authenticator.get_assertion(good_request()).await;
+        if let Some(passkey) = authenticator.store_mut() {
+            passkey.disabled = false;
+        }
+        let enabled = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(disabled.unwrap_err(), Ctap2Error::NoCredentials.into());
+        assert!(enabled.is_ok());
+        assert!(authenticator.store().is_some());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_is_denied_without_user_presence() {
+        // Arrange
+        let passkey = Passkey {
+            counter: Some(9000),
+            ..create_passkey()
+        };
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey.clone()),
+            MockUserValidationMethod::absent_user(1),
+        );
+
+        // Act
+        let response = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(response.unwrap_err(), Ctap2Error::OperationDenied.into());
+        assert_eq!(authenticator.store(), &Some(passkey));
+    }
+
+    #[tokio::test]
+    async fn get_assertion_is_denied_rather_than_not_found_when_the_user_is_not_verified() {
+        // Arrange
+        let passkey = Passkey {
+            counter: Some(9000),
+            ..create_passkey()
+        };
+        let mut unverified = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey.clone()),
+            MockUserValidationMethod::unverified_user(1),
+        );
+        let mut user_mock = MockUserValidationMethod::verified_user(0);
+        user_mock.checkpoint();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true));
+        user_mock
+            .expect_check_user()
+            .once()
+            .returning(|_, _, _| Err(Ctap2Error::OperationDenied));
+        let mut cancelled =
+            Authenticator::new(Aaguid::new_empty(), Some(passkey.clone()), user_mock);
+
+        // Act
+        let denied = unverified.get_assertion(good_request()).await;
+        let cancelled_result = cancelled.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(denied.unwrap_err(), Ctap2Error::OperationDenied.into());
+        assert_eq!(
+            cancelled_result.unwrap_err(),
+            Ctap2Error::OperationDenied.into()
+        );
+        assert_eq!(unverified.store(), &Some(passkey));
+    }
+
+    #[tokio::test]
+    async fn get_assertion_rejects_credentials_with_an_unsupported_algorithm() {
+        // Arrange
+        let passkey = Passkey {
+            key: CoseKey {
+                kty: coset::KeyType::Assigned(iana::KeyType::RSA),
+                alg: Some(coset::Algorithm::Assigned(iana::Algorithm::PS256)),
+                ..Default::default()
+            },
+            counter: Some(9000),
+            ..create_passkey()
+        };
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey.clone()),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        // Act
+        let response = authenticator.get_assertion(good_request()).await;
+
+        // Assert
+        assert_eq!(
+            response.unwrap_err(),
+            Ctap2Error::UnsupportedAlgorithm.into()
+        );
+        assert_eq!(authenticator.store(), &Some(passkey));
+    }
+
+    #[tokio::test]
+    async fn get_assertion_rejects_credentials_with_a_corrupted_key() {
+        // Arrange
+        let mut es256 = private_key_for_testing();
+        for (label, value) in es256.params.iter_mut() {
+            if *label == coset::Label::Int(iana::Ec2KeyParameter::D.to_i64()) {
+                *value = ciborium::Value::Bytes(vec![0xff; 32]);
+            }
+        }
+        let ed25519 = CoseKeyBuilder::new_okp_key()
+            .algorithm(iana::Algorithm::EdDSA)
+            .param(
+                iana::OkpKeyParameter::Crv.to_i64(),
+                ciborium::Value::from(iana::EllipticCurve::Ed25519.to_i64()),
+            )
+            .param(
+                iana::OkpKeyParameter::D.to_i64(),
+                ciborium::Value::Bytes(vec![1; 31]),
+            )
+            .build();
+        let rs256 = CoseKey {
+            kty: coset::KeyType::Assigned(iana::KeyType::RSA),
+            alg: Some(coset::Algorithm::Assigned(iana::Algorithm::RS256)),
+            params: [
+                iana::RsaKeyParameter::N,
+                iana::RsaKeyParameter::E,
+                iana::RsaKeyParameter::D,
+                iana::RsaKeyParameter::P,
+                iana::RsaKeyParameter::Q,
+            ]
+            .into_iter()
+            .map(|parameter| {
+                (
+                    coset::Label::Int(parameter.to_i64()),
+                    ciborium::Value::Bytes(vec![3]),
+                )
+            })
+            .collect(),
+            ..Default::default()
+        };
+
+        for key in [es256, ed25519, rs256] {
+            let passkey = Passkey {
+                key,
+                counter: Some(9000),
+                ..create_passkey()
+            };
+            let mut authenticator = Authenticator::new(
+                Aaguid::new_empty(),
+                Some(passkey.clone()),
+                MockUserValidationMethod::verified_user(1),
+            );
+
+            // Act
+            let response = authenticator.get_assertion(good_request()).await;
+
+            // Assert
+            assert_eq!(response.unwrap_err(), Ctap2Error::InvalidCredential.into());
+            assert_eq!(authenticator.store(), &Some(passkey));
+        }
+    }
+
+    fn cred_random_for_testing() -> CredRandom {
+        CredRandom {
+            with_uv: vec![1; 32].into(),
+            without_uv: vec![2; 32].into(),
+        }
+    }
+
+    /// Encrypt the salts the way the platform does, returning the secret it shares with the
+    /// authenticator along with the `hmac-secret` input.
+    fn encrypt_salts(authenticator_key: &CoseKey, salts: &[u8]) -> (SharedSecret, HmacSecretInput) {
+        let platform_key = KeyAgreementKey::generate();
+        let shared_secret = platform_key.shared_secret(authenticator_key).unwrap();
+        let salt_enc = shared_secret.encrypt(salts).unwrap();
+        let input = HmacSecretInput {
+            key_agreement: platform_key.public_key(),
+            salt_auth: shared_secret.authenticate(&salt_enc).to_vec().into(),
+            salt_enc: salt_enc.into(),
+            pin_uv_auth_protocol: Some(1),
+        };
+        (shared_secret, input)
+    }
+
+    fn request_with_hmac_secret(input: HmacSecretInput) -> Request {
+        Request {
+            extensions: Some(ExtensionInputs {
+                prf: None,
+                hmac_secret: Some(input),
+                large_blob_key: None,
+                uvm: None,
+                device_pub_key: None,
+            }),
+            ..good_request()
+        }
+    }
+
+    /// The `hmac-secret` output in the extensions of the authenticator data.
+    fn hmac_secret_output(auth_data: &AuthenticatorData) -> Option<Vec<u8>> {
+        auth_data
+            .extensions
+            .clone()
+            .and_then(|extensions| extensions.into_map().ok())
+            .and_then(|outputs| {
+                outputs
+                    .into_iter()
+                    .find(|(id, _)| id.as_text() == Some("hmac-secret"))
+            })
+            .and_then(|(_, output)| output.into_bytes().ok())
+    }
+
+    #[tokio::test]
+    async fn get_assertion_returns_the_encrypted_hmac_secret_outputs() {
+        // Arrange
+        let cred_random = cred_random_for_testing();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                extensions: CredentialExtensions {
+                    cred_random: Some(cred_random.clone()),
+                    ..Default::default()
+                },
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let salts = [[3; 32], [4; 32]].concat();
+        let (shared_secret, input) = encrypt_salts(&authenticator.key_agreement_key(), &salts);
+
+        // Act
+        let response = authenticator
+            .get_assertion(request_with_hmac_secret(input))
+            .await
+            .expect("assertion with hmac-secret failed");
+
+        // Assert
+        let expected = evaluate_hmac_secret(
+            &cred_random,
+            Flags::UP | Flags::UV,
+            &AuthenticationExtensionsPrfValues {
+                first: vec![3; 32].into(),
+                second: Some(vec![4; 32].into()),
+            },
+        )
+        .unwrap();
+        let output = hmac_secret_output(&response.auth_data).expect("no hmac-secret output");
+        let output = shared_secret.decrypt(&output).unwrap();
+        assert!(response.auth_data.flags.contains(Flags::ED));
+        assert_eq!(output[..32], expected.first[..]);
+        assert_eq!(Some(output[32..].to_vec().into()), expected.second);
+    }
+
+    #[tokio::test]
+    async fn get_assertion_has_no_hmac_secret_output_for_credentials_without_it() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(create_passkey()),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let (_, input) = encrypt_salts(&authenticator.key_agreement_key(), &[3; 32]);
+
+        // Act
+        let response = authenticator
+            .get_assertion(request_with_hmac_secret(input))
+            .await
+            .expect("assertion with hmac-secret failed");
+
+        // Assert
+        assert!(response.auth_data.extensions.is_none());
+        assert!(!response.auth_data.flags.contains(Flags::ED));
+    }
+
+    #[tokio::test]
+    async fn get_assertion_rejects_unauthenticated_hmac_secret_salts() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(Passkey {
+                extensions: CredentialExtensions {
+                    cred_random: Some(cred_random_for_testing()),
+                    ..Default::default()
+                },
+                ..create_passkey()
+            }),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let (_, mut input) = encrypt_salts(&authenticator.key_agreement_key(), &[3; 32]);
+        input.salt_auth = vec![0; 16].into();
+
+        // Act
+        let response = authenticator
+            .get_assertion(request_with_hmac_secret(input))
+            .await;
+
+        // Assert
+        assert_eq!(response.unwrap_err(), Ctap2Error::PinAuthInvalid.into());
+    }
+
+    #[tokio::test]
+    async fn get_assertion_returns_the_large_blob_key_when_requested() {
+        // Arrange
+        let passkey = Passkey {
+            extensions: CredentialExtensions {
+                large_blob_key: Some(vec![5; 32].into()),
+                ..Default::default()
+            },
+            ..create_passkey()
+        };
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(passkey),
+            MockUserValidationMethod::verified_user(2),
+        );
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                large_blob_key: Some(true),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+
+        // Act
+        let requested = authenticator.get_assertion(request).await.unwrap();
+        let not_requested = authenticator.get_assertion(good_request()).await.unwrap();
+
+        // Assert
+        assert_eq!(requested.large_blob_key, Some(vec![5; 32].into()));
+        assert!(requested.auth_data.extensions.is_none());
+        assert_eq!(not_requested.large_blob_key, None);
+    }
+
+    fn request_with_device_pub_key() -> Request {
+        Request {
+            extensions: Some(ExtensionInputs {
+                device_pub_key: Some(Default::default()),
+                ..Default::default()
+            }),
+            ..good_request()
+        }
+    }
+
+    fn device_pub_key_attestation(response: &Response) -> DevicePublicKeyAttestation {
+        let outputs = response.auth_data.extensions().unwrap().unwrap();
+        DevicePublicKeyAttestation::from_slice(&outputs.device_pub_key.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_assertion_attests_the_same_device_key_until_used_on_another_device() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(create_passkey()),
+            MockUserValidationMethod::verified_user(3),
+        );
+        // The credential is synced to another device, which has its own device key.
+        let mut other_device = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(create_passkey()),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        // Act
+        let first = authenticator
+            .get_assertion(request_with_device_pub_key())
+            .await
+            .unwrap();
+        let second = authenticator
+            .get_assertion(request_with_device_pub_key())
+            .await
+            .unwrap();
+        let not_requested = authenticator.get_assertion(good_request()).await.unwrap();
+        let other = other_device
+            .get_assertion(request_with_device_pub_key())
+            .await
+            .unwrap();
+
+        // Assert
+        let first = device_pub_key_attestation(&first);
+        assert_eq!(first.fmt, "none");
+        assert_eq!(first.dpk, device_pub_key_attestation(&second).dpk);
+        assert_ne!(first.dpk, device_pub_key_attestation(&other).dpk);
+        assert!(not_requested.auth_data.extensions.is_none());
+    }
+
+    #[test]
+    fn hmac_secret_depends_on_user_verification() {
+        // Arrange
+        let cred_random = CredRandom {
+            with_uv: vec![1; 32].into(),
+            without_uv: vec![2; 32].into(),
+        };
+        let salts = AuthenticationExtensionsPrfValues {
+            first: vec![3; 32].into(),
+            second: Some(vec![4; 32].into()),
+        };
+
+        // Act
+        let verified = evaluate_hmac_secret(&cred_random, Flags::UP | Flags::UV, &salts).unwrap();
+        let present = evaluate_hmac_secret(&cred_random, Flags::UP, &salts).unwrap();
+
+        // Assert
+        assert_eq!(verified.first.len(), 32);
+        assert_ne!(verified.first, present.first);
+        assert_ne!(verified.second, present.second);
+        assert_ne!(Some(verified.first), verified.second);
+    }
+
+    #[test]
+    fn hmac_secret_rejects_salts_that_are_not_32_bytes() {
+        // Arrange
+        let cred_random = CredRandom {
+            with_uv: vec![1; 32].into(),
+            without_uv: vec![2; 32].into(),
+        };
+        let salts = AuthenticationExtensionsPrfValues {
+            first: vec![3; 32].into(),
+            second: Some(vec![4; 16].into()),
+        };
+
+        // Act
+        let result = evaluate_hmac_secret(&cred_random, Flags::UP | Flags::UV, &salts);
+
+        // Assert
+        assert_eq!(result.unwrap_err(), U2FError::InvalidLength.into());
+    }
 }
@@ -1,27 +1,115 @@
-use passkey_types::ctap2::get_info::{Options, Response};
+use passkey_types::{
+    ctap2::get_info::{Options, Response},
+    webauthn::{PublicKeyCredentialParameters, PublicKeyCredentialType},
+};
 
 use crate::{
-    credential_store::DiscoverabilitySupport, Authenticator, CredentialStore, UserValidationMethod,
+    credential_store::DiscoverabilitySupport, pin_protocol::PROTOCOL_ONE, Authenticator,
+    CredentialStore, UserValidationMethod,
 };
 
-impl<S: CredentialStore, U: UserValidationMethod> Authenticator<S, U> {
+impl<S: CredentialStore + Sync, U: UserValidationMethod> Authenticator<S, U> {
     /// Using this method, the host can request that the authenticator report a list of all
     /// supported protocol versions, supported extensions, AAGUID of the device, and its capabilities.
     pub async fn get_info(&self) -> Response {
+        // Large blobs are only supported when the store is able to hold them.
+        let large_blobs = self.store.get_large_blob().await.is_some();
+        // Credentials can only be managed when the store is able to enumerate them.
+        let cred_mgmt = self.store.list_credentials(None).await.is_ok();
+        let mut extensions = Vec::new();
+        if self.hmac_secret {
+            extensions.push("hmac-secret".into());
+        }
+        extensions.push("credProtect".into());
+        extensions.push("minPinLength".into());
+        if large_blobs {
+            extensions.push("largeBlobKey".into());
+        }
+
         Response {
             versions: vec!["FIDO_2_0".into(), "U2F_V2".into()],
-            extensions: None,
+            extensions: Some(extensions),
             aaguid: *self.aaguid(),
             options: Some(Options {
                 rk: self.store.get_info().await.discoverability
                     != DiscoverabilitySupport::OnlyNonDiscoverable,
+                client_pin: Some(self.is_pin_set()),
                 uv: self.user_validation.is_verification_enabled().await,
                 up: self.user_validation.is_presence_enabled().await,
+                always_uv: Some(self.always_uv),
+                // Always verifying the user takes precedence over allowing credential creation
+                // without user verification.
+                make_cred_uv_not_rqd: Some(self.make_credential_uv_not_required && !self.always_uv),
+                large_blobs: large_blobs.then_some(true),
+                cred_mgmt: cred_mgmt.then_some(true),
+                ep: self.enterprise_attestation_rp_ids.is_some().then_some(true),
                 ..Default::default()
             }),
             max_msg_size: None,
-            pin_protocols: None,
+            algorithms: Some(
+                self.algs
+                    .iter()
+                    .map(|&alg| PublicKeyCredentialParameters {
+                        ty: PublicKeyCredentialType::PublicKey,
+                        alg,
+                    })
+                    .collect(),
+            ),
+            max_serialized_large_blob_array: large_blobs
+                .then_some(self.max_serialized_large_blob_array),
+            min_pin_length: Some(self.min_pin_length),
+            pin_protocols: Some(vec![PROTOCOL_ONE]),
+            max_credential_id_length: self.max_credential_id_length,
             transports: Some(self.transports.clone()),
+            uv_modality: self.uv_modality,
+            certifications: (!self.certifications.is_empty()).then(|| self.certifications.clone()),
+            remaining_discoverable_credentials: self.remaining_discoverable_credentials().await,
         }
     }
+
+    /// The number of discoverable credentials that can still be created, which follows the
+    /// credentials currently in the [`CredentialStore`] so that it goes back up when they are
+    /// deleted.
+    pub async fn remaining_discoverable_credentials(&self) -> Option<u32> {
+        let max = self.max_discoverable_credentials?;
+        let count = self.store.discoverable_credentials_count().await?;
+        // A count that does not fit in a u32 is necessarily over the maximum.
+        Some(max.saturating_sub(u32::try_from(count).unwrap_or(u32::MAX)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use passkey_types::ctap2::Aaguid;
+
+    use crate::{Authenticator, MemoryStore, MockUserValidationMethod};
+
+    #[tokio::test]
+    async fn get_info_reports_the_configured_certifications() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(0),
+        );
+        let uncertified = authenticator.get_info().await;
+
+        // Act
+        authenticator.set_uv_modality(Some(0x0002));
+        authenticator.set_certification("FIDO", Some(2));
+        authenticator.set_certification("FIPS-CMVP-2", Some(1));
+        authenticator.set_certification("FIPS-CMVP-2", None);
+        let info = authenticator.get_info().await;
+
+        // Assert
+        assert_eq!(uncertified.uv_modality, None);
+        assert_eq!(uncertified.certifications, None);
+        assert_eq!(info.uv_modality, Some(0x0002));
+        assert_eq!(
+            info.certifications,
+            Some(BTreeMap::from([("FIDO".to_owned(), 2)]))
+        );
+    }
 }
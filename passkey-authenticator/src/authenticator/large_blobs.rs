@@ -0,0 +1,309 @@
+use passkey_types::{
+    crypto::sha256,
+    ctap2::{
+        large_blobs::{
+            has_valid_checksum, Request, Response, CHECKSUM_LENGTH, INITIAL_SERIALIZED_ARRAY,
+        },
+        Ctap2Error, StatusCode, U2FError,
+    },
+    Bytes, Passkey,
+};
+
+use crate::{pin_protocol::PROTOCOL_ONE, Authenticator, CredentialStore, UserValidationMethod};
+
+/// The smallest maximum size of the serialized large-blob array an authenticator may report.
+pub(super) const MIN_SERIALIZED_LARGE_BLOB_ARRAY: usize = 1024;
+
+/// The largest fragment that can be read or written at once, which is the default maximum message
+/// size of 1024 bytes minus the 64 bytes the specification reserves for the rest of the message.
+const MAX_FRAGMENT_LENGTH: usize = 960;
+
+/// A write of the serialized large-blob array that has not received all of its fragments yet.
+pub(super) struct PendingLargeBlob {
+    expected_length: usize,
+    data: Vec<u8>,
+}
+
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem> + Clone,
+{
+    /// This method is used by the host to read or write a fragment of the serialized large-blob
+    /// array, which holds the blobs the client encrypted with the `largeBlobKey` of their
+    /// credential. The array is only written to the [`CredentialStore`] once all of its fragments
+    /// were received and its checksum is valid.
+    ///
+    /// Fails with [`U2FError::InvalidCommand`] when the store cannot hold large blobs.
+    pub async fn large_blobs(&mut self, input: Request) -> Result<Response, StatusCode> {
+        let Some(stored) = self.store().get_large_blob().await else {
+            return Err(U2FError::InvalidCommand.into());
+        };
+
+        match (input.get, input.set) {
+            (Some(get), None) => {
+                if input.length.is_some() {
+                    return Err(U2FError::InvalidParameter.into());
+                }
+                if get > MAX_FRAGMENT_LENGTH {
+                    return Err(U2FError::InvalidLength.into());
+                }
+                let array = if stored.is_empty() {
+                    INITIAL_SERIALIZED_ARRAY.to_vec()
+                } else {
+                    stored
+                };
+                if input.offset > array.len() {
+                    return Err(U2FError::InvalidParameter.into());
+                }
+                let end = array.len().min(input.offset.saturating_add(get));
+
+                Ok(Response {
+                    config: Some(array[input.offset..end].to_vec().into()),
+                })
+            }
+            (None, Some(set)) => {
+                // Writes must be authorized with the pinToken once a PIN is set, which
+                // authenticates 32 bytes of 0xff, the command byte 0x0c, a zero byte, the offset
+                // as a little-endian u32 and then the SHA-256 hash of the fragment.
+                match &input.pin_uv_auth_param {
+                    Some(pin_uv_auth_param) => {
+                        if input.pin_uv_auth_protocol != Some(PROTOCOL_ONE) {
+                            return Err(U2FError::InvalidParameter.into());
+                        }
+                        let offset =
+                            u32::try_from(input.offset).map_err(|_| U2FError::InvalidParameter)?;
+                        let mut message = vec![0xff; 32];
+                        message.extend_from_slice(&[0x0c, 0x00]);
+                        message.extend_from_slice(&offset.to_le_bytes());
+                        message.extend_from_slice(&sha256(&set));
+                        self.verify_pin_auth(
+                            Some(pin_uv_auth_param),
+                            input.pin_uv_auth_protocol,
+                            &message,
+                        )?;
+                    }
+                    None if self.is_pin_set() => return Err(Ctap2Error::PuatRequired.into()),
+                    None => {}
+                }
+                self.write_large_blob_fragment(input.offset, input.length, set)
+                    .await?;
+
+                Ok(Response { config: None })
+            }
+            _ => Err(U2FError::InvalidParameter.into()),
+        }
+    }
+
+    async fn write_large_blob_fragment(
+        &mut self,
+        offset: usize,
+        length: Option<usize>,
+        fragment: Bytes,
+    ) -> Result<(), StatusCode> {
+        if fragment.len() > MAX_FRAGMENT_LENGTH {
+            return Err(U2FError::InvalidLength.into());
+        }
+
+        // The first fragment announces the length of the whole array, which starts a new write.
+        if offset == 0 {
+            let Some(length) = length else {
+                return Err(U2FError::InvalidParameter.into());
+            };
+            if length > self.max_serialized_large_blob_array {
+                return Err(Ctap2Error::LargeBlobStorageFull.into());
+            }
+            if length <= CHECKSUM_LENGTH {
+                return Err(U2FError::InvalidParameter.into());
+            }
+            self.pending_large_blob = Some(PendingLargeBlob {
+                expected_length: length,
+                data: Vec::with_capacity(length),
+            });
+        } else if length.is_some() {
+            return Err(U2FError::InvalidParameter.into());
+        }
+
+        let Some(pending) = self
+            .pending_large_blob
+            .as_mut()
+            .filter(|pending| pending.data.len() == offset)
+        else {
+            return Err(U2FError::InvalidSequence.into());
+        };
+        if offset + fragment.len() > pending.expected_length {
+            return Err(U2FError::InvalidParameter.into());
+        }
+        pending.data.extend_from_slice(&fragment);
+        if pending.data.len() < pending.expected_length {
+            return Ok(());
+        }
+
+        // SAFETY: the pending write was matched above.
+        let array = self.pending_large_blob.take().unwrap().data;
+        if !has_valid_checksum(&array) {
+            return Err(Ctap2Error::IntegrityFailure.into());
+        }
+        self.store_mut().put_large_blob(array).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use passkey_types::{
+        ctap2::{
+            large_blobs::{serialize_array, LargeBlobEntry, Request, INITIAL_SERIALIZED_ARRAY},
+            Aaguid, Ctap2Error, U2FError,
+        },
+        Passkey,
+    };
+
+    use crate::{Authenticator, CredentialStore, MemoryStore, MockUserValidationMethod};
+
+    fn get(offset: usize, length: usize) -> Request {
+        Request {
+            get: Some(length),
+            set: None,
+            offset,
+            length: None,
+            pin_uv_auth_param: None,
+            pin_uv_auth_protocol: None,
+        }
+    }
+
+    fn set(offset: usize, fragment: &[u8], length: Option<usize>) -> Request {
+        Request {
+            get: None,
+            set: Some(fragment.to_vec().into()),
+            offset,
+            length,
+            pin_uv_auth_param: None,
+            pin_uv_auth_protocol: None,
+        }
+    }
+
+    fn large_array() -> Vec<u8> {
+        serialize_array(&[LargeBlobEntry {
+            ciphertext: vec![7; 900].into(),
+            nonce: vec![1; 12].into(),
+            orig_size: 1000,
+        }])
+    }
+
+    #[tokio::test]
+    async fn large_blob_array_starts_as_the_initial_array() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(0),
+        );
+
+        // Act
+        let response = authenticator.large_blobs(get(0, 960)).await.unwrap();
+        let info = authenticator.get_info().await;
+
+        // Assert
+        assert_eq!(
+            response.config.unwrap().as_slice(),
+            INITIAL_SERIALIZED_ARRAY
+        );
+        assert_eq!(info.options.unwrap().large_blobs, Some(true));
+        assert_eq!(info.max_serialized_large_blob_array, Some(1024));
+        assert!(info.extensions.unwrap().contains(&"largeBlobKey".into()));
+    }
+
+    #[tokio::test]
+    async fn large_blob_array_is_written_and_read_in_fragments() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(0),
+        );
+        let array = large_array();
+        let (first, second) = array.split_at(600);
+
+        // Act
+        authenticator
+            .large_blobs(set(0, first, Some(array.len())))
+            .await
+            .unwrap();
+        let pending = authenticator.store().get_large_blob().await;
+        authenticator
+            .large_blobs(set(600, second, None))
+            .await
+            .unwrap();
+        let mut read = Vec::new();
+        for offset in [0, 600] {
+            let response = authenticator.large_blobs(get(offset, 600)).await.unwrap();
+            read.extend_from_slice(&response.config.unwrap());
+        }
+
+        // Assert
+        assert_eq!(pending, Some(Vec::new()));
+        assert_eq!(
+            authenticator.store().get_large_blob().await,
+            Some(array.clone())
+        );
+        assert_eq!(read, array);
+    }
+
+    #[tokio::test]
+    async fn large_blob_writes_are_validated() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(0),
+        );
+        let mut corrupted = INITIAL_SERIALIZED_ARRAY;
+        corrupted[16] ^= 1;
+
+        // Act & Assert
+        assert_eq!(
+            authenticator.large_blobs(set(17, &[0; 17], None)).await,
+            Err(U2FError::InvalidSequence.into())
+        );
+        assert_eq!(
+            authenticator.large_blobs(set(0, &[0; 17], None)).await,
+            Err(U2FError::InvalidParameter.into())
+        );
+        assert_eq!(
+            authenticator
+                .large_blobs(set(0, &[0; 17], Some(2048)))
+                .await,
+            Err(Ctap2Error::LargeBlobStorageFull.into())
+        );
+        assert_eq!(
+            authenticator
+                .large_blobs(set(0, &corrupted, Some(17)))
+                .await,
+            Err(Ctap2Error::IntegrityFailure.into())
+        );
+        assert_eq!(
+            authenticator.store().get_large_blob().await,
+            Some(Vec::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn large_blobs_are_unsupported_without_storage() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            None::<Passkey>,
+            MockUserValidationMethod::verified_user(0),
+        );
+
+        // Act
+        let result = authenticator.large_blobs(get(0, 960)).await;
+        let info = authenticator.get_info().await;
+
+        // Assert
+        assert_eq!(result, Err(U2FError::InvalidCommand.into()));
+        assert_eq!(info.options.unwrap().large_blobs, None);
+        assert_eq!(info.max_serialized_large_blob_array, None);
+    }
+}
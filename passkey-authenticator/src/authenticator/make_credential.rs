@@ -1,16 +1,26 @@
-use p256::SecretKey;
+use ciborium::Value;
 use passkey_types::{
     ctap2::{
-        make_credential::{Request, Response},
-        AttestedCredentialData, AuthenticatorData, Ctap2Error, StatusCode,
+        make_credential::{
+            Options, Request, Response, ENTERPRISE_ATTESTATION_PLATFORM_MANAGED,
+            ENTERPRISE_ATTESTATION_VENDOR_FACILITATED,
+        },
+        Aaguid, AttestedCredentialData, Ctap2Error, Flags, StatusCode, U2FError,
     },
-    Passkey,
+    rand::random_vec,
+    webauthn::{AttestationStatementFormatIdentifiers, CredentialProtectionPolicy},
+    CredRandom, CredentialExtensions, Passkey,
 };
 
+use super::extensions_in_canonical_order;
 use crate::{
-    user_validation::UIHint, Authenticator, CoseKeyPair, CredentialStore, UserValidationMethod,
+    user_validation::UIHint, AttestationInput, Authenticator, CoseKeyPair, CounterMode,
+    CredentialStore, KeyExport, NoneAttestation, UserValidationMethod,
 };
 
+/// The length of the credential IDs generated for new credentials.
+pub(super) const CREDENTIAL_ID_LENGTH: usize = 16;
+
 impl<S, U> Authenticator<S, U>
 where
     S: CredentialStore + Sync,
@@ -19,10 +29,51 @@ where
 {
     /// This method is invoked by the host to request generation of a new credential in the authenticator.
     pub async fn make_credential(&mut self, input: Request) -> Result<Response, StatusCode> {
+        self.create_credential(input, None)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// ⚠ Insecure, for advanced use only: create a credential like [`Self::make_credential`], but
+    /// also return the [`Passkey`] with its private key to the caller.
+    ///
+    /// This is meant for applications that must escrow or back up the keys of their credentials
+    /// themselves. The private key leaves the authenticator, so its protection is entirely up to
+    /// the caller. With [`KeyExport::ExportOnly`] the credential is not saved in the
+    /// [`CredentialStore`] at all.
+    pub async fn make_credential_exporting_key(
+        &mut self,
+        input: Request,
+        export: KeyExport,
+    ) -> Result<(Response, Passkey), StatusCode> {
+        let (response, passkey) = self.create_credential(input, Some(export)).await?;
+        // SAFETY: the credential is always returned when it is exported.
+        Ok((response, passkey.unwrap()))
+    }
+
+    /// Create a credential, which is returned rather than only saved when it is exported.
+    async fn create_credential(
+        &mut self,
+        mut input: Request,
+        export: Option<KeyExport>,
+    ) -> Result<(Response, Option<Passkey>), StatusCode> {
+        self.effective_options = None;
         if !input.options.up {
             return Err(Ctap2Error::InvalidOption.into());
         };
 
+        // When the authenticator is protected by user verification, only create a credential
+        // without it if this is allowed by the `makeCredUvNotRqd` option.
+        if self.always_uv {
+            input.options.uv = true;
+        } else if !input.options.uv
+            && input.pin_auth.is_none()
+            && !self.make_credential_uv_not_required
+            && self.user_validation.is_verification_enabled().await == Some(true)
+        {
+            return Err(Ctap2Error::PuatRequired.into());
+        }
+
         // 1. If the excludeList parameter is present and contains a credential ID that is present
         //    on this authenticator and bound to the specified rpId, wait for user presence, then
         //    terminate this procedure and return error code CTAP2_ERR_CREDENTIAL_EXCLUDED. User
@@ -34,15 +85,25 @@ where
             .filter(|list| !list.is_empty())
             .is_some()
         {
-            if let Some(excluded_credential) = self
+            let excluded_credentials = match self
                 .store()
                 .find_credentials(input.exclude_list.as_deref(), &input.rp.id)
-                .await?
-                .first()
+                .await
             {
+                // NB: Stores report that none of the excluded credentials are present with a
+                // `NoCredentials` error, which is not an error when creating a credential.
+                Err(status) if status == Ctap2Error::NoCredentials.into() => Vec::new(),
+                result => result?,
+            };
+            // NB: Only the user's presence is collected, verifying the user is pointless since
+            // no credential is created.
+            if let Some(excluded_credential) = excluded_credentials.first() {
                 self.check_user(
                     UIHint::InformExcludedCredentialFound(excluded_credential),
-                    &input.options,
+                    &Options {
+                        uv: false,
+                        ..input.options.clone()
+                    },
                 )
                 .await?;
 
@@ -69,13 +130,77 @@ where
             return Err(Ctap2Error::UnsupportedOption.into());
         }
 
-        // 4. TODO, if the extensions parameter is present, process any extensions that this
+        // If the enterpriseAttestation parameter is present, as introduced in CTAP 2.1, the
+        // authenticator must have enterprise attestation enabled. Vendor facilitated enterprise
+        // attestation is limited to the configured relying parties, while a managed platform
+        // vouches for the relying parties it requests it for.
+        let enterprise_attestation = match input.enterprise_attestation {
+            None => false,
+            Some(_) if self.enterprise_attestation_rp_ids.is_none() => {
+                return Err(U2FError::InvalidParameter.into());
+            }
+            Some(ENTERPRISE_ATTESTATION_VENDOR_FACILITATED) => self
+                .enterprise_attestation_rp_ids
+                .iter()
+                .flatten()
+                .any(|rp_id| *rp_id == input.rp.id),
+            Some(ENTERPRISE_ATTESTATION_PLATFORM_MANAGED) => true,
+            Some(_) => return Err(Ctap2Error::InvalidOption.into()),
+        };
+
+        // 4. If the extensions parameter is present, process any extensions that this
         //    authenticator supports. Authenticator extension outputs generated by the authenticator
         //    extension processing are returned in the authenticator data.
+        // NB: Only `hmac-secret`, `credProtect`, `largeBlobKey`, `minPinLength`, `uvm` and
+        // `devicePubKey` are supported.
+        // The `hmac-secret` secrets and the `largeBlobKey` are generated along with the key pair,
+        // and a requested protection policy takes precedence over the authenticator's default one.
+        let hmac_secret = self.hmac_secret
+            && input
+                .extensions
+                .as_ref()
+                .and_then(|extensions| extensions.hmac_secret)
+                == Some(true);
+        let cred_protect = input
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.cred_protect)
+            .or(self.default_credential_protection_policy);
+        // A credential that can only be used with user verification is created with it as well,
+        // when the user can be verified and no PIN is given instead.
+        if cred_protect == Some(CredentialProtectionPolicy::UserVerificationRequired)
+            && !input.options.uv
+            && input.pin_auth.is_none()
+            && self.user_validation.is_verification_enabled().await == Some(true)
+        {
+            input.options.uv = true;
+        }
+        // The minimum PIN length is only disclosed to the relying parties allowed to learn it.
+        let min_pin_length = input
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.min_pin_length)
+            == Some(true)
+            && self.min_pin_length_rp_ids.contains(&input.rp.id);
+        // The user verification method is only known once the user was checked, see step 8.
+        let uvm = input
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.uvm)
+            == Some(true);
+        // The `largeBlobKey` extension is ignored when the store cannot hold large blobs, and
+        // otherwise only valid with value `true` for discoverable credentials.
+        let large_blob_key = match input
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.large_blob_key)
+        {
+            Some(_) if self.store().get_large_blob().await.is_none() => false,
+            Some(true) if input.options.rk => true,
+            Some(_) => return Err(Ctap2Error::InvalidOption.into()),
+            None => false,
+        };
 
-        // NB: We do not currently support any Pin Protocols (1 or 2) as this does not make sense
-        // in the context of 1Password. This is to be revisisted to see if we can hook this into
-        // using some key that we already have, such as the Biometry unlock key for example.
         // 5. If pinAuth parameter is present and pinProtocol is 1, verify it by matching it against
         //    first 16 bytes of HMAC-SHA-256 of clientDataHash parameter using
         //    pinToken: HMAC- SHA-256(pinToken, clientDataHash).
@@ -85,9 +210,17 @@ where
         //    return CTAP2_ERR_PIN_REQUIRED error.
         // 7. If pinAuth parameter is present and the pinProtocol is not supported,
         //    return CTAP2_ERR_PIN_AUTH_INVALID.
-        if input.pin_auth.is_some() {
-            // we currently don't support pin authentication
-            return Err(Ctap2Error::UnsupportedOption.into());
+        // NB: The built-in user verification can still be requested in place of the PIN, and a
+        // verified PIN replaces it.
+        let pin_verified = self.verify_pin_auth(
+            input.pin_auth.as_ref(),
+            input.pin_protocol,
+            &input.client_data_hash,
+        )?;
+        if pin_verified {
+            input.options.uv = false;
+        } else if self.is_pin_set() && !input.options.uv {
+            return Err(Ctap2Error::PuatRequired.into());
         }
 
         // 8. Moving step 8 to after step 9 so that the new credential can be displayed to the user
@@ -95,27 +228,45 @@ where
         // 9. Generate a new credential key pair for the algorithm specified.
         let credential_id: Vec<u8> = {
             use rand::RngCore;
-            let mut data = vec![0u8; 16];
-            rand::thread_rng().fill_bytes(&mut data);
+            let mut data = vec![0u8; CREDENTIAL_ID_LENGTH];
+            match self.credential_id_rng.as_mut() {
+                Some(rng) => rng.fill_bytes(&mut data),
+                None => rand::thread_rng().fill_bytes(&mut data),
+            }
             data
         };
 
-        let private_key = {
-            let mut rng = rand::thread_rng();
-            SecretKey::random(&mut rng)
-        };
-
         // Encoding of the keypair into their CoseKey representation before moving the private CoseKey
         // into the passkey. Keeping the public key ready for step 11 below and returning the attested
         // credential.
-        let CoseKeyPair { public, private } = CoseKeyPair::from_secret_key(&private_key, algorithm);
+        let CoseKeyPair {
+            mut public,
+            private,
+        } = CoseKeyPair::generate(algorithm);
+        if !self.public_key_alg_included {
+            public.alg = None;
+        }
 
         let passkey = Passkey {
             key: private,
             rp_id: input.rp.id.clone(),
             credential_id: credential_id.into(),
             user_handle: input.options.rk.then_some(input.user.id.clone()),
-            counter: self.make_credentials_with_signature_counter.then_some(0),
+            counter: (self.counter_mode == CounterMode::PerCredential
+                && self.make_credentials_with_signature_counter)
+                .then_some(self.initial_signature_counter),
+            disabled: false,
+            transports: Some(self.transports.clone()),
+            extensions: CredentialExtensions {
+                cred_protect,
+                cred_blob: None,
+                large_blob_key: large_blob_key.then(|| random_vec(32).into()),
+                cred_random: hmac_secret.then(|| CredRandom {
+                    with_uv: random_vec(32).into(),
+                    without_uv: random_vec(32).into(),
+                }),
+            },
+            last_used_at: None,
         };
 
         // 8. If the authenticator has a display, show the items contained within the user and rp
@@ -123,12 +274,21 @@ where
         //    authenticator-specific way (e.g., flash the LED light). Request permission to create
         //    a credential. If the user declines permission, return the CTAP2_ERR_OPERATION_DENIED
         //    error.
-        let flags = self
+        self.set_effective_options(&input.options, pin_verified);
+        let mut flags = self
             .check_user(
                 UIHint::RequestNewCredential(&input.user.clone().into(), &input.rp),
                 &input.options,
             )
             .await?;
+        if pin_verified {
+            flags |= Flags::UV;
+        }
+        let uvm = if uvm {
+            self.uvm_extension_output(flags, pin_verified).await
+        } else {
+            None
+        };
 
         // 10. If "rk" in options parameter is set to true:
         //     1. If a credential for the same RP ID and account ID already exists on the
@@ -137,34 +297,116 @@ where
         //     3. If authenticator does not have enough internal storage to persist the new
         //        credential, return CTAP2_ERR_KEY_STORE_FULL.
         // --> This seems like in the wrong place since we still need the passkey, see after step 11.
+        if input.options.rk && self.remaining_discoverable_credentials().await == Some(0) {
+            return Err(Ctap2Error::KeyStoreFull.into());
+        }
 
         // 11. Generate an attestation statement for the newly-created key using clientDataHash.
 
+        // When the client only accepts `none` attestation, skip the configured provider entirely
+        // so that no signing work is done for a statement that would be discarded.
+        let none_only = matches!(
+            input.attestation_formats_preference.as_deref(),
+            Some([AttestationStatementFormatIdentifiers::None])
+        );
+        let enterprise_attestation = enterprise_attestation && !none_only;
+        // Self attestation must not identify the authenticator by its AAGUID, unlike enterprise
+        // attestation.
+        let aaguid =
+            if !none_only && !enterprise_attestation && self.attestation.is_self_attestation() {
+                Aaguid::new_empty()
+            } else {
+                *self.aaguid()
+            };
+
         // SAFETY: the only case where this fails is if credential_id's length cannot be represented
         // as a u16. This is checked at step 9, therefore this will never return an error
-        let acd = AttestedCredentialData::new(
-            *self.aaguid(),
-            passkey.credential_id.clone().into(),
-            public,
-        )
-        .unwrap();
+        let acd = AttestedCredentialData::new(aaguid, passkey.credential_id.clone().into(), public)
+            .unwrap();
 
-        let auth_data = AuthenticatorData::new(&input.rp.id, passkey.counter)
+        let counter = match self.counter_mode {
+            CounterMode::Global => Some(self.global_signature_counter),
+            CounterMode::PerCredential => passkey.counter,
+            CounterMode::Disabled => None,
+        };
+        let mut auth_data = self
+            .authenticator_data(&input.rp.id, counter)
             .set_flags(flags)
             .set_attested_credential_data(acd);
+        let mut extension_outputs = Vec::new();
+        if hmac_secret {
+            extension_outputs.push((Value::Text("hmac-secret".into()), Value::Bool(true)));
+        }
+        if let Some(cred_protect) = cred_protect {
+            extension_outputs.push((
+                Value::Text("credProtect".into()),
+                Value::from(cred_protect.ctap_value()),
+            ));
+        }
+        if min_pin_length {
+            extension_outputs.push((
+                Value::Text("minPinLength".into()),
+                Value::from(self.min_pin_length),
+            ));
+        }
+        if let Some(uvm) = uvm {
+            extension_outputs.push((Value::Text("uvm".into()), uvm));
+        }
+        if let Some(device_pub_key) = input
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.device_pub_key.as_ref())
+        {
+            let output = self
+                .device_pub_key_extension_output(
+                    &input.rp.id,
+                    &passkey.credential_id,
+                    &input.client_data_hash,
+                    device_pub_key,
+                )
+                .await?;
+            extension_outputs.push((Value::Text("devicePubKey".into()), output));
+        }
+        if !extension_outputs.is_empty() {
+            auth_data = auth_data.set_extensions(extensions_in_canonical_order(extension_outputs));
+        }
+
+        let (fmt, att_stmt) = if none_only {
+            NoneAttestation::statement()
+        } else {
+            self.attestation
+                .attestation_statement(AttestationInput {
+                    auth_data: &auth_data,
+                    client_data_hash: &input.client_data_hash,
+                    credential_key: &passkey.key,
+                    formats_preference: input.attestation_formats_preference.as_deref(),
+                    transports: &self.transports,
+                    enterprise_attestation,
+                })
+                .await?
+        };
 
         let response = Response {
             auth_data,
-            fmt: "None".into(),
-            att_stmt: vec![0xa0].into(), // CBOR exquivalent to empty map
+            fmt,
+            att_stmt,
+            ep_att: enterprise_attestation.then_some(true),
+            large_blob_key: passkey.extensions.large_blob_key.clone(),
         };
 
         // 10
-        self.store_mut()
-            .save_credential(passkey, input.user.into(), input.rp, input.options)
-            .await?;
+        let (saved, exported) = match export {
+            None => (Some(passkey), None),
+            Some(KeyExport::AlsoStore) => (Some(passkey.clone()), Some(passkey)),
+            Some(KeyExport::ExportOnly) => (None, Some(passkey)),
+        };
+        if let Some(passkey) = saved {
+            self.store_mut()
+                .save_credential(passkey, input.user.into(), input.rp, input.options)
+                .await?;
+        }
 
-        Ok(response)
+        Ok((response, exported))
     }
 }
 
@@ -176,21 +418,23 @@ mod tests {
     use passkey_types::{
         ctap2::{
             make_credential::{
-                Options, PublicKeyCredentialRpEntity, PublicKeyCredentialUserEntity,
+                ExtensionInputs, Options, PublicKeyCredentialRpEntity,
+                PublicKeyCredentialUserEntity,
             },
-            Aaguid,
+            Aaguid, AuthenticatorData,
         },
         rand::random_vec,
         webauthn, Bytes,
     };
 
+    use ciborium::Value;
     use tokio::sync::Mutex;
 
     use super::*;
     use crate::{
         credential_store::{DiscoverabilitySupport, StoreInfo},
         user_validation::{MockUIHint, MockUserValidationMethod},
-        MemoryStore,
+        MemoryStore, SelfAttestation, UserCheck,
     };
 
     fn good_request() -> Request {
@@ -218,6 +462,8 @@ mod tests {
             },
             pin_auth: None,
             pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
         }
     }
 
@@ -257,13 +503,17 @@ mod tests {
         let passkey = Passkey {
             // contents of key doesn't matter, only the id
             key: Default::default(),
-            rp_id: "".into(),
+            rp_id: "future.1password.com".into(),
             credential_id: cred_id.clone(),
             user_handle: Some(response.user.id.clone()),
             counter: None,
+            disabled: false,
+            transports: None,
+            extensions: Default::default(),
+            last_used_at: None,
         };
         let shared_store = Arc::new(Mutex::new(MemoryStore::new()));
-        let user_mock = MockUserValidationMethod::verified_user_with_hint(
+        let user_mock = MockUserValidationMethod::present_user_with_hint(
             1,
             MockUIHint::InformExcludedCredentialFound(passkey.clone()),
         );
@@ -282,6 +532,69 @@ mod tests {
         assert_eq!(shared_store.lock().await.len(), 1);
     }
 
+    #[tokio::test]
+    async fn make_credential_succeeds_when_excluded_credentials_are_not_stored() {
+        let request = Request {
+            exclude_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id: random_vec(16).into(),
+                transports: None,
+            }]),
+            ..good_request()
+        };
+        let shared_store = Arc::new(Mutex::new(MemoryStore::new()));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            shared_store.clone(),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        authenticator
+            .make_credential(request)
+            .await
+            .expect("make credential failed with an exclude list of unknown credentials");
+
+        assert_eq!(shared_store.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn assert_disabled_credentials_are_still_excluded() {
+        let cred_id: Bytes = random_vec(16).into();
+        let request = Request {
+            exclude_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id: cred_id.clone(),
+                transports: None,
+            }]),
+            ..good_request()
+        };
+        let passkey = Passkey {
+            key: Default::default(),
+            rp_id: "future.1password.com".into(),
+            credential_id: cred_id.clone(),
+            user_handle: Some(request.user.id.clone()),
+            counter: None,
+            disabled: true,
+            transports: None,
+            extensions: Default::default(),
+            last_used_at: None,
+        };
+        let mut store = MemoryStore::new();
+        store.insert(cred_id.into(), passkey.clone());
+        let user_mock = MockUserValidationMethod::present_user_with_hint(
+            1,
+            MockUIHint::InformExcludedCredentialFound(passkey),
+        );
+        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_mock);
+
+        let err = authenticator
+            .make_credential(request)
+            .await
+            .expect_err("make credential succeded even though a disabled credential is excluded");
+
+        assert_eq!(err, Ctap2Error::CredentialExcluded.into());
+    }
+
     #[tokio::test]
     async fn assert_unsupported_algorithm() {
         let user_mock = MockUserValidationMethod::verified_user(0);
@@ -305,76 +618,1352 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn make_credential_counter_is_some_0_when_counters_are_enabled() {
+    async fn make_credential_creates_rsa_keys_for_rs256() {
         // Arrange
-        let shared_store = Arc::new(Mutex::new(None));
         let user_mock = MockUserValidationMethod::verified_user(1);
-        let request = good_request();
         let mut authenticator =
-            Authenticator::new(Aaguid::new_empty(), shared_store.clone(), user_mock);
-        authenticator.set_make_credentials_with_signature_counter(true);
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let request = Request {
+            pub_key_cred_params: vec![
+                webauthn::PublicKeyCredentialParameters {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    alg: iana::Algorithm::RSAES_OAEP_SHA_256,
+                },
+                webauthn::PublicKeyCredentialParameters {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    alg: iana::Algorithm::RS256,
+                },
+            ],
+            ..good_request()
+        };
 
         // Act
-        authenticator.make_credential(request).await.unwrap();
+        let response = authenticator.make_credential(request).await.unwrap();
+        let info = authenticator.get_info().await;
 
         // Assert
-        let store = shared_store.lock().await;
-        assert_eq!(store.as_ref().and_then(|c| c.counter).unwrap(), 0);
+        let key = response.auth_data.attested_credential_data.unwrap().key;
+        assert_eq!(
+            key.kty,
+            coset::RegisteredLabel::Assigned(iana::KeyType::RSA)
+        );
+        assert_eq!(
+            key.alg,
+            Some(coset::RegisteredLabelWithPrivate::Assigned(
+                iana::Algorithm::RS256
+            ))
+        );
+        assert!(info
+            .algorithms
+            .unwrap()
+            .contains(&webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::RS256,
+            }));
     }
 
     #[tokio::test]
-    async fn make_credential_returns_err_when_rk_is_requested_but_not_supported() {
-        struct StoreWithoutDiscoverableSupport;
-        #[async_trait::async_trait]
-        impl CredentialStore for StoreWithoutDiscoverableSupport {
-            type PasskeyItem = Passkey;
+    async fn credential_ids_are_drawn_from_the_credential_id_rng() {
+        use rand::SeedableRng;
 
-            async fn find_credentials(
-                &self,
-                _id: Option<&[webauthn::PublicKeyCredentialDescriptor]>,
-                _rp_id: &str,
-            ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
-                #![allow(clippy::unimplemented)]
-                unimplemented!("The test should not call find_credentials")
-            }
+        // Arrange
+        let mut authenticators = [0, 1].map(|_| {
+            let mut authenticator = Authenticator::new(
+                Aaguid::new_empty(),
+                MemoryStore::new(),
+                MockUserValidationMethod::verified_user(1),
+            );
+            authenticator.set_credential_id_rng(rand::rngs::StdRng::seed_from_u64(7));
+            authenticator
+        });
 
-            async fn save_credential(
-                &mut self,
-                _cred: Passkey,
-                _user: PublicKeyCredentialUserEntity,
-                _rp: PublicKeyCredentialRpEntity,
-                _options: Options,
-            ) -> Result<(), StatusCode> {
-                #![allow(clippy::unimplemented)]
-                unimplemented!("The test should not call save_credential")
-            }
+        // Act
+        let mut credentials = Vec::new();
+        for authenticator in &mut authenticators {
+            let response = authenticator.make_credential(good_request()).await.unwrap();
+            credentials.push(response.auth_data.attested_credential_data.unwrap());
+        }
 
-            async fn update_credential(&mut self, _cred: Passkey) -> Result<(), StatusCode> {
-                #![allow(clippy::unimplemented)]
-                unimplemented!("The test should not call update_credential")
-            }
+        // Assert
+        assert_eq!(
+            credentials[0].credential_id(),
+            credentials[1].credential_id()
+        );
+        assert_ne!(credentials[0].key, credentials[1].key);
+    }
 
-            async fn get_info(&self) -> StoreInfo {
-                StoreInfo {
-                    discoverability: DiscoverabilitySupport::OnlyNonDiscoverable,
-                }
-            }
+    /// Produces a recognizable attestation statement and records every call.
+    #[derive(Clone, Default)]
+    struct RecordingAttestation {
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl RecordingAttestation {
+        fn calls(&self) -> usize {
+            self.calls.load(std::sync::atomic::Ordering::SeqCst)
         }
+    }
+
+    #[async_trait::async_trait]
+    impl crate::AttestationStatementProvider for RecordingAttestation {
+        async fn attestation_statement(
+            &self,
+            input: AttestationInput<'_>,
+        ) -> Result<(String, Value), Ctap2Error> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok((
+                "org.example.recording".into(),
+                Value::Map(vec![(
+                    Value::Text("clientDataHash".into()),
+                    Value::Bytes(input.client_data_hash.to_vec()),
+                )]),
+            ))
+        }
+    }
 
+    #[tokio::test]
+    async fn make_credential_returns_empty_none_attestation_when_none_is_preferred() {
         // Arrange
-        let store = StoreWithoutDiscoverableSupport;
-        let user_mock = MockUserValidationMethod::verified_user(0);
+        let user_mock = MockUserValidationMethod::verified_user(1);
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let provider = RecordingAttestation::default();
+        authenticator.set_attestation_statement_provider(provider.clone());
+        let request = Request {
+            attestation_formats_preference: Some(vec![AttestationStatementFormatIdentifiers::None]),
+            ..good_request()
+        };
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.fmt, "none");
+        assert_eq!(response.att_stmt, Value::Map(Vec::new()));
+        assert!(response.is_none_attestation());
+        assert_eq!(provider.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn make_credential_skips_enterprise_attestation_when_none_is_preferred() {
+        // Arrange
+        let user_mock = MockUserValidationMethod::verified_user(1);
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        authenticator.set_enterprise_attestation_rp_ids(Some(vec!["future.1password.com".into()]));
+        let provider = RecordingAttestation::default();
+        authenticator.set_attestation_statement_provider(provider.clone());
+        let request = Request {
+            enterprise_attestation: Some(ENTERPRISE_ATTESTATION_VENDOR_FACILITATED),
+            attestation_formats_preference: Some(vec![AttestationStatementFormatIdentifiers::None]),
+            ..good_request()
+        };
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert!(response.is_none_attestation());
+        assert_eq!(response.ep_att, None);
+        assert_eq!(provider.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn make_credential_uses_custom_attestation_statement_provider() {
+        // Arrange
+        let user_mock = MockUserValidationMethod::verified_user(1);
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let provider = RecordingAttestation::default();
+        authenticator.set_attestation_statement_provider(provider.clone());
         let request = good_request();
-        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_mock);
-        authenticator.set_make_credentials_with_signature_counter(true);
+        let client_data_hash = request.client_data_hash.to_vec();
 
         // Act
-        let err = authenticator
-            .make_credential(request)
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert_eq!(provider.calls(), 1);
+        let attestation_object: Value =
+            ciborium::de::from_reader(response.as_bytes().as_slice()).unwrap();
+        let attestation_object = attestation_object.into_map().unwrap();
+        let field = |name: &str| {
+            attestation_object
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(field("fmt"), Value::Text("org.example.recording".into()));
+        assert_eq!(
+            field("attStmt"),
+            Value::Map(vec![(
+                Value::Text("clientDataHash".into()),
+                Value::Bytes(client_data_hash),
+            )])
+        );
+    }
+
+    #[tokio::test]
+    async fn enterprise_attestation_is_only_produced_for_allowlisted_rp_ids() {
+        // Arrange
+        let aaguid = Aaguid::from_uuid_str("ee882879-721c-4913-9775-3dfcce97072a").unwrap();
+        let user_mock = MockUserValidationMethod::verified_user(2);
+        let mut authenticator = Authenticator::new(aaguid, MemoryStore::new(), user_mock);
+        authenticator.set_attestation_statement_provider(SelfAttestation);
+        authenticator.set_enterprise_attestation_rp_ids(Some(vec!["future.1password.com".into()]));
+        let enterprise_request = |rp_id: &str| Request {
+            rp: PublicKeyCredentialRpEntity {
+                id: rp_id.into(),
+                name: None,
+            },
+            enterprise_attestation: Some(ENTERPRISE_ATTESTATION_VENDOR_FACILITATED),
+            ..good_request()
+        };
+
+        // Act
+        let allowlisted = authenticator
+            .make_credential(enterprise_request("future.1password.com"))
             .await
-            .expect_err("Succeeded with unsupported rk");
+            .unwrap();
+        let other = authenticator
+            .make_credential(enterprise_request("example.com"))
+            .await
+            .unwrap();
 
         // Assert
-        assert_eq!(err, Ctap2Error::UnsupportedOption.into());
+        assert!(authenticator.get_info().await.options.unwrap().ep.unwrap());
+        assert_eq!(allowlisted.ep_att, Some(true));
+        // The self attestation of the enterprise reports the AAGUID of the authenticator.
+        assert_eq!(
+            allowlisted
+                .auth_data
+                .attested_credential_data
+                .unwrap()
+                .aaguid,
+            aaguid
+        );
+        assert_eq!(other.ep_att, None);
+        assert_eq!(
+            other.auth_data.attested_credential_data.unwrap().aaguid,
+            Aaguid::new_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn enterprise_attestation_is_rejected_when_it_is_not_enabled() {
+        // Arrange
+        let user_mock = MockUserValidationMethod::verified_user(0);
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let request = Request {
+            enterprise_attestation: Some(ENTERPRISE_ATTESTATION_VENDOR_FACILITATED),
+            ..good_request()
+        };
+
+        // Act
+        let result = authenticator.make_credential(request).await;
+
+        // Assert
+        assert_eq!(result.unwrap_err(), U2FError::InvalidParameter.into());
+        assert_eq!(authenticator.get_info().await.options.unwrap().ep, None);
+    }
+
+    /// A self-signed DER certificate for the batch attestation key.
+    fn batch_certificate(batch_key: &p256::SecretKey) -> Vec<u8> {
+        use p256::ecdsa::{DerSignature, SigningKey};
+        use std::{str::FromStr, time::Duration};
+        use x509_cert::{
+            builder::{Builder, CertificateBuilder, Profile},
+            der::Encode,
+            name::Name,
+            serial_number::SerialNumber,
+            spki::SubjectPublicKeyInfoOwned,
+            time::Validity,
+        };
+
+        let signer = SigningKey::from(batch_key);
+        CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            Name::from_str("CN=Batch,OU=Authenticator Attestation,O=1Password,C=CA").unwrap(),
+            SubjectPublicKeyInfoOwned::from_key(*signer.verifying_key()).unwrap(),
+            &signer,
+        )
+        .unwrap()
+        .build::<DerSignature>()
+        .unwrap()
+        .to_der()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn make_credential_returns_packed_attestation_signed_by_the_batch_key() {
+        use p256::{
+            ecdsa::{signature::Verifier, Signature, VerifyingKey},
+            pkcs8::DecodePublicKey,
+        };
+        use x509_cert::{
+            der::{Decode, Encode},
+            Certificate,
+        };
+
+        // Arrange
+        let batch_key = p256::SecretKey::random(&mut rand::thread_rng());
+        let certificate = batch_certificate(&batch_key);
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(crate::PackedAttestation::new(
+            vec![certificate.into()],
+            batch_key,
+        ));
+        let request = Request {
+            attestation_formats_preference: Some(vec![
+                AttestationStatementFormatIdentifiers::Packed,
+            ]),
+            ..good_request()
+        };
+        let client_data_hash = request.client_data_hash.to_vec();
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let attestation_object: Value =
+            ciborium::de::from_reader(response.as_bytes().as_slice()).unwrap();
+        let field = |map: &Value, name: &str| {
+            map.as_map()
+                .unwrap()
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            field(&attestation_object, "fmt"),
+            Value::Text("packed".into())
+        );
+        let statement = field(&attestation_object, "attStmt");
+        assert_eq!(field(&statement, "alg"), Value::from(-7));
+        let x5c = field(&statement, "x5c").into_array().unwrap();
+        let leaf = Certificate::from_der(x5c[0].as_bytes().unwrap()).unwrap();
+        let public_key = VerifyingKey::from_public_key_der(
+            &leaf
+                .tbs_certificate
+                .subject_public_key_info
+                .to_der()
+                .unwrap(),
+        )
+        .unwrap();
+        let signature = Signature::from_der(field(&statement, "sig").as_bytes().unwrap()).unwrap();
+        let mut signed_data = response.auth_data.to_vec();
+        signed_data.extend_from_slice(&client_data_hash);
+        public_key
+            .verify(&signed_data, &signature)
+            .expect("the attestation signature does not match the leaf certificate");
+    }
+
+    #[tokio::test]
+    async fn make_credential_returns_fido_u2f_attestation_when_preferred() {
+        use p256::{
+            ecdsa::{signature::Verifier, Signature, VerifyingKey},
+            elliptic_curve::sec1::ToEncodedPoint,
+        };
+
+        // Arrange
+        let batch_key = p256::SecretKey::random(&mut rand::thread_rng());
+        let certificate = batch_certificate(&batch_key);
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(crate::FidoU2fAttestation::new(
+            certificate.clone().into(),
+            batch_key.clone(),
+        ));
+        let request = Request {
+            attestation_formats_preference: Some(vec![
+                AttestationStatementFormatIdentifiers::FidoU2f,
+            ]),
+            ..good_request()
+        };
+        let client_data_hash = request.client_data_hash.to_vec();
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.fmt, "fido-u2f");
+        let statement = response.att_stmt.as_map().unwrap();
+        let field = |name: &str| {
+            statement
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(field("x5c"), Value::Array(vec![Value::Bytes(certificate)]));
+        let acd = response
+            .auth_data
+            .attested_credential_data
+            .as_ref()
+            .unwrap();
+        let credential_key = crate::ec2_public_key_from_cose_key(&acd.key).unwrap();
+        let mut signed_data = vec![0x00];
+        signed_data.extend_from_slice(response.auth_data.rp_id_hash());
+        signed_data.extend_from_slice(&client_data_hash);
+        signed_data.extend_from_slice(acd.credential_id());
+        signed_data.extend_from_slice(credential_key.to_encoded_point(false).as_bytes());
+        let signature = Signature::from_der(field("sig").as_bytes().unwrap()).unwrap();
+        VerifyingKey::from(batch_key.public_key())
+            .verify(&signed_data, &signature)
+            .expect("the fido-u2f signature does not match the batch key");
+    }
+
+    #[tokio::test]
+    async fn make_credential_rejects_fido_u2f_attestation_of_non_es256_credentials() {
+        // Arrange
+        let batch_key = p256::SecretKey::random(&mut rand::thread_rng());
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(crate::FidoU2fAttestation::new(
+            batch_certificate(&batch_key).into(),
+            batch_key,
+        ));
+        let request = Request {
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::EdDSA,
+            }],
+            attestation_formats_preference: Some(vec![
+                AttestationStatementFormatIdentifiers::FidoU2f,
+            ]),
+            ..good_request()
+        };
+
+        // Act
+        let result = authenticator.make_credential(request).await;
+
+        // Assert
+        assert_eq!(result.unwrap_err(), Ctap2Error::UnsupportedAlgorithm.into());
+        assert!(authenticator.store().is_empty());
+    }
+
+    #[tokio::test]
+    async fn make_credential_self_attests_with_the_credential_key() {
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::from([7; 16]),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(SelfAttestation);
+        let request = good_request();
+        let client_data_hash = request.client_data_hash.to_vec();
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.fmt, "packed");
+        let statement = response.att_stmt.as_map().unwrap();
+        let field = |name: &str| {
+            statement
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field("alg"), Some(Value::from(-7)));
+        assert_eq!(field("x5c"), None);
+        let acd = response
+            .auth_data
+            .attested_credential_data
+            .as_ref()
+            .unwrap();
+        assert_eq!(acd.aaguid, Aaguid::new_empty());
+        let public_key = VerifyingKey::from(crate::ec2_public_key_from_cose_key(&acd.key).unwrap());
+        let signature = Signature::from_der(field("sig").unwrap().as_bytes().unwrap()).unwrap();
+        let mut signed_data = response.auth_data.to_vec();
+        signed_data.extend_from_slice(&client_data_hash);
+        public_key
+            .verify(&signed_data, &signature)
+            .expect("the self attestation signature does not match the credential key");
+    }
+
+    #[tokio::test]
+    async fn make_credential_public_key_alg_follows_the_setting() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+
+        // Act
+        let included = authenticator.make_credential(good_request()).await.unwrap();
+        authenticator.set_public_key_alg_included(false);
+        let omitted = authenticator.make_credential(good_request()).await.unwrap();
+
+        // Assert
+        let public_key = |response: &Response| {
+            response
+                .auth_data
+                .attested_credential_data
+                .as_ref()
+                .unwrap()
+                .key
+                .clone()
+        };
+        let (included, omitted) = (public_key(&included), public_key(&omitted));
+        assert_eq!(
+            included.alg,
+            Some(coset::RegisteredLabelWithPrivate::Assigned(
+                iana::Algorithm::ES256
+            ))
+        );
+        assert_eq!(omitted.alg, None);
+        assert_eq!(
+            crate::public_key_algorithm(&omitted),
+            Some(iana::Algorithm::ES256)
+        );
+        // The stored private keys keep the algorithm they sign with.
+        assert!(authenticator
+            .store()
+            .values()
+            .all(|passkey| passkey.key.alg == included.alg));
+    }
+
+    #[tokio::test]
+    async fn make_credential_exporting_key_returns_the_signing_key() {
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+        use passkey_types::ctap2::get_assertion;
+
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+
+        // Act
+        let (stored, stored_passkey) = authenticator
+            .make_credential_exporting_key(good_request(), KeyExport::AlsoStore)
+            .await
+            .unwrap();
+        let (exported, exported_passkey) = authenticator
+            .make_credential_exporting_key(good_request(), KeyExport::ExportOnly)
+            .await
+            .unwrap();
+
+        // Assert
+        let stored_id = stored
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .to_vec();
+        assert_eq!(authenticator.store()[&stored_id].key, stored_passkey.key);
+        assert_eq!(authenticator.store().len(), 1);
+
+        // The exported key signs the assertions of the credential once it is imported.
+        let acd = exported.auth_data.attested_credential_data.unwrap();
+        let mut importer = Authenticator::new(
+            Aaguid::new_empty(),
+            Some(exported_passkey),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let client_data_hash = random_vec(32);
+        let assertion = importer
+            .get_assertion(get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: client_data_hash.clone().into(),
+                allow_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: acd.credential_id().to_vec().into(),
+                    transports: None,
+                }]),
+                extensions: None,
+                options: Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await
+            .unwrap();
+        let public_key = VerifyingKey::from(crate::ec2_public_key_from_cose_key(&acd.key).unwrap());
+        let signature = Signature::from_der(&assertion.signature).unwrap();
+        let mut signed_data = assertion.auth_data.to_vec();
+        signed_data.extend_from_slice(&client_data_hash);
+        public_key
+            .verify(&signed_data, &signature)
+            .expect("the exported key does not sign for the credential");
+    }
+
+    #[tokio::test]
+    async fn make_credential_auth_data_has_attested_credential_data_flag() {
+        // Arrange
+        let request = good_request();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let auth_data = AuthenticatorData::from_slice(&response.auth_data.to_vec())
+            .expect("could not decode the authenticator data");
+        assert!(auth_data.flags.attested_credential_data_included());
+        assert!(auth_data.attested_credential_data.is_some());
+    }
+
+    fn request_without_uv() -> Request {
+        Request {
+            options: Options {
+                rk: false,
+                up: true,
+                uv: false,
+            },
+            ..good_request()
+        }
+    }
+
+    #[tokio::test]
+    async fn make_credential_without_uv_is_only_allowed_with_make_cred_uv_not_rqd() {
+        // Arrange
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true));
+        user_mock.expect_is_presence_enabled().returning(|| true);
+        user_mock
+            .expect_check_user()
+            .with(
+                mockall::predicate::always(),
+                mockall::predicate::eq(true),
+                mockall::predicate::eq(false),
+            )
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: true,
+                    verification: false,
+                })
+            })
+            .once();
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+
+        // Act
+        let allowed_info = authenticator.get_info().await.options.unwrap();
+        let allowed = authenticator.make_credential(request_without_uv()).await;
+        authenticator.set_make_credential_uv_not_required(false);
+        let required_info = authenticator.get_info().await.options.unwrap();
+        let required = authenticator.make_credential(request_without_uv()).await;
+
+        // Assert
+        assert_eq!(allowed_info.make_cred_uv_not_rqd, Some(true));
+        assert!(!allowed
+            .expect("failed to make a credential without UV")
+            .auth_data
+            .flags
+            .user_verified());
+        assert_eq!(required_info.make_cred_uv_not_rqd, Some(false));
+        assert_eq!(required.unwrap_err(), Ctap2Error::PuatRequired.into());
+        assert_eq!(authenticator.store().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn make_credential_verifies_the_user_when_always_uv_is_set() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_always_uv(true);
+
+        // Act
+        let info = authenticator.get_info().await.options.unwrap();
+        let response = authenticator
+            .make_credential(request_without_uv())
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(info.always_uv, Some(true));
+        assert_eq!(info.make_cred_uv_not_rqd, Some(false));
+        assert!(response.auth_data.flags.user_verified());
+    }
+
+    #[tokio::test]
+    async fn make_credential_counter_is_some_0_when_counters_are_enabled() {
+        // Arrange
+        let shared_store = Arc::new(Mutex::new(None));
+        let user_mock = MockUserValidationMethod::verified_user(1);
+        let request = good_request();
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), shared_store.clone(), user_mock);
+        authenticator.set_make_credentials_with_signature_counter(true);
+
+        // Act
+        authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let store = shared_store.lock().await;
+        assert_eq!(store.as_ref().and_then(|c| c.counter).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn make_credential_counter_starts_at_the_initial_signature_counter() {
+        // Arrange
+        let request = good_request();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        authenticator.set_initial_signature_counter(5);
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+        let credential_id = response
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .to_vec();
+        let assertion = authenticator
+            .get_assertion(passkey_types::ctap2::get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: random_vec(32).into(),
+                allow_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: credential_id.into(),
+                    transports: None,
+                }]),
+                extensions: None,
+                options: Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(response.auth_data.counter, Some(5));
+        assert_eq!(assertion.auth_data.counter, Some(6));
+    }
+
+    #[tokio::test]
+    async fn make_credential_counter_saturates_at_the_maximum_signature_counter() {
+        // Arrange
+        let request = good_request();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        authenticator.set_initial_signature_counter(u32::MAX);
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+        let credential_id = response
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .to_vec();
+        let assertion = authenticator
+            .get_assertion(passkey_types::ctap2::get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: random_vec(32).into(),
+                allow_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: credential_id.into(),
+                    transports: None,
+                }]),
+                extensions: None,
+                options: Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(response.auth_data.counter, Some(u32::MAX));
+        assert_eq!(assertion.auth_data.counter, Some(u32::MAX));
+    }
+
+    #[tokio::test]
+    async fn make_credential_returns_err_when_rk_is_requested_but_not_supported() {
+        struct StoreWithoutDiscoverableSupport;
+        #[async_trait::async_trait]
+        impl CredentialStore for StoreWithoutDiscoverableSupport {
+            type PasskeyItem = Passkey;
+
+            async fn find_credentials(
+                &self,
+                _id: Option<&[webauthn::PublicKeyCredentialDescriptor]>,
+                _rp_id: &str,
+            ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+                #![allow(clippy::unimplemented)]
+                unimplemented!("The test should not call find_credentials")
+            }
+
+            async fn save_credential(
+                &mut self,
+                _cred: Passkey,
+                _user: PublicKeyCredentialUserEntity,
+                _rp: PublicKeyCredentialRpEntity,
+                _options: Options,
+            ) -> Result<(), StatusCode> {
+                #![allow(clippy::unimplemented)]
+                unimplemented!("The test should not call save_credential")
+            }
+
+            async fn update_credential(&mut self, _cred: Passkey) -> Result<(), StatusCode> {
+                #![allow(clippy::unimplemented)]
+                unimplemented!("The test should not call update_credential")
+            }
+
+            async fn get_info(&self) -> StoreInfo {
+                StoreInfo {
+                    discoverability: DiscoverabilitySupport::OnlyNonDiscoverable,
+                }
+            }
+        }
+
+        // Arrange
+        let store = StoreWithoutDiscoverableSupport;
+        let user_mock = MockUserValidationMethod::verified_user(0);
+        let request = good_request();
+        let mut authenticator = Authenticator::new(Aaguid::new_empty(), store, user_mock);
+        authenticator.set_make_credentials_with_signature_counter(true);
+
+        // Act
+        let err = authenticator
+            .make_credential(request)
+            .await
+            .expect_err("Succeeded with unsupported rk");
+
+        // Assert
+        assert_eq!(err, Ctap2Error::UnsupportedOption.into());
+    }
+
+    #[tokio::test]
+    async fn remaining_discoverable_credentials_follow_the_store_usage() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(4),
+        );
+        authenticator.set_max_discoverable_credentials(Some(3));
+        assert_eq!(
+            authenticator
+                .get_info()
+                .await
+                .remaining_discoverable_credentials,
+            Some(3)
+        );
+
+        // Act
+        for _ in 0..2 {
+            authenticator
+                .make_credential(good_request())
+                .await
+                .expect("error happened while trying to make a new credential");
+        }
+        let remaining_after_two = authenticator
+            .get_info()
+            .await
+            .remaining_discoverable_credentials;
+        authenticator
+            .make_credential(good_request())
+            .await
+            .expect("error happened while trying to make a new credential");
+        let full_err = authenticator
+            .make_credential(good_request())
+            .await
+            .expect_err("Succeeded with a full key store");
+        // Resetting the authenticator clears all of its credentials.
+        authenticator.store_mut().clear();
+
+        // Assert
+        assert_eq!(remaining_after_two, Some(1));
+        assert_eq!(full_err, Ctap2Error::KeyStoreFull.into());
+        assert_eq!(
+            authenticator
+                .get_info()
+                .await
+                .remaining_discoverable_credentials,
+            Some(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn make_credential_is_denied_without_user_presence() {
+        // Arrange
+        let shared_store = Arc::new(Mutex::new(MemoryStore::new()));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            shared_store.clone(),
+            MockUserValidationMethod::absent_user(1),
+        );
+
+        // Act
+        let err = authenticator
+            .make_credential(good_request())
+            .await
+            .expect_err("Succeeded without user presence");
+
+        // Assert
+        assert_eq!(err, Ctap2Error::OperationDenied.into());
+        assert!(shared_store.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn make_credential_stores_the_credential_protection_policy() {
+        // Arrange
+        let shared_store = Arc::new(Mutex::new(MemoryStore::new()));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            shared_store.clone(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                cred_protect: Some(CredentialProtectionPolicy::UserVerificationRequired),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert_eq!(
+            response.auth_data.extensions,
+            Some(Value::Map(vec![(
+                Value::Text("credProtect".into()),
+                Value::from(0x03)
+            )]))
+        );
+        let store = shared_store.lock().await;
+        assert_eq!(
+            store.values().next().unwrap().extensions.cred_protect,
+            Some(CredentialProtectionPolicy::UserVerificationRequired)
+        );
+    }
+
+    #[tokio::test]
+    async fn effective_options_report_uv_forced_by_the_credential_protection_policy() {
+        // Arrange
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true));
+        user_mock.expect_is_presence_enabled().returning(|| true);
+        user_mock
+            .expect_check_user()
+            .with(
+                mockall::predicate::always(),
+                mockall::predicate::eq(true),
+                mockall::predicate::eq(true),
+            )
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: true,
+                    verification: true,
+                })
+            })
+            .once();
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        authenticator.set_default_credential_protection_policy(Some(
+            CredentialProtectionPolicy::UserVerificationRequired,
+        ));
+        let mut request = good_request();
+        request.options.uv = false;
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        assert!(response.auth_data.flags.contains(Flags::UV));
+        assert_eq!(
+            authenticator.effective_options(),
+            Some(&Options {
+                rk: true,
+                up: true,
+                uv: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn effective_options_are_cleared_by_a_failed_ceremony() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.make_credential(good_request()).await.unwrap();
+        let mut request = good_request();
+        request.pub_key_cred_params.clear();
+
+        // Act
+        let before = authenticator.effective_options().cloned();
+        let err = authenticator.make_credential(request).await;
+
+        // Assert
+        assert_eq!(before.map(|options| options.uv), Some(true));
+        assert_eq!(err.err(), Some(Ctap2Error::UnsupportedAlgorithm.into()));
+        assert_eq!(authenticator.effective_options(), None);
+    }
+
+    #[tokio::test]
+    async fn min_pin_length_is_only_reported_to_allowed_relying_parties() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+        authenticator.set_min_pin_length(6);
+        authenticator.set_min_pin_length_rp_ids(vec!["future.1password.com".into()]);
+        let request = |rp_id: &str| {
+            let mut request = Request {
+                extensions: Some(ExtensionInputs {
+                    min_pin_length: Some(true),
+                    ..Default::default()
+                }),
+                ..good_request()
+            };
+            request.rp.id = rp_id.into();
+            request
+        };
+
+        // Act
+        let allowed = authenticator
+            .make_credential(request("future.1password.com"))
+            .await
+            .unwrap();
+        let other = authenticator
+            .make_credential(request("1password.com"))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            allowed.auth_data.extensions,
+            Some(Value::Map(vec![(
+                Value::Text("minPinLength".into()),
+                Value::from(6)
+            )]))
+        );
+        assert_eq!(other.auth_data.extensions, None);
+    }
+
+    fn request_with_large_blob_key(rk: bool) -> Request {
+        let mut request = good_request();
+        request.options.rk = rk;
+        request.extensions = Some(ExtensionInputs {
+            large_blob_key: Some(true),
+            ..Default::default()
+        });
+        request
+    }
+
+    #[tokio::test]
+    async fn make_credential_returns_the_stored_large_blob_key() {
+        // Arrange
+        let shared_store = Arc::new(Mutex::new(MemoryStore::new()));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            shared_store.clone(),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        // Act
+        let response = authenticator
+            .make_credential(request_with_large_blob_key(true))
+            .await
+            .unwrap();
+
+        // Assert
+        let large_blob_key = response.large_blob_key.expect("no largeBlobKey returned");
+        assert_eq!(large_blob_key.len(), 32);
+        assert!(response.auth_data.extensions.is_none());
+        let store = shared_store.lock().await;
+        let stored = store.values().next().unwrap();
+        assert_eq!(stored.extensions.large_blob_key, Some(large_blob_key));
+    }
+
+    #[tokio::test]
+    async fn make_credential_large_blob_key_requires_a_discoverable_credential() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(0),
+        );
+
+        // Act
+        let err = authenticator
+            .make_credential(request_with_large_blob_key(false))
+            .await
+            .expect_err("Created a non-discoverable credential with a largeBlobKey");
+
+        // Assert
+        assert_eq!(err, Ctap2Error::InvalidOption.into());
+        assert!(authenticator.store().is_empty());
+    }
+
+    #[tokio::test]
+    async fn make_credential_ignores_large_blob_key_without_large_blob_storage() {
+        // Arrange
+        let shared_store = Arc::new(Mutex::new(None));
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            shared_store.clone(),
+            MockUserValidationMethod::verified_user(1),
+        );
+
+        // Act
+        let response = authenticator
+            .make_credential(request_with_large_blob_key(true))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(response.large_blob_key, None);
+        let store = shared_store.lock().await;
+        assert_eq!(store.as_ref().unwrap().extensions.large_blob_key, None);
+    }
+
+    #[tokio::test]
+    async fn uvm_reports_how_the_user_was_verified_when_requested() {
+        // Arrange
+        let mut user_mock = MockUserValidationMethod::verified_user(2);
+        user_mock
+            .expect_verification_method()
+            .with(mockall::predicate::eq(UserCheck {
+                presence: true,
+                verification: true,
+            }))
+            .returning(|_| Some(crate::VerificationMethod::FingerprintInternal))
+            .once();
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                uvm: Some(true),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+
+        // Act
+        let requested = authenticator.make_credential(request).await.unwrap();
+        let not_requested = authenticator.make_credential(good_request()).await.unwrap();
+
+        // Assert
+        let outputs = requested.auth_data.extensions().unwrap().unwrap();
+        // USER_VERIFY_FINGERPRINT_INTERNAL, KEY_PROTECTION_SOFTWARE, MATCHER_PROTECTION_SOFTWARE
+        assert_eq!(outputs.uvm, Some(vec![[0x0002, 0x0001, 0x0001]]));
+        assert_eq!(not_requested.auth_data.extensions, None);
+    }
+
+    #[tokio::test]
+    async fn extension_outputs_are_in_canonical_order() {
+        // Arrange
+        let mut user_mock = MockUserValidationMethod::verified_user(1);
+        user_mock
+            .expect_verification_method()
+            .returning(|_| Some(crate::VerificationMethod::FingerprintInternal))
+            .once();
+        let mut authenticator =
+            Authenticator::new(Aaguid::new_empty(), MemoryStore::new(), user_mock);
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                cred_protect: Some(CredentialProtectionPolicy::UserVerificationOptional),
+                uvm: Some(true),
+                device_pub_key: Some(Default::default()),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let keys: Vec<_> = response
+            .auth_data
+            .extensions
+            .unwrap()
+            .into_map()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key.into_text().unwrap())
+            .collect();
+        assert_eq!(keys, ["uvm", "credProtect", "devicePubKey"]);
+    }
+
+    #[tokio::test]
+    async fn device_pub_key_attests_a_key_of_this_device_when_requested() {
+        use coset::iana::EnumI64;
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+        use passkey_types::ctap2::DevicePublicKeyAttestation;
+
+        // Arrange
+        let aaguid = Aaguid::from_uuid_str("ee882879-721c-4913-9775-3dfcce97072a").unwrap();
+        let mut authenticator = Authenticator::new(
+            aaguid,
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                device_pub_key: Some(Default::default()),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+        let client_data_hash = request.client_data_hash.to_vec();
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let outputs = response.auth_data.extensions().unwrap().unwrap();
+        let attestation =
+            DevicePublicKeyAttestation::from_slice(&outputs.device_pub_key.unwrap()).unwrap();
+        assert_eq!(attestation.aaguid, aaguid);
+        assert_eq!(attestation.scope, 0);
+        assert!(attestation.nonce.is_empty());
+        assert_eq!(attestation.fmt, "none");
+        assert_eq!(attestation.att_stmt, Value::Map(Vec::new()));
+
+        let credential_id = response
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .to_vec();
+        assert!(authenticator.device_key(&credential_id).is_some());
+        let dpk = attestation.device_public_key().unwrap();
+        // The private key of the device is never disclosed.
+        assert!(!dpk
+            .params
+            .iter()
+            .any(|(label, _)| *label == coset::Label::Int(iana::Ec2KeyParameter::D.to_i64())));
+        let device_public_key = crate::ec2_public_key_from_cose_key(&dpk).unwrap();
+        let mut signed_data = client_data_hash;
+        signed_data.extend_from_slice(&credential_id);
+        VerifyingKey::from(device_public_key)
+            .verify(
+                &signed_data,
+                &Signature::from_der(&attestation.sig).unwrap(),
+            )
+            .expect("the signature does not match the device public key");
+    }
+
+    #[tokio::test]
+    async fn device_pub_key_is_self_attested_when_direct_attestation_is_requested() {
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+        use passkey_types::{
+            ctap2::{AttestedCredentialData, DevicePublicKeyAttestation},
+            webauthn::{
+                AttestationConveyancePreference, AuthenticationExtensionsDevicePublicKeyInputs,
+            },
+        };
+
+        // Arrange
+        let aaguid = Aaguid::from_uuid_str("ee882879-721c-4913-9775-3dfcce97072a").unwrap();
+        let mut authenticator = Authenticator::new(
+            aaguid,
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(SelfAttestation);
+        let request = Request {
+            extensions: Some(ExtensionInputs {
+                device_pub_key: Some(AuthenticationExtensionsDevicePublicKeyInputs {
+                    attestation: AttestationConveyancePreference::Direct,
+                    attestation_formats: vec![AttestationStatementFormatIdentifiers::Packed],
+                }),
+                ..Default::default()
+            }),
+            ..good_request()
+        };
+        let client_data_hash = request.client_data_hash.to_vec();
+
+        // Act
+        let response = authenticator.make_credential(request).await.unwrap();
+
+        // Assert
+        let outputs = response.auth_data.extensions().unwrap().unwrap();
+        let attestation =
+            DevicePublicKeyAttestation::from_slice(&outputs.device_pub_key.unwrap()).unwrap();
+        assert_eq!(attestation.fmt, "packed");
+        // Self attestation does not identify the authenticator.
+        assert_eq!(attestation.aaguid, Aaguid::new_empty());
+        let statement = attestation.att_stmt.as_map().unwrap();
+        let field = |name: &str| {
+            statement
+                .iter()
+                .find(|(key, _)| key.as_text() == Some(name))
+                .map(|(_, value)| value.clone())
+                .unwrap()
+        };
+        assert_eq!(field("alg"), Value::from(-7));
+
+        let credential_id = response
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .to_vec();
+        let dpk = attestation.device_public_key().unwrap();
+        let acd =
+            AttestedCredentialData::new(Aaguid::new_empty(), credential_id, dpk.clone()).unwrap();
+        let mut signed_data = AuthenticatorData::new("future.1password.com", None)
+            .set_attested_credential_data(acd)
+            .to_vec();
+        signed_data.extend_from_slice(&client_data_hash);
+        let signature = Signature::from_der(field("sig").as_bytes().unwrap()).unwrap();
+        VerifyingKey::from(crate::ec2_public_key_from_cose_key(&dpk).unwrap())
+            .verify(&signed_data, &signature)
+            .expect("the attestation signature does not match the device public key");
     }
 }
@@ -0,0 +1,149 @@
+use passkey_types::{ctap2::StatusCode, Passkey};
+
+use super::ClientPin;
+use crate::{pin_protocol::KeyAgreementKey, Authenticator, CredentialStore, UserValidationMethod};
+
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem> + Clone,
+{
+    /// This method is used by the host to reset the authenticator back to a factory default state,
+    /// deleting every credential along with their signature counters and the large-blob array
+    /// through [`CredentialStore::reset`]. The global signature counter goes back to zero and the
+    /// key agreement key is regenerated, which invalidates the secrets shared with the platform.
+    /// The PIN is removed, which unblocks an authenticator whose PIN retries ran out.
+    ///
+    /// Unlike a physical authenticator, this does not ask for the user's presence, so it is meant
+    /// for authenticators used in tests.
+    pub async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.store_mut().reset().await?;
+        self.key_agreement = KeyAgreementKey::generate();
+        self.client_pin = ClientPin::new();
+        self.global_signature_counter = 0;
+        self.emitted_signature_counters.clear();
+        self.device_keys.clear();
+        self.pending_large_blob = None;
+        self.pending_enumeration = None;
+        self.pending_assertions = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        ctap2::{
+            get_assertion, large_blobs::INITIAL_SERIALIZED_ARRAY, make_credential, Aaguid,
+            Ctap2Error,
+        },
+        rand::random_vec,
+        webauthn,
+    };
+
+    use crate::{Authenticator, CredentialStore, MemoryStore, MockUserValidationMethod};
+
+    fn make_credential_request() -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: random_vec(32).into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: "future.1password.com".into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: random_vec(16).into(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: true,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    fn get_assertion_request(
+        allow_list: Option<Vec<webauthn::PublicKeyCredentialDescriptor>>,
+    ) -> get_assertion::Request {
+        get_assertion::Request {
+            rp_id: "future.1password.com".into(),
+            client_data_hash: random_vec(32).into(),
+            allow_list,
+            extensions: None,
+            options: get_assertion::Options {
+                rk: false,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reset_deletes_every_credential() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(5),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        let mut credential_ids = Vec::new();
+        for _ in 0..3 {
+            let response = authenticator
+                .make_credential(make_credential_request())
+                .await
+                .unwrap();
+            let credential_id = response
+                .auth_data
+                .attested_credential_data
+                .unwrap()
+                .credential_id()
+                .to_vec();
+            credential_ids.push(webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id: credential_id.into(),
+                transports: None,
+            });
+        }
+        authenticator
+            .store_mut()
+            .put_large_blob(INITIAL_SERIALIZED_ARRAY.to_vec())
+            .await
+            .unwrap();
+        let key_agreement_key = authenticator.key_agreement_key();
+
+        // Act
+        authenticator.reset().await.expect("failed to reset");
+        let allowed = authenticator
+            .get_assertion(get_assertion_request(Some(credential_ids)))
+            .await;
+        let discovered = authenticator
+            .get_assertion(get_assertion_request(None))
+            .await;
+
+        // Assert
+        assert_eq!(allowed.unwrap_err(), Ctap2Error::NoCredentials.into());
+        assert_eq!(discovered.unwrap_err(), Ctap2Error::NoCredentials.into());
+        assert!(authenticator.store().is_empty());
+        assert_eq!(
+            authenticator.store().get_large_blob().await,
+            Some(Vec::new())
+        );
+        assert_ne!(authenticator.key_agreement_key(), key_agreement_key);
+    }
+}
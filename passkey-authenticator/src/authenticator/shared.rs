@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use passkey_types::{
+    ctap2::{
+        client_pin, credential_management, get_assertion, get_info, large_blobs, make_credential,
+        StatusCode,
+    },
+    Passkey,
+};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{Authenticator, CredentialStore, UserValidationMethod};
+
+/// An [`Authenticator`] which can be shared between tasks and threads.
+///
+/// Cloning a `SharedAuthenticator` is cheap and every clone refers to the same [`Authenticator`].
+/// Operations lock the inner authenticator for their whole duration, so overlapping requests are
+/// processed one after the other, the same way a physical authenticator would handle them.
+///
+/// The wrapper is [`Send`] and [`Sync`] as long as both the [`CredentialStore`] and the
+/// [`UserValidationMethod`] are [`Send`].
+pub struct SharedAuthenticator<S, U> {
+    inner: Arc<Mutex<Authenticator<S, U>>>,
+}
+
+impl<S, U> Clone for SharedAuthenticator<S, U> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, U> From<Authenticator<S, U>> for SharedAuthenticator<S, U> {
+    fn from(authenticator: Authenticator<S, U>) -> Self {
+        Self::new(authenticator)
+    }
+}
+
+impl<S, U> SharedAuthenticator<S, U> {
+    /// Wrap an [`Authenticator`] so that it can be shared.
+    pub fn new(authenticator: Authenticator<S, U>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(authenticator)),
+        }
+    }
+
+    /// Exclusively access the inner [`Authenticator`], for example to configure it or to inspect
+    /// its [`CredentialStore`]. Any other operation on this authenticator will wait until the
+    /// returned guard is dropped.
+    pub async fn lock(&self) -> MutexGuard<'_, Authenticator<S, U>> {
+        self.inner.lock().await
+    }
+}
+
+impl<S, U> SharedAuthenticator<S, U>
+where
+    S: CredentialStore + Sync,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem> + Clone,
+{
+    /// See [`Authenticator::get_info`].
+    pub async fn get_info(&self) -> get_info::Response {
+        self.lock().await.get_info().await
+    }
+
+    /// See [`Authenticator::make_credential`].
+    pub async fn make_credential(
+        &self,
+        input: make_credential::Request,
+    ) -> Result<make_credential::Response, StatusCode> {
+        self.lock().await.make_credential(input).await
+    }
+
+    /// See [`Authenticator::get_assertion`].
+    pub async fn get_assertion(
+        &self,
+        input: get_assertion::Request,
+    ) -> Result<get_assertion::Response, StatusCode> {
+        self.lock().await.get_assertion(input).await
+    }
+
+    /// See [`Authenticator::get_next_assertion`].
+    pub async fn get_next_assertion(&self) -> Result<get_assertion::Response, StatusCode> {
+        self.lock().await.get_next_assertion().await
+    }
+
+    /// See [`Authenticator::large_blobs`].
+    pub async fn large_blobs(
+        &self,
+        input: large_blobs::Request,
+    ) -> Result<large_blobs::Response, StatusCode>
+    where
+        S: Send,
+    {
+        self.lock().await.large_blobs(input).await
+    }
+
+    /// See [`Authenticator::client_pin`].
+    pub async fn client_pin(
+        &self,
+        input: client_pin::Request,
+    ) -> Result<client_pin::Response, StatusCode> {
+        self.lock().await.client_pin(input)
+    }
+
+    /// See [`Authenticator::credential_management`].
+    pub async fn credential_management(
+        &self,
+        input: credential_management::Request,
+    ) -> Result<credential_management::Response, StatusCode>
+    where
+        S: Send,
+    {
+        self.lock().await.credential_management(input).await
+    }
+
+    /// See [`Authenticator::reset`].
+    pub async fn reset(&self) -> Result<(), StatusCode>
+    where
+        S: Send,
+    {
+        self.lock().await.reset().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        ctap2::{get_assertion, make_credential, Aaguid},
+        rand::random_vec,
+        webauthn,
+    };
+
+    use super::*;
+    use crate::{MemoryStore, MockUserValidationMethod};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn authenticators_are_send_and_sync() {
+        assert_send_sync::<Authenticator<MemoryStore, MockUserValidationMethod>>();
+        assert_send_sync::<SharedAuthenticator<MemoryStore, MockUserValidationMethod>>();
+    }
+
+    fn make_credential_request() -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: random_vec(32).into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: "future.1password.com".into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: random_vec(16).into(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: false,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_authenticator_serves_assertions_from_spawned_tasks() {
+        // Arrange
+        const TASKS: u32 = 4;
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1 + usize::try_from(TASKS).unwrap()),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        let shared = SharedAuthenticator::new(authenticator);
+        let credential_id: Vec<u8> = shared
+            .make_credential(make_credential_request())
+            .await
+            .unwrap()
+            .auth_data
+            .attested_credential_data
+            .unwrap()
+            .credential_id()
+            .into();
+
+        // Act
+        let handles = (0..TASKS).map(|_| {
+            let shared = shared.clone();
+            let request = get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: random_vec(32).into(),
+                allow_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: credential_id.clone().into(),
+                    transports: None,
+                }]),
+                extensions: None,
+                options: get_assertion::Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            };
+            tokio::spawn(async move { shared.get_assertion(request).await })
+        });
+        let mut counters = Vec::new();
+        for handle in handles.collect::<Vec<_>>() {
+            let response = handle.await.unwrap().expect("assertion failed");
+            counters.push(response.auth_data.counter.unwrap());
+        }
+
+        // Assert
+        counters.sort_unstable();
+        assert_eq!(counters, (1..=TASKS).collect::<Vec<_>>());
+        let authenticator = shared.lock().await;
+        let stored = authenticator.store().get(&credential_id).unwrap();
+        assert_eq!(stored.counter, Some(TASKS));
+    }
+}
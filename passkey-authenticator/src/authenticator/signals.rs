@@ -0,0 +1,204 @@
+use passkey_types::{
+    ctap2::{make_credential::PublicKeyCredentialUserEntity, Ctap2Error, StatusCode},
+    webauthn::{PublicKeyCredentialDescriptor, PublicKeyCredentialType},
+    Bytes, Passkey,
+};
+
+use crate::{Authenticator, CredentialStore, UserValidationMethod};
+
+impl<S, U> Authenticator<S, U>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    /// Delete the credential of `rp_id` with the given ID, which the relying party signaled it
+    /// does not recognize through `signalUnknownCredential`. It is deleted with
+    /// [`CredentialStore::delete_credential`].
+    ///
+    /// Returns whether the store held the credential.
+    pub async fn delete_unknown_credential(
+        &mut self,
+        rp_id: &str,
+        credential_id: &[u8],
+    ) -> Result<bool, StatusCode> {
+        let descriptor = PublicKeyCredentialDescriptor {
+            ty: PublicKeyCredentialType::PublicKey,
+            id: credential_id.to_vec().into(),
+            transports: None,
+        };
+        let is_stored = match self
+            .store()
+            .find_credentials(Some(&[descriptor]), rp_id)
+            .await
+        {
+            Ok(found) => !found.is_empty(),
+            Err(status) if status == Ctap2Error::NoCredentials.into() => false,
+            Err(status) => return Err(status),
+        };
+        if is_stored {
+            self.store_mut().delete_credential(credential_id).await?;
+        }
+        Ok(is_stored)
+    }
+
+    /// Delete the discoverable credentials of the user `user_id` of `rp_id` whose IDs are not in
+    /// `accepted_ids`, which the relying party signaled as the complete list of the credentials
+    /// of the user through `signalAllAcceptedCredentials`.
+    ///
+    /// Returns the number of deleted credentials.
+    pub async fn delete_unaccepted_credentials(
+        &mut self,
+        rp_id: &str,
+        user_id: &[u8],
+        accepted_ids: &[Bytes],
+    ) -> Result<usize, StatusCode> {
+        let unaccepted: Vec<Bytes> = self
+            .user_credentials(rp_id, user_id)
+            .await?
+            .into_iter()
+            .filter(|credential| !accepted_ids.contains(&credential.credential_id))
+            .map(|credential| credential.credential_id)
+            .collect();
+        for credential_id in &unaccepted {
+            self.store_mut().delete_credential(credential_id).await?;
+        }
+        Ok(unaccepted.len())
+    }
+
+    /// Update the name and display name of the user with the ID `user.id`, which the relying
+    /// party signaled through `signalCurrentUserDetails`, with [`CredentialStore::update_user`].
+    ///
+    /// Returns whether the user has a discoverable credential of `rp_id`, nothing is updated
+    /// otherwise.
+    pub async fn update_user_details(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<bool, StatusCode> {
+        if self.user_credentials(rp_id, &user.id).await?.is_empty() {
+            return Ok(false);
+        }
+        self.store_mut().update_user(rp_id, user).await?;
+        Ok(true)
+    }
+
+    /// The discoverable credentials of the user `user_id` of `rp_id`.
+    async fn user_credentials(
+        &self,
+        rp_id: &str,
+        user_id: &[u8],
+    ) -> Result<Vec<Passkey>, StatusCode> {
+        Ok(self
+            .store()
+            .list_credentials(Some(rp_id))
+            .await?
+            .into_iter()
+            .filter_map(|credential| Passkey::try_from(credential).ok())
+            .filter(|credential| {
+                credential.user_handle.as_deref().map(Vec::as_slice) == Some(user_id)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        ctap2::{make_credential, Aaguid},
+        rand::random_vec,
+        webauthn, Bytes,
+    };
+
+    use crate::{Authenticator, MemoryStore, MockUserValidationMethod};
+
+    fn make_credential_request(user_id: &Bytes) -> make_credential::Request {
+        make_credential::Request {
+            client_data_hash: random_vec(32).into(),
+            rp: make_credential::PublicKeyCredentialRpEntity {
+                id: "future.1password.com".into(),
+                name: None,
+            },
+            user: webauthn::PublicKeyCredentialUserEntity {
+                id: user_id.clone(),
+                display_name: "wendy".into(),
+                name: "Appleseed".into(),
+            },
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::ES256,
+            }],
+            exclude_list: None,
+            extensions: None,
+            options: make_credential::Options {
+                rk: true,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+            enterprise_attestation: None,
+            attestation_formats_preference: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn signals_clean_up_the_credentials_and_details_of_a_user() {
+        // Arrange
+        let user_id: Bytes = random_vec(16).into();
+        let other_user_id: Bytes = random_vec(16).into();
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(3),
+        );
+        let mut credential_ids = Vec::new();
+        for user in [&user_id, &user_id, &other_user_id] {
+            let response = authenticator
+                .make_credential(make_credential_request(user))
+                .await
+                .expect("failed to create a credential");
+            let credential_id = response
+                .auth_data
+                .attested_credential_data
+                .expect("missing attested credential data")
+                .credential_id()
+                .to_vec();
+            credential_ids.push(Bytes::from(credential_id));
+        }
+
+        // Act
+        let unknown = authenticator
+            .delete_unknown_credential("future.1password.com", &random_vec(16))
+            .await;
+        let deleted = authenticator
+            .delete_unaccepted_credentials("future.1password.com", &user_id, &credential_ids[..1])
+            .await;
+        let updated = authenticator
+            .update_user_details(
+                "future.1password.com",
+                make_credential::PublicKeyCredentialUserEntity {
+                    id: user_id.clone(),
+                    name: Some("appleseed@1password.com".into()),
+                    display_name: Some("Wendy Appleseed".into()),
+                    icon_url: None,
+                },
+            )
+            .await;
+
+        // Assert
+        assert_eq!(unknown, Ok(false));
+        assert_eq!(deleted, Ok(1));
+        assert_eq!(updated, Ok(true));
+        let store = authenticator.store();
+        assert!(store.contains_key(&*credential_ids[0]));
+        assert!(!store.contains_key(&*credential_ids[1]));
+        // The credentials of other users are left untouched.
+        assert!(store.contains_key(&*credential_ids[2]));
+        let user = store
+            .user("future.1password.com", &user_id)
+            .expect("missing user details");
+        assert_eq!(user.display_name.as_deref(), Some("Wendy Appleseed"));
+    }
+}
@@ -1,5 +1,9 @@
 #[cfg(any(feature = "tokio", test))]
 use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
 
 use passkey_types::{
     ctap2::{
@@ -8,7 +12,7 @@ use passkey_types::{
         Ctap2Error, StatusCode,
     },
     webauthn::PublicKeyCredentialDescriptor,
-    Passkey,
+    CredentialExtensions, Passkey,
 };
 
 /// A struct that defines the capabilities of a store.
@@ -49,6 +53,26 @@ pub trait CredentialStore {
         rp_id: &str,
     ) -> Result<Vec<Self::PasskeyItem>, StatusCode>;
 
+    /// List the discoverable credentials of the given `rp_id`, or of every relying party when it
+    /// is `None`. This is used to gather the candidates of an assertion without an allow list,
+    /// an empty list means that there are none.
+    ///
+    /// The default implementation looks the credentials of the relying party up with
+    /// [`Self::find_credentials`] and fails with [`Ctap2Error::NotAllowed`] when asked for every
+    /// relying party, meaning the store cannot enumerate its credentials.
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        let Some(rp_id) = rp_id else {
+            return Err(Ctap2Error::NotAllowed.into());
+        };
+        match self.find_credentials(None, rp_id).await {
+            Err(status) if status == Ctap2Error::NoCredentials.into() => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
     /// Save the new credential into your store
     async fn save_credential(
         &mut self,
@@ -63,12 +87,147 @@ pub trait CredentialStore {
 
     /// Get information about the store
     async fn get_info(&self) -> StoreInfo;
+
+    /// Whether the given credential is disabled and must be skipped during assertions. A disabled
+    /// credential is still matched against the exclude list of new credentials.
+    ///
+    /// The default implementation never disables credentials.
+    async fn is_credential_disabled(&self, _cred: &Self::PasskeyItem) -> bool {
+        false
+    }
+
+    /// The state of the extensions the given credential was created with, such as the `credProtect`
+    /// policy which decides whether it can be used in assertions without user verification. The
+    /// state is given to the store in [`Passkey::extensions`] by [`Self::save_credential`] and
+    /// [`Self::update_credential`], and must be persisted along with the credential.
+    ///
+    /// The default implementation returns no state, meaning the credential is not protected.
+    async fn credential_extensions(&self, _cred: &Self::PasskeyItem) -> CredentialExtensions {
+        CredentialExtensions::default()
+    }
+
+    /// The number of discoverable credentials currently in the store, which is used to report the
+    /// remaining capacity of an authenticator configured with a maximum number of discoverable
+    /// credentials.
+    ///
+    /// The default implementation returns `None`, meaning the store cannot tell.
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Get the serialized large-blob array of the `largeBlobs` extension, including its trailing
+    /// checksum. An empty array means that nothing was written yet, which is read as the initial
+    /// empty large-blob array.
+    ///
+    /// The default implementation returns `None`, meaning the store cannot hold large blobs and
+    /// the authenticator does not support them.
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Replace the serialized large-blob array, once it was entirely written and its checksum was
+    /// verified by the authenticator. It is only called when [`Self::get_large_blob`] returns a
+    /// value.
+    ///
+    /// The default implementation fails with [`Ctap2Error::LargeBlobStorageFull`].
+    async fn put_large_blob(&mut self, _array: Vec<u8>) -> Result<(), StatusCode> {
+        Err(Ctap2Error::LargeBlobStorageFull.into())
+    }
+
+    /// Delete the credential with the given ID, which is not an error when the store does not
+    /// hold it. This is used when the relying party signals that it no longer accepts the
+    /// credential.
+    ///
+    /// The default implementation fails with [`Ctap2Error::NotAllowed`], meaning the store cannot
+    /// delete credentials.
+    async fn delete_credential(&mut self, _credential_id: &[u8]) -> Result<(), StatusCode> {
+        Err(Ctap2Error::NotAllowed.into())
+    }
+
+    /// Replace the name and display name of the user with the ID `user.id`, as given to
+    /// [`Self::save_credential`] along with the credentials of `rp_id`. This is used when the
+    /// relying party signals the current details of the user.
+    ///
+    /// The default implementation fails with [`Ctap2Error::NotAllowed`], meaning the store does not
+    /// keep the details of users.
+    async fn update_user(
+        &mut self,
+        _rp_id: &str,
+        _user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        Err(Ctap2Error::NotAllowed.into())
+    }
+
+    /// Get the details of the user with the given ID, as given to [`Self::save_credential`] along
+    /// with the credentials of `rp_id` or last updated with [`Self::update_user`]. This is used to
+    /// describe the users of the credentials enumerated through credential management.
+    ///
+    /// The default implementation returns `None`, meaning the store does not keep the details of
+    /// users, in which case only their ID is known.
+    async fn get_user(
+        &self,
+        _rp_id: &str,
+        _user_id: &[u8],
+    ) -> Option<PublicKeyCredentialUserEntity> {
+        None
+    }
+
+    /// Delete every credential in the store, along with the large-blob array, when the
+    /// authenticator is reset.
+    ///
+    /// The default implementation fails with [`Ctap2Error::NotAllowed`], meaning the store cannot
+    /// be reset.
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        Err(Ctap2Error::NotAllowed.into())
+    }
 }
 
-/// In-memory store for Passkeys
+/// In-memory store for Passkeys, keyed by their credential ID, along with the large-blob array
+/// and the details of their users.
 ///
-/// Useful for tests.
-pub type MemoryStore = std::collections::HashMap<Vec<u8>, Passkey>;
+/// Useful for tests. The store dereferences to the map of its passkeys.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    passkeys: HashMap<Vec<u8>, Passkey>,
+    large_blob: Vec<u8>,
+    users: HashMap<(String, Vec<u8>), PublicKeyCredentialUserEntity>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The details of the user with the given ID of the relying party, as they were last saved
+    /// with a credential or updated.
+    pub fn user(&self, rp_id: &str, user_id: &[u8]) -> Option<&PublicKeyCredentialUserEntity> {
+        self.users.get(&(rp_id.to_owned(), user_id.to_vec()))
+    }
+}
+
+impl From<HashMap<Vec<u8>, Passkey>> for MemoryStore {
+    fn from(passkeys: HashMap<Vec<u8>, Passkey>) -> Self {
+        Self {
+            passkeys,
+            ..Self::default()
+        }
+    }
+}
+
+impl Deref for MemoryStore {
+    type Target = HashMap<Vec<u8>, Passkey>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.passkeys
+    }
+}
+
+impl DerefMut for MemoryStore {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.passkeys
+    }
+}
 
 #[async_trait::async_trait]
 impl CredentialStore for MemoryStore {
@@ -77,14 +236,23 @@ impl CredentialStore for MemoryStore {
     async fn find_credentials(
         &self,
         allow_credentials: Option<&[PublicKeyCredentialDescriptor]>,
-        _rp_id: &str,
+        rp_id: &str,
     ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
-        let creds: Vec<Passkey> = allow_credentials
-            .into_iter()
-            .flatten()
-            .filter_map(|id| self.get(&*id.id))
-            .cloned()
-            .collect();
+        let creds: Vec<Passkey> = match allow_credentials {
+            Some(allow_credentials) => allow_credentials
+                .iter()
+                .filter_map(|id| self.get(&*id.id))
+                .filter(|pk| pk.rp_id == rp_id)
+                .cloned()
+                .collect(),
+            // Without an allow list, only discoverable credentials, which are the ones storing a
+            // user handle, can be returned.
+            None => self
+                .values()
+                .filter(|pk| pk.rp_id == rp_id && pk.user_handle.is_some())
+                .cloned()
+                .collect(),
+        };
         if creds.is_empty() {
             Err(Ctap2Error::NoCredentials.into())
         } else {
@@ -92,13 +260,28 @@ impl CredentialStore for MemoryStore {
         }
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        Ok(self
+            .values()
+            .filter(|pk| pk.user_handle.is_some() && rp_id.map_or(true, |id| pk.rp_id == id))
+            .cloned()
+            .collect())
+    }
+
     async fn save_credential(
         &mut self,
-        cred: Passkey,
-        _user: PublicKeyCredentialUserEntity,
+        mut cred: Passkey,
+        user: PublicKeyCredentialUserEntity,
         _rp: PublicKeyCredentialRpEntity,
         _options: Options,
     ) -> Result<(), StatusCode> {
+        // Credentials are always discoverable in this store, see `get_info`.
+        cred.user_handle.get_or_insert(user.id.clone());
+        self.users
+            .insert((cred.rp_id.clone(), user.id.to_vec()), user);
         self.insert(cred.credential_id.clone().into(), cred);
         Ok(())
     }
@@ -113,6 +296,53 @@ impl CredentialStore for MemoryStore {
             discoverability: DiscoverabilitySupport::ForcedDiscoverable,
         }
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        cred.disabled
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        cred.extensions.clone()
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        Some(self.values().filter(|pk| pk.user_handle.is_some()).count())
+    }
+
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        Some(self.large_blob.clone())
+    }
+
+    async fn put_large_blob(&mut self, array: Vec<u8>) -> Result<(), StatusCode> {
+        self.large_blob = array;
+        Ok(())
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        self.remove(credential_id);
+        Ok(())
+    }
+
+    async fn update_user(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        self.users
+            .insert((rp_id.to_owned(), user.id.to_vec()), user);
+        Ok(())
+    }
+
+    async fn get_user(&self, rp_id: &str, user_id: &[u8]) -> Option<PublicKeyCredentialUserEntity> {
+        self.user(rp_id, user_id).cloned()
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.passkeys.clear();
+        self.large_blob.clear();
+        self.users.clear();
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -122,20 +352,31 @@ impl CredentialStore for Option<Passkey> {
     async fn find_credentials(
         &self,
         id: Option<&[PublicKeyCredentialDescriptor]>,
-        _rp_id: &str,
+        rp_id: &str,
     ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
         if let Some(id) = id {
             id.iter().find_map(|id| {
-                // TODO: && pk.rp_id == rp_id) need rp_id on typeshared passkey
-                self.clone().filter(|pk| pk.credential_id == id.id)
+                self.clone()
+                    .filter(|pk| pk.credential_id == id.id && pk.rp_id == rp_id)
             })
         } else {
-            self.clone() // TODO: .filter(|pk| pk.rp_id == rp_id) need rp_id on typeshared passkey
+            self.clone().filter(|pk| pk.rp_id == rp_id)
         }
         .map(|pk| vec![pk])
         .ok_or(Ctap2Error::NoCredentials.into())
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        Ok(self
+            .iter()
+            .filter(|pk| rp_id.map_or(true, |id| pk.rp_id == id))
+            .cloned()
+            .collect())
+    }
+
     async fn save_credential(
         &mut self,
         cred: Passkey,
@@ -157,6 +398,35 @@ impl CredentialStore for Option<Passkey> {
             discoverability: DiscoverabilitySupport::ForcedDiscoverable,
         }
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        cred.disabled
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        cred.extensions.clone()
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        Some(usize::from(
+            self.as_ref().is_some_and(|pk| pk.user_handle.is_some()),
+        ))
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        if self
+            .as_ref()
+            .is_some_and(|pk| *pk.credential_id == *credential_id)
+        {
+            *self = None;
+        }
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        *self = None;
+        Ok(())
+    }
 }
 
 #[cfg(any(feature = "tokio", test))]
@@ -174,6 +444,13 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
         self.lock().await.find_credentials(ids, rp_id).await
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        self.lock().await.list_credentials(rp_id).await
+    }
+
     async fn save_credential(
         &mut self,
         cred: Passkey,
@@ -194,6 +471,46 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
     async fn get_info(&self) -> StoreInfo {
         self.lock().await.get_info().await
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        self.lock().await.is_credential_disabled(cred).await
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        self.lock().await.credential_extensions(cred).await
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        self.lock().await.discoverable_credentials_count().await
+    }
+
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        self.lock().await.get_large_blob().await
+    }
+
+    async fn put_large_blob(&mut self, array: Vec<u8>) -> Result<(), StatusCode> {
+        self.lock().await.put_large_blob(array).await
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        self.lock().await.delete_credential(credential_id).await
+    }
+
+    async fn update_user(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        self.lock().await.update_user(rp_id, user).await
+    }
+
+    async fn get_user(&self, rp_id: &str, user_id: &[u8]) -> Option<PublicKeyCredentialUserEntity> {
+        self.lock().await.get_user(rp_id, user_id).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.lock().await.reset().await
+    }
 }
 
 #[cfg(any(feature = "tokio", test))]
@@ -211,6 +528,13 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
         self.read().await.find_credentials(ids, rp_id).await
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        self.read().await.list_credentials(rp_id).await
+    }
+
     async fn save_credential(
         &mut self,
         cred: Passkey,
@@ -231,6 +555,46 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
     async fn get_info(&self) -> StoreInfo {
         self.read().await.get_info().await
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        self.read().await.is_credential_disabled(cred).await
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        self.read().await.credential_extensions(cred).await
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        self.read().await.discoverable_credentials_count().await
+    }
+
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        self.read().await.get_large_blob().await
+    }
+
+    async fn put_large_blob(&mut self, array: Vec<u8>) -> Result<(), StatusCode> {
+        self.write().await.put_large_blob(array).await
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        self.write().await.delete_credential(credential_id).await
+    }
+
+    async fn update_user(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        self.write().await.update_user(rp_id, user).await
+    }
+
+    async fn get_user(&self, rp_id: &str, user_id: &[u8]) -> Option<PublicKeyCredentialUserEntity> {
+        self.read().await.get_user(rp_id, user_id).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.write().await.reset().await
+    }
 }
 
 #[cfg(any(feature = "tokio", test))]
@@ -248,6 +612,13 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
         self.lock().await.find_credentials(ids, rp_id).await
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        self.lock().await.list_credentials(rp_id).await
+    }
+
     async fn save_credential(
         &mut self,
         cred: Passkey,
@@ -268,6 +639,46 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
     async fn get_info(&self) -> StoreInfo {
         self.lock().await.get_info().await
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        self.lock().await.is_credential_disabled(cred).await
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        self.lock().await.credential_extensions(cred).await
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        self.lock().await.discoverable_credentials_count().await
+    }
+
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        self.lock().await.get_large_blob().await
+    }
+
+    async fn put_large_blob(&mut self, array: Vec<u8>) -> Result<(), StatusCode> {
+        self.lock().await.put_large_blob(array).await
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        self.lock().await.delete_credential(credential_id).await
+    }
+
+    async fn update_user(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        self.lock().await.update_user(rp_id, user).await
+    }
+
+    async fn get_user(&self, rp_id: &str, user_id: &[u8]) -> Option<PublicKeyCredentialUserEntity> {
+        self.lock().await.get_user(rp_id, user_id).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.lock().await.reset().await
+    }
 }
 
 #[cfg(any(feature = "tokio", test))]
@@ -285,6 +696,13 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
         self.read().await.find_credentials(ids, rp_id).await
     }
 
+    async fn list_credentials(
+        &self,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<Self::PasskeyItem>, StatusCode> {
+        self.read().await.list_credentials(rp_id).await
+    }
+
     async fn save_credential(
         &mut self,
         cred: Passkey,
@@ -305,4 +723,193 @@ impl<S: CredentialStore<PasskeyItem = Passkey> + Send + Sync> CredentialStore
     async fn get_info(&self) -> StoreInfo {
         self.read().await.get_info().await
     }
+
+    async fn is_credential_disabled(&self, cred: &Passkey) -> bool {
+        self.read().await.is_credential_disabled(cred).await
+    }
+
+    async fn credential_extensions(&self, cred: &Passkey) -> CredentialExtensions {
+        self.read().await.credential_extensions(cred).await
+    }
+
+    async fn discoverable_credentials_count(&self) -> Option<usize> {
+        self.read().await.discoverable_credentials_count().await
+    }
+
+    async fn get_large_blob(&self) -> Option<Vec<u8>> {
+        self.read().await.get_large_blob().await
+    }
+
+    async fn put_large_blob(&mut self, array: Vec<u8>) -> Result<(), StatusCode> {
+        self.write().await.put_large_blob(array).await
+    }
+
+    async fn delete_credential(&mut self, credential_id: &[u8]) -> Result<(), StatusCode> {
+        self.write().await.delete_credential(credential_id).await
+    }
+
+    async fn update_user(
+        &mut self,
+        rp_id: &str,
+        user: PublicKeyCredentialUserEntity,
+    ) -> Result<(), StatusCode> {
+        self.write().await.update_user(rp_id, user).await
+    }
+
+    async fn get_user(&self, rp_id: &str, user_id: &[u8]) -> Option<PublicKeyCredentialUserEntity> {
+        self.read().await.get_user(rp_id, user_id).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        self.write().await.reset().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana;
+    use passkey_types::{
+        ctap2::{
+            get_assertion::Options,
+            make_credential::{PublicKeyCredentialRpEntity, PublicKeyCredentialUserEntity},
+            Ctap2Error, StatusCode,
+        },
+        rand::random_vec,
+        webauthn::{CredentialProtectionPolicy, PublicKeyCredentialDescriptor},
+        CredRandom, CredentialExtensions, Passkey,
+    };
+
+    use super::{CredentialStore, DiscoverabilitySupport, MemoryStore, StoreInfo};
+    use crate::CoseKeyPair;
+
+    #[tokio::test]
+    async fn memory_store_keeps_the_extension_state_of_its_credentials() {
+        // Arrange
+        let mut store = MemoryStore::new();
+        let passkey = Passkey {
+            key: CoseKeyPair::generate(iana::Algorithm::ES256).private,
+            credential_id: random_vec(16).into(),
+            rp_id: "future.1password.com".into(),
+            user_handle: Some(random_vec(16).into()),
+            counter: None,
+            disabled: false,
+            transports: None,
+            extensions: CredentialExtensions {
+                cred_protect: Some(
+                    CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList,
+                ),
+                cred_blob: Some(vec![1, 2, 3].into()),
+                large_blob_key: None,
+                cred_random: Some(CredRandom {
+                    with_uv: vec![4; 32].into(),
+                    without_uv: vec![5; 32].into(),
+                }),
+            },
+            last_used_at: None,
+        };
+
+        // Act
+        store
+            .save_credential(
+                passkey.clone(),
+                PublicKeyCredentialUserEntity {
+                    id: passkey.user_handle.clone().unwrap(),
+                    display_name: None,
+                    name: None,
+                    icon_url: None,
+                },
+                PublicKeyCredentialRpEntity {
+                    id: passkey.rp_id.clone(),
+                    name: None,
+                },
+                Options::default(),
+            )
+            .await
+            .unwrap();
+        let reloaded = store
+            .find_credentials(None, "future.1password.com")
+            .await
+            .unwrap()
+            .remove(0);
+
+        // Assert
+        let extensions = store.credential_extensions(&reloaded).await;
+        assert_eq!(extensions, passkey.extensions);
+        assert_eq!(extensions.cred_blob, Some(vec![1, 2, 3].into()));
+        let cred_random = reloaded.extensions.cred_random.unwrap();
+        assert_eq!(cred_random.with_uv, vec![4; 32].into());
+        assert_eq!(cred_random.without_uv, vec![5; 32].into());
+    }
+
+    #[tokio::test]
+    async fn memory_store_deletes_credentials_and_ignores_missing_ones() {
+        // Arrange
+        let passkey = Passkey {
+            key: CoseKeyPair::generate(iana::Algorithm::ES256).private,
+            credential_id: random_vec(16).into(),
+            rp_id: "future.1password.com".into(),
+            user_handle: Some(random_vec(16).into()),
+            counter: None,
+            disabled: false,
+            transports: None,
+            extensions: CredentialExtensions::default(),
+            last_used_at: None,
+        };
+        let mut store = MemoryStore::new();
+        store.insert(passkey.credential_id.to_vec(), passkey.clone());
+
+        // Act
+        let deleted = store.delete_credential(&passkey.credential_id).await;
+        let missing = store.delete_credential(&passkey.credential_id).await;
+
+        // Assert
+        assert_eq!(deleted, Ok(()));
+        assert_eq!(missing, Ok(()));
+        assert!(store.is_empty());
+    }
+
+    /// A store which only implements the required methods of [`CredentialStore`].
+    struct ReadOnlyStore;
+
+    #[async_trait::async_trait]
+    impl CredentialStore for ReadOnlyStore {
+        type PasskeyItem = Passkey;
+
+        async fn find_credentials(
+            &self,
+            _ids: Option<&[PublicKeyCredentialDescriptor]>,
+            _rp_id: &str,
+        ) -> Result<Vec<Passkey>, StatusCode> {
+            Err(Ctap2Error::NoCredentials.into())
+        }
+
+        async fn save_credential(
+            &mut self,
+            _cred: Passkey,
+            _user: PublicKeyCredentialUserEntity,
+            _rp: PublicKeyCredentialRpEntity,
+            _options: Options,
+        ) -> Result<(), StatusCode> {
+            Err(Ctap2Error::KeyStoreFull.into())
+        }
+
+        async fn update_credential(&mut self, _cred: Passkey) -> Result<(), StatusCode> {
+            Err(Ctap2Error::KeyStoreFull.into())
+        }
+
+        async fn get_info(&self) -> StoreInfo {
+            StoreInfo {
+                discoverability: DiscoverabilitySupport::Full,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn deleting_credentials_is_not_allowed_by_default() {
+        // Act
+        let result = ReadOnlyStore.delete_credential(&random_vec(16)).await;
+
+        // Assert
+        assert_eq!(result, Err(Ctap2Error::NotAllowed.into()));
+    }
 }
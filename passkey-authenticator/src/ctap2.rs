@@ -5,7 +5,13 @@
 //!
 //! <https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticator-api>
 
-use passkey_types::ctap2::{get_assertion, get_info, make_credential, StatusCode};
+use passkey_types::{
+    ctap2::{
+        client_pin, credential_management, get_assertion, get_info, large_blobs, make_credential,
+        StatusCode,
+    },
+    Passkey,
+};
 
 use crate::{Authenticator, CredentialStore, UserValidationMethod};
 
@@ -15,6 +21,9 @@ mod sealed {
     pub trait Sealed {}
 
     impl<S: CredentialStore, U: UserValidationMethod> Sealed for Authenticator<S, U> {}
+
+    #[cfg(any(feature = "tokio", test))]
+    impl<S: CredentialStore, U: UserValidationMethod> Sealed for crate::SharedAuthenticator<S, U> {}
 }
 
 /// Methods defined as being required for a [CTAP 2.0] compliant authenticator to implement.
@@ -40,32 +49,185 @@ pub trait Ctap2Api: sealed::Sealed {
 
     /// Request to assert a user's existing credential that might exist in the authenticator.
     async fn get_assertion(
-        &self,
+        &mut self,
         request: get_assertion::Request,
     ) -> Result<get_assertion::Response, StatusCode>;
+
+    /// Request the assertion of the next credential located by the preceding `get_assertion`.
+    async fn get_next_assertion(&mut self) -> Result<get_assertion::Response, StatusCode>;
+
+    /// Request to read or write a fragment of the authenticator's serialized large-blob array.
+    async fn large_blobs(
+        &mut self,
+        request: large_blobs::Request,
+    ) -> Result<large_blobs::Response, StatusCode>;
+
+    /// Request to configure the PIN of the authenticator or to exchange it for a `pinToken`.
+    async fn client_pin(
+        &mut self,
+        request: client_pin::Request,
+    ) -> Result<client_pin::Response, StatusCode>;
+
+    /// Request to count, enumerate, delete or update the discoverable credentials of the
+    /// authenticator.
+    async fn credential_management(
+        &mut self,
+        request: credential_management::Request,
+    ) -> Result<credential_management::Response, StatusCode>;
+
+    /// Request to reset the authenticator back to a factory default state, deleting every
+    /// credential.
+    async fn reset(&mut self) -> Result<(), StatusCode>;
 }
 
+// The inherent methods are called through their full path, otherwise unsatisfied bounds make
+// method resolution fall back onto the trait methods, recursing infinitely.
 #[async_trait::async_trait]
 impl<S, U> Ctap2Api for Authenticator<S, U>
 where
     S: CredentialStore + Sync + Send,
-    U: UserValidationMethod + Sync + Send,
+    S::PasskeyItem: Send + Sync,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync + Send,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem> + Clone,
+{
+    async fn get_info(&self) -> get_info::Response {
+        Authenticator::get_info(self).await
+    }
+
+    async fn make_credential(
+        &mut self,
+        request: make_credential::Request,
+    ) -> Result<make_credential::Response, StatusCode> {
+        Authenticator::make_credential(self, request).await
+    }
+
+    async fn get_assertion(
+        &mut self,
+        request: get_assertion::Request,
+    ) -> Result<get_assertion::Response, StatusCode> {
+        Authenticator::get_assertion(self, request).await
+    }
+
+    async fn get_next_assertion(&mut self) -> Result<get_assertion::Response, StatusCode> {
+        Authenticator::get_next_assertion(self).await
+    }
+
+    async fn large_blobs(
+        &mut self,
+        request: large_blobs::Request,
+    ) -> Result<large_blobs::Response, StatusCode> {
+        Authenticator::large_blobs(self, request).await
+    }
+
+    async fn client_pin(
+        &mut self,
+        request: client_pin::Request,
+    ) -> Result<client_pin::Response, StatusCode> {
+        Authenticator::client_pin(self, request)
+    }
+
+    async fn credential_management(
+        &mut self,
+        request: credential_management::Request,
+    ) -> Result<credential_management::Response, StatusCode> {
+        Authenticator::credential_management(self, request).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        Authenticator::reset(self).await
+    }
+}
+
+#[cfg(any(feature = "tokio", test))]
+#[async_trait::async_trait]
+impl<S, U> Ctap2Api for crate::SharedAuthenticator<S, U>
+where
+    S: CredentialStore + Sync + Send,
+    S::PasskeyItem: Send + Sync,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync + Send,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem> + Clone,
 {
     async fn get_info(&self) -> get_info::Response {
-        self.get_info().await
+        crate::SharedAuthenticator::get_info(self).await
     }
 
     async fn make_credential(
         &mut self,
         request: make_credential::Request,
     ) -> Result<make_credential::Response, StatusCode> {
-        self.make_credential(request).await
+        crate::SharedAuthenticator::make_credential(self, request).await
     }
 
     async fn get_assertion(
-        &self,
+        &mut self,
         request: get_assertion::Request,
     ) -> Result<get_assertion::Response, StatusCode> {
-        self.get_assertion(request).await
+        crate::SharedAuthenticator::get_assertion(self, request).await
+    }
+
+    async fn get_next_assertion(&mut self) -> Result<get_assertion::Response, StatusCode> {
+        crate::SharedAuthenticator::get_next_assertion(self).await
+    }
+
+    async fn large_blobs(
+        &mut self,
+        request: large_blobs::Request,
+    ) -> Result<large_blobs::Response, StatusCode> {
+        crate::SharedAuthenticator::large_blobs(self, request).await
+    }
+
+    async fn client_pin(
+        &mut self,
+        request: client_pin::Request,
+    ) -> Result<client_pin::Response, StatusCode> {
+        crate::SharedAuthenticator::client_pin(self, request).await
+    }
+
+    async fn credential_management(
+        &mut self,
+        request: credential_management::Request,
+    ) -> Result<credential_management::Response, StatusCode> {
+        crate::SharedAuthenticator::credential_management(self, request).await
+    }
+
+    async fn reset(&mut self) -> Result<(), StatusCode> {
+        crate::SharedAuthenticator::reset(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use passkey_types::ctap2::{Aaguid, Ctap2Error};
+
+    use super::*;
+    use crate::{MemoryStore, MockUserValidationMethod};
+
+    #[tokio::test]
+    async fn ctap2_api_get_assertion_calls_into_the_authenticator() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        let request = get_assertion::Request {
+            rp_id: "future.1password.com".into(),
+            client_data_hash: vec![0; 32].into(),
+            allow_list: None,
+            extensions: None,
+            options: get_assertion::Options {
+                rk: false,
+                up: true,
+                uv: true,
+            },
+            pin_auth: None,
+            pin_protocol: None,
+        };
+
+        // Act
+        let result = Ctap2Api::get_assertion(&mut authenticator, request).await;
+
+        // Assert
+        assert_eq!(result.unwrap_err(), Ctap2Error::NoCredentials.into());
     }
 }
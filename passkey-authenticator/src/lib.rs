@@ -23,9 +23,11 @@
 //! [CTAP 2.0]: https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html
 //! [RustCrypto]: https://github.com/RustCrypto
 
+mod attestation;
 mod authenticator;
 mod credential_store;
 mod ctap2;
+mod pin_protocol;
 mod u2f;
 mod user_validation;
 
@@ -34,23 +36,34 @@ use coset::{
     CoseKey, CoseKeyBuilder,
 };
 use p256::{
-    ecdsa::SigningKey,
+    ecdsa::{signature::SignerMut, SigningKey},
     elliptic_curve::{generic_array::GenericArray, sec1::FromEncodedPoint},
     pkcs8::EncodePublicKey,
     EncodedPoint, PublicKey, SecretKey,
 };
-use passkey_types::{ctap2::Ctap2Error, Bytes};
+use passkey_types::{ctap2::Ctap2Error, Bytes, Passkey};
 
 pub use self::{
-    authenticator::Authenticator,
+    attestation::{
+        AttestationInput, AttestationStatementProvider, FidoU2fAttestation, NoneAttestation,
+        PackedAttestation, SelfAttestation,
+    },
+    authenticator::{
+        Authenticator, AuthenticatorBuilder, BackupState, CounterMode, KeyExport, MAX_PIN_RETRIES,
+    },
     credential_store::{CredentialStore, DiscoverabilitySupport, MemoryStore, StoreInfo},
     ctap2::Ctap2Api,
     u2f::U2fApi,
-    user_validation::{UIHint, UserCheck, UserValidationMethod},
+    user_validation::{UIHint, UserCheck, UserValidationMethod, VerificationMethod},
 };
 
+#[cfg(any(feature = "tokio", test))]
+pub use self::authenticator::SharedAuthenticator;
+
 #[cfg(any(test, feature = "testable"))]
-pub use self::user_validation::MockUserValidationMethod;
+pub use self::user_validation::{
+    MockUserValidationMethod, RecordingUserValidation, UserValidationCall, UserValidationCalls,
+};
 
 /// Extract a cryptographic secret key from a [`CoseKey`].
 // possible candidate for a `passkey-crypto` crate?
@@ -85,19 +98,169 @@ pub fn private_key_from_cose_key(key: &CoseKey) -> Result<SecretKey, Ctap2Error>
         .ok_or(Ctap2Error::InvalidCredential)
 }
 
+/// The private key of a credential, for each signature algorithm supported by this build.
+pub(crate) enum CredentialSigningKey {
+    Es256(SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+    Rs256(rsa::pkcs1v15::SigningKey<sha2::Sha256>),
+}
+
+impl CredentialSigningKey {
+    /// Get the signing key of a credential, failing with [`Ctap2Error::UnsupportedAlgorithm`]
+    /// when this build cannot sign with the credential's algorithm, and with
+    /// [`Ctap2Error::InvalidCredential`] when the key is malformed, such as a stored key whose
+    /// private key bytes were corrupted.
+    pub(crate) fn from_cose_key(key: &CoseKey) -> Result<Self, Ctap2Error> {
+        match key.alg {
+            Some(coset::RegisteredLabelWithPrivate::Assigned(Algorithm::ES256)) => {
+                private_key_from_cose_key(key).map(|secret| Self::Es256(SigningKey::from(secret)))
+            }
+            Some(coset::RegisteredLabelWithPrivate::Assigned(Algorithm::EdDSA)) => {
+                let secret = okp_ed25519_parameter(key, iana::OkpKeyParameter::D)?;
+                Ok(Self::Ed25519(ed25519_dalek::SigningKey::from_bytes(
+                    &secret,
+                )))
+            }
+            Some(coset::RegisteredLabelWithPrivate::Assigned(Algorithm::RS256)) => {
+                let [n, e, d, p, q] = [
+                    iana::RsaKeyParameter::N,
+                    iana::RsaKeyParameter::E,
+                    iana::RsaKeyParameter::D,
+                    iana::RsaKeyParameter::P,
+                    iana::RsaKeyParameter::Q,
+                ]
+                .map(|parameter| rsa_parameter(key, parameter));
+                rsa::RsaPrivateKey::from_components(n?, e?, d?, vec![p?, q?])
+                    .map(|key| Self::Rs256(rsa::pkcs1v15::SigningKey::new(key)))
+                    .map_err(|_| Ctap2Error::InvalidCredential)
+            }
+            _ => Err(Ctap2Error::UnsupportedAlgorithm),
+        }
+    }
+
+    /// Sign the data, returning the signature in the encoding expected by WebAuthn.
+    pub(crate) fn sign(&mut self, data: &[u8]) -> Bytes {
+        match self {
+            Self::Es256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(data);
+                signature.to_der().to_bytes().to_vec().into()
+            }
+            // EdDSA signatures are used as is, they have no DER encoding.
+            Self::Ed25519(key) => {
+                let signature: ed25519_dalek::Signature = key.sign(data);
+                signature.to_bytes().to_vec().into()
+            }
+            Self::Rs256(key) => {
+                let signature: rsa::pkcs1v15::Signature = key.sign(data);
+                Box::<[u8]>::from(signature).into_vec().into()
+            }
+        }
+    }
+}
+
+/// Get a 32 byte parameter of an Ed25519 OKP [`CoseKey`], such as its public key `x` or its
+/// private key `d`.
+fn okp_ed25519_parameter(
+    key: &CoseKey,
+    parameter: iana::OkpKeyParameter,
+) -> Result<[u8; 32], Ctap2Error> {
+    if !matches!(
+        key.kty,
+        coset::RegisteredLabel::Assigned(iana::KeyType::OKP)
+    ) {
+        return Err(Ctap2Error::InvalidCredential);
+    }
+    if cose_key_parameter(key, iana::OkpKeyParameter::Crv.to_i64()).and_then(|crv| crv.as_integer())
+        != Some(iana::EllipticCurve::Ed25519.to_i64().into())
+    {
+        return Err(Ctap2Error::InvalidCredential);
+    }
+
+    cose_key_parameter(key, parameter.to_i64())
+        .and_then(|value| value.as_bytes())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+        .ok_or(Ctap2Error::InvalidCredential)
+}
+
+/// Get a parameter of an RSA [`CoseKey`], such as its modulus `n` or its private exponent `d`.
+fn rsa_parameter(
+    key: &CoseKey,
+    parameter: iana::RsaKeyParameter,
+) -> Result<rsa::BigUint, Ctap2Error> {
+    if !matches!(
+        key.kty,
+        coset::RegisteredLabel::Assigned(iana::KeyType::RSA)
+    ) {
+        return Err(Ctap2Error::InvalidCredential);
+    }
+
+    cose_key_parameter(key, parameter.to_i64())
+        .and_then(|value| value.as_bytes())
+        .map(|bytes| rsa::BigUint::from_bytes_be(bytes))
+        .ok_or(Ctap2Error::InvalidCredential)
+}
+
+/// Get the value of the key type specific parameter with the given label.
+fn cose_key_parameter(key: &CoseKey, label: i64) -> Option<&ciborium::Value> {
+    key.params
+        .iter()
+        .find_map(|(key_label, value)| (key_label == &coset::Label::Int(label)).then_some(value))
+}
+
+/// Get the signature algorithm of a credential's public key. Public keys that omit their `alg`
+/// parameter, see [`Authenticator::set_public_key_alg_included`], are recognized by their key
+/// type and curve.
+pub fn public_key_algorithm(key: &CoseKey) -> Option<Algorithm> {
+    match key.alg {
+        Some(coset::RegisteredLabelWithPrivate::Assigned(algorithm)) => Some(algorithm),
+        Some(_) => None,
+        None => {
+            let curve = cose_key_parameter(key, iana::Ec2KeyParameter::Crv.to_i64())
+                .and_then(|crv| crv.as_integer())
+                .and_then(|crv| i64::try_from(crv).ok())
+                .and_then(iana::EllipticCurve::from_i64);
+            match (&key.kty, curve) {
+                (
+                    coset::RegisteredLabel::Assigned(iana::KeyType::EC2),
+                    Some(iana::EllipticCurve::P_256),
+                ) => Some(Algorithm::ES256),
+                (
+                    coset::RegisteredLabel::Assigned(iana::KeyType::OKP),
+                    Some(iana::EllipticCurve::Ed25519),
+                ) => Some(Algorithm::EdDSA),
+                (coset::RegisteredLabel::Assigned(iana::KeyType::RSA), _) => Some(Algorithm::RS256),
+                _ => None,
+            }
+        }
+    }
+}
+
 /// Convert a Cose Key to a X.509 SubjectPublicKeyInfo formatted byte array.
 ///
 /// This should be used by the client when creating the [Easy Credential Data Accessors][ez]
 ///
 /// [ez]: https://w3c.github.io/webauthn/#sctn-public-key-easy
 pub fn public_key_der_from_cose_key(key: &CoseKey) -> Result<Bytes, Ctap2Error> {
-    if !matches!(
-        key.alg,
-        Some(coset::RegisteredLabelWithPrivate::Assigned(
-            Algorithm::ES256
-        ))
-    ) {
-        return Err(Ctap2Error::UnsupportedAlgorithm);
+    match public_key_algorithm(key) {
+        Some(Algorithm::ES256) => (),
+        Some(Algorithm::EdDSA) => {
+            let public_key = okp_ed25519_parameter(key, iana::OkpKeyParameter::X)?;
+            return ed25519_dalek::VerifyingKey::from_bytes(&public_key)
+                .ok()
+                .and_then(|public_key| public_key.to_public_key_der().ok())
+                .map(|der| der.as_bytes().to_vec().into())
+                .ok_or(Ctap2Error::InvalidCredential);
+        }
+        Some(Algorithm::RS256) => {
+            let n = rsa_parameter(key, iana::RsaKeyParameter::N)?;
+            let e = rsa_parameter(key, iana::RsaKeyParameter::E)?;
+            return rsa::RsaPublicKey::new(n, e)
+                .ok()
+                .and_then(|public_key| public_key.to_public_key_der().ok())
+                .map(|der| der.as_bytes().to_vec().into())
+                .ok_or(Ctap2Error::InvalidCredential);
+        }
+        _ => return Err(Ctap2Error::UnsupportedAlgorithm),
     }
     if !matches!(
         key.kty,
@@ -106,6 +269,54 @@ pub fn public_key_der_from_cose_key(key: &CoseKey) -> Result<Bytes, Ctap2Error>
         return Err(Ctap2Error::InvalidCredential);
     }
 
+    ec2_public_key_from_cose_key(key)?
+        .to_public_key_der()
+        .map_err(|_| Ctap2Error::InvalidCredential)
+        .map(|pk| pk.as_ref().to_vec().into())
+}
+
+/// What a credential's key is and what this build can do with it, see [`credential_key_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CredentialKeyInfo {
+    /// The signature algorithm of the credential, if it is a registered one.
+    pub algorithm: Option<Algorithm>,
+    /// The type of the credential's key, if it is a registered one.
+    pub key_type: Option<iana::KeyType>,
+    /// The curve of EC2 and OKP keys, `None` for other key types.
+    pub curve: Option<iana::EllipticCurve>,
+    /// Whether the [`Authenticator`] can sign assertions with the credential.
+    pub can_sign: bool,
+    /// Whether the public key of the credential can be encoded to verify its signatures, see
+    /// [`public_key_der_from_cose_key`].
+    pub can_verify: bool,
+}
+
+/// Inspect the key of a stored credential, reporting its algorithm, key type and curve along with
+/// whether this build can sign and verify with it.
+pub fn credential_key_info(passkey: &Passkey) -> CredentialKeyInfo {
+    let key = &passkey.key;
+    CredentialKeyInfo {
+        algorithm: match key.alg {
+            Some(coset::RegisteredLabelWithPrivate::Assigned(algorithm)) => Some(algorithm),
+            _ => None,
+        },
+        key_type: match key.kty {
+            coset::RegisteredLabel::Assigned(key_type) => Some(key_type),
+            _ => None,
+        },
+        // EC2 and OKP keys share the label of their curve parameter.
+        curve: cose_key_parameter(key, iana::Ec2KeyParameter::Crv.to_i64())
+            .and_then(|crv| crv.as_integer())
+            .and_then(|crv| i64::try_from(crv).ok())
+            .and_then(iana::EllipticCurve::from_i64),
+        can_sign: CredentialSigningKey::from_cose_key(key).is_ok(),
+        can_verify: public_key_der_from_cose_key(key).is_ok(),
+    }
+}
+
+/// Get the P-256 public key from the coordinates of an EC2 [`CoseKey`], without checking its
+/// algorithm.
+pub(crate) fn ec2_public_key_from_cose_key(key: &CoseKey) -> Result<PublicKey, Ctap2Error> {
     let (mut x, mut y) = (None, None);
     for (key, value) in &key.params {
         if let coset::Label::Int(i) = key {
@@ -129,18 +340,15 @@ pub fn public_key_der_from_cose_key(key: &CoseKey) -> Result<Bytes, Ctap2Error>
         return Err(Ctap2Error::CborUnexpectedType);
     };
 
+    if x.len() != 32 || y.len() != 32 {
+        return Err(Ctap2Error::InvalidCredential);
+    }
     let point = EncodedPoint::from_affine_coordinates(
         GenericArray::from_slice(x.as_slice()),
         GenericArray::from_slice(y.as_slice()),
         false,
     );
-    let Some(pub_key): Option<PublicKey> = PublicKey::from_encoded_point(&point).into() else {
-        return Err(Ctap2Error::InvalidCredential);
-    };
-    pub_key
-        .to_public_key_der()
-        .map_err(|_| Ctap2Error::InvalidCredential)
-        .map(|pk| pk.as_ref().to_vec().into())
+    Option::from(PublicKey::from_encoded_point(&point)).ok_or(Ctap2Error::InvalidCredential)
 }
 
 /// A COSE key pair, containing both the public and private keys.
@@ -175,11 +383,85 @@ impl CoseKeyPair {
 
         Self { public, private }
     }
+
+    /// Generate a new random key pair for the algorithm, which must be one of the algorithms
+    /// supported by the [`Authenticator`]: `EdDSA` generates an Ed25519 key, `RS256` a 2048-bit
+    /// RSA key, and any other algorithm a P-256 key.
+    pub(crate) fn generate(algorithm: Algorithm) -> Self {
+        let mut rng = rand::thread_rng();
+        match algorithm {
+            Algorithm::EdDSA => {
+                let signing_key = ed25519_dalek::SigningKey::generate(&mut rng);
+                let x = signing_key.verifying_key().to_bytes().to_vec();
+                let okp_key = |builder: CoseKeyBuilder| {
+                    builder
+                        .algorithm(Algorithm::EdDSA)
+                        .param(
+                            iana::OkpKeyParameter::Crv.to_i64(),
+                            iana::EllipticCurve::Ed25519.to_i64().into(),
+                        )
+                        .param(iana::OkpKeyParameter::X.to_i64(), x.clone().into())
+                };
+
+                Self {
+                    public: okp_key(CoseKeyBuilder::new_okp_key()).build(),
+                    private: okp_key(CoseKeyBuilder::new_okp_key())
+                        .param(
+                            iana::OkpKeyParameter::D.to_i64(),
+                            signing_key.to_bytes().to_vec().into(),
+                        )
+                        .build(),
+                }
+            }
+            Algorithm::RS256 => {
+                use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+
+                // SAFETY: generating a key only fails for sizes the rsa crate does not support,
+                // which 2048 bits is not.
+                let private_key = rsa::RsaPrivateKey::new(&mut rng, 2048).unwrap();
+                let rsa_key = |parameters: &[(iana::RsaKeyParameter, Option<&rsa::BigUint>)]| {
+                    parameters
+                        .iter()
+                        .filter_map(|(parameter, value)| Some((parameter, (*value)?)))
+                        .fold(
+                            CoseKeyBuilder::new()
+                                .key_type(iana::KeyType::RSA)
+                                .algorithm(Algorithm::RS256),
+                            |builder, (parameter, value)| {
+                                builder.param(parameter.to_i64(), value.to_bytes_be().into())
+                            },
+                        )
+                        .build()
+                };
+                let public = [
+                    (iana::RsaKeyParameter::N, Some(private_key.n())),
+                    (iana::RsaKeyParameter::E, Some(private_key.e())),
+                ];
+                let primes = private_key.primes();
+                let q_inv = private_key.crt_coefficient();
+
+                Self {
+                    public: rsa_key(&public),
+                    private: rsa_key(&[
+                        public[0],
+                        public[1],
+                        (iana::RsaKeyParameter::D, Some(private_key.d())),
+                        (iana::RsaKeyParameter::P, primes.first()),
+                        (iana::RsaKeyParameter::Q, primes.get(1)),
+                        (iana::RsaKeyParameter::DP, private_key.dp()),
+                        (iana::RsaKeyParameter::DQ, private_key.dq()),
+                        (iana::RsaKeyParameter::QInv, q_inv.as_ref()),
+                    ]),
+                }
+            }
+            _ => Self::from_secret_key(&SecretKey::random(&mut rng), algorithm),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use coset::iana;
+    use coset::iana::{self, EnumI64};
     use p256::{
         ecdsa::{
             signature::{Signer, Verifier},
@@ -187,9 +469,17 @@ mod tests {
         },
         SecretKey,
     };
-    use passkey_types::{ctap2::AuthenticatorData, rand::random_vec};
+    use passkey_types::{
+        ctap2::{make_credential, Aaguid, AuthenticatorData},
+        rand::random_vec,
+        webauthn,
+    };
 
-    use super::{private_key_from_cose_key, CoseKeyPair};
+    use super::{
+        credential_key_info, private_key_from_cose_key, public_key_der_from_cose_key, CoseKeyPair,
+        CredentialKeyInfo, CredentialSigningKey,
+    };
+    use crate::{Authenticator, MemoryStore, MockUserValidationMethod};
 
     #[test]
     fn private_key_cose_round_trip_sanity_check() {
@@ -217,4 +507,124 @@ mod tests {
             .verify(&signature_target, &signature)
             .expect("failed to verify signature")
     }
+
+    #[test]
+    fn ed25519_key_pair_signs_with_its_public_key() {
+        use ed25519_dalek::{pkcs8::DecodePublicKey, Verifier};
+
+        let CoseKeyPair { public, private } = CoseKeyPair::generate(iana::Algorithm::EdDSA);
+        let message = random_vec(64);
+
+        let signature = CredentialSigningKey::from_cose_key(&private)
+            .expect("to get an Ed25519 signing key")
+            .sign(&message);
+        let public_key = ed25519_dalek::VerifyingKey::from_public_key_der(
+            &public_key_der_from_cose_key(&public).expect("to encode the public key"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            public.kty,
+            coset::RegisteredLabel::Assigned(iana::KeyType::OKP)
+        );
+        assert!(!public
+            .params
+            .iter()
+            .any(|(label, _)| *label == coset::Label::Int(iana::OkpKeyParameter::D.to_i64())));
+        public_key
+            .verify(
+                &message,
+                &ed25519_dalek::Signature::from_slice(&signature).unwrap(),
+            )
+            .expect("failed to verify signature");
+    }
+
+    #[test]
+    fn rs256_key_pair_signs_with_its_public_key() {
+        use rsa::{
+            pkcs1v15::{Signature, VerifyingKey},
+            pkcs8::DecodePublicKey,
+            signature::Verifier,
+        };
+
+        let CoseKeyPair { public, private } = CoseKeyPair::generate(iana::Algorithm::RS256);
+        let message = random_vec(64);
+
+        let signature = CredentialSigningKey::from_cose_key(&private)
+            .expect("to get an RSA signing key")
+            .sign(&message);
+        let public_key = VerifyingKey::<sha2::Sha256>::from_public_key_der(
+            &public_key_der_from_cose_key(&public).expect("to encode the public key"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            public.kty,
+            coset::RegisteredLabel::Assigned(iana::KeyType::RSA)
+        );
+        assert_eq!(public.params.len(), 2);
+        assert_eq!(signature.len(), 256);
+        public_key
+            .verify(
+                &message,
+                &Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .expect("failed to verify signature");
+    }
+
+    #[tokio::test]
+    async fn credential_key_info_reports_es256_credentials() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator
+            .make_credential(make_credential::Request {
+                client_data_hash: random_vec(32).into(),
+                rp: make_credential::PublicKeyCredentialRpEntity {
+                    id: "future.1password.com".into(),
+                    name: None,
+                },
+                user: webauthn::PublicKeyCredentialUserEntity {
+                    id: random_vec(16).into(),
+                    display_name: "wendy".into(),
+                    name: "Appleseed".into(),
+                },
+                pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    alg: iana::Algorithm::ES256,
+                }],
+                exclude_list: None,
+                extensions: None,
+                options: make_credential::Options {
+                    rk: true,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+                enterprise_attestation: None,
+                attestation_formats_preference: None,
+            })
+            .await
+            .unwrap();
+        let passkey = authenticator.store().values().next().unwrap().clone();
+
+        // Act
+        let info = credential_key_info(&passkey);
+
+        // Assert
+        assert_eq!(
+            info,
+            CredentialKeyInfo {
+                algorithm: Some(iana::Algorithm::ES256),
+                key_type: Some(iana::KeyType::EC2),
+                curve: Some(iana::EllipticCurve::P_256),
+                can_sign: true,
+                can_verify: true,
+            }
+        );
+    }
 }
@@ -0,0 +1,210 @@
+//! The [PIN/UV auth protocol one], which lets the platform and the authenticator agree on a
+//! shared secret to encrypt and authenticate the parameters they exchange, such as the salts of
+//! the `hmac-secret` extension or the PIN, and authenticate requests with a `pinToken`.
+//!
+//! [PIN/UV auth protocol one]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#pinProto1
+
+use aes::cipher::{block_padding::NoPadding, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use coset::{iana, CoseKey, CoseKeyBuilder};
+use hmac::{Hmac, Mac};
+use p256::{ecdh::diffie_hellman, elliptic_curve::sec1::ToEncodedPoint, SecretKey};
+use passkey_types::{
+    crypto::sha256,
+    ctap2::{Ctap2Error, StatusCode, U2FError},
+};
+use sha2::Sha256;
+
+use crate::ec2_public_key_from_cose_key;
+
+/// The identifier of PIN/UV auth protocol one.
+pub(crate) const PROTOCOL_ONE: u8 = 1;
+
+/// Protocol one always uses an all zero initialization vector, the shared secret is never reused
+/// across key agreements.
+const ZERO_IV: [u8; 16] = [0; 16];
+
+/// The key agreement key of one side of the protocol. The authenticator regenerates its own
+/// whenever it is powered up.
+pub(crate) struct KeyAgreementKey(SecretKey);
+
+impl KeyAgreementKey {
+    /// Generate a new random key agreement key.
+    pub(crate) fn generate() -> Self {
+        Self(SecretKey::random(&mut rand::thread_rng()))
+    }
+
+    /// The public part of the key agreement key, in the form returned by `getKeyAgreement`.
+    pub(crate) fn public_key(&self) -> CoseKey {
+        let public_key = self.0.public_key().to_encoded_point(false);
+        // SAFETY: These unwraps are safe because the public_key above is not compressed (false
+        // parameter) therefore x and y are guarateed to contain values.
+        let x = public_key.x().unwrap().as_slice().to_vec();
+        let y = public_key.y().unwrap().as_slice().to_vec();
+        CoseKeyBuilder::new_ec2_pub_key(iana::EllipticCurve::P_256, x, y)
+            .algorithm(iana::Algorithm::ECDH_ES_HKDF_256)
+            .build()
+    }
+
+    /// Compute the secret shared with the owner of the given public key agreement key, which is
+    /// `SHA-256(Z)` where `Z` is the x coordinate of the ECDH shared point.
+    pub(crate) fn shared_secret(&self, peer: &CoseKey) -> Result<SharedSecret, StatusCode> {
+        if !matches!(
+            peer.kty,
+            coset::RegisteredLabel::Assigned(iana::KeyType::EC2)
+        ) {
+            return Err(U2FError::InvalidParameter.into());
+        }
+        let peer = ec2_public_key_from_cose_key(peer).map_err(|_| U2FError::InvalidParameter)?;
+        let shared_point = diffie_hellman(self.0.to_nonzero_scalar(), peer.as_affine());
+
+        Ok(SharedSecret(sha256(shared_point.raw_secret_bytes())))
+    }
+}
+
+/// A secret shared between the platform and the authenticator through a key agreement.
+pub(crate) struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// Encrypt the data with AES-256-CBC, the data must be a multiple of the block size.
+    pub(crate) fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, StatusCode> {
+        if data.len() % 16 != 0 {
+            return Err(U2FError::InvalidLength.into());
+        }
+        Ok(
+            cbc::Encryptor::<aes::Aes256>::new(&self.0.into(), &ZERO_IV.into())
+                .encrypt_padded_vec_mut::<NoPadding>(data),
+        )
+    }
+
+    /// Decrypt data encrypted with [`Self::encrypt`].
+    pub(crate) fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, StatusCode> {
+        cbc::Decryptor::<aes::Aes256>::new(&self.0.into(), &ZERO_IV.into())
+            .decrypt_padded_vec_mut::<NoPadding>(data)
+            .map_err(|_| U2FError::InvalidLength.into())
+    }
+
+    /// Authenticate the message, which is the first 16 bytes of `HMAC-SHA-256(secret, message)`.
+    /// Only the platform authenticates messages, which the tests do in its place.
+    #[cfg(test)]
+    pub(crate) fn authenticate(&self, message: &[u8]) -> [u8; 16] {
+        let mut signature = [0; 16];
+        signature.copy_from_slice(&self.hmac(message).finalize().into_bytes()[..16]);
+        signature
+    }
+
+    /// Verify that the signature is the result of authenticating the message, failing with
+    /// [`Ctap2Error::PinAuthInvalid`] otherwise.
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), StatusCode> {
+        self.hmac(message)
+            .verify_truncated_left(signature)
+            .ok()
+            .filter(|_| signature.len() == 16)
+            .ok_or_else(|| Ctap2Error::PinAuthInvalid.into())
+    }
+
+    fn hmac(&self, message: &[u8]) -> Hmac<Sha256> {
+        hmac(&self.0, message)
+    }
+}
+
+/// The `pinToken` given to the platform once it proved knowledge of the PIN, which then
+/// authenticates the parameters of its requests with it.
+pub(crate) struct PinToken([u8; 32]);
+
+impl PinToken {
+    /// Generate a new random token, which invalidates the ones given before.
+    pub(crate) fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    /// The token encrypted with the secret shared with the platform.
+    pub(crate) fn encrypt(&self, secret: &SharedSecret) -> Result<Vec<u8>, StatusCode> {
+        secret.encrypt(&self.0)
+    }
+
+    /// Verify that the signature is the first 16 bytes of `HMAC-SHA-256(pinToken, message)`,
+    /// failing with [`Ctap2Error::PinAuthInvalid`] otherwise.
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), StatusCode> {
+        hmac(&self.0, message)
+            .verify_truncated_left(signature)
+            .ok()
+            .filter(|_| signature.len() == 16)
+            .ok_or_else(|| Ctap2Error::PinAuthInvalid.into())
+    }
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    // SAFETY: HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(message);
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::CoseKeyBuilder;
+    use passkey_types::ctap2::{Ctap2Error, U2FError};
+
+    use super::{iana, KeyAgreementKey};
+
+    #[test]
+    fn both_sides_agree_on_the_shared_secret() {
+        let authenticator = KeyAgreementKey::generate();
+        let platform = KeyAgreementKey::generate();
+
+        let authenticator_secret = authenticator.shared_secret(&platform.public_key()).unwrap();
+        let platform_secret = platform.shared_secret(&authenticator.public_key()).unwrap();
+
+        let ciphertext = platform_secret.encrypt(&[7; 64]).unwrap();
+        assert_ne!(ciphertext, vec![7; 64]);
+        assert_eq!(
+            authenticator_secret.decrypt(&ciphertext).unwrap(),
+            vec![7; 64]
+        );
+        assert_eq!(
+            authenticator_secret.verify(&ciphertext, &platform_secret.authenticate(&ciphertext)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verify_rejects_other_messages_and_short_signatures() {
+        let secret = KeyAgreementKey::generate()
+            .shared_secret(&KeyAgreementKey::generate().public_key())
+            .unwrap();
+        let signature = secret.authenticate(b"message");
+
+        assert_eq!(
+            secret.verify(b"other message", &signature),
+            Err(Ctap2Error::PinAuthInvalid.into())
+        );
+        assert_eq!(
+            secret.verify(b"message", &signature[..8]),
+            Err(Ctap2Error::PinAuthInvalid.into())
+        );
+        assert_eq!(
+            secret.encrypt(&[0; 20]),
+            Err(U2FError::InvalidLength.into())
+        );
+    }
+
+    #[test]
+    fn shared_secret_rejects_invalid_public_keys() {
+        let key = KeyAgreementKey::generate();
+        let not_on_curve =
+            CoseKeyBuilder::new_ec2_pub_key(iana::EllipticCurve::P_256, vec![1; 32], vec![2; 32])
+                .build();
+        let truncated =
+            CoseKeyBuilder::new_ec2_pub_key(iana::EllipticCurve::P_256, vec![1; 16], vec![2; 16])
+                .build();
+
+        assert_eq!(
+            key.shared_secret(&not_on_curve).err(),
+            Some(U2FError::InvalidParameter.into())
+        );
+        assert_eq!(
+            key.shared_secret(&truncated).err(),
+            Some(U2FError::InvalidParameter.into())
+        );
+    }
+}
@@ -29,7 +29,7 @@ pub enum UIHint<'a, P> {
 }
 
 /// The result of a user validation check.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UserCheck {
     /// Indicates whether the user was present.
     pub presence: bool,
@@ -38,6 +38,32 @@ pub struct UserCheck {
     pub verification: bool,
 }
 
+/// The method with which the user was checked, as reported by the [`Authenticator`] in the `uvm`
+/// extension. These are the `USER_VERIFY` values of the [FIDO Registry of Predefined Values].
+///
+/// [FIDO Registry of Predefined Values]: https://fidoalliance.org/specs/common-specs/fido-registry-v2.2-ps-20220523.html#user-verification-methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerificationMethod {
+    /// Only the user's presence was tested, `USER_VERIFY_PRESENCE_INTERNAL`.
+    PresenceInternal,
+    /// The user was verified with a fingerprint, `USER_VERIFY_FINGERPRINT_INTERNAL`.
+    FingerprintInternal,
+    /// The user was verified with a passcode, `USER_VERIFY_PASSCODE_INTERNAL`.
+    PasscodeInternal,
+}
+
+impl VerificationMethod {
+    /// The value of this method in the FIDO Registry of Predefined Values.
+    pub fn value(self) -> u32 {
+        match self {
+            Self::PresenceInternal => 0x0001,
+            Self::FingerprintInternal => 0x0002,
+            Self::PasscodeInternal => 0x0004,
+        }
+    }
+}
+
 /// Pluggable trait for the [`Authenticator`] to do user interaction and verification.
 #[cfg_attr(any(test, feature = "testable"), mockall::automock(type PasskeyItem = Passkey;))]
 #[cfg_attr(any(test, feature = "testable"), allow(clippy::unused_async))] // Generated by the `mockall` macro.
@@ -79,10 +105,177 @@ pub trait UserValidationMethod {
     /// If a device is capable of verifying the user within itself as well as able to do Client PIN,
     ///  it will return both `Some` and the Client PIN option.
     async fn is_verification_enabled(&self) -> Option<bool>;
+
+    /// Let the user select the credential to use among the discoverable `credentials` of an
+    /// assertion made without an allow list, returning the index of the selected one. It is only
+    /// called when there is more than one candidate, before the user is checked for the
+    /// selected credential.
+    ///
+    /// Implementations should fail with [`Ctap2Error::OperationDenied`] when the user declines to
+    /// select one. The default implementation selects the first credential.
+    async fn select_credential(
+        &self,
+        _credentials: &[Self::PasskeyItem],
+    ) -> Result<usize, Ctap2Error> {
+        Ok(0)
+    }
+
+    /// The method with which [`Self::check_user`] checked the user, given the `check` it
+    /// returned. It is only called when the `uvm` extension is requested, and nothing is reported
+    /// in the extension output when it returns `None`.
+    ///
+    /// The default implementation reports [`VerificationMethod::PresenceInternal`] when only the
+    /// user's presence was tested, as it cannot know how the user was verified.
+    async fn verification_method(&self, check: UserCheck) -> Option<VerificationMethod> {
+        (check.presence && !check.verification).then_some(VerificationMethod::PresenceInternal)
+    }
+}
+
+/// A call made to the [`UserValidationMethod`] wrapped by a [`RecordingUserValidation`], along
+/// with what it returned.
+#[cfg(any(test, feature = "testable"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserValidationCall {
+    /// A call to [`UserValidationMethod::check_user`].
+    CheckUser {
+        /// The name of the [`UIHint`] variant that was given.
+        hint: &'static str,
+        /// Whether the user's presence was required.
+        presence: bool,
+        /// Whether the user should have been verified.
+        verification: bool,
+        /// The checks that were performed, or the error that was returned.
+        result: Result<UserCheck, Ctap2Error>,
+    },
+    /// A call to [`UserValidationMethod::select_credential`].
+    SelectCredential {
+        /// The number of credentials that were presented.
+        candidates: usize,
+        /// The index of the selected credential, or the error that was returned.
+        result: Result<usize, Ctap2Error>,
+    },
+    /// A call to [`UserValidationMethod::is_presence_enabled`].
+    IsPresenceEnabled(bool),
+    /// A call to [`UserValidationMethod::is_verification_enabled`].
+    IsVerificationEnabled(Option<bool>),
+    /// A call to [`UserValidationMethod::verification_method`].
+    VerificationMethod(Option<VerificationMethod>),
+}
+
+/// A handle to the calls recorded by a [`RecordingUserValidation`], which stays usable after
+/// the [`UserValidationMethod`] was moved into an [`Authenticator`].
+#[cfg(any(test, feature = "testable"))]
+#[derive(Debug, Clone, Default)]
+pub struct UserValidationCalls(std::sync::Arc<std::sync::Mutex<Vec<UserValidationCall>>>);
+
+#[cfg(any(test, feature = "testable"))]
+impl UserValidationCalls {
+    /// The calls recorded so far, in the order in which they were made.
+    pub fn to_vec(&self) -> Vec<UserValidationCall> {
+        self.lock().clone()
+    }
+
+    fn record(&self, call: UserValidationCall) {
+        log::debug!("User validation call: {call:?}");
+        self.lock().push(call);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<UserValidationCall>> {
+        // A panic while recording cannot leave the calls in an inconsistent state.
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Wraps a [`UserValidationMethod`] to log and record every call made to it, which helps to
+/// understand why a ceremony did or did not prompt the user.
+#[cfg(any(test, feature = "testable"))]
+pub struct RecordingUserValidation<U> {
+    inner: U,
+    calls: UserValidationCalls,
+}
+
+#[cfg(any(test, feature = "testable"))]
+impl<U> RecordingUserValidation<U> {
+    /// Record the calls made to `inner`.
+    pub fn new(inner: U) -> Self {
+        Self {
+            inner,
+            calls: UserValidationCalls::default(),
+        }
+    }
+
+    /// A handle to the recorded calls, to keep before moving this into an [`Authenticator`].
+    pub fn calls(&self) -> UserValidationCalls {
+        self.calls.clone()
+    }
+}
+
+#[cfg(any(test, feature = "testable"))]
+#[async_trait::async_trait]
+impl<U: UserValidationMethod + Sync> UserValidationMethod for RecordingUserValidation<U> {
+    type PasskeyItem = U::PasskeyItem;
+
+    async fn check_user<'a>(
+        &self,
+        hint: UIHint<'a, Self::PasskeyItem>,
+        presence: bool,
+        verification: bool,
+    ) -> Result<UserCheck, Ctap2Error> {
+        let hint_name = match hint {
+            UIHint::InformExcludedCredentialFound(_) => "InformExcludedCredentialFound",
+            UIHint::InformNoCredentialsFound => "InformNoCredentialsFound",
+            UIHint::RequestNewCredential(_, _) => "RequestNewCredential",
+            UIHint::RequestExistingCredential(_) => "RequestExistingCredential",
+        };
+        let result = self.inner.check_user(hint, presence, verification).await;
+        self.calls.record(UserValidationCall::CheckUser {
+            hint: hint_name,
+            presence,
+            verification,
+            result,
+        });
+        result
+    }
+
+    async fn select_credential(
+        &self,
+        credentials: &[Self::PasskeyItem],
+    ) -> Result<usize, Ctap2Error> {
+        let result = self.inner.select_credential(credentials).await;
+        self.calls.record(UserValidationCall::SelectCredential {
+            candidates: credentials.len(),
+            result,
+        });
+        result
+    }
+
+    async fn is_presence_enabled(&self) -> bool {
+        let enabled = self.inner.is_presence_enabled().await;
+        self.calls
+            .record(UserValidationCall::IsPresenceEnabled(enabled));
+        enabled
+    }
+
+    async fn is_verification_enabled(&self) -> Option<bool> {
+        let enabled = self.inner.is_verification_enabled().await;
+        self.calls
+            .record(UserValidationCall::IsVerificationEnabled(enabled));
+        enabled
+    }
+
+    async fn verification_method(&self, check: UserCheck) -> Option<VerificationMethod> {
+        let method = self.inner.verification_method(check).await;
+        self.calls
+            .record(UserValidationCall::VerificationMethod(method));
+        method
+    }
 }
 
 /// A version of the [`UIHint`] that uses a [`Passkey`] as the passkey item, is not tied to any specific lifetime,
 /// and does not verify new passkey items which contain new random data that the tests cannot know about beforehand.
+#[cfg(any(test, feature = "testable"))]
 #[derive(Debug, Clone, PartialEq)]
 pub enum MockUIHint {
     InformExcludedCredentialFound(Passkey),
@@ -91,6 +284,27 @@ pub enum MockUIHint {
     RequestExistingCredential(Passkey),
 }
 
+#[cfg(any(test, feature = "testable"))]
+impl MockUIHint {
+    /// Whether the given hint is the expected one.
+    fn matches(&self, actual_hint: &UIHint<'_, Passkey>) -> bool {
+        match self {
+            MockUIHint::InformExcludedCredentialFound(p) => {
+                actual_hint == &UIHint::InformExcludedCredentialFound(p)
+            }
+            MockUIHint::InformNoCredentialsFound => {
+                matches!(actual_hint, UIHint::InformNoCredentialsFound)
+            }
+            MockUIHint::RequestNewCredential(user, rp) => {
+                actual_hint == &UIHint::RequestNewCredential(user, rp)
+            }
+            MockUIHint::RequestExistingCredential(p) => {
+                actual_hint == &UIHint::RequestExistingCredential(p)
+            }
+        }
+    }
+}
+
 #[cfg(any(test, feature = "testable"))]
 impl MockUserValidationMethod {
     /// Sets up the mock for returning true for the verification.
@@ -136,26 +350,86 @@ impl MockUserValidationMethod {
         user_mock
             .expect_check_user()
             .withf(move |actual_hint, presence, verification| {
-                *presence
-                    && *verification
-                    && match &expected_hint {
-                        MockUIHint::InformExcludedCredentialFound(p) => {
-                            actual_hint == &UIHint::InformExcludedCredentialFound(p)
-                        }
-                        MockUIHint::InformNoCredentialsFound => {
-                            matches!(actual_hint, UIHint::InformNoCredentialsFound)
-                        }
-                        MockUIHint::RequestNewCredential(user, rp) => {
-                            actual_hint == &UIHint::RequestNewCredential(user, rp)
-                        }
-                        MockUIHint::RequestExistingCredential(p) => {
-                            actual_hint == &UIHint::RequestExistingCredential(p)
-                        }
-                    }
+                *presence && *verification && expected_hint.matches(actual_hint)
+            })
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: true,
+                    verification: true,
+                })
+            })
+            .times(times);
+        user_mock
+    }
+
+    /// Sets up the mock for returning true for the presence, when only the presence is
+    /// requested.
+    pub fn present_user_with_hint(times: usize, expected_hint: MockUIHint) -> Self {
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true))
+            .times(..);
+        user_mock
+            .expect_is_presence_enabled()
+            .returning(|| true)
+            .times(..);
+        user_mock
+            .expect_check_user()
+            .withf(move |actual_hint, presence, verification| {
+                *presence && !*verification && expected_hint.matches(actual_hint)
             })
             .returning(|_, _, _| {
                 Ok(UserCheck {
                     presence: true,
+                    verification: false,
+                })
+            })
+            .times(times);
+        user_mock
+    }
+
+    /// Sets up the mock for a user who consents but fails, or refuses, to verify themselves, so
+    /// that the verification check fails.
+    pub fn unverified_user(times: usize) -> Self {
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true))
+            .times(..);
+        user_mock
+            .expect_is_presence_enabled()
+            .returning(|| true)
+            .times(..);
+        user_mock
+            .expect_check_user()
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: true,
+                    verification: false,
+                })
+            })
+            .times(times);
+        user_mock
+    }
+
+    /// Sets up the mock for a user who verifies themselves but does not consent, so that the
+    /// presence check fails.
+    pub fn absent_user(times: usize) -> Self {
+        let mut user_mock = MockUserValidationMethod::new();
+        user_mock
+            .expect_is_verification_enabled()
+            .returning(|| Some(true))
+            .times(..);
+        user_mock
+            .expect_is_presence_enabled()
+            .returning(|| true)
+            .times(..);
+        user_mock
+            .expect_check_user()
+            .returning(|_, _, _| {
+                Ok(UserCheck {
+                    presence: false,
                     verification: true,
                 })
             })
@@ -0,0 +1,100 @@
+//! Redacted records of the ceremonies performed by a [`Client`](crate::Client), meant for audit
+//! trails. See [`Client::with_audit_hook`](crate::Client::with_audit_hook).
+//!
+//! A record never contains secrets: the credential ID is hashed, and neither the keys, the
+//! challenge nor the client data of the ceremony are recorded.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use passkey_types::{crypto::sha256, encoding};
+use serde::Serialize;
+
+use crate::WebauthnError;
+
+/// The kind of ceremony an [`AuditRecord`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CeremonyOperation {
+    /// A credential was requested through [`Client::register`](crate::Client::register).
+    Registration,
+    /// An assertion was requested through [`Client::authenticate`](crate::Client::authenticate).
+    Authentication,
+}
+
+/// How a ceremony ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum CeremonyOutcome {
+    /// The ceremony returned a credential.
+    Success,
+    /// The ceremony failed with the given [`WebauthnError`], formatted with its `Debug`
+    /// implementation.
+    Failure {
+        /// The error the ceremony failed with.
+        error: String,
+    },
+}
+
+/// A redacted record of a single ceremony.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// When the ceremony started, in seconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// The origin the request came from.
+    pub origin: String,
+
+    /// The RP ID of the ceremony once it was verified against the origin, `None` when the
+    /// verification failed.
+    pub rp_id: Option<String>,
+
+    /// Whether a credential was created or asserted.
+    pub operation: CeremonyOperation,
+
+    /// The COSE algorithm identifier of the credential, when one was created or asserted.
+    pub algorithm: Option<i64>,
+
+    /// Whether the authenticator verified the user, when it answered the request.
+    pub user_verified: Option<bool>,
+
+    /// The SHA-256 hash of the credential ID, encoded in base64url, when one was created or
+    /// asserted.
+    pub credential_id_hash: Option<String>,
+
+    /// How the ceremony ended.
+    pub outcome: CeremonyOutcome,
+}
+
+impl AuditRecord {
+    /// Start the record of a ceremony, which succeeds until told otherwise.
+    pub(crate) fn start(operation: CeremonyOperation, origin: String) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            origin,
+            rp_id: None,
+            operation,
+            algorithm: None,
+            user_verified: None,
+            credential_id_hash: None,
+            outcome: CeremonyOutcome::Success,
+        }
+    }
+
+    /// Record the credential of the ceremony by the hash of its ID.
+    pub(crate) fn set_credential_id(&mut self, credential_id: &[u8]) {
+        self.credential_id_hash = Some(encoding::base64url(&sha256(credential_id)));
+    }
+
+    /// Record how the ceremony ended.
+    pub(crate) fn finish<T>(mut self, result: &Result<T, WebauthnError>) -> Self {
+        if let Err(error) = result {
+            self.outcome = CeremonyOutcome::Failure {
+                error: format!("{error:?}"),
+            };
+        }
+        self
+    }
+}
@@ -5,8 +5,24 @@ pub trait ClientData<E: Serialize> {
     /// Extra client data to be appended to the automatically generated client data.
     fn extra_client_data(&self) -> E;
 
+    /// Extra client data for an operation of the relying party `rp_id` requested by `origin`,
+    /// once both were validated by the client. Producers whose extra client data is computed from
+    /// them, such as the payment data of Secure Payment Confirmation, override this method.
+    ///
+    /// The default implementation returns [`Self::extra_client_data`].
+    fn extra_client_data_for(&self, _rp_id: &str, _origin: &str) -> E {
+        self.extra_client_data()
+    }
+
     /// The hash of the client data to be used in the WebAuthn operation.
     fn client_data_hash(&self) -> Option<Vec<u8>>;
+
+    /// The origin of the top level document when the operation is requested from a cross-origin
+    /// iframe, which sets `crossOrigin` and `topOrigin` in the generated client data. By default
+    /// the operation is requested by the top level document itself.
+    fn top_origin(&self) -> Option<String> {
+        None
+    }
 }
 
 /// The client data and its hash will be automatically generated from the request
@@ -43,6 +59,32 @@ impl ClientData<()> for DefaultClientDataWithCustomHash {
     }
 }
 
+/// The client data of an operation requested from a cross-origin iframe embedded in the
+/// `top_origin` document. Everything else is generated like the wrapped `client_data` would.
+pub struct CrossOriginClientData<C> {
+    /// The client data being wrapped.
+    pub client_data: C,
+    /// The origin of the top level document, reported as `topOrigin`.
+    pub top_origin: String,
+}
+impl<E: Serialize, C: ClientData<E>> ClientData<E> for CrossOriginClientData<C> {
+    fn extra_client_data(&self) -> E {
+        self.client_data.extra_client_data()
+    }
+
+    fn extra_client_data_for(&self, rp_id: &str, origin: &str) -> E {
+        self.client_data.extra_client_data_for(rp_id, origin)
+    }
+
+    fn client_data_hash(&self) -> Option<Vec<u8>> {
+        self.client_data.client_data_hash()
+    }
+
+    fn top_origin(&self) -> Option<String> {
+        Some(self.top_origin.clone())
+    }
+}
+
 /// Backwards compatibility with the previous `register` and `authenticate` functions
 /// which only took `Option<Vec<u8>>` as a client data hash.
 impl ClientData<()> for Option<Vec<u8>> {
@@ -52,3 +94,24 @@ impl ClientData<()> for Option<Vec<u8>> {
         self.clone()
     }
 }
+
+/// Recursively sort the keys of the JSON objects in `value`, so that logically equal values are
+/// always serialized to the same bytes regardless of the order in which their keys were inserted.
+pub(crate) fn canonicalize_json(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize_json(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(canonicalize_json).collect())
+        }
+        value => value,
+    }
+}
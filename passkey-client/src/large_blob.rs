@@ -0,0 +1,484 @@
+//! Client side processing of the data stored through the [`largeBlob`] extension.
+//!
+//! The bytes an application reads or writes are the uncompressed payload, while the blob stored
+//! in the authenticator's large-blob array is the DEFLATE ([RFC1951]) compressed payload along
+//! with its original size, which is also bound to the encryption through the associated data.
+//!
+//! Each blob is then encrypted with AES-256-GCM under the 32 byte `largeBlobKey` of its credential
+//! and stored as an entry of the authenticator's serialized large-blob array.
+//!
+//! The [`Client`](crate::Client) processes the `read` and `write` inputs of the extension with
+//! these functions, reading and writing the serialized array through the authenticator's
+//! `authenticatorLargeBlobs` command.
+//!
+//! [`largeBlob`]: https://w3c.github.io/webauthn/#sctn-large-blob-extension
+//! [RFC1951]: https://www.rfc-editor.org/rfc/rfc1951
+
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, KeyInit, Nonce,
+};
+use passkey_authenticator::{Authenticator, CredentialStore, UserValidationMethod};
+use passkey_types::{
+    ctap2::{
+        large_blobs::{self, deserialize_array, serialize_array, LargeBlobEntry},
+        StatusCode,
+    },
+    rand::random_vec,
+    webauthn::{AuthenticationExtensionsLargeBlobInputs, AuthenticationExtensionsLargeBlobOutputs},
+    Passkey,
+};
+
+/// The compression level used when writing a large blob, this is the default level of zlib.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// The length of the AES-256-GCM nonce of a large-blob array entry.
+const NONCE_LENGTH: usize = 12;
+
+/// The largest fragment of the serialized large-blob array read or written at once, which fits in
+/// the default maximum message size of 1024 bytes.
+const MAX_FRAGMENT_LENGTH: usize = 960;
+
+/// Errors produced while processing a large blob.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LargeBlobError {
+    /// The stored data is not a valid DEFLATE stream or it is larger than its original size.
+    InvalidCompression,
+    /// The decompressed data does not have the expected original size.
+    SizeMismatch,
+    /// The `largeBlobKey` is not a 32 byte AES-256-GCM key.
+    InvalidKey,
+    /// The entry was not encrypted with this `largeBlobKey`, or it was tampered with.
+    DecryptionFailed,
+}
+
+/// A large blob payload in its stored form, compressed and framed with its original size.
+///
+/// <https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#large-blob>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedLargeBlob {
+    data: Vec<u8>,
+    orig_size: u64,
+}
+
+impl CompressedLargeBlob {
+    /// Compress the payload provided by the application.
+    pub fn compress(payload: &[u8]) -> Self {
+        Self {
+            data: miniz_oxide::deflate::compress_to_vec(payload, COMPRESSION_LEVEL),
+            // SAFETY: a usize always fits in a u64 on the supported platforms.
+            orig_size: u64::try_from(payload.len()).unwrap(),
+        }
+    }
+
+    /// Create a compressed blob from its stored DEFLATE data and original size.
+    pub fn new(data: Vec<u8>, orig_size: u64) -> Self {
+        Self { data, orig_size }
+    }
+
+    /// The DEFLATE compressed payload.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The size of the payload before compression.
+    pub fn orig_size(&self) -> u64 {
+        self.orig_size
+    }
+
+    /// The associated data used when encrypting this blob: `"blob" || uint64LittleEndian(origSize)`.
+    pub fn associated_data(&self) -> [u8; 12] {
+        let mut associated_data = [0; 12];
+        associated_data[..4].copy_from_slice(b"blob");
+        associated_data[4..].copy_from_slice(&self.orig_size.to_le_bytes());
+        associated_data
+    }
+
+    /// Decompress the stored data back into the payload originally provided by the application.
+    pub fn decompress(&self) -> Result<Vec<u8>, LargeBlobError> {
+        let orig_size =
+            usize::try_from(self.orig_size).map_err(|_| LargeBlobError::SizeMismatch)?;
+        let payload = miniz_oxide::inflate::decompress_to_vec_with_limit(&self.data, orig_size)
+            .map_err(|_| LargeBlobError::InvalidCompression)?;
+
+        if payload.len() != orig_size {
+            return Err(LargeBlobError::SizeMismatch);
+        }
+
+        Ok(payload)
+    }
+
+    /// Encrypt this blob with the `largeBlobKey` of its credential into an entry of the
+    /// large-blob array, using a random nonce.
+    pub fn encrypt(&self, large_blob_key: &[u8]) -> Result<LargeBlobEntry, LargeBlobError> {
+        let cipher = cipher(large_blob_key)?;
+        let nonce = random_vec(NONCE_LENGTH);
+        // SAFETY: AES-GCM only fails to encrypt messages of more than 64 GiB.
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &self.data,
+                    aad: &self.associated_data(),
+                },
+            )
+            .unwrap();
+
+        Ok(LargeBlobEntry {
+            ciphertext: ciphertext.into(),
+            nonce: nonce.into(),
+            orig_size: self.orig_size,
+        })
+    }
+
+    /// Decrypt an entry of the large-blob array with the `largeBlobKey` of its credential.
+    pub fn decrypt(entry: &LargeBlobEntry, large_blob_key: &[u8]) -> Result<Self, LargeBlobError> {
+        let cipher = cipher(large_blob_key)?;
+        if entry.nonce.len() != NONCE_LENGTH {
+            return Err(LargeBlobError::DecryptionFailed);
+        }
+        let associated_data = Self::new(Vec::new(), entry.orig_size).associated_data();
+        let data = cipher
+            .decrypt(
+                Nonce::from_slice(&entry.nonce),
+                Payload {
+                    msg: &entry.ciphertext,
+                    aad: &associated_data,
+                },
+            )
+            .map_err(|_| LargeBlobError::DecryptionFailed)?;
+
+        Ok(Self::new(data, entry.orig_size))
+    }
+}
+
+fn cipher(large_blob_key: &[u8]) -> Result<Aes256Gcm, LargeBlobError> {
+    Aes256Gcm::new_from_slice(large_blob_key).map_err(|_| LargeBlobError::InvalidKey)
+}
+
+/// Read the payload of the credential owning `large_blob_key` from a serialized large-blob array,
+/// which is the first entry that can be decrypted with the key. Returns `Ok(None)` when the array
+/// holds no blob for this credential.
+pub fn read_large_blob(
+    serialized_array: &[u8],
+    large_blob_key: &[u8],
+) -> Result<Option<Vec<u8>>, LargeBlobError> {
+    cipher(large_blob_key)?;
+    deserialize_array(serialized_array)
+        .iter()
+        .find_map(|entry| CompressedLargeBlob::decrypt(entry, large_blob_key).ok())
+        .map(|blob| blob.decompress())
+        .transpose()
+}
+
+/// Write the payload of the credential owning `large_blob_key` into a serialized large-blob
+/// array, replacing any blob it already had, and return the new serialized array.
+pub fn write_large_blob(
+    serialized_array: &[u8],
+    large_blob_key: &[u8],
+    payload: &[u8],
+) -> Result<Vec<u8>, LargeBlobError> {
+    let entry = CompressedLargeBlob::compress(payload).encrypt(large_blob_key)?;
+    let mut entries: Vec<_> = deserialize_array(serialized_array)
+        .into_iter()
+        .filter(|entry| CompressedLargeBlob::decrypt(entry, large_blob_key).is_err())
+        .collect();
+    entries.push(entry);
+
+    Ok(serialize_array(&entries))
+}
+
+/// Process the `read` or `write` input of the `largeBlob` extension for an assertion, with the
+/// `largeBlobKey` the authenticator returned for the asserted credential.
+///
+/// A failure to read or write the blob does not fail the assertion, it is reported by the absence
+/// of [`AuthenticationExtensionsLargeBlobOutputs::blob`] or by a `false`
+/// [`AuthenticationExtensionsLargeBlobOutputs::written`].
+pub(crate) async fn process_assertion<S, U>(
+    authenticator: &mut Authenticator<S, U>,
+    inputs: &AuthenticationExtensionsLargeBlobInputs,
+    large_blob_key: Option<&[u8]>,
+) -> AuthenticationExtensionsLargeBlobOutputs
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    let mut outputs = AuthenticationExtensionsLargeBlobOutputs::default();
+    if let Some(payload) = &inputs.write {
+        let written = match large_blob_key {
+            Some(key) => match read_serialized_array(authenticator).await {
+                Ok(array) => match write_large_blob(&array, key, payload) {
+                    Ok(array) => write_serialized_array(authenticator, &array).await.is_ok(),
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            },
+            None => false,
+        };
+        outputs.written = Some(written);
+    } else if inputs.read == Some(true) {
+        if let Some(key) = large_blob_key {
+            outputs.blob = read_serialized_array(authenticator)
+                .await
+                .ok()
+                .and_then(|array| read_large_blob(&array, key).ok().flatten())
+                .map(Into::into);
+        }
+    }
+    outputs
+}
+
+/// Read the whole serialized large-blob array of the authenticator, one fragment at a time until
+/// a fragment shorter than requested is returned.
+async fn read_serialized_array<S, U>(
+    authenticator: &mut Authenticator<S, U>,
+) -> Result<Vec<u8>, StatusCode>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    let mut array = Vec::new();
+    loop {
+        let fragment = authenticator
+            .large_blobs(large_blobs::Request {
+                get: Some(MAX_FRAGMENT_LENGTH),
+                set: None,
+                offset: array.len(),
+                length: None,
+                pin_uv_auth_param: None,
+                pin_uv_auth_protocol: None,
+            })
+            .await?
+            .config
+            .unwrap_or_default();
+        array.extend_from_slice(&fragment);
+        if fragment.len() < MAX_FRAGMENT_LENGTH {
+            return Ok(array);
+        }
+    }
+}
+
+/// Write the whole serialized large-blob array to the authenticator, one fragment at a time. The
+/// first fragment announces the length of the array.
+async fn write_serialized_array<S, U>(
+    authenticator: &mut Authenticator<S, U>,
+    array: &[u8],
+) -> Result<(), StatusCode>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    for (index, fragment) in array.chunks(MAX_FRAGMENT_LENGTH).enumerate() {
+        let offset = index * MAX_FRAGMENT_LENGTH;
+        authenticator
+            .large_blobs(large_blobs::Request {
+                get: None,
+                set: Some(fragment.to_vec().into()),
+                offset,
+                length: (offset == 0).then_some(array.len()),
+                pin_uv_auth_param: None,
+                pin_uv_auth_protocol: None,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use passkey_authenticator::{Authenticator, MemoryStore, MockUserValidationMethod};
+    use passkey_types::{
+        ctap2::{
+            get_assertion, large_blobs, large_blobs::INITIAL_SERIALIZED_ARRAY, make_credential,
+            Aaguid,
+        },
+        rand::random_vec,
+        webauthn,
+    };
+
+    use super::{read_large_blob, write_large_blob, CompressedLargeBlob, LargeBlobError};
+
+    #[test]
+    fn payload_round_trips_through_compression() {
+        let payload = b"a large blob, a large blob, a large blob, a large blob".repeat(8);
+
+        let blob = CompressedLargeBlob::compress(&payload);
+        let stored = CompressedLargeBlob::new(blob.data().to_vec(), blob.orig_size());
+
+        assert!(stored.data().len() < payload.len());
+        assert_eq!(stored.orig_size(), 432);
+        assert_eq!(stored.decompress().unwrap(), payload);
+    }
+
+    #[test]
+    fn associated_data_frames_the_original_size() {
+        let blob = CompressedLargeBlob::compress(&[0xff; 258]);
+
+        assert_eq!(
+            blob.associated_data(),
+            [b'b', b'l', b'o', b'b', 0x02, 0x01, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn decompress_rejects_mismatched_original_size() {
+        let blob = CompressedLargeBlob::compress(b"payload");
+
+        let shorter = CompressedLargeBlob::new(blob.data().to_vec(), 3);
+        let longer = CompressedLargeBlob::new(blob.data().to_vec(), 12);
+
+        assert_eq!(
+            shorter.decompress(),
+            Err(LargeBlobError::InvalidCompression)
+        );
+        assert_eq!(longer.decompress(), Err(LargeBlobError::SizeMismatch));
+    }
+
+    #[test]
+    fn decompress_rejects_invalid_data() {
+        let blob = CompressedLargeBlob::new(vec![0xff; 16], 16);
+
+        assert_eq!(blob.decompress(), Err(LargeBlobError::InvalidCompression));
+    }
+
+    #[test]
+    fn encrypted_blob_is_bound_to_its_key() {
+        let blob = CompressedLargeBlob::compress(b"payload");
+
+        let entry = blob.encrypt(&[1; 32]).unwrap();
+
+        assert_eq!(entry.nonce.len(), 12);
+        assert_eq!(entry.orig_size, 7);
+        assert_eq!(CompressedLargeBlob::decrypt(&entry, &[1; 32]), Ok(blob));
+        assert_eq!(
+            CompressedLargeBlob::decrypt(&entry, &[2; 32]),
+            Err(LargeBlobError::DecryptionFailed)
+        );
+        assert_eq!(
+            CompressedLargeBlob::compress(b"payload").encrypt(&[1; 16]),
+            Err(LargeBlobError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn writing_a_blob_replaces_the_previous_one_of_the_credential() {
+        let array = write_large_blob(&INITIAL_SERIALIZED_ARRAY, &[1; 32], b"first").unwrap();
+        let array = write_large_blob(&array, &[2; 32], b"other").unwrap();
+        let array = write_large_blob(&array, &[1; 32], b"second").unwrap();
+
+        assert_eq!(
+            read_large_blob(&array, &[1; 32]),
+            Ok(Some(b"second".to_vec()))
+        );
+        assert_eq!(
+            read_large_blob(&array, &[2; 32]),
+            Ok(Some(b"other".to_vec()))
+        );
+        assert_eq!(read_large_blob(&array, &[3; 32]), Ok(None));
+    }
+
+    #[tokio::test]
+    async fn large_blob_round_trips_through_the_authenticator() {
+        // Arrange
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+        let user_id = random_vec(16);
+        let created = authenticator
+            .make_credential(make_credential::Request {
+                client_data_hash: random_vec(32).into(),
+                rp: make_credential::PublicKeyCredentialRpEntity {
+                    id: "future.1password.com".into(),
+                    name: None,
+                },
+                user: webauthn::PublicKeyCredentialUserEntity {
+                    id: user_id.into(),
+                    display_name: "wendy".into(),
+                    name: "Appleseed".into(),
+                },
+                pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    alg: coset::iana::Algorithm::ES256,
+                }],
+                exclude_list: None,
+                extensions: Some(make_credential::ExtensionInputs {
+                    large_blob_key: Some(true),
+                    ..Default::default()
+                }),
+                options: make_credential::Options {
+                    rk: true,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+                enterprise_attestation: None,
+                attestation_formats_preference: None,
+            })
+            .await
+            .unwrap();
+        let payload = b"a large blob, a large blob".repeat(16);
+
+        // Act
+        let array = write_large_blob(
+            &INITIAL_SERIALIZED_ARRAY,
+            &created.large_blob_key.unwrap(),
+            &payload,
+        )
+        .unwrap();
+        authenticator
+            .large_blobs(large_blobs::Request {
+                get: None,
+                set: Some(array.clone().into()),
+                offset: 0,
+                length: Some(array.len()),
+                pin_uv_auth_param: None,
+                pin_uv_auth_protocol: None,
+            })
+            .await
+            .unwrap();
+        let asserted = authenticator
+            .get_assertion(get_assertion::Request {
+                rp_id: "future.1password.com".into(),
+                client_data_hash: random_vec(32).into(),
+                allow_list: None,
+                extensions: Some(get_assertion::ExtensionInputs {
+                    large_blob_key: Some(true),
+                    ..Default::default()
+                }),
+                options: get_assertion::Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await
+            .unwrap();
+        let stored = authenticator
+            .large_blobs(large_blobs::Request {
+                get: Some(960),
+                set: None,
+                offset: 0,
+                length: None,
+                pin_uv_auth_param: None,
+                pin_uv_auth_protocol: None,
+            })
+            .await
+            .unwrap()
+            .config
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            read_large_blob(&stored, &asserted.large_blob_key.unwrap()),
+            Ok(Some(payload))
+        );
+    }
+}
@@ -17,6 +17,12 @@
 mod client_data;
 pub use client_data::*;
 
+mod origin_matcher;
+pub use origin_matcher::OriginMatcher;
+
+mod related_origins;
+pub use related_origins::{RelatedOriginsFetcher, MAX_RELATED_ORIGIN_LABELS};
+
 use std::{borrow::Cow, fmt::Display};
 
 use coset::{iana::EnumI64, Algorithm};
@@ -24,11 +30,12 @@ use passkey_authenticator::{
     Authenticator, CredentialStore, DiscoverabilitySupport, UserValidationMethod,
 };
 use passkey_types::{
-    crypto::sha256,
+    crypto::client_data_hash,
     ctap2, encoding,
     webauthn::{
         self, AuthenticatorExtensionsClientOutputs, AuthenticatorSelectionCriteria,
-        CredentialPropertiesOutput, ResidentKeyRequirement, UserVerificationRequirement,
+        CredentialPropertiesOutput, LargeBlobSupport, ResidentKeyRequirement,
+        UserVerificationRequirement,
     },
     Passkey,
 };
@@ -40,6 +47,13 @@ use url::Url;
 mod quirks;
 use quirks::QuirkyRp;
 
+use audit::{AuditRecord, CeremonyOperation};
+
+pub mod audit;
+pub mod large_blob;
+pub mod prf;
+pub mod relying_party;
+
 #[cfg(feature = "android-asset-validation")]
 mod android;
 
@@ -58,6 +72,9 @@ pub enum WebauthnError {
     CredentialIdTooLong,
     /// The request origin was missing a proper domain part.
     OriginMissingDomain,
+    /// The request origin is not a web origin, such as a URL without a host or whose scheme is
+    /// neither `https` nor `http`, like `data:` and `file:` URLs.
+    InvalidOrigin,
     /// The request origin is not a sub-domain of the RP ID.
     OriginRpMissmatch,
     /// The origin of the request does not use HTTPS.
@@ -70,6 +87,28 @@ pub enum WebauthnError {
     InvalidRpId,
     /// Internal authenticator error whose value represents a `ctap2::StatusCode`
     AuthenticatorError(u8),
+    /// The assertion produced by the authenticator could not be verified with the public key of
+    /// its credential.
+    InvalidAssertionSignature,
+    /// The operation was aborted through its `AbortSignal`.
+    Aborted,
+    /// The credential used for the assertion belongs to a user other than the one expected by
+    /// the Relying Party.
+    UserHandleMismatch,
+    /// The request comes from an `android:apk-key-hash:` origin whose application is not trusted
+    /// with the RP ID.
+    UntrustedAndroidOrigin,
+    /// The related origins document of the RP ID is not a JSON object with an `origins` list.
+    InvalidRelatedOrigins,
+    /// The authenticator already holds one of the credentials excluded from the registration,
+    /// which WebAuthn reports as an `InvalidStateError`.
+    InvalidState,
+    /// The challenge of the request was rejected by the callback given to
+    /// [`Client::with_challenge_validator`], for example because it expired.
+    InvalidChallenge,
+    /// The request uses an extension in a way that is invalid for the ceremony, or that the
+    /// authenticator cannot satisfy, which WebAuthn reports as a `NotSupportedError`.
+    NotSupported,
 }
 
 impl WebauthnError {
@@ -86,6 +125,9 @@ impl From<ctap2::StatusCode> for WebauthnError {
             ctap2::StatusCode::Ctap2(ctap2::Ctap2Code::Known(ctap2::Ctap2Error::NoCredentials)) => {
                 WebauthnError::CredentialNotFound
             }
+            ctap2::StatusCode::Ctap2(ctap2::Ctap2Code::Known(
+                ctap2::Ctap2Error::CredentialExcluded,
+            )) => WebauthnError::InvalidState,
             ctap2::StatusCode::Ctap2(ctap2code) => {
                 WebauthnError::AuthenticatorError(ctap2code.into())
             }
@@ -104,6 +146,45 @@ fn decode_host(host: &str) -> Option<Cow<str>> {
     }
 }
 
+/// The user verification methods the authenticator reported in the `uvm` extension output of the
+/// authenticator data.
+fn reported_uvm(auth_data: &ctap2::AuthenticatorData) -> Option<Vec<webauthn::UvmEntry>> {
+    auth_data.extensions().ok().flatten()?.uvm
+}
+
+/// The device public key attestation the authenticator reported in the `devicePubKey` extension
+/// output of the authenticator data.
+fn reported_device_pub_key(
+    auth_data: &ctap2::AuthenticatorData,
+) -> Option<webauthn::AuthenticationExtensionsDevicePublicKeyOutputs> {
+    auth_data
+        .extensions()
+        .ok()
+        .flatten()?
+        .device_pub_key
+        .map(
+            |authenticator_output| webauthn::AuthenticationExtensionsDevicePublicKeyOutputs {
+                authenticator_output,
+            },
+        )
+}
+
+/// The protection policy the authenticator applied to a new credential, which it reports in the
+/// `credProtect` extension output of the authenticator data.
+fn applied_cred_protect(
+    auth_data: &ctap2::AuthenticatorData,
+) -> Option<webauthn::CredentialProtectionPolicy> {
+    let ciborium::Value::Map(outputs) = auth_data.extensions.as_ref()? else {
+        return None;
+    };
+    outputs
+        .iter()
+        .find(|(id, _)| id.as_text() == Some("credProtect"))
+        .and_then(|(_, value)| value.as_integer())
+        .and_then(|value| u8::try_from(value).ok())
+        .and_then(webauthn::CredentialProtectionPolicy::from_ctap_value)
+}
+
 /// The origin of a WebAuthn request.
 pub enum Origin<'a> {
     /// A Url, meant for a request in the web browser.
@@ -142,6 +223,146 @@ impl Display for Origin<'_> {
     }
 }
 
+/// A lock that can be shared between multiple [`Client`]s so that their ceremonies are run one at
+/// a time.
+///
+/// Authenticators are usually single threaded devices. When several clients share the same
+/// [`CredentialStore`] or the same [`UserValidationMethod`], overlapping `register` and
+/// `authenticate` calls could otherwise race on signature counters or prompt the user twice.
+/// Cloning a `CeremonyLock` shares the underlying lock.
+#[cfg(any(feature = "tokio", test))]
+#[derive(Debug, Clone, Default)]
+pub struct CeremonyLock(std::sync::Arc<tokio::sync::Mutex<()>>);
+
+#[cfg(any(feature = "tokio", test))]
+impl CeremonyLock {
+    /// Create a new, unshared, `CeremonyLock`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Aborts the operations given one of its [`AbortSignal`]s, in the manner of the `AbortController`
+/// of the [Web IDL specification](https://dom.spec.whatwg.org/#interface-abortcontroller).
+#[cfg(any(feature = "tokio", test))]
+#[derive(Debug)]
+pub struct AbortController(tokio::sync::watch::Sender<bool>);
+
+#[cfg(any(feature = "tokio", test))]
+impl AbortController {
+    /// Create a new `AbortController` which has not aborted yet.
+    pub fn new() -> Self {
+        Self(tokio::sync::watch::channel(false).0)
+    }
+
+    /// A signal to pass to the operations that this controller should be able to abort.
+    pub fn signal(&self) -> AbortSignal {
+        AbortSignal(self.0.subscribe())
+    }
+
+    /// Abort every operation given a signal of this controller, including those started later.
+    pub fn abort(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+#[cfg(any(feature = "tokio", test))]
+impl Default for AbortController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Signals that an operation should be aborted once its [`AbortController`] aborts. A signal
+/// whose controller was dropped without aborting never aborts.
+#[cfg(any(feature = "tokio", test))]
+#[derive(Debug, Clone)]
+pub struct AbortSignal(tokio::sync::watch::Receiver<bool>);
+
+#[cfg(any(feature = "tokio", test))]
+impl AbortSignal {
+    /// Whether the controller of this signal has aborted.
+    pub fn is_aborted(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until the controller of this signal aborts.
+    async fn aborted(mut self) {
+        if self.0.wait_for(|aborted| *aborted).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Run the operation until it completes or this signal aborts, whichever comes first. An
+    /// operation that completes at the same time as the signal aborts is not aborted.
+    async fn run<T>(
+        self,
+        operation: impl std::future::Future<Output = Result<T, WebauthnError>>,
+    ) -> Result<T, WebauthnError> {
+        use std::{future::Future, task::Poll};
+
+        if self.is_aborted() {
+            return Err(WebauthnError::Aborted);
+        }
+        let mut operation = std::pin::pin!(operation);
+        let mut aborted = std::pin::pin!(self.aborted());
+        std::future::poll_fn(|cx| match operation.as_mut().poll(cx) {
+            Poll::Pending if aborted.as_mut().poll(cx).is_ready() => {
+                Poll::Ready(Err(WebauthnError::Aborted))
+            }
+            poll => poll,
+        })
+        .await
+    }
+}
+
+/// The shortest timeout, in milliseconds, applied to a ceremony. Shorter timeouts requested by
+/// the Relying Party are raised to it.
+pub const MIN_TIMEOUT: u32 = 30_000;
+
+/// The longest timeout, in milliseconds, applied to a ceremony. Longer timeouts requested by the
+/// Relying Party are lowered to it.
+pub const MAX_TIMEOUT: u32 = 600_000;
+
+/// The timeout, in milliseconds, applied to a ceremony without discoverable credentials when the
+/// Relying Party does not request one.
+pub const DEFAULT_TIMEOUT: u32 = 300_000;
+
+/// The timeout, in milliseconds, applied to a ceremony with discoverable credentials when the
+/// Relying Party does not request one. It is longer than [`DEFAULT_TIMEOUT`] since the user
+/// usually has to pick one of their accounts.
+pub const DEFAULT_DISCOVERABLE_TIMEOUT: u32 = 600_000;
+
+/// Clamp the `timeout` requested by the Relying Party, or pick the default for the residency of
+/// the ceremony.
+fn effective_timeout(timeout: Option<u32>, discoverable: bool) -> u32 {
+    match timeout {
+        Some(timeout) => timeout.clamp(MIN_TIMEOUT, MAX_TIMEOUT),
+        None if discoverable => DEFAULT_DISCOVERABLE_TIMEOUT,
+        None => DEFAULT_TIMEOUT,
+    }
+}
+
+/// A callback applied to every credential returned by [`Client::register`].
+type RegistrationTransform = Box<
+    dyn Fn(webauthn::CreatedPublicKeyCredential) -> webauthn::CreatedPublicKeyCredential
+        + Send
+        + Sync,
+>;
+
+/// A callback applied to every credential returned by [`Client::authenticate`].
+type AuthenticationTransform = Box<
+    dyn Fn(webauthn::AuthenticatedPublicKeyCredential) -> webauthn::AuthenticatedPublicKeyCredential
+        + Send
+        + Sync,
+>;
+
+/// A callback receiving the [`AuditRecord`] of every ceremony.
+type AuditHook = Box<dyn Fn(AuditRecord) + Send + Sync>;
+
+/// A callback deciding whether the challenge of a ceremony is acceptable.
+type ChallengeValidator = Box<dyn Fn(&[u8]) -> bool + Send + Sync>;
+
 /// A `Client` represents a Webauthn client. Users of this struct should supply a
 /// [`CredentialStore`], a [`UserValidationMethod`] and, optionally, an implementation of
 /// [`public_suffix::EffectiveTLDProvider`].
@@ -160,6 +381,16 @@ where
 {
     authenticator: Authenticator<S, U>,
     rp_id_verifier: RpIdVerifier<P>,
+    canonical_client_data: bool,
+    verify_assertions: bool,
+    always_return_user_handle: bool,
+    registration_transform: Option<RegistrationTransform>,
+    authentication_transform: Option<AuthenticationTransform>,
+    audit_hook: Option<AuditHook>,
+    challenge_validator: Option<ChallengeValidator>,
+    default_attestation: webauthn::AttestationConveyancePreference,
+    #[cfg(any(feature = "tokio", test))]
+    ceremony_lock: Option<CeremonyLock>,
 }
 
 impl<S, U> Client<S, U, public_suffix::PublicSuffixList>
@@ -174,13 +405,23 @@ where
         Self {
             authenticator,
             rp_id_verifier: RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER),
+            canonical_client_data: false,
+            verify_assertions: false,
+            always_return_user_handle: false,
+            registration_transform: None,
+            authentication_transform: None,
+            audit_hook: None,
+            challenge_validator: None,
+            default_attestation: webauthn::AttestationConveyancePreference::None,
+            #[cfg(any(feature = "tokio", test))]
+            ceremony_lock: None,
         }
     }
 }
 
 impl<S, U, P> Client<S, U, P>
 where
-    S: CredentialStore + Sync,
+    S: CredentialStore + Sync + Send,
     U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
     P: public_suffix::EffectiveTLDProvider + Sync + 'static,
     Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
@@ -194,6 +435,16 @@ where
         Self {
             authenticator,
             rp_id_verifier: RpIdVerifier::new(custom_provider),
+            canonical_client_data: false,
+            verify_assertions: false,
+            always_return_user_handle: false,
+            registration_transform: None,
+            authentication_transform: None,
+            audit_hook: None,
+            challenge_validator: None,
+            default_attestation: webauthn::AttestationConveyancePreference::None,
+            #[cfg(any(feature = "tokio", test))]
+            ceremony_lock: None,
         }
     }
 
@@ -203,6 +454,189 @@ where
         self
     }
 
+    /// Set the schemes of the origins the internal [RpIdVerifier] accepts, `https` only by
+    /// default. See [`RpIdVerifier::allows_schemes`].
+    pub fn allows_schemes(mut self, schemes: &[&str]) -> Self {
+        self.rp_id_verifier = self.rp_id_verifier.allows_schemes(schemes);
+        self
+    }
+
+    /// Allows the internal [RpIdVerifier] to accept an RP ID equal to the origin's domain when its
+    /// registrable domain cannot be derived. See [`RpIdVerifier::allows_exact_origin_rp_id_match`].
+    pub fn allows_exact_origin_rp_id_match(mut self, is_allowed: bool) -> Self {
+        self.rp_id_verifier = self
+            .rp_id_verifier
+            .allows_exact_origin_rp_id_match(is_allowed);
+        self
+    }
+
+    /// Allow the given origins to use `rp_id` as their RP ID, even though it is not a registrable
+    /// domain suffix of their domain. See [`RpIdVerifier::with_related_origins`].
+    pub fn with_related_origins(
+        mut self,
+        rp_id: impl Into<String>,
+        origins: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        self.rp_id_verifier = self.rp_id_verifier.with_related_origins(rp_id, origins);
+        self
+    }
+
+    /// Fetch the related origins document of `rp_id` and allow the origins it lists to use it as
+    /// their RP ID. See [`RpIdVerifier::fetch_related_origins`].
+    pub async fn fetch_related_origins(
+        mut self,
+        rp_id: &str,
+        fetcher: &impl RelatedOriginsFetcher,
+    ) -> Result<Self, WebauthnError> {
+        self.rp_id_verifier = self
+            .rp_id_verifier
+            .fetch_related_origins(rp_id, fetcher)
+            .await?;
+        Ok(self)
+    }
+
+    /// Trust the Android application whose signing certificate has the SHA-256 `hash` with
+    /// `rp_id`. See [`RpIdVerifier::with_android_apk_key_hash`].
+    pub fn with_android_apk_key_hash(
+        mut self,
+        rp_id: impl Into<String>,
+        hash: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.rp_id_verifier = self.rp_id_verifier.with_android_apk_key_hash(rp_id, hash);
+        self
+    }
+
+    /// Use `preference` as the attestation conveyance of the registrations whose options do not
+    /// specify [`attestation`](webauthn::PublicKeyCredentialCreationOptions::attestation). The
+    /// value given by the Relying Party always takes precedence, even when it is
+    /// [`AttestationConveyancePreference::None`](webauthn::AttestationConveyancePreference::None),
+    /// which is also the default of this setting.
+    pub fn with_default_attestation(
+        mut self,
+        preference: webauthn::AttestationConveyancePreference,
+    ) -> Self {
+        self.default_attestation = preference;
+        self
+    }
+
+    /// Sort the keys of the extra client data supplied through [`ClientData::extra_client_data`]
+    /// before it is serialized into the client data JSON.
+    ///
+    /// By default the extra client data keeps the order in which its keys were serialized, which
+    /// makes the client data, and therefore its hash, depend on how the caller built it. Enabling
+    /// this produces identical client data for logically identical extensions, which is useful
+    /// for deterministic test vectors.
+    pub fn canonicalize_client_data_extensions(mut self, is_canonical: bool) -> Self {
+        self.canonical_client_data = is_canonical;
+        self
+    }
+
+    /// Return the user handle of the credential in every assertion, rather than only in those
+    /// of requests without allowed credentials.
+    ///
+    /// By default the user handle is omitted when the Relying Party gave a list of allowed
+    /// credentials, as it already knows which user is signing in, and only returned when the
+    /// user was discovered from a discoverable credential.
+    pub fn always_return_user_handle(mut self, always: bool) -> Self {
+        self.always_return_user_handle = always;
+        self
+    }
+
+    /// Verify the signature of every assertion produced by the authenticator against the public
+    /// key of the stored credential before returning it.
+    ///
+    /// This is a defensive check that catches corrupted credentials, such as a private key which
+    /// no longer matches its public key, before the assertion is sent to the Relying Party. A
+    /// failed check returns [`WebauthnError::InvalidAssertionSignature`].
+    pub fn verify_own_assertions(mut self, is_enabled: bool) -> Self {
+        self.verify_assertions = is_enabled;
+        self
+    }
+
+    /// Transform every credential returned by [`Self::register`], for example to strip fields or
+    /// to wrap it in a platform-specific envelope. The `transform` is applied last, once the
+    /// credential was entirely built, and replaces any transform given before.
+    pub fn map_registration_result(
+        mut self,
+        transform: impl Fn(webauthn::CreatedPublicKeyCredential) -> webauthn::CreatedPublicKeyCredential
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.registration_transform = Some(Box::new(transform));
+        self
+    }
+
+    /// Transform every credential returned by [`Self::authenticate`], like
+    /// [`Self::map_registration_result`] does for registrations. The `transform` is applied after
+    /// the assertion was verified when [`Self::verify_own_assertions`] is enabled.
+    pub fn map_authentication_result(
+        mut self,
+        transform: impl Fn(
+                webauthn::AuthenticatedPublicKeyCredential,
+            ) -> webauthn::AuthenticatedPublicKeyCredential
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.authentication_transform = Some(Box::new(transform));
+        self
+    }
+
+    /// Emit an [`AuditRecord`] of every [`Self::register`] and [`Self::authenticate`] call to
+    /// `hook`, whether it succeeded or not. Records are only produced once a hook is given, and it
+    /// replaces any hook given before.
+    pub fn with_audit_hook(mut self, hook: impl Fn(AuditRecord) + Send + Sync + 'static) -> Self {
+        self.audit_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Check the challenge of every [`Self::register`] and [`Self::authenticate`] call with
+    /// `validator` before the ceremony starts, for example to reject the challenges the Relying
+    /// Party issued too long ago. The ceremony fails with [`WebauthnError::InvalidChallenge`] when
+    /// it returns `false`. Every challenge is accepted by default, and `validator` replaces any
+    /// validator given before.
+    pub fn with_challenge_validator(
+        mut self,
+        validator: impl Fn(&[u8]) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.challenge_validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Check the `challenge` of a ceremony with the challenge validator, if there is one.
+    fn validate_challenge(&self, challenge: &[u8]) -> Result<(), WebauthnError> {
+        match &self.challenge_validator {
+            Some(validator) if !validator(challenge) => Err(WebauthnError::InvalidChallenge),
+            _ => Ok(()),
+        }
+    }
+
+    /// Give the record of a finished ceremony to the audit hook, if there is one.
+    fn emit_audit_record<T>(&self, record: AuditRecord, result: &Result<T, WebauthnError>) {
+        if let Some(hook) = &self.audit_hook {
+            hook(record.finish(result));
+        }
+    }
+
+    /// Serialize this client's `register` and `authenticate` calls with every other client using
+    /// the same [`CeremonyLock`]. Overlapping calls will wait for the ongoing ceremony to finish
+    /// rather than racing on the credential store or the user validation prompt.
+    #[cfg(any(feature = "tokio", test))]
+    pub fn with_ceremony_lock(mut self, lock: CeremonyLock) -> Self {
+        self.ceremony_lock = Some(lock);
+        self
+    }
+
+    /// Wait for any other ceremony sharing this client's [`CeremonyLock`] to finish.
+    #[cfg(any(feature = "tokio", test))]
+    async fn acquire_ceremony_lock(&self) -> Option<tokio::sync::OwnedMutexGuard<()>> {
+        match self.ceremony_lock.as_ref() {
+            Some(CeremonyLock(lock)) => Some(lock.clone().lock_owned().await),
+            None => None,
+        }
+    }
+
     /// Read access to the Client's `Authenticator`.
     pub fn authenticator(&self) -> &Authenticator<S, U> {
         &self.authenticator
@@ -216,6 +650,18 @@ where
     /// Register a webauthn `request` from the given `origin`.
     ///
     /// Returns either a [`webauthn::CreatedPublicKeyCredential`] on success or some [`WebauthnError`]
+    ///
+    /// When the `Authenticator` has a default behavior for an extension, the relying party's input
+    /// is merged with it and may only raise the assurance it provides, never lower it. For
+    /// `credProtect`, the most protective of the requested policy and
+    /// [`Authenticator::default_credential_protection_policy`] is applied, and it is reported in the
+    /// `cred_protect` extension output when the relying party requested a policy. The
+    /// `Authenticator` supports `credProtect`, so `enforce_credential_protection_policy` is always
+    /// satisfied.
+    ///
+    /// The `largeBlob` extension is supported for discoverable credentials when the store of the
+    /// `Authenticator` can hold large blobs. A `required` support that cannot be satisfied fails
+    /// with [`WebauthnError::NotSupported`].
     pub async fn register<D: ClientData<E>, E: Serialize + Clone>(
         &mut self,
         origin: impl Into<Origin<'_>>,
@@ -223,6 +669,24 @@ where
         client_data: D,
     ) -> Result<webauthn::CreatedPublicKeyCredential, WebauthnError> {
         let origin = origin.into();
+        let mut record = AuditRecord::start(CeremonyOperation::Registration, origin.to_string());
+        let result = self
+            .perform_registration(origin, request, client_data, &mut record)
+            .await;
+        self.emit_audit_record(record, &result);
+        result
+    }
+
+    /// The registration of [`Self::register`], which fills in the audit `record` along the way.
+    async fn perform_registration<D: ClientData<E>, E: Serialize + Clone>(
+        &mut self,
+        origin: Origin<'_>,
+        request: webauthn::CredentialCreationOptions,
+        client_data: D,
+        record: &mut AuditRecord,
+    ) -> Result<webauthn::CreatedPublicKeyCredential, WebauthnError> {
+        #[cfg(any(feature = "tokio", test))]
+        let _ceremony_guard = self.acquire_ceremony_lock().await;
 
         // extract inner value of request as there is nothing else of value directly in CredentialCreationOptions
         let request = request.public_key;
@@ -233,39 +697,83 @@ where
         } else {
             request.pub_key_cred_params
         };
-        // TODO: Handle given timeout here, If the value is not within what we consider a reasonable range
-        // override to our default
-        // let timeout = request
-        //     .timeout
-        //     .map(|t| t.clamp(MIN_TIMEOUT, MAX_TIMEOUT))
-        //     .unwrap_or(MAX_TIMEOUT);
+        // NB: the timeout is not enforced here, callers bound the ceremony with the one given by
+        // `Self::registration_timeout`, for example by aborting it through an `AbortSignal`.
 
         let rp_id = self
             .rp_id_verifier
             .assert_domain(&origin, request.rp.id.as_deref())?;
-
-        let collected_client_data = webauthn::CollectedClientData::<E> {
-            ty: webauthn::ClientDataType::Create,
-            challenge: encoding::base64url(&request.challenge),
-            origin: origin.to_string(),
-            cross_origin: None,
-            extra_data: client_data.extra_client_data(),
-            unknown_keys: Default::default(),
-        };
-
-        // SAFETY: it is a developer error if serializing this struct fails.
-        let client_data_json = serde_json::to_string(&collected_client_data).unwrap();
+        record.rp_id = Some(rp_id.to_owned());
+        self.validate_challenge(&request.challenge)?;
+
+        let client_data_json = self.client_data_json(
+            webauthn::ClientDataType::Create,
+            &request.challenge,
+            &origin,
+            client_data.top_origin(),
+            client_data.extra_client_data_for(rp_id, &origin.to_string()),
+        );
         let client_data_json_hash = client_data
             .client_data_hash()
-            .unwrap_or_else(|| sha256(client_data_json.as_bytes()).to_vec());
+            .unwrap_or_else(|| client_data_hash(client_data_json.as_bytes()).to_vec());
 
         let cred_props_requested =
             request.extensions.as_ref().and_then(|ext| ext.cred_props) == Some(true);
+        let prf_requested = request
+            .extensions
+            .as_ref()
+            .is_some_and(|ext| ext.prf.is_some());
+        let requested_cred_protect = request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.credential_protection_policy);
+        let cred_protect =
+            requested_cred_protect.max(self.authenticator.default_credential_protection_policy());
+        let uvm_requested = request.extensions.as_ref().and_then(|ext| ext.uvm) == Some(true);
+        let device_pub_key = request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.device_pub_key.clone());
 
         let rk = self.map_rk(&request.authenticator_selection, &auth_info);
+
+        // The `largeBlob` extension is backed by the `largeBlobKey` of discoverable credentials.
+        let large_blob_support = match request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.large_blob.as_ref())
+        {
+            Some(large_blob) if large_blob.read.is_some() || large_blob.write.is_some() => {
+                return Err(WebauthnError::NotSupported);
+            }
+            Some(large_blob) => Some(large_blob.support.unwrap_or(LargeBlobSupport::Preferred)),
+            None => None,
+        };
+        let large_blob_key = large_blob_support.is_some()
+            && rk
+            && auth_info.options.as_ref().and_then(|o| o.large_blobs) == Some(true);
+        if large_blob_support == Some(LargeBlobSupport::Required) && !large_blob_key {
+            return Err(WebauthnError::NotSupported);
+        }
         let uv = request.authenticator_selection.map(|s| s.user_verification)
             != Some(UserVerificationRequirement::Discouraged);
 
+        // When the RP is not interested in attestation, tell the authenticator to not even bother
+        // generating one.
+        let attestation = request.attestation.unwrap_or(self.default_attestation);
+        let attestation_formats_preference =
+            if attestation == webauthn::AttestationConveyancePreference::None {
+                Some(vec![webauthn::AttestationStatementFormatIdentifiers::None])
+            } else {
+                request.attestation_formats
+            };
+        // Enterprise attestation is only requested from authenticators that have it enabled, which
+        // then decide whether the relying party is allowed to receive it.
+        let enterprise_attestation = (attestation
+            == webauthn::AttestationConveyancePreference::Enterprise
+            && auth_info.options.as_ref().and_then(|options| options.ep) == Some(true))
+        .then_some(ctap2::make_credential::ENTERPRISE_ATTESTATION_VENDOR_FACILITATED);
+
         let ctap2_response = self
             .authenticator
             .make_credential(ctap2::make_credential::Request {
@@ -277,13 +785,28 @@ where
                 user: request.user,
                 pub_key_cred_params,
                 exclude_list: request.exclude_credentials,
-                extensions: request.extensions,
+                // The `prf` extension is backed by the authenticator's `hmac-secret` extension.
+                extensions: (prf_requested
+                    || cred_protect.is_some()
+                    || uvm_requested
+                    || device_pub_key.is_some()
+                    || large_blob_key)
+                    .then(|| ctap2::make_credential::ExtensionInputs {
+                        hmac_secret: prf_requested.then_some(true),
+                        cred_protect,
+                        large_blob_key: large_blob_key.then_some(true),
+                        min_pin_length: None,
+                        uvm: uvm_requested.then_some(true),
+                        device_pub_key: device_pub_key.clone(),
+                    }),
                 options: ctap2::make_credential::Options { rk, up: true, uv },
                 pin_auth: None,
                 pin_protocol: None,
+                enterprise_attestation,
+                attestation_formats_preference,
             })
-            .await
-            .map_err(|sc| WebauthnError::AuthenticatorError(sc.into()))?;
+            .await?;
+        record.user_verified = Some(ctap2_response.auth_data.flags.contains(ctap2::Flags::UV));
 
         // SAFETY: this unwrap is safe because the ctap2_response was just created in make_credential()
         // above, which currently sets auth_data.attested_credential_data unconditionally.
@@ -294,13 +817,22 @@ where
             .attested_credential_data
             .as_ref()
             .unwrap();
-        let alg = match credential_id.key.alg.as_ref().unwrap() {
-            Algorithm::PrivateUse(val) => *val,
-            Algorithm::Assigned(alg) => alg.to_i64(),
-            Algorithm::Text(_) => {
+        let alg = match credential_id.key.alg.as_ref() {
+            Some(Algorithm::PrivateUse(val)) => *val,
+            Some(Algorithm::Text(_)) => {
                 unreachable!()
             }
+            // The algorithm is also known when the public key omits it.
+            Some(Algorithm::Assigned(_)) | None => {
+                passkey_authenticator::public_key_algorithm(&credential_id.key)
+                    .ok_or(WebauthnError::AuthenticatorError(
+                        ctap2::Ctap2Error::UnsupportedAlgorithm.into(),
+                    ))?
+                    .to_i64()
+            }
         };
+        record.algorithm = Some(alg);
+        record.set_credential_id(credential_id.credential_id());
         let public_key = Some(
             passkey_authenticator::public_key_der_from_cose_key(&credential_id.key)
                 .map_err(|e| WebauthnError::AuthenticatorError(e.into()))?,
@@ -322,6 +854,33 @@ where
             None
         };
 
+        // NB: evaluating the PRF during registration is not supported, so no results are given.
+        let prf = prf_requested.then(|| webauthn::AuthenticationExtensionsPrfOutputs {
+            enabled: Some(prf::hmac_secret_enabled(&ctap2_response.auth_data)),
+            results: None,
+        });
+        let cred_protect =
+            requested_cred_protect.and_then(|_| applied_cred_protect(&ctap2_response.auth_data));
+        let uvm = uvm_requested
+            .then(|| reported_uvm(&ctap2_response.auth_data))
+            .flatten();
+        let device_pub_key =
+            device_pub_key.and_then(|_| reported_device_pub_key(&ctap2_response.auth_data));
+        let large_blob =
+            large_blob_support.map(|_| webauthn::AuthenticationExtensionsLargeBlobOutputs {
+                supported: Some(ctap2_response.large_blob_key.is_some()),
+                blob: None,
+                written: None,
+            });
+
+        // The transports are reported in the lexicographical order of their names, which is the
+        // order of their serialization.
+        let transports = auth_info.transports.map(|mut transports| {
+            transports.sort_by_cached_key(|transport| serde_json::to_string(transport).ok());
+            transports.dedup();
+            transports
+        });
+
         let response = webauthn::CreatedPublicKeyCredential {
             id: encoding::base64url(credential_id.credential_id()),
             raw_id: credential_id.credential_id().to_vec().into(),
@@ -332,20 +891,37 @@ where
                 public_key,
                 public_key_algorithm: alg,
                 attestation_object: ctap2_response.as_bytes(),
-                transports: auth_info.transports,
+                transports,
             },
             authenticator_attachment: Some(self.authenticator().attachment_type()),
-            client_extension_results: AuthenticatorExtensionsClientOutputs { cred_props },
+            client_extension_results: AuthenticatorExtensionsClientOutputs {
+                cred_props,
+                prf,
+                cred_protect,
+                appid: None,
+                uvm,
+                device_pub_key,
+                large_blob,
+            },
         };
 
         // Sanitize output before sending it back to the RP
         let maybe_quirky_rp = QuirkyRp::from_rp_id(rp_id);
-        Ok(maybe_quirky_rp.map_create_credential(response))
+        let response = maybe_quirky_rp.map_create_credential(response);
+        Ok(match &self.registration_transform {
+            Some(transform) => transform(response),
+            None => response,
+        })
     }
 
     /// Authenticate a Webauthn request.
     ///
     /// Returns either an [`webauthn::AuthenticatedPublicKeyCredential`] on success or some [`WebauthnError`].
+    ///
+    /// The blob of the `largeBlob` extension is read from or written to the large-blob array of
+    /// the `Authenticator` with the `largeBlobKey` of the asserted credential, see
+    /// [`large_blob`]. Requesting a `support`, or both a `read` and a `write`, fails with
+    /// [`WebauthnError::NotSupported`].
     pub async fn authenticate<D: ClientData<E>, E: Serialize + Clone>(
         &mut self,
         origin: impl Into<Origin<'_>>,
@@ -353,46 +929,195 @@ where
         client_data: D,
     ) -> Result<webauthn::AuthenticatedPublicKeyCredential, WebauthnError> {
         let origin = origin.into();
+        let mut record = AuditRecord::start(CeremonyOperation::Authentication, origin.to_string());
+        let result = self
+            .perform_authentication(origin, request, client_data, None, &mut record)
+            .await;
+        self.emit_audit_record(record, &result);
+        result
+    }
+
+    /// Authenticate a Webauthn request like [`Self::authenticate`], for a Relying Party which
+    /// already knows which user is signing in.
+    ///
+    /// The ceremony fails with [`WebauthnError::UserHandleMismatch`] when the credential used for
+    /// the assertion has a user handle other than `expected_user_handle`. Credentials which were
+    /// not created as discoverable have no user handle, and are accepted as they can only be
+    /// chosen from the allowed credentials of the request.
+    pub async fn authenticate_for_user<D: ClientData<E>, E: Serialize + Clone>(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        request: webauthn::CredentialRequestOptions,
+        client_data: D,
+        expected_user_handle: &[u8],
+    ) -> Result<webauthn::AuthenticatedPublicKeyCredential, WebauthnError> {
+        let origin = origin.into();
+        let mut record = AuditRecord::start(CeremonyOperation::Authentication, origin.to_string());
+        let result = self
+            .perform_authentication(
+                origin,
+                request,
+                client_data,
+                Some(expected_user_handle),
+                &mut record,
+            )
+            .await;
+        self.emit_audit_record(record, &result);
+        result
+    }
+
+    /// The authentication of [`Self::authenticate`] and [`Self::authenticate_for_user`], which
+    /// fills in the audit `record` along the way.
+    async fn perform_authentication<D: ClientData<E>, E: Serialize + Clone>(
+        &mut self,
+        origin: Origin<'_>,
+        request: webauthn::CredentialRequestOptions,
+        client_data: D,
+        expected_user_handle: Option<&[u8]>,
+        record: &mut AuditRecord,
+    ) -> Result<webauthn::AuthenticatedPublicKeyCredential, WebauthnError> {
+        #[cfg(any(feature = "tokio", test))]
+        let _ceremony_guard = self.acquire_ceremony_lock().await;
 
         // extract inner value of request as there is nothing else of value directly in CredentialRequestOptions
         let request = request.public_key;
 
-        // TODO: Handle given timeout here, If the value is not within what we consider a reasonable range
-        // override to our default
-        // let timeout = request
-        //     .timeout
-        //     .map(|t| t.clamp(MIN_TIMEOUT, MAX_TIMEOUT))
-        //     .unwrap_or(MAX_TIMEOUT);
+        // NB: the timeout is not enforced here, callers bound the ceremony with the one given by
+        // `Self::authentication_timeout`, for example by aborting it through an `AbortSignal`.
 
         let rp_id = self
             .rp_id_verifier
             .assert_domain(&origin, request.rp_id.as_deref())?;
-
-        let collected_client_data = webauthn::CollectedClientData::<E> {
-            ty: webauthn::ClientDataType::Get,
-            challenge: encoding::base64url(&request.challenge),
-            origin: origin.to_string(),
-            cross_origin: None, //Some(false),
-            extra_data: client_data.extra_client_data(),
-            unknown_keys: Default::default(),
-        };
-
-        // SAFETY: it is a developer error if serializing this struct fails.
-        let client_data_json = serde_json::to_string(&collected_client_data).unwrap();
+        record.rp_id = Some(rp_id.to_owned());
+        self.validate_challenge(&request.challenge)?;
+
+        let client_data_json = self.client_data_json(
+            webauthn::ClientDataType::Get,
+            &request.challenge,
+            &origin,
+            client_data.top_origin(),
+            client_data.extra_client_data_for(rp_id, &origin.to_string()),
+        );
         let client_data_json_hash = client_data
             .client_data_hash()
-            .unwrap_or_else(|| sha256(client_data_json.as_bytes()).to_vec());
+            .unwrap_or_else(|| client_data_hash(client_data_json.as_bytes()).to_vec());
 
         let rk = false;
-        let uv = request.user_verification != UserVerificationRequirement::Discouraged;
+        let auth_info = self.authenticator.get_info().await;
+        let supports_uv = auth_info
+            .options
+            .as_ref()
+            .is_some_and(|o| o.uv == Some(true));
+        let uv = match request.user_verification {
+            // The RP will reject an assertion without the UV flag, so fail before prompting the
+            // user for presence only.
+            UserVerificationRequirement::Required if !supports_uv => {
+                return Err(ctap2::StatusCode::from(ctap2::Ctap2Error::OperationDenied).into());
+            }
+            UserVerificationRequirement::Required => true,
+            UserVerificationRequirement::Preferred => supports_uv,
+            UserVerificationRequirement::Discouraged => false,
+        };
+
+        // The `prf` inputs are hashed into `hmac-secret` salts before reaching the authenticator.
+        let prf_inputs = request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.prf.as_ref())
+            .map(prf::hash_prf_inputs);
+        let prf_requested = prf_inputs.is_some();
+        let uvm_requested = request.extensions.as_ref().and_then(|ext| ext.uvm) == Some(true);
+        let device_pub_key = request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.device_pub_key.clone());
+        let large_blob = request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.large_blob.clone());
+        if let Some(large_blob) = &large_blob {
+            if large_blob.support.is_some()
+                || (large_blob.read == Some(true) && large_blob.write.is_some())
+            {
+                return Err(WebauthnError::NotSupported);
+            }
+        }
+        let large_blob_key = large_blob
+            .as_ref()
+            .is_some_and(|ext| ext.read == Some(true) || ext.write.is_some());
+
+        // Credentials with an ID longer than the authenticator accepts cannot belong to it. When
+        // none is left, the allow list must not be sent empty, which would look for discoverable
+        // credentials instead.
+        let allow_list = match (
+            request.allow_credentials,
+            auth_info.max_credential_id_length,
+        ) {
+            (Some(allow_list), Some(max)) if !allow_list.is_empty() => {
+                let allow_list: Vec<_> = allow_list
+                    .into_iter()
+                    .filter(|credential| credential.id.len() <= max)
+                    .collect();
+                if allow_list.is_empty() {
+                    return Err(WebauthnError::CredentialNotFound);
+                }
+                Some(allow_list)
+            }
+            (allow_list, _) => allow_list,
+        };
+
+        // Credentials registered through the U2F API are scoped to their AppID, which is only used
+        // when none of the allowed credentials is scoped to the RP ID.
+        let app_id = match request
+            .extensions
+            .as_ref()
+            .and_then(|ext| ext.appid.as_deref())
+        {
+            Some(app_id) => {
+                self.verify_app_id(&origin, app_id)?;
+                Some(app_id)
+            }
+            None => None,
+        };
+        let uses_app_id = match (app_id, allow_list.as_deref()) {
+            (Some(app_id), Some(allow_list)) if !allow_list.is_empty() => {
+                !self
+                    .stores_any_credential(allow_list, rp_id)
+                    .await
+                    .unwrap_or(false)
+                    && self
+                        .stores_any_credential(allow_list, app_id)
+                        .await
+                        .unwrap_or(false)
+            }
+            _ => false,
+        };
+        let rp_id = match app_id {
+            Some(app_id) if uses_app_id => app_id,
+            _ => rp_id,
+        };
+
+        // The user handle is only needed to identify the user when they were discovered.
+        let return_user_handle =
+            self.always_return_user_handle || allow_list.as_deref().map_or(true, <[_]>::is_empty);
 
         let ctap2_response = self
             .authenticator
             .get_assertion(ctap2::get_assertion::Request {
                 rp_id: rp_id.to_owned(),
-                client_data_hash: client_data_json_hash.into(),
-                allow_list: request.allow_credentials,
-                extensions: request.extensions,
+                client_data_hash: client_data_json_hash.clone().into(),
+                allow_list,
+                extensions: (prf_requested
+                    || uvm_requested
+                    || device_pub_key.is_some()
+                    || large_blob_key)
+                    .then(|| ctap2::get_assertion::ExtensionInputs {
+                        prf: prf_inputs,
+                        hmac_secret: None,
+                        large_blob_key: large_blob_key.then_some(true),
+                        uvm: uvm_requested.then_some(true),
+                        device_pub_key: device_pub_key.clone(),
+                    }),
                 options: ctap2::get_assertion::Options { rk, up: true, uv },
                 pin_auth: None,
                 pin_protocol: None,
@@ -404,23 +1129,272 @@ where
         // above and the postcondition of that function is that response.credential
         // will yield a credential. If none was found, we will have already returned
         // a WebauthnError::CredentialNotFound error from map_err in that line.
-        let credential_id_bytes = ctap2_response.credential.unwrap().id;
-        Ok(webauthn::AuthenticatedPublicKeyCredential {
+        let credential = ctap2_response.credential.unwrap();
+        let authenticator_data = ctap2_response.auth_data.to_vec();
+        record.user_verified = Some(ctap2_response.auth_data.flags.contains(ctap2::Flags::UV));
+        record.set_credential_id(&credential.id);
+
+        if let (Some(expected), Some(user)) = (expected_user_handle, &ctap2_response.user) {
+            if user.id.as_slice() != expected {
+                return Err(WebauthnError::UserHandleMismatch);
+            }
+        }
+
+        let stored_passkey = self
+            .authenticator
+            .store()
+            .find_credentials(Some(std::slice::from_ref(&credential)), rp_id)
+            .await
+            .ok()
+            .and_then(|found| found.into_iter().next())
+            .and_then(|item| Passkey::try_from(item).ok());
+        record.algorithm =
+            stored_passkey
+                .as_ref()
+                .and_then(|passkey| match passkey.key.alg.as_ref()? {
+                    Algorithm::Assigned(alg) => Some(alg.to_i64()),
+                    Algorithm::PrivateUse(val) => Some(*val),
+                    Algorithm::Text(_) => None,
+                });
+
+        if self.verify_assertions {
+            let key = stored_passkey
+                .as_ref()
+                .map(|passkey| &passkey.key)
+                .ok_or(WebauthnError::InvalidAssertionSignature)?;
+            relying_party::verify_signature(
+                key,
+                &authenticator_data,
+                &client_data_json_hash,
+                &ctap2_response.signature,
+            )
+            .map_err(|_| WebauthnError::InvalidAssertionSignature)?;
+        }
+
+        let large_blob = match large_blob {
+            Some(inputs) => Some(
+                large_blob::process_assertion(
+                    &mut self.authenticator,
+                    &inputs,
+                    ctap2_response.large_blob_key.as_deref().map(Vec::as_slice),
+                )
+                .await,
+            ),
+            None => None,
+        };
+
+        let credential_id_bytes = credential.id;
+        let response = webauthn::AuthenticatedPublicKeyCredential {
             id: encoding::base64url(&credential_id_bytes),
             raw_id: credential_id_bytes.to_vec().into(),
             ty: webauthn::PublicKeyCredentialType::PublicKey,
             response: webauthn::AuthenticatorAssertionResponse {
                 client_data_json: Vec::from(client_data_json).into(),
-                authenticator_data: ctap2_response.auth_data.to_vec().into(),
+                authenticator_data: authenticator_data.into(),
                 signature: ctap2_response.signature,
-                user_handle: ctap2_response.user.map(|user| user.id),
+                user_handle: ctap2_response
+                    .user
+                    .filter(|_| return_user_handle)
+                    .map(|user| user.id),
                 attestation_object: None,
+                transports: stored_passkey.and_then(|passkey| passkey.transports),
             },
             authenticator_attachment: Some(self.authenticator().attachment_type()),
-            client_extension_results: AuthenticatorExtensionsClientOutputs::default(),
+            client_extension_results: AuthenticatorExtensionsClientOutputs {
+                cred_props: None,
+                cred_protect: None,
+                prf: prf_requested.then(|| webauthn::AuthenticationExtensionsPrfOutputs {
+                    enabled: None,
+                    results: ctap2_response
+                        .unsigned_extension_outputs
+                        .and_then(|outputs| outputs.prf),
+                }),
+                appid: app_id.map(|_| uses_app_id),
+                uvm: uvm_requested
+                    .then(|| reported_uvm(&ctap2_response.auth_data))
+                    .flatten(),
+                device_pub_key: device_pub_key
+                    .and_then(|_| reported_device_pub_key(&ctap2_response.auth_data)),
+                large_blob,
+            },
+        };
+
+        Ok(match &self.authentication_transform {
+            Some(transform) => transform(response),
+            None => response,
         })
     }
 
+    /// Register a webauthn `request` from the given `origin` like [`Self::register`], unless the
+    /// `signal` aborts first, in which case it fails with [`WebauthnError::Aborted`].
+    ///
+    /// An aborted registration is dropped at the point it was waiting on, usually the user's
+    /// validation, so it is never resumed. The credential is only persisted by the last step of
+    /// `make_credential` though: when the signal aborts after the [`CredentialStore`] started
+    /// saving it, the registration still fails but the credential may exist in the store.
+    #[cfg(any(feature = "tokio", test))]
+    pub async fn register_with_signal<D: ClientData<E>, E: Serialize + Clone>(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        request: webauthn::CredentialCreationOptions,
+        client_data: D,
+        signal: AbortSignal,
+    ) -> Result<webauthn::CreatedPublicKeyCredential, WebauthnError> {
+        signal
+            .run(self.register(origin, request, client_data))
+            .await
+    }
+
+    /// Authenticate a Webauthn request like [`Self::authenticate`], unless the `signal` aborts
+    /// first, in which case it fails with [`WebauthnError::Aborted`].
+    ///
+    /// As with [`Self::register_with_signal`], the signature counter of the credential may have
+    /// been updated in the [`CredentialStore`] when the signal aborts late in the ceremony.
+    #[cfg(any(feature = "tokio", test))]
+    pub async fn authenticate_with_signal<D: ClientData<E>, E: Serialize + Clone>(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        request: webauthn::CredentialRequestOptions,
+        client_data: D,
+        signal: AbortSignal,
+    ) -> Result<webauthn::AuthenticatedPublicKeyCredential, WebauthnError> {
+        signal
+            .run(self.authenticate(origin, request, client_data))
+            .await
+    }
+
+    /// The timeout, in milliseconds, within which [`Self::register`] should complete for
+    /// `options`.
+    ///
+    /// The timeout requested by the Relying Party is clamped between [`MIN_TIMEOUT`] and
+    /// [`MAX_TIMEOUT`]. Without one, [`DEFAULT_DISCOVERABLE_TIMEOUT`] applies when a discoverable
+    /// credential would be created and [`DEFAULT_TIMEOUT`] otherwise.
+    pub async fn registration_timeout(&self, options: &webauthn::CredentialCreationOptions) -> u32 {
+        let auth_info = self.authenticator.get_info().await;
+        let rk = self.map_rk(&options.public_key.authenticator_selection, &auth_info);
+        effective_timeout(options.public_key.timeout, rk)
+    }
+
+    /// The timeout, in milliseconds, within which [`Self::authenticate`] should complete for
+    /// `options`. It is chosen like in [`Self::registration_timeout`], the ceremony being
+    /// discoverable when `options` has no allowed credentials.
+    pub fn authentication_timeout(&self, options: &webauthn::CredentialRequestOptions) -> u32 {
+        let discoverable = options
+            .public_key
+            .allow_credentials
+            .as_ref()
+            .map_or(true, Vec::is_empty);
+        effective_timeout(options.public_key.timeout, discoverable)
+    }
+
+    /// Check, without prompting the user, whether the authenticator holds one of the credentials
+    /// excluded by `options`, in which case [`Self::register`] would fail with
+    /// [`WebauthnError::InvalidState`] once the user confirmed their presence.
+    ///
+    /// This lets an application tell the user that they already have a passkey on this device
+    /// before starting a registration. The origin is validated the same way as in
+    /// [`Self::register`], and no ceremony is started.
+    pub async fn has_excluded_credential(
+        &self,
+        origin: impl Into<Origin<'_>>,
+        options: &webauthn::CredentialCreationOptions,
+    ) -> Result<bool, WebauthnError> {
+        let origin = origin.into();
+        let rp_id = self
+            .rp_id_verifier
+            .assert_domain(&origin, options.public_key.rp.id.as_deref())?;
+
+        let Some(exclude_list) = options
+            .public_key
+            .exclude_credentials
+            .as_deref()
+            .filter(|list| !list.is_empty())
+        else {
+            return Ok(false);
+        };
+
+        self.stores_any_credential(exclude_list, rp_id)
+            .await
+            .map_err(|status| WebauthnError::AuthenticatorError(status.into()))
+    }
+
+    /// Collect the client data of a ceremony and serialize it to JSON.
+    fn client_data_json<E: Serialize + Clone>(
+        &self,
+        ty: webauthn::ClientDataType,
+        challenge: &[u8],
+        origin: &Origin<'_>,
+        top_origin: Option<String>,
+        extra_data: E,
+    ) -> String {
+        let collected_client_data = webauthn::CollectedClientData::<E> {
+            ty,
+            challenge: encoding::base64url(challenge),
+            origin: origin.to_string(),
+            cross_origin: top_origin.is_some().then_some(true),
+            top_origin,
+            extra_data,
+            unknown_keys: Default::default(),
+        };
+
+        // SAFETY: it is a developer error if serializing these structs fails.
+        if self.canonical_client_data {
+            if let Ok(extra @ serde_json::Value::Object(_)) =
+                serde_json::to_value(&collected_client_data.extra_data)
+            {
+                let canonical_client_data = webauthn::CollectedClientData {
+                    ty: collected_client_data.ty,
+                    challenge: collected_client_data.challenge,
+                    origin: collected_client_data.origin,
+                    cross_origin: collected_client_data.cross_origin,
+                    top_origin: collected_client_data.top_origin,
+                    extra_data: canonicalize_json(extra),
+                    unknown_keys: collected_client_data.unknown_keys,
+                };
+                return serde_json::to_string(&canonical_client_data).unwrap();
+            }
+        }
+        serde_json::to_string(&collected_client_data).unwrap()
+    }
+
+    /// Check that the legacy FIDO AppID of the `appid` extension may be used by `origin`, which
+    /// is the case of `https` URLs whose host is valid as an RP ID for the origin.
+    fn verify_app_id(&self, origin: &Origin<'_>, app_id: &str) -> Result<(), WebauthnError> {
+        let app_id = Url::parse(app_id).map_err(|_| WebauthnError::InvalidRpId)?;
+        if app_id.scheme() != "https" {
+            return Err(WebauthnError::UnprotectedOrigin);
+        }
+        let host = app_id.host_str().ok_or(WebauthnError::InvalidRpId)?;
+        self.rp_id_verifier.assert_domain(origin, Some(host))?;
+        Ok(())
+    }
+
+    /// Whether the store holds any of the `list` credentials scoped to `rp_id`, which is checked
+    /// without involving the user.
+    ///
+    /// The credentials are probed one at a time in the order of the list, which is the Relying
+    /// Party's order of preference, and the probing stops at the first one the store holds.
+    async fn stores_any_credential(
+        &self,
+        list: &[webauthn::PublicKeyCredentialDescriptor],
+        rp_id: &str,
+    ) -> Result<bool, ctap2::StatusCode> {
+        for descriptor in list {
+            match self
+                .authenticator
+                .store()
+                .find_credentials(Some(std::slice::from_ref(descriptor)), rp_id)
+                .await
+            {
+                Ok(found) if !found.is_empty() => return Ok(true),
+                Ok(_) => {}
+                Err(status) if status == ctap2::Ctap2Error::NoCredentials.into() => {}
+                Err(status) => return Err(status),
+            }
+        }
+        Ok(false)
+    }
+
     fn map_rk(
         &self,
         criteria: &Option<AuthenticatorSelectionCriteria>,
@@ -465,6 +1439,88 @@ where
     }
 }
 
+/// The Signal API, which needs a store that can be sent between threads to modify it.
+impl<S, U, P> Client<S, U, P>
+where
+    S: CredentialStore + Sync + Send,
+    U: UserValidationMethod<PasskeyItem = <S as CredentialStore>::PasskeyItem> + Sync,
+    P: public_suffix::EffectiveTLDProvider + Sync + 'static,
+    Passkey: TryFrom<<S as CredentialStore>::PasskeyItem>,
+{
+    /// Handle `signalUnknownCredential`: the Relying Party does not recognize the credential, which
+    /// is deleted from the authenticator when it holds it.
+    ///
+    /// The RP ID of the options is validated against the origin the same way as in
+    /// [`Self::authenticate`]. Whether a credential was deleted is not revealed to the Relying
+    /// Party, see [`Authenticator::delete_unknown_credential`].
+    pub async fn signal_unknown_credential(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        options: &webauthn::UnknownCredentialOptions,
+    ) -> Result<(), WebauthnError> {
+        let origin = origin.into();
+        let rp_id = self
+            .rp_id_verifier
+            .assert_domain(&origin, Some(&options.rp_id))?;
+        self.authenticator
+            .delete_unknown_credential(rp_id, &options.credential_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Handle `signalAllAcceptedCredentials`: the discoverable credentials of the user that are
+    /// not in the list accepted by the Relying Party are deleted from the authenticator.
+    ///
+    /// The RP ID of the options is validated against the origin the same way as in
+    /// [`Self::authenticate`], see [`Authenticator::delete_unaccepted_credentials`].
+    pub async fn signal_all_accepted_credentials(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        options: &webauthn::AllAcceptedCredentialsOptions,
+    ) -> Result<(), WebauthnError> {
+        let origin = origin.into();
+        let rp_id = self
+            .rp_id_verifier
+            .assert_domain(&origin, Some(&options.rp_id))?;
+        self.authenticator
+            .delete_unaccepted_credentials(
+                rp_id,
+                &options.user_id,
+                &options.all_accepted_credential_ids,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Handle `signalCurrentUserDetails`: the name and display name of the user are updated in
+    /// the store of the authenticator.
+    ///
+    /// The RP ID of the options is validated against the origin the same way as in
+    /// [`Self::authenticate`], see [`Authenticator::update_user_details`].
+    pub async fn signal_current_user_details(
+        &mut self,
+        origin: impl Into<Origin<'_>>,
+        options: &webauthn::CurrentUserDetailsOptions,
+    ) -> Result<(), WebauthnError> {
+        let origin = origin.into();
+        let rp_id = self
+            .rp_id_verifier
+            .assert_domain(&origin, Some(&options.rp_id))?;
+        self.authenticator
+            .update_user_details(
+                rp_id,
+                ctap2::make_credential::PublicKeyCredentialUserEntity {
+                    id: options.user_id.clone(),
+                    name: Some(options.name.clone()),
+                    display_name: Some(options.display_name.clone()),
+                    icon_url: None,
+                },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
 /// Wrapper struct for verifying that a given RpId matches the request's origin.
 ///
 /// While most cases should not use this type directly and instead use [`Client`], there are some
@@ -472,7 +1528,8 @@ where
 /// the rest of pieces that the client needs.
 pub struct RpIdVerifier<P> {
     tld_provider: Box<P>,
-    allows_insecure_localhost: bool,
+    allows_exact_origin_rp_id_match: bool,
+    origin_matcher: OriginMatcher,
 }
 
 impl<P> RpIdVerifier<P>
@@ -484,16 +1541,120 @@ where
     pub fn new(tld_provider: P) -> Self {
         Self {
             tld_provider: Box::new(tld_provider),
-            allows_insecure_localhost: false,
+            allows_exact_origin_rp_id_match: false,
+            origin_matcher: OriginMatcher::new(),
         }
     }
 
     /// Allows [`RpIdVerifier::assert_domain`] to pass through requests from `localhost`
     pub fn allows_insecure_localhost(mut self, is_allowed: bool) -> Self {
-        self.allows_insecure_localhost = is_allowed;
+        self.origin_matcher = self.origin_matcher.allows_insecure_localhost(is_allowed);
         self
     }
 
+    /// Set the schemes of the origins [`RpIdVerifier::assert_domain`] accepts, which replace the
+    /// default of only accepting `https`. See [`OriginMatcher::allows_schemes`].
+    pub fn allows_schemes(mut self, schemes: &[&str]) -> Self {
+        self.origin_matcher = self.origin_matcher.allows_schemes(schemes);
+        self
+    }
+
+    /// Allows [`RpIdVerifier::assert_domain`] to accept an RP ID whose registrable domain cannot be
+    /// derived by the TLD provider, as long as it is exactly the domain of the origin.
+    ///
+    /// This is off by default since it also accepts RP IDs the provider rejects on purpose, such
+    /// as public suffixes served from their own origin. It is meant for deployments whose domains
+    /// are missing from the provider's list.
+    pub fn allows_exact_origin_rp_id_match(mut self, is_allowed: bool) -> Self {
+        self.allows_exact_origin_rp_id_match = is_allowed;
+        self
+    }
+
+    /// Allow the given origins to use `rp_id` as their RP ID, even though it is not a registrable
+    /// domain suffix of their domain. See [`OriginMatcher::with_related_origins`].
+    pub fn with_related_origins(
+        mut self,
+        rp_id: impl Into<String>,
+        origins: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        self.origin_matcher = self.origin_matcher.with_related_origins(rp_id, origins);
+        self
+    }
+
+    /// Allow the origins listed in the [related origins] `document` of `rp_id`, the body of
+    /// `https://{rp_id}/.well-known/webauthn`, to use it as their RP ID.
+    ///
+    /// Only the origins whose registrable domain has one of the first
+    /// [`MAX_RELATED_ORIGIN_LABELS`] distinct labels of the document are allowed, so
+    /// `https://example.com` and `https://example.co.uk` count once as `example`. The entries
+    /// that are not valid origins are skipped. Returns [`WebauthnError::InvalidRelatedOrigins`]
+    /// when the document is not a JSON object with an `origins` list.
+    ///
+    /// [related origins]: https://w3c.github.io/webauthn/#sctn-related-origins
+    pub fn with_related_origins_document(
+        self,
+        rp_id: impl Into<String>,
+        document: &[u8],
+    ) -> Result<Self, WebauthnError> {
+        let document: related_origins::RelatedOriginsDocument =
+            serde_json::from_slice(document).map_err(|_| WebauthnError::InvalidRelatedOrigins)?;
+        let origins = related_origins::eligible_origins(&document.origins, |origin| {
+            self.registrable_label(origin)
+        });
+        Ok(self.with_related_origins(rp_id, origins))
+    }
+
+    /// Fetch the related origins document of `rp_id` with the `fetcher` and allow the origins it
+    /// lists to use it as their RP ID, see [`Self::with_related_origins_document`]. Nothing is
+    /// allowed when the document could not be fetched.
+    pub async fn fetch_related_origins(
+        self,
+        rp_id: &str,
+        fetcher: &impl RelatedOriginsFetcher,
+    ) -> Result<Self, WebauthnError> {
+        match fetcher.fetch_related_origins(rp_id).await {
+            Some(document) => self.with_related_origins_document(rp_id, &document),
+            None => Ok(self),
+        }
+    }
+
+    /// The label of the registrable domain of the origin, which is `example` for
+    /// `https://www.example.co.uk`.
+    fn registrable_label(&self, origin: &Url) -> Option<String> {
+        let domain = decode_host(origin.domain()?)?;
+        let registrable = self.tld_provider.effective_tld_plus_one(&domain).ok()?;
+        registrable
+            .split('.')
+            .next()
+            .filter(|label| !label.is_empty())
+            .map(str::to_owned)
+    }
+
+    /// Trust the Android application whose signing certificate has the SHA-256 `hash` with
+    /// `rp_id`. See [`OriginMatcher::with_android_apk_key_hash`].
+    pub fn with_android_apk_key_hash(
+        mut self,
+        rp_id: impl Into<String>,
+        hash: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.origin_matcher = self.origin_matcher.with_android_apk_key_hash(rp_id, hash);
+        self
+    }
+
+    /// Replace the [`OriginMatcher`] of the verifier, including the settings given through
+    /// [`Self::allows_insecure_localhost`], [`Self::allows_schemes`],
+    /// [`Self::with_related_origins`] and [`Self::with_android_apk_key_hash`].
+    pub fn with_origin_matcher(mut self, origin_matcher: OriginMatcher) -> Self {
+        self.origin_matcher = origin_matcher;
+        self
+    }
+
+    /// The [`OriginMatcher`] the origins are matched against their RP ID with, before it is
+    /// checked against the TLD provider.
+    pub fn origin_matcher(&self) -> &OriginMatcher {
+        &self.origin_matcher
+    }
+
     /// Parse the given Relying Party Id and verify it against the origin url of the request.
     ///
     /// This follows the steps defined in: <https://html.spec.whatwg.org/multipage/browsers.html#is-a-registrable-domain-suffix-of-or-is-equal-to>
@@ -505,39 +1666,24 @@ where
         rp_id: Option<&'a str>,
     ) -> Result<&'a str, WebauthnError> {
         match origin {
-            Origin::Web(url) => self.assert_web_rp_id(url, rp_id),
+            Origin::Web(url) => self.assert_web_rp_id(origin, url, rp_id),
             #[cfg(feature = "android-asset-validation")]
-            Origin::Android(unverified) => self.assert_android_rp_id(unverified, rp_id),
+            Origin::Android(_) => self.assert_android_rp_id(origin, rp_id),
         }
     }
 
     fn assert_web_rp_id<'a>(
         &self,
-        origin: &'a Url,
+        origin: &'a Origin,
+        url: &Url,
         rp_id: Option<&'a str>,
     ) -> Result<&'a str, WebauthnError> {
-        let mut effective_domain = origin.domain().ok_or(WebauthnError::OriginMissingDomain)?;
-
-        if let Some(rp_id) = rp_id {
-            if !effective_domain.ends_with(rp_id) {
-                return Err(WebauthnError::OriginRpMissmatch);
-            }
-
-            effective_domain = rp_id;
-        }
-
-        // guard against localhost effective domain, return early
-        if effective_domain == "localhost" {
-            return if self.allows_insecure_localhost {
-                Ok(effective_domain)
-            } else {
-                Err(WebauthnError::InsecureLocalhostNotAllowed)
-            };
-        }
+        let effective_domain = self.origin_matcher.matches(origin, rp_id)?;
 
-        // Make sure origin uses https://
-        if !(origin.scheme().eq_ignore_ascii_case("https")) {
-            return Err(WebauthnError::UnprotectedOrigin);
+        // localhost is not a registrable domain, the matcher only accepts it when it is allowed.
+        // Android applications are trusted with their RP IDs by the matcher as well.
+        if effective_domain == "localhost" || origin_matcher::apk_key_hash(url).is_some() {
+            return Ok(effective_domain);
         }
 
         // assert rp_id is not part of the public suffix list and is a registerable domain.
@@ -546,7 +1692,11 @@ where
             .and_then(|s| self.tld_provider.effective_tld_plus_one(s).ok())
             .is_none()
         {
-            return Err(WebauthnError::InvalidRpId);
+            // NB: An RP ID that is exactly the origin's domain may be allowed without it.
+            let is_exact_match = url.domain() == Some(effective_domain);
+            if !(self.allows_exact_origin_rp_id_match && is_exact_match) {
+                return Err(WebauthnError::InvalidRpId);
+            }
         }
 
         Ok(effective_domain)
@@ -555,18 +1705,10 @@ where
     #[cfg(feature = "android-asset-validation")]
     fn assert_android_rp_id<'a>(
         &self,
-        target_link: &'a UnverifiedAssetLink,
+        origin: &'a Origin,
         rp_id: Option<&'a str>,
     ) -> Result<&'a str, WebauthnError> {
-        let mut effective_rp_id = target_link.host();
-
-        if let Some(rp_id) = rp_id {
-            // subset from assert_web_rp_id
-            if !effective_rp_id.ends_with(rp_id) {
-                return Err(WebauthnError::OriginRpMissmatch);
-            }
-            effective_rp_id = rp_id;
-        }
+        let effective_rp_id = self.origin_matcher.matches(origin, rp_id)?;
 
         if decode_host(effective_rp_id)
             .as_ref()
@@ -658,10 +1800,22 @@ mod test {
                     up: true,
                     plat: true,
                     client_pin: None,
+                    always_uv: None,
+                    make_cred_uv_not_rqd: None,
+                    large_blobs: None,
+                    cred_mgmt: None,
+                    ep: None,
                 }),
                 max_msg_size: None,
+                algorithms: None,
+                max_serialized_large_blob_array: None,
+                min_pin_length: None,
                 pin_protocols: None,
+                max_credential_id_length: None,
                 transports: None,
+                uv_modality: None,
+                certifications: None,
+                remaining_discoverable_credentials: None,
             };
             let client = Client::new(Authenticator::new(
                 ctap2::Aaguid::new_empty(),
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use passkey_types::encoding;
+use url::Url;
+
+#[cfg(feature = "android-asset-validation")]
+use crate::UnverifiedAssetLink;
+use crate::{Origin, WebauthnError};
+
+#[cfg(doc)]
+use crate::RpIdVerifier;
+
+/// Matches the origin of a request against the RP ID it asks for, which is the part of
+/// [`RpIdVerifier`] that does not depend on a TLD provider.
+///
+/// It encodes the policies used to accept an origin: its scheme, whether `localhost` is allowed,
+/// the related origins of RP IDs and the Android applications trusted with them. The RP ID is not checked against the public suffix list,
+/// see [`RpIdVerifier::assert_domain`] for the full verification.
+#[derive(Debug, Clone)]
+pub struct OriginMatcher {
+    allows_insecure_localhost: bool,
+    allowed_schemes: Vec<String>,
+    related_origins: HashMap<String, Vec<Url>>,
+    apk_key_hashes: HashMap<Vec<u8>, Vec<String>>,
+}
+
+/// The prefix of the origins of native Android applications, which is followed by the base64url
+/// encoded SHA-256 hash of their signing certificate.
+const APK_KEY_HASH_PREFIX: &str = "android:apk-key-hash:";
+
+/// The signing certificate hash encoded in an `android:apk-key-hash:` origin.
+pub(crate) fn apk_key_hash(origin: &Url) -> Option<&str> {
+    origin.as_str().strip_prefix(APK_KEY_HASH_PREFIX)
+}
+
+impl Default for OriginMatcher {
+    fn default() -> Self {
+        Self {
+            allows_insecure_localhost: false,
+            allowed_schemes: vec!["https".into()],
+            related_origins: HashMap::new(),
+            apk_key_hashes: HashMap::new(),
+        }
+    }
+}
+
+impl OriginMatcher {
+    /// Create a matcher which only accepts `https` origins whose domain is the RP ID or one of
+    /// its subdomains.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows [`OriginMatcher::matches`] to pass through requests from `localhost`, whatever
+    /// their scheme.
+    pub fn allows_insecure_localhost(mut self, is_allowed: bool) -> Self {
+        self.allows_insecure_localhost = is_allowed;
+        self
+    }
+
+    /// Set the schemes of the origins [`OriginMatcher::matches`] accepts, which replace the
+    /// default of only accepting `https`. Origins with a custom scheme, such as
+    /// `chrome-extension`, still need a host that matches the RP ID.
+    ///
+    /// Include `https` to keep accepting web origins. Unless allowed, `http` origins are still
+    /// rejected with [`WebauthnError::UnprotectedOrigin`] and origins with any other scheme with
+    /// [`WebauthnError::InvalidOrigin`].
+    pub fn allows_schemes(mut self, schemes: &[&str]) -> Self {
+        self.allowed_schemes = schemes
+            .iter()
+            .map(|scheme| scheme.to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Allow the given origins to use `rp_id` as their RP ID, even though it is not a registrable
+    /// domain suffix of their domain. These are the [related origins] the Relying Party lists in
+    /// `https://{rp_id}/.well-known/webauthn`, which the application is responsible for fetching.
+    ///
+    /// Credentials are bound to the RP ID rather than the origin, so a credential registered from
+    /// any of these origins can be used from all of them. Calling this again for the same `rp_id`
+    /// adds to its related origins.
+    ///
+    /// [related origins]: https://w3c.github.io/webauthn/#sctn-related-origins
+    pub fn with_related_origins(
+        mut self,
+        rp_id: impl Into<String>,
+        origins: impl IntoIterator<Item = Url>,
+    ) -> Self {
+        self.related_origins
+            .entry(rp_id.into())
+            .or_default()
+            .extend(origins);
+        self
+    }
+
+    /// Trust the Android application whose signing certificate has the SHA-256 `hash` with
+    /// `rp_id`. Its requests come from the `android:apk-key-hash:` origin of the hash, which has no
+    /// domain, and are rejected with [`WebauthnError::UntrustedAndroidOrigin`] for the RP IDs it
+    /// is not trusted with. Calling this again for the same `hash` adds to its RP IDs.
+    pub fn with_android_apk_key_hash(
+        mut self,
+        rp_id: impl Into<String>,
+        hash: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.apk_key_hashes
+            .entry(hash.into())
+            .or_default()
+            .push(rp_id.into());
+        self
+    }
+
+    /// Whether the origin was configured as a related origin of the RP ID.
+    pub fn is_related_origin(&self, origin: &Url, rp_id: &str) -> bool {
+        self.related_origins.get(rp_id).is_some_and(|origins| {
+            origins
+                .iter()
+                .any(|related| related.origin() == origin.origin())
+        })
+    }
+
+    /// Match the origin of a request against its RP ID, or against its own domain when the
+    /// request does not give one.
+    ///
+    /// Returns the effective RP ID on success or some [`WebauthnError`].
+    pub fn matches<'a>(
+        &self,
+        origin: &'a Origin,
+        rp_id: Option<&'a str>,
+    ) -> Result<&'a str, WebauthnError> {
+        match origin {
+            Origin::Web(url) => self.match_web_origin(url, rp_id),
+            #[cfg(feature = "android-asset-validation")]
+            Origin::Android(unverified) => Self::match_android_origin(unverified, rp_id),
+        }
+    }
+
+    fn match_web_origin<'a>(
+        &self,
+        origin: &'a Url,
+        rp_id: Option<&'a str>,
+    ) -> Result<&'a str, WebauthnError> {
+        // Android applications are matched by their signing certificate rather than a domain.
+        if let Some(hash) = apk_key_hash(origin) {
+            // NB: the RP ID cannot be derived from the origin.
+            let rp_id = rp_id.ok_or(WebauthnError::OriginMissingDomain)?;
+            let is_trusted = encoding::try_from_base64url(hash)
+                .and_then(|hash| self.apk_key_hashes.get(&hash))
+                .is_some_and(|rp_ids| rp_ids.iter().any(|trusted| trusted == rp_id));
+            return if is_trusted {
+                Ok(rp_id)
+            } else {
+                Err(WebauthnError::UntrustedAndroidOrigin)
+            };
+        }
+
+        // Opaque origins, like those of `data:` URLs, cannot be compared to an RP ID.
+        let is_allowed_scheme = self
+            .allowed_schemes
+            .iter()
+            .any(|scheme| origin.scheme().eq_ignore_ascii_case(scheme));
+        if !(is_allowed_scheme || matches!(origin.scheme(), "https" | "http")) || !origin.has_host()
+        {
+            return Err(WebauthnError::InvalidOrigin);
+        }
+
+        let mut effective_domain = origin.domain().ok_or(WebauthnError::OriginMissingDomain)?;
+
+        if let Some(rp_id) = rp_id {
+            if !effective_domain.ends_with(rp_id) && !self.is_related_origin(origin, rp_id) {
+                return Err(WebauthnError::OriginRpMissmatch);
+            }
+
+            effective_domain = rp_id;
+        }
+
+        // guard against localhost effective domain, return early
+        if effective_domain == "localhost" {
+            return if self.allows_insecure_localhost {
+                Ok(effective_domain)
+            } else {
+                Err(WebauthnError::InsecureLocalhostNotAllowed)
+            };
+        }
+
+        // Make sure origin uses https://, or one of the allowed schemes
+        if !is_allowed_scheme {
+            return Err(WebauthnError::UnprotectedOrigin);
+        }
+
+        Ok(effective_domain)
+    }
+
+    #[cfg(feature = "android-asset-validation")]
+    fn match_android_origin<'a>(
+        target_link: &'a UnverifiedAssetLink,
+        rp_id: Option<&'a str>,
+    ) -> Result<&'a str, WebauthnError> {
+        let effective_rp_id = target_link.host();
+
+        match rp_id {
+            // subset from match_web_origin
+            Some(rp_id) if !effective_rp_id.ends_with(rp_id) => {
+                Err(WebauthnError::OriginRpMissmatch)
+            }
+            Some(rp_id) => Ok(rp_id),
+            None => Ok(effective_rp_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::{ParseError, Url};
+
+    use super::OriginMatcher;
+    use crate::WebauthnError;
+
+    #[test]
+    fn matches_the_rp_id_of_the_origin() -> Result<(), ParseError> {
+        let matcher = OriginMatcher::new();
+
+        let future = Url::parse("https://www.future.1password.com")?.into();
+        let sub_domain_ignored = matcher.matches(&future, Some("future.1password.com"));
+        assert_eq!(sub_domain_ignored, Ok("future.1password.com"));
+
+        let use_effective_domain = matcher.matches(&future, None);
+        assert_eq!(use_effective_domain, Ok("www.future.1password.com"));
+
+        let other_site = matcher.matches(&future, Some("example.com"));
+        assert_eq!(other_site, Err(WebauthnError::OriginRpMissmatch));
+
+        let not_protected = Url::parse("http://example.com")?.into();
+        let not_https = matcher.matches(&not_protected, Some("example.com"));
+        assert_eq!(not_https, Err(WebauthnError::UnprotectedOrigin));
+
+        let ip_address = Url::parse("https://127.0.0.1")?.into();
+        let no_domain = matcher.matches(&ip_address, None);
+        assert_eq!(no_domain, Err(WebauthnError::OriginMissingDomain));
+
+        let data = Url::parse("data:text/html,<h1>future.1password.com</h1>")?.into();
+        let opaque = matcher.matches(&data, None);
+        assert_eq!(opaque, Err(WebauthnError::InvalidOrigin));
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_localhost_only_when_allowed() -> Result<(), ParseError> {
+        let matcher = OriginMatcher::new();
+        let localhost = Url::parse("http://localhost:8080")?.into();
+
+        let should_still_match = matcher.matches(&localhost, Some("example.com"));
+        assert_eq!(should_still_match, Err(WebauthnError::OriginRpMissmatch));
+
+        let localhost_not_allowed = matcher.matches(&localhost, Some("localhost"));
+        assert_eq!(
+            localhost_not_allowed,
+            Err(WebauthnError::InsecureLocalhostNotAllowed)
+        );
+
+        let matcher = matcher.allows_insecure_localhost(true);
+        let skips_http_check = matcher.matches(&localhost, None);
+        assert_eq!(skips_http_check, Ok("localhost"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_related_origins_and_allowed_schemes() -> Result<(), ParseError> {
+        let matcher = OriginMatcher::new()
+            .with_related_origins(
+                "future.1password.com",
+                [Url::parse("https://1password.ca")?],
+            )
+            .allows_schemes(&["https", "chrome-extension"]);
+        let related = Url::parse("https://1password.ca")?.into();
+        let extension = Url::parse("chrome-extension://future.1password.com")?.into();
+        let http = Url::parse("http://future.1password.com")?.into();
+
+        assert_eq!(
+            matcher.matches(&related, Some("future.1password.com")),
+            Ok("future.1password.com")
+        );
+        assert_eq!(
+            matcher.matches(&related, Some("other.1password.com")),
+            Err(WebauthnError::OriginRpMissmatch)
+        );
+        assert_eq!(
+            matcher.matches(&extension, Some("future.1password.com")),
+            Ok("future.1password.com")
+        );
+        assert_eq!(
+            matcher.matches(&http, None),
+            Err(WebauthnError::UnprotectedOrigin)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_the_rp_ids_of_trusted_android_applications() -> Result<(), ParseError> {
+        let hash = [0x4b; 32];
+        let matcher = OriginMatcher::new().with_android_apk_key_hash("future.1password.com", hash);
+        let trusted = Url::parse(&format!(
+            "android:apk-key-hash:{}",
+            passkey_types::encoding::base64url(&hash)
+        ))?
+        .into();
+        let untrusted = Url::parse(&format!(
+            "android:apk-key-hash:{}",
+            passkey_types::encoding::base64url(&[0x2a; 32])
+        ))?
+        .into();
+
+        assert_eq!(
+            matcher.matches(&trusted, Some("future.1password.com")),
+            Ok("future.1password.com")
+        );
+        assert_eq!(
+            matcher.matches(&trusted, Some("other.1password.com")),
+            Err(WebauthnError::UntrustedAndroidOrigin)
+        );
+        assert_eq!(
+            matcher.matches(&untrusted, Some("future.1password.com")),
+            Err(WebauthnError::UntrustedAndroidOrigin)
+        );
+        assert_eq!(
+            matcher.matches(&trusted, None),
+            Err(WebauthnError::OriginMissingDomain)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Client side processing of the [`prf`] extension inputs.
+//!
+//! The inputs given by the relying party are not sent as is to the authenticator, they are first
+//! hashed with a context string into the salts of the CTAP2 [`hmac-secret`] extension, so that
+//! the PRF results cannot be confused with other uses of the credential's secret.
+//!
+//! [`prf`]: https://w3c.github.io/webauthn/#prf-extension
+//! [`hmac-secret`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension
+
+use ciborium::Value;
+use passkey_types::{
+    crypto::sha256,
+    ctap2::AuthenticatorData,
+    webauthn::{AuthenticationExtensionsPrfInputs, AuthenticationExtensionsPrfValues},
+};
+
+/// The context string prepended, along with a zero byte, to every PRF input.
+const PRF_CONTEXT: &[u8] = b"WebAuthn PRF";
+
+/// The salts given to the `hmac-secret` extension to evaluate the PRF of a credential.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HmacSecretSalts {
+    /// The salt derived from the first PRF input.
+    pub salt1: [u8; 32],
+    /// The salt derived from the second PRF input, if one was given.
+    pub salt2: Option<[u8; 32]>,
+}
+
+impl From<&AuthenticationExtensionsPrfValues> for HmacSecretSalts {
+    fn from(values: &AuthenticationExtensionsPrfValues) -> Self {
+        Self {
+            salt1: prf_salt(&values.first),
+            salt2: values.second.as_ref().map(|second| prf_salt(second)),
+        }
+    }
+}
+
+/// Compute the `hmac-secret` salt of a PRF input: `SHA-256("WebAuthn PRF" || 0x00 || input)`.
+fn prf_salt(input: &[u8]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(PRF_CONTEXT.len() + 1 + input.len());
+    data.extend_from_slice(PRF_CONTEXT);
+    data.push(0);
+    data.extend_from_slice(input);
+    sha256(&data)
+}
+
+/// Compute the salts used to evaluate the PRF of the credential that was used in an assertion.
+///
+/// The inputs listed for this credential in `evalByCredential` are used first, falling back to
+/// the top-level `eval`. Without either, there is no PRF to evaluate and `None` is returned.
+pub fn hmac_secret_salts(
+    inputs: &AuthenticationExtensionsPrfInputs,
+    credential_id: &[u8],
+) -> Option<HmacSecretSalts> {
+    inputs
+        .values_for_credential(credential_id)
+        .map(HmacSecretSalts::from)
+}
+
+/// Hash every PRF input into its `hmac-secret` salt, keeping the structure of the inputs so that
+/// the authenticator can select the salts of the credential it uses.
+pub fn hash_prf_inputs(
+    inputs: &AuthenticationExtensionsPrfInputs,
+) -> AuthenticationExtensionsPrfInputs {
+    let hash = |values: &AuthenticationExtensionsPrfValues| {
+        let salts = HmacSecretSalts::from(values);
+        AuthenticationExtensionsPrfValues {
+            first: salts.salt1.to_vec().into(),
+            second: salts.salt2.map(|salt| salt.to_vec().into()),
+        }
+    };
+
+    AuthenticationExtensionsPrfInputs {
+        eval: inputs.eval.as_ref().map(hash),
+        eval_by_credential: inputs.eval_by_credential.as_ref().map(|by_credential| {
+            by_credential
+                .iter()
+                .map(|(id, values)| (id.clone(), hash(values)))
+                .collect()
+        }),
+    }
+}
+
+/// Whether the authenticator created the credential with the `hmac-secret` extension, which it
+/// reports in the extension outputs of the authenticator data.
+pub fn hmac_secret_enabled(auth_data: &AuthenticatorData) -> bool {
+    matches!(&auth_data.extensions, Some(Value::Map(outputs)) if outputs.iter().any(|(id, enabled)| {
+        id.as_text() == Some("hmac-secret") && enabled.as_bool() == Some(true)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use passkey_types::{
+        encoding,
+        webauthn::{AuthenticationExtensionsPrfInputs, AuthenticationExtensionsPrfValues},
+    };
+
+    use super::{hmac_secret_salts, prf_salt, HmacSecretSalts};
+
+    fn values(first: &[u8], second: Option<&[u8]>) -> AuthenticationExtensionsPrfValues {
+        AuthenticationExtensionsPrfValues {
+            first: first.to_vec().into(),
+            second: second.map(|second| second.to_vec().into()),
+        }
+    }
+
+    #[test]
+    fn eval_by_credential_salts_are_used_for_the_asserted_credential() {
+        // Arrange
+        let first_credential = [1; 16];
+        let second_credential = [2; 16];
+        let inputs = AuthenticationExtensionsPrfInputs {
+            eval: Some(values(b"default", None)),
+            eval_by_credential: Some(
+                [
+                    (
+                        encoding::base64url(&first_credential),
+                        values(b"first credential", Some(b"first credential, second input")),
+                    ),
+                    (
+                        encoding::base64url(&second_credential),
+                        values(b"second credential", None),
+                    ),
+                ]
+                .into(),
+            ),
+        };
+
+        // Act
+        let first_salts = hmac_secret_salts(&inputs, &first_credential);
+        let second_salts = hmac_secret_salts(&inputs, &second_credential);
+
+        // Assert
+        assert_eq!(
+            first_salts,
+            Some(HmacSecretSalts {
+                salt1: prf_salt(b"first credential"),
+                salt2: Some(prf_salt(b"first credential, second input")),
+            })
+        );
+        assert_eq!(
+            second_salts,
+            Some(HmacSecretSalts {
+                salt1: prf_salt(b"second credential"),
+                salt2: None,
+            })
+        );
+    }
+
+    #[test]
+    fn unlisted_credentials_fall_back_to_eval() {
+        let mut inputs = AuthenticationExtensionsPrfInputs {
+            eval: Some(values(b"default", None)),
+            eval_by_credential: Some(
+                [(encoding::base64url(&[1; 16]), values(b"listed", None))].into(),
+            ),
+        };
+
+        assert_eq!(
+            hmac_secret_salts(&inputs, &[3; 16]).map(|salts| salts.salt1),
+            Some(prf_salt(b"default"))
+        );
+
+        inputs.eval = None;
+        assert_eq!(hmac_secret_salts(&inputs, &[3; 16]), None);
+    }
+}
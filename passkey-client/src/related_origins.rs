@@ -0,0 +1,53 @@
+//! Related origin requests, which let a Relying Party authorize origins outside of its RP ID
+//! through the document served at `https://{rp_id}/.well-known/webauthn`.
+//!
+//! <https://w3c.github.io/webauthn/#sctn-related-origins>
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use url::Url;
+
+#[cfg(doc)]
+use crate::RpIdVerifier;
+
+/// The maximum number of distinct labels of the registrable domains of related origins. The
+/// origins whose label comes after this many others in the document are ignored.
+pub const MAX_RELATED_ORIGIN_LABELS: usize = 5;
+
+/// Fetches the related origins document of an RP ID, see
+/// [`RpIdVerifier::fetch_related_origins`]. This lets the application choose how the document
+/// is fetched and cached, and tests provide it offline.
+#[async_trait::async_trait]
+pub trait RelatedOriginsFetcher: Sync {
+    /// Fetch the body of `https://{rp_id}/.well-known/webauthn`, or `None` when it could not be
+    /// fetched, in which case no origin is related to the RP ID.
+    async fn fetch_related_origins(&self, rp_id: &str) -> Option<Vec<u8>>;
+}
+
+/// The related origins document of an RP ID.
+#[derive(Deserialize)]
+pub(crate) struct RelatedOriginsDocument {
+    pub(crate) origins: Vec<String>,
+}
+
+/// Keep the origins of the document that may be related to the RP ID, in order: the ones whose
+/// label, given by `label_of`, is among the first [`MAX_RELATED_ORIGIN_LABELS`] labels of the
+/// document. Origins that cannot be parsed or have no label are skipped.
+pub(crate) fn eligible_origins(
+    origins: &[String],
+    label_of: impl Fn(&Url) -> Option<String>,
+) -> Vec<Url> {
+    let mut labels_seen = HashSet::new();
+    origins
+        .iter()
+        .filter_map(|origin| Url::parse(origin).ok())
+        .filter(|origin| {
+            let Some(label) = label_of(origin) else {
+                return false;
+            };
+            labels_seen.contains(&label)
+                || (labels_seen.len() < MAX_RELATED_ORIGIN_LABELS && labels_seen.insert(label))
+        })
+        .collect()
+}
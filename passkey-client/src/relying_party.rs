@@ -0,0 +1,1188 @@
+//! Helpers for the Relying Party side of a WebAuthn ceremony.
+//!
+//! While the rest of this crate is concerned with producing credentials on behalf of a user, the
+//! types in this module are meant to be used by a Relying Party's server to make sense of the
+//! credentials it receives.
+
+use ciborium::Value;
+use coset::{
+    iana::{self, EnumI64},
+    CoseKey,
+};
+use p256::{
+    ecdsa::{signature::Verifier as _, Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use passkey_types::{
+    crypto::{client_data_hash, sha256},
+    ctap2::{Aaguid, AuthenticatorData},
+    encoding::try_from_base64url,
+    webauthn::{
+        self, AuthenticatedPublicKeyCredential, AuthenticatorTransport, ClientDataType,
+        CollectedClientData, CreatedPublicKeyCredential,
+    },
+    Bytes,
+};
+use x509_cert::{
+    der::{Decode, Encode},
+    Certificate,
+};
+
+/// Errors produced while verifying or processing a credential received by a Relying Party.
+///
+/// Each variant identifies the verification step which failed.
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The client data JSON could not be decoded.
+    MalformedClientData,
+    /// The client data is not of the type expected by the ceremony.
+    ClientDataTypeMismatch,
+    /// The challenge in the client data does not match the expected challenge.
+    ChallengeMismatch,
+    /// The origin in the client data is not one of the allowed origins.
+    OriginNotAllowed,
+    /// The authenticator data could not be decoded.
+    MalformedAuthenticatorData,
+    /// The RP ID hash in the authenticator data does not match the expected RP ID.
+    RpIdHashMismatch,
+    /// The UP flag is not set in the authenticator data.
+    UserNotPresent,
+    /// User verification is required but the UV flag is not set in the authenticator data.
+    UserNotVerified,
+    /// The authenticator data of a registration did not contain any attested credential data.
+    MissingAttestedCredentialData,
+    /// The assertion was not made with the stored credential.
+    CredentialMismatch,
+    /// The stored public key, or the key of an enterprise attestation certificate, cannot be used
+    /// to verify signatures.
+    UnsupportedPublicKey,
+    /// The assertion signature is not valid for the stored public key.
+    InvalidSignature,
+    /// The signature counter did not increase since the last assertion, which may indicate that
+    /// the credential was cloned.
+    StaleCounter,
+    /// The attestation object of a registration could not be decoded.
+    MalformedAttestationObject,
+    /// The attestation statement starts with a trusted enterprise attestation certificate but is
+    /// not signed with its key.
+    InvalidAttestationSignature,
+}
+
+/// The fields of a newly registered credential that a Relying Party should persist in order to
+/// verify future assertions made with it.
+///
+/// <https://w3c.github.io/webauthn/#sctn-registering-a-new-credential>
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCredential {
+    /// The credential ID, chosen by the authenticator.
+    pub credential_id: Bytes,
+    /// The credential public key, used to verify assertion signatures.
+    pub public_key: CoseKey,
+    /// The signature counter at registration, `0` when the authenticator does not use counters.
+    pub sign_count: u32,
+    /// The transports reported by the client, suitable to be returned as hints in future
+    /// `allow_credentials` and `exclude_credentials` lists.
+    pub transports: Vec<AuthenticatorTransport>,
+    /// The AAGUID of the authenticator which created the credential.
+    pub aaguid: Aaguid,
+    /// Whether the credential is eligible to be backed up (the BE flag).
+    pub backup_eligible: bool,
+    /// Whether the credential is currently backed up (the BS flag).
+    pub backed_up: bool,
+    /// Whether the credential was registered with an enterprise attestation, which is only the case
+    /// when [`Verifier::verify_registration`] verified its attestation statement against one of the
+    /// [`Verifier::enterprise_attestation_certificate`]s.
+    pub enterprise_attestation: bool,
+}
+
+impl StoredCredential {
+    /// Extract the fields that need to be stored from a registration response.
+    ///
+    /// This does not verify the registration; it is expected to be called once the Relying Party
+    /// has verified the response. Since the attestation statement is not verified either, the
+    /// credential is never reported as an enterprise attestation.
+    pub fn from_registration(
+        credential: &CreatedPublicKeyCredential,
+    ) -> Result<Self, VerificationError> {
+        let auth_data = AuthenticatorData::from_slice(&credential.response.authenticator_data)
+            .map_err(|_| VerificationError::MalformedAuthenticatorData)?;
+        let attested_credential_data = auth_data
+            .attested_credential_data
+            .ok_or(VerificationError::MissingAttestedCredentialData)?;
+
+        Ok(Self {
+            credential_id: attested_credential_data.credential_id().to_vec().into(),
+            public_key: attested_credential_data.key,
+            sign_count: auth_data.counter.unwrap_or_default(),
+            transports: credential.response.transports.clone().unwrap_or_default(),
+            aaguid: attested_credential_data.aaguid,
+            backup_eligible: auth_data.flags.backup_eligible(),
+            backed_up: auth_data.flags.backed_up(),
+            enterprise_attestation: false,
+        })
+    }
+}
+
+/// The result of a successfully verified assertion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedAssertion {
+    /// The new signature counter, which should replace [`StoredCredential::sign_count`].
+    pub sign_count: u32,
+    /// Whether the user was verified during the assertion (the UV flag).
+    pub user_verified: bool,
+    /// Whether the credential is currently backed up (the BS flag).
+    pub backed_up: bool,
+}
+
+/// An assertion to verify with [`Verifier::verify_assertions`], along with the stored credential
+/// it was made with and the challenge of its ceremony.
+#[derive(Debug, Clone, Copy)]
+pub struct AssertionInput<'a> {
+    /// The assertion received by the Relying Party.
+    pub credential: &'a AuthenticatedPublicKeyCredential,
+    /// The credential the assertion claims to be made with.
+    pub stored: &'a StoredCredential,
+    /// The challenge of the authentication ceremony of this assertion.
+    pub challenge: &'a [u8],
+}
+
+/// Verifies registration and assertion responses against the values a Relying Party expects.
+///
+/// ```
+/// # use passkey_client::relying_party::Verifier;
+/// let verifier = Verifier::new("example.com", vec![0; 32])
+///     .allowed_origin("https://example.com")
+///     .require_user_verification(true);
+/// ```
+///
+/// <https://w3c.github.io/webauthn/#sctn-rp-operations>
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    rp_id: String,
+    additional_rp_ids: Vec<String>,
+    challenge: Bytes,
+    origins: Vec<String>,
+    require_user_verification: bool,
+    parallelism: usize,
+    enterprise_attestation_certificates: Vec<(Aaguid, Bytes)>,
+}
+
+impl Verifier {
+    /// Create a verifier for a ceremony of the given RP ID which was started with `challenge`.
+    ///
+    /// At least one origin needs to be allowed with [`Self::allowed_origin`] for any response to
+    /// be accepted.
+    pub fn new(rp_id: impl Into<String>, challenge: impl Into<Bytes>) -> Self {
+        Self {
+            rp_id: rp_id.into(),
+            additional_rp_ids: Vec::new(),
+            challenge: challenge.into(),
+            origins: Vec::new(),
+            require_user_verification: false,
+            parallelism: 1,
+            enterprise_attestation_certificates: Vec::new(),
+        }
+    }
+
+    /// Accept responses from the given origin, for example `https://example.com`.
+    pub fn allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    /// Also accept authenticator data whose RP ID hash is the hash of `rp_id`, besides the RP ID
+    /// of the verifier. This is needed for credentials scoped to another identifier, such as the
+    /// legacy AppID `https://example.com/app-id.json` of credentials migrated with the `appid`
+    /// extension.
+    pub fn allowed_rp_id(mut self, rp_id: impl Into<String>) -> Self {
+        self.additional_rp_ids.push(rp_id.into());
+        self
+    }
+
+    /// Reject responses for which the authenticator did not verify the user.
+    pub fn require_user_verification(mut self, is_required: bool) -> Self {
+        self.require_user_verification = is_required;
+        self
+    }
+
+    /// Verify the signatures of [`Self::verify_assertions`] on up to `threads` threads, instead of
+    /// only the calling thread by default.
+    pub fn parallelism(mut self, threads: usize) -> Self {
+        self.parallelism = threads.max(1);
+        self
+    }
+
+    /// Recognize the enterprise attestations of the authenticators with the given AAGUID, which the
+    /// enterprise provisioned with the DER encoded attestation `certificate`.
+    ///
+    /// [`Self::verify_registration`] reports a [`StoredCredential::enterprise_attestation`] when
+    /// the `packed` attestation statement of a credential from such an authenticator starts its
+    /// certificate chain with this certificate and is signed with its key. What the client reports
+    /// about the attestation is not taken into account.
+    pub fn enterprise_attestation_certificate(
+        mut self,
+        aaguid: Aaguid,
+        certificate: impl Into<Bytes>,
+    ) -> Self {
+        self.enterprise_attestation_certificates
+            .push((aaguid, certificate.into()));
+        self
+    }
+
+    /// Build the options of the authentication ceremony checked by this verifier, in the JSON
+    /// representation that browsers and native clients parse, for example with
+    /// `PublicKeyCredential.parseRequestOptionsFromJSON()`.
+    ///
+    /// The challenge and the IDs of the `allow_credentials` are `base64url` encoded, along with
+    /// their stored transports as hints. An empty list leaves `allowCredentials` out, which asks
+    /// for a discoverable credential. User verification is required when the verifier requires
+    /// it, and preferred otherwise.
+    pub fn authentication_options_json(
+        &self,
+        allow_credentials: &[StoredCredential],
+    ) -> webauthn::PublicKeyCredentialRequestOptionsJson {
+        let allow_credentials = allow_credentials
+            .iter()
+            .map(|stored| webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id: stored.credential_id.clone(),
+                transports: (!stored.transports.is_empty()).then(|| stored.transports.clone()),
+            })
+            .collect::<Vec<_>>();
+        webauthn::PublicKeyCredentialRequestOptions {
+            challenge: self.challenge.clone(),
+            timeout: None,
+            rp_id: Some(self.rp_id.clone()),
+            allow_credentials: (!allow_credentials.is_empty()).then_some(allow_credentials),
+            user_verification: if self.require_user_verification {
+                webauthn::UserVerificationRequirement::Required
+            } else {
+                webauthn::UserVerificationRequirement::Preferred
+            },
+            hints: None,
+            attestation: webauthn::AttestationConveyancePreference::None,
+            attestation_formats: None,
+            extensions: None,
+        }
+        .into()
+    }
+
+    /// Verify a registration response and extract the credential to store.
+    ///
+    /// The attestation statement itself is only verified to recognize the enterprise attestations
+    /// made with an [`Self::enterprise_attestation_certificate`].
+    ///
+    /// <https://w3c.github.io/webauthn/#sctn-registering-a-new-credential>
+    pub fn verify_registration(
+        &self,
+        credential: &CreatedPublicKeyCredential,
+    ) -> Result<StoredCredential, VerificationError> {
+        self.verify_client_data(
+            &credential.response.client_data_json,
+            ClientDataType::Create,
+            &self.challenge,
+        )?;
+        self.verify_authenticator_data(
+            &credential.response.authenticator_data,
+            &self.rp_id_hashes(),
+        )?;
+        let mut stored = StoredCredential::from_registration(credential)?;
+        stored.enterprise_attestation =
+            self.verify_enterprise_attestation(credential, stored.aaguid)?;
+        Ok(stored)
+    }
+
+    /// Verify an assertion made with a previously stored credential.
+    ///
+    /// On success the [`StoredCredential::sign_count`] should be updated with the returned
+    /// [`VerifiedAssertion::sign_count`].
+    ///
+    /// <https://w3c.github.io/webauthn/#sctn-verifying-assertion>
+    pub fn verify_assertion(
+        &self,
+        credential: &AuthenticatedPublicKeyCredential,
+        stored: &StoredCredential,
+    ) -> Result<VerifiedAssertion, VerificationError> {
+        let input = AssertionInput {
+            credential,
+            stored,
+            challenge: &self.challenge,
+        };
+        self.verify_assertion_input(&input, &self.rp_id_hashes())
+    }
+
+    /// Verify a batch of assertions, such as the ones of a bulk re-authentication, each against
+    /// the challenge of its own ceremony rather than the one of the verifier. The accepted RP ID
+    /// hashes are only computed once for the whole batch, and the assertions are split between
+    /// the threads given to [`Self::parallelism`].
+    ///
+    /// Returns the result of every assertion, in order, like [`Self::verify_assertion`] would.
+    pub fn verify_assertions(
+        &self,
+        inputs: &[AssertionInput<'_>],
+    ) -> Vec<Result<VerifiedAssertion, VerificationError>> {
+        let rp_id_hashes = self.rp_id_hashes();
+        let verify = |input| self.verify_assertion_input(input, &rp_id_hashes);
+        if self.parallelism == 1 || inputs.len() <= 1 {
+            return inputs.iter().map(verify).collect();
+        }
+
+        let chunk_size = inputs.len().div_ceil(self.parallelism);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = inputs
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(|| chunk.iter().map(verify).collect::<Vec<_>>()))
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        })
+    }
+
+    fn verify_assertion_input(
+        &self,
+        input: &AssertionInput<'_>,
+        rp_id_hashes: &[[u8; 32]],
+    ) -> Result<VerifiedAssertion, VerificationError> {
+        let AssertionInput {
+            credential,
+            stored,
+            challenge,
+        } = *input;
+        if credential.raw_id != stored.credential_id {
+            return Err(VerificationError::CredentialMismatch);
+        }
+        let response = &credential.response;
+        self.verify_client_data(&response.client_data_json, ClientDataType::Get, challenge)?;
+        let auth_data =
+            self.verify_authenticator_data(&response.authenticator_data, rp_id_hashes)?;
+
+        verify_signature(
+            &stored.public_key,
+            &response.authenticator_data,
+            &client_data_hash(&response.client_data_json),
+            &response.signature,
+        )?;
+
+        // A counter of 0 on both sides means the authenticator does not support counters.
+        let sign_count = auth_data.counter.unwrap_or_default();
+        if (sign_count != 0 || stored.sign_count != 0) && sign_count <= stored.sign_count {
+            return Err(VerificationError::StaleCounter);
+        }
+
+        Ok(VerifiedAssertion {
+            sign_count,
+            user_verified: auth_data.flags.user_verified(),
+            backed_up: auth_data.flags.backed_up(),
+        })
+    }
+
+    fn verify_client_data(
+        &self,
+        client_data_json: &[u8],
+        ty: ClientDataType,
+        challenge: &[u8],
+    ) -> Result<(), VerificationError> {
+        let client_data: CollectedClientData = serde_json::from_slice(client_data_json)
+            .map_err(|_| VerificationError::MalformedClientData)?;
+
+        if client_data.ty != ty {
+            return Err(VerificationError::ClientDataTypeMismatch);
+        }
+        if try_from_base64url(&client_data.challenge).as_deref() != Some(challenge) {
+            return Err(VerificationError::ChallengeMismatch);
+        }
+        if !self.origins.contains(&client_data.origin) {
+            return Err(VerificationError::OriginNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Whether the attestation statement of a registration is a `packed` full attestation made with
+    /// an enterprise attestation certificate trusted for the AAGUID of the credential.
+    ///
+    /// <https://w3c.github.io/webauthn/#sctn-packed-attestation>
+    fn verify_enterprise_attestation(
+        &self,
+        credential: &CreatedPublicKeyCredential,
+        aaguid: Aaguid,
+    ) -> Result<bool, VerificationError> {
+        let certificates = self
+            .enterprise_attestation_certificates
+            .iter()
+            .filter(|(trusted, _)| *trusted == aaguid)
+            .map(|(_, certificate)| certificate)
+            .collect::<Vec<_>>();
+        if certificates.is_empty() {
+            return Ok(false);
+        }
+
+        let attestation_object: Value =
+            ciborium::de::from_reader(credential.response.attestation_object.as_slice())
+                .map_err(|_| VerificationError::MalformedAttestationObject)?;
+        let attestation_object = attestation_object
+            .as_map()
+            .ok_or(VerificationError::MalformedAttestationObject)?;
+        if field(attestation_object, "fmt").and_then(Value::as_text) != Some("packed") {
+            return Ok(false);
+        }
+        let auth_data = field(attestation_object, "authData")
+            .and_then(Value::as_bytes)
+            .ok_or(VerificationError::MalformedAttestationObject)?;
+        let att_stmt = field(attestation_object, "attStmt")
+            .and_then(Value::as_map)
+            .ok_or(VerificationError::MalformedAttestationObject)?;
+        // The attestation must be over the authenticator data that was verified.
+        if auth_data.as_slice() != credential.response.authenticator_data.as_slice() {
+            return Err(VerificationError::MalformedAttestationObject);
+        }
+
+        // Statements without a certificate chain are self attestations.
+        let Some(leaf) = field(att_stmt, "x5c")
+            .and_then(Value::as_array)
+            .and_then(|x5c| x5c.first())
+        else {
+            return Ok(false);
+        };
+        let leaf = leaf
+            .as_bytes()
+            .ok_or(VerificationError::MalformedAttestationObject)?;
+        if !certificates
+            .iter()
+            .any(|certificate| certificate.as_slice() == leaf.as_slice())
+        {
+            return Ok(false);
+        }
+
+        let public_key = Certificate::from_der(leaf)
+            .ok()
+            .and_then(|certificate| {
+                certificate
+                    .tbs_certificate
+                    .subject_public_key_info
+                    .to_der()
+                    .ok()
+            })
+            .and_then(|der| VerifyingKey::from_public_key_der(&der).ok())
+            .ok_or(VerificationError::UnsupportedPublicKey)?;
+        if field(att_stmt, "alg").and_then(Value::as_integer)
+            != Some(iana::Algorithm::ES256.to_i64().into())
+        {
+            return Err(VerificationError::InvalidAttestationSignature);
+        }
+        let signature = field(att_stmt, "sig")
+            .and_then(Value::as_bytes)
+            .and_then(|sig| Signature::from_der(sig).ok())
+            .ok_or(VerificationError::InvalidAttestationSignature)?;
+        let mut signed_data = auth_data.clone();
+        signed_data.extend(client_data_hash(&credential.response.client_data_json));
+        public_key
+            .verify(&signed_data, &signature)
+            .map_err(|_| VerificationError::InvalidAttestationSignature)?;
+        Ok(true)
+    }
+
+    /// The hashes of the RP IDs accepted in authenticator data.
+    fn rp_id_hashes(&self) -> Vec<[u8; 32]> {
+        std::iter::once(&self.rp_id)
+            .chain(&self.additional_rp_ids)
+            .map(|rp_id| sha256(rp_id.as_bytes()))
+            .collect()
+    }
+
+    fn verify_authenticator_data(
+        &self,
+        authenticator_data: &[u8],
+        rp_id_hashes: &[[u8; 32]],
+    ) -> Result<AuthenticatorData, VerificationError> {
+        let auth_data = AuthenticatorData::from_slice(authenticator_data)
+            .map_err(|_| VerificationError::MalformedAuthenticatorData)?;
+
+        if !rp_id_hashes
+            .iter()
+            .any(|rp_id_hash| auth_data.rp_id_hash() == rp_id_hash)
+        {
+            return Err(VerificationError::RpIdHashMismatch);
+        }
+        if !auth_data.flags.user_present() {
+            return Err(VerificationError::UserNotPresent);
+        }
+        if self.require_user_verification && !auth_data.flags.user_verified() {
+            return Err(VerificationError::UserNotVerified);
+        }
+        Ok(auth_data)
+    }
+}
+
+/// The value of the text key `name` in a CBOR map.
+fn field<'a>(map: &'a [(Value, Value)], name: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(key, _)| key.as_text() == Some(name))
+        .map(|(_, value)| value)
+}
+
+/// Verify an assertion signature over `authenticator_data || client_data_hash` with the public
+/// part of `key`.
+pub(crate) fn verify_signature(
+    key: &CoseKey,
+    authenticator_data: &[u8],
+    client_data_hash: &[u8],
+    signature: &[u8],
+) -> Result<(), VerificationError> {
+    let der = passkey_authenticator::public_key_der_from_cose_key(key)
+        .map_err(|_| VerificationError::UnsupportedPublicKey)?;
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend(client_data_hash);
+
+    // ES256 signatures are DER encoded while EdDSA and RS256 signatures are used as is.
+    match passkey_authenticator::public_key_algorithm(key) {
+        Some(iana::Algorithm::EdDSA) => {
+            let public_key = ed25519_dalek::VerifyingKey::from_public_key_der(&der)
+                .map_err(|_| VerificationError::UnsupportedPublicKey)?;
+            let signature = ed25519_dalek::Signature::from_slice(signature)
+                .map_err(|_| VerificationError::InvalidSignature)?;
+            public_key.verify(&signed_data, &signature)
+        }
+        Some(iana::Algorithm::RS256) => {
+            let public_key = rsa::RsaPublicKey::from_public_key_der(&der)
+                .map_err(|_| VerificationError::UnsupportedPublicKey)?;
+            let signature = rsa::pkcs1v15::Signature::try_from(signature)
+                .map_err(|_| VerificationError::InvalidSignature)?;
+            rsa::pkcs1v15::VerifyingKey::<rsa::sha2::Sha256>::new(public_key)
+                .verify(&signed_data, &signature)
+        }
+        _ => {
+            let public_key = VerifyingKey::from_public_key_der(&der)
+                .map_err(|_| VerificationError::UnsupportedPublicKey)?;
+            let signature =
+                Signature::from_der(signature).map_err(|_| VerificationError::InvalidSignature)?;
+            public_key.verify(&signed_data, &signature)
+        }
+    }
+    .map_err(|_| VerificationError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use coset::iana::{self, EnumI64};
+    use passkey_authenticator::{
+        AttestationInput, AttestationStatementProvider, Authenticator, MemoryStore,
+        MockUserValidationMethod, PackedAttestation,
+    };
+    use passkey_types::{ctap2, rand::random_vec, webauthn};
+    use url::Url;
+
+    const ORIGIN: &str = "https://future.1password.com";
+
+    use super::*;
+    use crate::{Client, DefaultClientData};
+
+    fn creation_options() -> webauthn::CredentialCreationOptions {
+        webauthn::CredentialCreationOptions {
+            public_key: webauthn::PublicKeyCredentialCreationOptions {
+                rp: webauthn::PublicKeyCredentialRpEntity {
+                    id: Some("future.1password.com".into()),
+                    name: "future.1password.com".into(),
+                },
+                user: webauthn::PublicKeyCredentialUserEntity {
+                    id: random_vec(16).into(),
+                    display_name: "wendy".into(),
+                    name: "wendy".into(),
+                },
+                challenge: random_vec(32).into(),
+                pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    alg: iana::Algorithm::ES256,
+                }],
+                timeout: None,
+                exclude_credentials: None,
+                authenticator_selection: None,
+                hints: None,
+                attestation: Default::default(),
+                attestation_formats: None,
+                extensions: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn stored_credential_from_registration() {
+        // Arrange
+        let aaguid = Aaguid([0x2a; 16]);
+        let mut authenticator = Authenticator::new(
+            aaguid,
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        let mut client = Client::new(authenticator);
+        let origin = Url::parse("https://future.1password.com").unwrap();
+
+        let credential = client
+            .register(&origin, creation_options(), DefaultClientData)
+            .await
+            .expect("failed to register with options");
+
+        // Act
+        let stored = StoredCredential::from_registration(&credential)
+            .expect("failed to extract the stored credential");
+
+        // Assert
+        let auth_data = AuthenticatorData::from_slice(&credential.response.authenticator_data)
+            .expect("could not parse authenticator data");
+        let attested_credential_data = auth_data.attested_credential_data.unwrap();
+        assert_eq!(stored.credential_id, credential.raw_id);
+        assert_eq!(stored.public_key, attested_credential_data.key);
+        assert_eq!(stored.sign_count, 0);
+        assert_eq!(
+            stored.transports,
+            vec![
+                AuthenticatorTransport::Hybrid,
+                AuthenticatorTransport::Internal
+            ]
+        );
+        assert_eq!(stored.aaguid, aaguid);
+        assert!(stored.backup_eligible);
+        assert!(stored.backed_up);
+    }
+
+    #[test]
+    fn stored_credential_requires_attested_credential_data() {
+        let auth_data = AuthenticatorData::new("future.1password.com", Some(0));
+        let credential = CreatedPublicKeyCredential {
+            id: String::new(),
+            raw_id: Bytes::from(Vec::new()),
+            ty: webauthn::PublicKeyCredentialType::PublicKey,
+            response: webauthn::AuthenticatorAttestationResponse {
+                client_data_json: Vec::new().into(),
+                authenticator_data: auth_data.to_vec().into(),
+                public_key: None,
+                public_key_algorithm: iana::Algorithm::ES256.to_i64(),
+                attestation_object: Vec::new().into(),
+                transports: None,
+            },
+            authenticator_attachment: None,
+            client_extension_results: Default::default(),
+        };
+
+        let err = StoredCredential::from_registration(&credential)
+            .expect_err("extracted a stored credential without attested credential data");
+
+        assert_eq!(err, VerificationError::MissingAttestedCredentialData);
+    }
+
+    #[test]
+    fn authentication_options_json_encodes_the_challenge_and_allowed_ids() {
+        // Arrange
+        let verifier = Verifier::new("future.1password.com", vec![0xfb; 32])
+            .allowed_origin(ORIGIN)
+            .require_user_verification(true);
+        let stored = StoredCredential {
+            credential_id: vec![0xff, 0xee, 0xdd].into(),
+            public_key: CoseKey::default(),
+            sign_count: 0,
+            transports: vec![AuthenticatorTransport::Internal],
+            aaguid: Aaguid::new_empty(),
+            backup_eligible: true,
+            backed_up: true,
+            enterprise_attestation: false,
+        };
+
+        // Act
+        let options = verifier.authentication_options_json(&[stored]);
+        let discoverable = verifier.authentication_options_json(&[]);
+
+        // Assert
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "challenge": "-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s",
+                "rpId": "future.1password.com",
+                "allowCredentials": [{
+                    "id": "_-7d",
+                    "type": "public-key",
+                    "transports": ["internal"],
+                }],
+                "userVerification": "required",
+            })
+        );
+        assert!(discoverable.allow_credentials.is_none());
+    }
+
+    struct Ceremonies {
+        registration: CreatedPublicKeyCredential,
+        registration_challenge: Bytes,
+        assertion: AuthenticatedPublicKeyCredential,
+        assertion_challenge: Bytes,
+    }
+
+    async fn register_and_authenticate() -> Ceremonies {
+        let mut authenticator = Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        );
+        authenticator.set_make_credentials_with_signature_counter(true);
+        let mut client = Client::new(authenticator);
+        let origin = Url::parse(ORIGIN).unwrap();
+
+        let options = creation_options();
+        let registration_challenge = options.public_key.challenge.clone();
+        let registration = client
+            .register(&origin, options, DefaultClientData)
+            .await
+            .expect("failed to register with options");
+
+        let assertion_challenge: Bytes = random_vec(32).into();
+        let request = webauthn::CredentialRequestOptions {
+            public_key: webauthn::PublicKeyCredentialRequestOptions {
+                challenge: assertion_challenge.clone(),
+                timeout: None,
+                rp_id: Some("future.1password.com".into()),
+                allow_credentials: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: registration.raw_id.clone(),
+                    transports: None,
+                }]),
+                user_verification: Default::default(),
+                hints: None,
+                attestation: Default::default(),
+                attestation_formats: None,
+                extensions: None,
+            },
+        };
+        let assertion = client
+            .authenticate(&origin, request, DefaultClientData)
+            .await
+            .expect("failed to authenticate with freshly created credential");
+
+        Ceremonies {
+            registration,
+            registration_challenge,
+            assertion,
+            assertion_challenge,
+        }
+    }
+
+    fn verifier(challenge: &Bytes) -> Verifier {
+        Verifier::new("future.1password.com", challenge.clone())
+            .allowed_origin(ORIGIN)
+            .require_user_verification(true)
+    }
+
+    #[tokio::test]
+    async fn verifier_accepts_registration_and_assertion() {
+        // Arrange
+        let ceremonies = register_and_authenticate().await;
+
+        // Act
+        let stored = verifier(&ceremonies.registration_challenge)
+            .verify_registration(&ceremonies.registration)
+            .expect("failed to verify the registration");
+        let verified = verifier(&ceremonies.assertion_challenge)
+            .verify_assertion(&ceremonies.assertion, &stored)
+            .expect("failed to verify the assertion");
+
+        // Assert
+        assert_eq!(stored.credential_id, ceremonies.registration.raw_id);
+        assert_eq!(
+            verified,
+            VerifiedAssertion {
+                sign_count: 1,
+                user_verified: true,
+                backed_up: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_wrong_challenge() {
+        let ceremonies = register_and_authenticate().await;
+        let stored = StoredCredential::from_registration(&ceremonies.registration).unwrap();
+
+        let registration = verifier(&ceremonies.assertion_challenge)
+            .verify_registration(&ceremonies.registration)
+            .expect_err("verified a registration with the wrong challenge");
+        let assertion = verifier(&ceremonies.registration_challenge)
+            .verify_assertion(&ceremonies.assertion, &stored)
+            .expect_err("verified an assertion with the wrong challenge");
+
+        assert_eq!(registration, VerificationError::ChallengeMismatch);
+        assert_eq!(assertion, VerificationError::ChallengeMismatch);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_client_data_of_another_ceremony_type() {
+        let ceremonies = register_and_authenticate().await;
+        let mut registration = ceremonies.registration;
+        registration.response.client_data_json =
+            ceremonies.assertion.response.client_data_json.clone();
+
+        let err = verifier(&ceremonies.assertion_challenge)
+            .verify_registration(&registration)
+            .expect_err("verified a registration with assertion client data");
+
+        assert_eq!(err, VerificationError::ClientDataTypeMismatch);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_wrong_origin() {
+        let ceremonies = register_and_authenticate().await;
+        let stored = StoredCredential::from_registration(&ceremonies.registration).unwrap();
+
+        let err = Verifier::new("future.1password.com", ceremonies.assertion_challenge)
+            .allowed_origin("https://1password.com")
+            .verify_assertion(&ceremonies.assertion, &stored)
+            .expect_err("verified an assertion from a disallowed origin");
+
+        assert_eq!(err, VerificationError::OriginNotAllowed);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_wrong_rp_id() {
+        let ceremonies = register_and_authenticate().await;
+
+        let err = Verifier::new("1password.com", ceremonies.registration_challenge)
+            .allowed_origin(ORIGIN)
+            .verify_registration(&ceremonies.registration)
+            .expect_err("verified a registration for another RP ID");
+
+        assert_eq!(err, VerificationError::RpIdHashMismatch);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_bad_signature() {
+        let ceremonies = register_and_authenticate().await;
+        let stored = StoredCredential::from_registration(&ceremonies.registration).unwrap();
+        let mut assertion = ceremonies.assertion;
+        let mut authenticator_data = assertion.response.authenticator_data.to_vec();
+        // Flip a bit of the signature counter, which is covered by the signature.
+        authenticator_data[36] ^= 0x80;
+        assertion.response.authenticator_data = authenticator_data.into();
+
+        let err = verifier(&ceremonies.assertion_challenge)
+            .verify_assertion(&assertion, &stored)
+            .expect_err("verified an assertion with a bad signature");
+
+        assert_eq!(err, VerificationError::InvalidSignature);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_stale_counter() {
+        let ceremonies = register_and_authenticate().await;
+        let stored = StoredCredential {
+            sign_count: 1,
+            ..StoredCredential::from_registration(&ceremonies.registration).unwrap()
+        };
+
+        let err = verifier(&ceremonies.assertion_challenge)
+            .verify_assertion(&ceremonies.assertion, &stored)
+            .expect_err("verified an assertion with a stale counter");
+
+        assert_eq!(err, VerificationError::StaleCounter);
+    }
+
+    #[tokio::test]
+    async fn verifier_reports_the_result_of_every_assertion_of_a_batch() {
+        // Arrange
+        let first = register_and_authenticate().await;
+        let second = register_and_authenticate().await;
+        let first_stored = StoredCredential::from_registration(&first.registration).unwrap();
+        let second_stored = StoredCredential::from_registration(&second.registration).unwrap();
+        let third = register_and_authenticate().await;
+        let third_stored = StoredCredential::from_registration(&third.registration).unwrap();
+        let mut tampered = third.assertion;
+        let mut authenticator_data = tampered.response.authenticator_data.to_vec();
+        authenticator_data[36] ^= 0x80;
+        tampered.response.authenticator_data = authenticator_data.into();
+        let inputs = [
+            AssertionInput {
+                credential: &first.assertion,
+                stored: &first_stored,
+                challenge: &first.assertion_challenge,
+            },
+            AssertionInput {
+                credential: &tampered,
+                stored: &third_stored,
+                challenge: &third.assertion_challenge,
+            },
+            AssertionInput {
+                credential: &second.assertion,
+                stored: &second_stored,
+                challenge: &first.assertion_challenge,
+            },
+            AssertionInput {
+                credential: &second.assertion,
+                stored: &first_stored,
+                challenge: &second.assertion_challenge,
+            },
+            AssertionInput {
+                credential: &second.assertion,
+                stored: &second_stored,
+                challenge: &second.assertion_challenge,
+            },
+        ];
+        let verified = VerifiedAssertion {
+            sign_count: 1,
+            user_verified: true,
+            backed_up: true,
+        };
+        let expected = vec![
+            Ok(verified.clone()),
+            Err(VerificationError::InvalidSignature),
+            Err(VerificationError::ChallengeMismatch),
+            Err(VerificationError::CredentialMismatch),
+            Ok(verified),
+        ];
+
+        // Act
+        let sequential = verifier(&Bytes::from(Vec::new())).verify_assertions(&inputs);
+        let parallel = verifier(&Bytes::from(Vec::new()))
+            .parallelism(2)
+            .verify_assertions(&inputs);
+
+        // Assert
+        assert_eq!(sequential, expected);
+        assert_eq!(parallel, expected);
+    }
+
+    #[tokio::test]
+    async fn verifier_accepts_an_rp_id_hash_of_an_allowed_app_id() {
+        // Arrange
+        const APP_ID: &str = "https://future.1password.com/app-id.json";
+        let mut client = Client::new(Authenticator::new(
+            Aaguid::new_empty(),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(2),
+        ));
+        let registration = client
+            .register(
+                &Url::parse(ORIGIN).unwrap(),
+                creation_options(),
+                DefaultClientData,
+            )
+            .await
+            .expect("failed to register with options");
+        let stored = StoredCredential::from_registration(&registration).unwrap();
+        // Scope the credential to the AppID, as the credentials of U2F were.
+        client
+            .authenticator_mut()
+            .store_mut()
+            .get_mut(&*stored.credential_id)
+            .unwrap()
+            .rp_id = APP_ID.into();
+
+        let challenge: Bytes = random_vec(32).into();
+        let client_data_json = serde_json::json!({
+            "type": "webauthn.get",
+            "challenge": passkey_types::encoding::base64url(&challenge),
+            "origin": ORIGIN,
+        })
+        .to_string();
+        let response = client
+            .authenticator_mut()
+            .get_assertion(ctap2::get_assertion::Request {
+                rp_id: APP_ID.into(),
+                client_data_hash: client_data_hash(client_data_json.as_bytes())
+                    .to_vec()
+                    .into(),
+                allow_list: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                    ty: webauthn::PublicKeyCredentialType::PublicKey,
+                    id: stored.credential_id.clone(),
+                    transports: None,
+                }]),
+                extensions: None,
+                options: ctap2::get_assertion::Options {
+                    rk: false,
+                    up: true,
+                    uv: true,
+                },
+                pin_auth: None,
+                pin_protocol: None,
+            })
+            .await
+            .expect("failed to assert the credential of the AppID");
+        let assertion = AuthenticatedPublicKeyCredential {
+            id: registration.id.clone(),
+            raw_id: registration.raw_id.clone(),
+            ty: webauthn::PublicKeyCredentialType::PublicKey,
+            response: webauthn::AuthenticatorAssertionResponse {
+                client_data_json: client_data_json.into_bytes().into(),
+                authenticator_data: response.auth_data.to_vec().into(),
+                signature: response.signature,
+                user_handle: None,
+                attestation_object: None,
+                transports: None,
+            },
+            authenticator_attachment: None,
+            client_extension_results: Default::default(),
+        };
+
+        // Act
+        let rp_id_only = verifier(&challenge).verify_assertion(&assertion, &stored);
+        let with_app_id = verifier(&challenge)
+            .allowed_rp_id(APP_ID)
+            .verify_assertion(&assertion, &stored);
+
+        // Assert
+        assert_eq!(rp_id_only, Err(VerificationError::RpIdHashMismatch));
+        assert!(with_app_id.is_ok());
+    }
+
+    /// A self-signed DER certificate for an attestation key.
+    fn attestation_certificate(key: &p256::SecretKey) -> Bytes {
+        use p256::ecdsa::{DerSignature, SigningKey};
+        use std::{str::FromStr, time::Duration};
+        use x509_cert::{
+            builder::{Builder, CertificateBuilder, Profile},
+            name::Name,
+            serial_number::SerialNumber,
+            spki::SubjectPublicKeyInfoOwned,
+            time::Validity,
+        };
+
+        let signer = SigningKey::from(key);
+        CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(1u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            Name::from_str("CN=Enterprise,O=1Password,C=CA").unwrap(),
+            SubjectPublicKeyInfoOwned::from_key(*signer.verifying_key()).unwrap(),
+            &signer,
+        )
+        .unwrap()
+        .build::<DerSignature>()
+        .unwrap()
+        .to_der()
+        .unwrap()
+        .into()
+    }
+
+    /// Attests credentials with `enterprise` when an enterprise attestation was granted, and with
+    /// `batch` otherwise.
+    struct EnterpriseAttestation {
+        batch: PackedAttestation,
+        enterprise: PackedAttestation,
+    }
+
+    #[async_trait::async_trait]
+    impl AttestationStatementProvider for EnterpriseAttestation {
+        async fn attestation_statement(
+            &self,
+            input: AttestationInput<'_>,
+        ) -> Result<(String, Value), ctap2::Ctap2Error> {
+            if input.enterprise_attestation {
+                self.enterprise.attestation_statement(input).await
+            } else {
+                self.batch.attestation_statement(input).await
+            }
+        }
+    }
+
+    async fn register_with_attestation(
+        provider: impl AttestationStatementProvider + Send + Sync + 'static,
+        attestation: webauthn::AttestationConveyancePreference,
+    ) -> (CreatedPublicKeyCredential, Bytes) {
+        let mut authenticator = Authenticator::new(
+            Aaguid([0x2a; 16]),
+            MemoryStore::new(),
+            MockUserValidationMethod::verified_user(1),
+        );
+        authenticator.set_attestation_statement_provider(provider);
+        authenticator.set_enterprise_attestation_rp_ids(Some(vec!["future.1password.com".into()]));
+        let mut client = Client::new(authenticator);
+        let mut options = creation_options();
+        options.public_key.attestation = Some(attestation);
+        let challenge = options.public_key.challenge.clone();
+
+        let credential = client
+            .register(&Url::parse(ORIGIN).unwrap(), options, DefaultClientData)
+            .await
+            .expect("failed to register with attestation");
+        (credential, challenge)
+    }
+
+    #[tokio::test]
+    async fn verifier_reports_verified_enterprise_attestations() {
+        // Arrange
+        let batch_key = p256::SecretKey::from_slice(&[1; 32]).unwrap();
+        let enterprise_key = p256::SecretKey::from_slice(&[2; 32]).unwrap();
+        let batch_certificate = attestation_certificate(&batch_key);
+        let enterprise_certificate = attestation_certificate(&enterprise_key);
+        let provider = || EnterpriseAttestation {
+            batch: PackedAttestation::new(vec![batch_certificate.clone()], batch_key.clone()),
+            enterprise: PackedAttestation::new(
+                vec![enterprise_certificate.clone()],
+                enterprise_key.clone(),
+            ),
+        };
+        let (released, released_challenge) = register_with_attestation(
+            provider(),
+            webauthn::AttestationConveyancePreference::Enterprise,
+        )
+        .await;
+        let (direct, direct_challenge) = register_with_attestation(
+            provider(),
+            webauthn::AttestationConveyancePreference::Direct,
+        )
+        .await;
+        let enterprise_verifier = |challenge: &Bytes| {
+            verifier(challenge).enterprise_attestation_certificate(
+                Aaguid([0x2a; 16]),
+                enterprise_certificate.clone(),
+            )
+        };
+
+        // Act
+        let enterprise = enterprise_verifier(&released_challenge)
+            .verify_registration(&released)
+            .expect("failed to verify the enterprise attestation");
+        let batch = enterprise_verifier(&direct_challenge)
+            .verify_registration(&direct)
+            .expect("failed to verify the batch attestation");
+        let untrusted = verifier(&released_challenge)
+            .verify_registration(&released)
+            .expect("failed to verify the registration");
+        let other_aaguid = verifier(&released_challenge)
+            .enterprise_attestation_certificate(Aaguid::new_empty(), enterprise_certificate.clone())
+            .verify_registration(&released)
+            .expect("failed to verify the registration");
+
+        // Assert
+        assert!(enterprise.enterprise_attestation);
+        assert!(!batch.enterprise_attestation);
+        assert!(!untrusted.enterprise_attestation);
+        assert!(!other_aaguid.enterprise_attestation);
+    }
+
+    #[tokio::test]
+    async fn verifier_rejects_enterprise_attestations_not_signed_by_the_certificate() {
+        // Arrange
+        let enterprise_key = p256::SecretKey::from_slice(&[2; 32]).unwrap();
+        let enterprise_certificate = attestation_certificate(&enterprise_key);
+        let forged = PackedAttestation::new(
+            vec![enterprise_certificate.clone()],
+            p256::SecretKey::from_slice(&[3; 32]).unwrap(),
+        );
+        let (credential, challenge) = register_with_attestation(
+            forged,
+            webauthn::AttestationConveyancePreference::Enterprise,
+        )
+        .await;
+
+        // Act
+        let err = verifier(&challenge)
+            .enterprise_attestation_certificate(Aaguid([0x2a; 16]), enterprise_certificate)
+            .verify_registration(&credential)
+            .expect_err("verified an enterprise attestation signed by another key");
+
+        // Assert
+        assert_eq!(err, VerificationError::InvalidAttestationSignature);
+    }
+}
@@ -1,8 +1,11 @@
+use std::collections::HashMap;
+
 use super::*;
 use coset::iana;
-use passkey_authenticator::{MemoryStore, MockUserValidationMethod, UserCheck};
+use passkey_authenticator::{MemoryStore, MockUserValidationMethod, StoreInfo, UserCheck};
 use passkey_types::{
-    ctap2, encoding::try_from_base64url, rand::random_vec, webauthn::CollectedClientData, Bytes,
+    crypto::sha256, ctap2, encoding::try_from_base64url, rand::random_vec,
+    webauthn::CollectedClientData, Bytes,
 };
 use serde::Deserialize;
 use url::{ParseError, Url};
@@ -105,6 +108,432 @@ async fn create_and_authenticate() {
         .expect("failed to authenticate with freshly created credential");
 }
 
+#[tokio::test]
+async fn user_handle_is_only_returned_without_allowed_credentials() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(3),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let creation_options = webauthn::PublicKeyCredentialCreationOptions {
+        authenticator_selection: Some(AuthenticatorSelectionCriteria {
+            resident_key: Some(ResidentKeyRequirement::Required),
+            ..Default::default()
+        }),
+        ..good_credential_creation_options()
+    };
+    let user_handle = creation_options.user.id.clone();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: creation_options,
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let allowed_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id.clone()),
+    };
+    let discoverable_options = webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            allow_credentials: None,
+            ..good_credential_request_options(cred.raw_id)
+        },
+    };
+
+    // Act
+    let allowed = client
+        .authenticate(&origin, allowed_options, DefaultClientData)
+        .await;
+    let discovered = client
+        .authenticate(&origin, discoverable_options, DefaultClientData)
+        .await;
+
+    // Assert
+    assert_eq!(allowed.unwrap().response.user_handle, None);
+    assert_eq!(discovered.unwrap().response.user_handle, Some(user_handle));
+}
+
+#[tokio::test]
+async fn authenticate_for_user_rejects_a_credential_of_another_user() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(3),
+    );
+    let mut client = Client::new(auth).always_return_user_handle(true);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let creation_options = good_credential_creation_options();
+    let user_handle = creation_options.user.id.clone();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: creation_options,
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let auth_options = || webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id.clone()),
+    };
+
+    // Act
+    let mismatched = client
+        .authenticate_for_user(&origin, auth_options(), DefaultClientData, &[0xff; 16])
+        .await;
+    let matching = client
+        .authenticate_for_user(&origin, auth_options(), DefaultClientData, &user_handle)
+        .await;
+
+    // Assert
+    assert_eq!(mismatched.unwrap_err(), WebauthnError::UserHandleMismatch);
+    assert_eq!(matching.unwrap().response.user_handle, Some(user_handle));
+}
+
+#[tokio::test]
+async fn authenticate_prunes_the_credentials_longer_than_the_authenticator_accepts() {
+    // Arrange
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    auth.set_max_credential_id_length(Some(64));
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    // The same credential under an ID the authenticator would not accept, which would be found
+    // if it reached the authenticator.
+    let long_id: Bytes = vec![0xaa; 65].into();
+    let mut long_passkey = client.authenticator().store()[&*cred.raw_id].clone();
+    long_passkey.credential_id = long_id.clone();
+    client
+        .authenticator_mut()
+        .store_mut()
+        .insert(long_id.to_vec(), long_passkey);
+    let descriptor = |id: &Bytes| webauthn::PublicKeyCredentialDescriptor {
+        ty: webauthn::PublicKeyCredentialType::PublicKey,
+        id: id.clone(),
+        transports: None,
+    };
+    let auth_options = |allow_list| webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            allow_credentials: Some(allow_list),
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
+    };
+
+    // Act
+    let mixed = client
+        .authenticate(
+            &origin,
+            auth_options(vec![descriptor(&long_id), descriptor(&cred.raw_id)]),
+            DefaultClientData,
+        )
+        .await;
+    let only_long = client
+        .authenticate(
+            &origin,
+            auth_options(vec![descriptor(&long_id)]),
+            DefaultClientData,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(
+        client
+            .authenticator()
+            .get_info()
+            .await
+            .max_credential_id_length,
+        Some(64)
+    );
+    assert_eq!(mixed.unwrap().raw_id, cred.raw_id);
+    assert_eq!(only_long.unwrap_err(), WebauthnError::CredentialNotFound);
+}
+
+#[tokio::test]
+async fn register_reports_the_algorithm_of_a_public_key_without_alg() {
+    // Arrange
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    auth.set_public_key_alg_included(false);
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+
+    // Act
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+
+    // Assert
+    assert_eq!(
+        cred.response.public_key_algorithm,
+        iana::Algorithm::ES256.to_i64()
+    );
+    assert!(cred.response.public_key.is_some());
+}
+
+#[tokio::test]
+async fn authenticate_falls_back_to_the_app_id_of_u2f_credentials() {
+    // Arrange
+    const APP_ID: &str = "https://future.1password.com/app-id.json";
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(4),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let mut credential_ids = Vec::new();
+    for _ in 0..2 {
+        let cred = client
+            .register(
+                &origin,
+                webauthn::CredentialCreationOptions {
+                    public_key: good_credential_creation_options(),
+                },
+                DefaultClientData,
+            )
+            .await
+            .expect("failed to register with options");
+        credential_ids.push(cred.raw_id);
+    }
+    let [u2f_id, webauthn_id] = <[Bytes; 2]>::try_from(credential_ids).unwrap();
+    // Scope the first credential to the AppID, as the credentials of U2F were.
+    client
+        .authenticator_mut()
+        .store_mut()
+        .get_mut(&*u2f_id)
+        .unwrap()
+        .rp_id = APP_ID.into();
+    let auth_options = |credential_id: &Bytes| webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            extensions: Some(webauthn::AuthenticationExtensionsClientInputs {
+                cred_props: None,
+                prf: None,
+                credential_protection_policy: None,
+                enforce_credential_protection_policy: None,
+                appid: Some(APP_ID.into()),
+                uvm: None,
+                device_pub_key: None,
+                large_blob: None,
+            }),
+            ..good_credential_request_options(credential_id.clone())
+        },
+    };
+
+    // Act
+    let u2f = client
+        .authenticate(&origin, auth_options(&u2f_id), DefaultClientData)
+        .await
+        .expect("failed to authenticate with the AppID");
+    let webauthn = client
+        .authenticate(&origin, auth_options(&webauthn_id), DefaultClientData)
+        .await
+        .expect("failed to authenticate with the RP ID");
+
+    // Assert
+    assert_eq!(u2f.client_extension_results.appid, Some(true));
+    assert_eq!(
+        &u2f.response.authenticator_data[..32],
+        sha256(APP_ID.as_bytes())
+    );
+    assert_eq!(webauthn.client_extension_results.appid, Some(false));
+    assert_eq!(
+        &webauthn.response.authenticator_data[..32],
+        sha256(b"future.1password.com")
+    );
+}
+
+/// A [`MemoryStore`] which records the credential IDs of every lookup made in it.
+#[derive(Default)]
+struct ProbedStore {
+    store: MemoryStore,
+    lookups: std::sync::Mutex<Vec<Vec<Bytes>>>,
+}
+
+#[async_trait::async_trait]
+impl CredentialStore for ProbedStore {
+    type PasskeyItem = Passkey;
+
+    async fn find_credentials(
+        &self,
+        ids: Option<&[webauthn::PublicKeyCredentialDescriptor]>,
+        rp_id: &str,
+    ) -> Result<Vec<Passkey>, ctap2::StatusCode> {
+        self.lookups.lock().unwrap().push(
+            ids.unwrap_or_default()
+                .iter()
+                .map(|descriptor| descriptor.id.clone())
+                .collect(),
+        );
+        self.store.find_credentials(ids, rp_id).await
+    }
+
+    async fn save_credential(
+        &mut self,
+        cred: Passkey,
+        user: ctap2::make_credential::PublicKeyCredentialUserEntity,
+        rp: ctap2::make_credential::PublicKeyCredentialRpEntity,
+        options: ctap2::get_assertion::Options,
+    ) -> Result<(), ctap2::StatusCode> {
+        self.store.save_credential(cred, user, rp, options).await
+    }
+
+    async fn update_credential(&mut self, cred: Passkey) -> Result<(), ctap2::StatusCode> {
+        self.store.update_credential(cred).await
+    }
+
+    async fn get_info(&self) -> StoreInfo {
+        self.store.get_info().await
+    }
+}
+
+#[tokio::test]
+async fn allow_list_probing_stops_at_the_first_stored_credential() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        ProbedStore::default(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let preferred = [cred.raw_id, random_vec(16).into(), random_vec(16).into()];
+    let mut request = good_credential_request_options(preferred[0].clone());
+    request.allow_credentials = Some(
+        preferred
+            .iter()
+            .map(|id| webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id: id.clone(),
+                transports: None,
+            })
+            .collect(),
+    );
+    request.extensions = Some(webauthn::AuthenticationExtensionsClientInputs {
+        cred_props: None,
+        prf: None,
+        credential_protection_policy: None,
+        enforce_credential_protection_policy: None,
+        appid: Some("https://future.1password.com/app-id.json".into()),
+        uvm: None,
+        device_pub_key: None,
+        large_blob: None,
+    });
+    client
+        .authenticator()
+        .store()
+        .lookups
+        .lock()
+        .unwrap()
+        .clear();
+
+    // Act
+    let res = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: request,
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with the preferred credential");
+
+    // Assert
+    assert_eq!(res.raw_id, preferred[0]);
+    assert_eq!(res.client_extension_results.appid, Some(false));
+    // Probing for a credential of the RP ID before falling back to the AppID only looks the
+    // first credential up, the authenticator then receives the whole allow list.
+    let lookups = client.authenticator().store().lookups.lock().unwrap();
+    assert_eq!(lookups[0], [preferred[0].clone()]);
+    assert_eq!(lookups[1], preferred);
+}
+
+#[tokio::test]
+async fn authenticate_rejects_an_app_id_of_another_site() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let options = webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            extensions: Some(webauthn::AuthenticationExtensionsClientInputs {
+                cred_props: None,
+                prf: None,
+                credential_protection_policy: None,
+                enforce_credential_protection_policy: None,
+                appid: Some("https://example.com/app-id.json".into()),
+                uvm: None,
+                device_pub_key: None,
+                large_blob: None,
+            }),
+            ..good_credential_request_options(cred.raw_id)
+        },
+    };
+
+    // Act
+    let result = client
+        .authenticate(&origin, options, DefaultClientData)
+        .await;
+
+    // Assert
+    assert_eq!(result.unwrap_err(), WebauthnError::OriginRpMissmatch);
+}
+
 #[tokio::test]
 async fn create_and_authenticate_with_extra_client_data() {
     #[derive(Clone, Serialize, Deserialize)]
@@ -174,92 +603,166 @@ async fn create_and_authenticate_with_extra_client_data() {
 }
 
 #[tokio::test]
-async fn create_and_authenticate_with_origin_subdomain() {
+async fn extra_client_data_is_produced_for_the_rp_id_and_origin() {
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PaymentClientData {
+        payment_rp_id: String,
+        payment_origin: String,
+    }
+    struct PaymentClientDataProducer;
+    impl ClientData<PaymentClientData> for PaymentClientDataProducer {
+        fn extra_client_data(&self) -> PaymentClientData {
+            self.extra_client_data_for("", "")
+        }
+
+        fn extra_client_data_for(&self, rp_id: &str, origin: &str) -> PaymentClientData {
+            PaymentClientData {
+                payment_rp_id: rp_id.to_owned(),
+                payment_origin: origin.to_owned(),
+            }
+        }
+
+        fn client_data_hash(&self) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    // Arrange
     let auth = Authenticator::new(
         ctap2::Aaguid::new_empty(),
         MemoryStore::new(),
-        uv_mock_with_creation(2),
+        uv_mock_with_creation(1),
     );
     let mut client = Client::new(auth);
-
-    let origin = Url::parse("https://www.future.1password.com").unwrap();
+    let origin = Url::parse("https://future.1password.com").unwrap();
     let options = webauthn::CredentialCreationOptions {
         public_key: good_credential_creation_options(),
     };
+
+    // Act
     let cred = client
-        .register(&origin, options, DefaultClientData)
+        .register(&origin, options, PaymentClientDataProducer)
         .await
         .expect("failed to register with options");
 
-    let att_obj: ctap2::make_credential::Response =
-        ciborium::de::from_reader(cred.response.attestation_object.as_slice())
-            .expect("could not deserialize response");
-    assert_eq!(
-        att_obj.auth_data.rp_id_hash(),
-        &sha256(b"future.1password.com")
-    );
+    // Assert
+    let client_data: CollectedClientData<PaymentClientData> =
+        serde_json::from_slice(&cred.response.client_data_json)
+            .expect("could not json deserialize client data");
+    assert_eq!(client_data.extra_data.payment_rp_id, "future.1password.com");
+    assert_eq!(client_data.extra_data.payment_origin, client_data.origin);
+    assert_eq!(client_data.origin, "https://future.1password.com");
+}
 
-    let auth_options = webauthn::CredentialRequestOptions {
-        public_key: good_credential_request_options(cred.raw_id),
+#[tokio::test]
+async fn cross_origin_client_data_reports_the_top_origin() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let client_data_json = |json: Bytes| -> serde_json::Value {
+        serde_json::from_slice(&json).expect("could not json deserialize client data")
     };
-    let res = client
-        .authenticate(&origin, auth_options, DefaultClientData)
+
+    // Act
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
         .await
-        .expect("failed to authenticate with freshly created credential");
-    let att_obj = ctap2::AuthenticatorData::from_slice(&res.response.authenticator_data)
-        .expect("could not deserialize response");
-    assert_eq!(att_obj.rp_id_hash(), &sha256(b"future.1password.com"));
+        .expect("failed to register with options");
+    let framed = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(cred.raw_id.clone()),
+            },
+            CrossOriginClientData {
+                client_data: DefaultClientData,
+                top_origin: "https://shop.example.com".into(),
+            },
+        )
+        .await
+        .expect("failed to authenticate from a cross-origin iframe");
+
+    // Assert
+    let top_level = client_data_json(cred.response.client_data_json);
+    assert_eq!(top_level["crossOrigin"], false);
+    assert!(top_level.get("topOrigin").is_none());
+    let framed = client_data_json(framed.response.client_data_json);
+    assert_eq!(framed["crossOrigin"], true);
+    assert_eq!(framed["topOrigin"], "https://shop.example.com");
 }
 
 #[tokio::test]
-async fn create_and_authenticate_without_rp_id() {
+async fn canonical_client_data_extensions_are_encoded_identically() {
+    #[derive(Clone, Serialize)]
+    struct Extensions {
+        zeta: bool,
+        alpha: serde_json::Value,
+    }
+    #[derive(Clone, Serialize)]
+    struct ReorderedExtensions {
+        alpha: serde_json::Value,
+        zeta: bool,
+    }
     let auth = Authenticator::new(
         ctap2::Aaguid::new_empty(),
         MemoryStore::new(),
         uv_mock_with_creation(2),
     );
-    let mut client = Client::new(auth);
-
-    let origin = Url::parse("https://www.future.1password.com").unwrap();
-    let options = webauthn::CredentialCreationOptions {
+    let mut client = Client::new(auth).canonicalize_client_data_extensions(true);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let challenge: Bytes = random_vec(32).into();
+    let options = || webauthn::CredentialCreationOptions {
         public_key: webauthn::PublicKeyCredentialCreationOptions {
-            rp: webauthn::PublicKeyCredentialRpEntity {
-                id: None,
-                name: "future.1password.com".into(),
-            },
+            challenge: challenge.clone(),
             ..good_credential_creation_options()
         },
     };
-    let cred = client
-        .register(&origin, options, DefaultClientData)
-        .await
-        .expect("failed to register with options");
 
-    let att_obj: ctap2::make_credential::Response =
-        ciborium::de::from_reader(cred.response.attestation_object.as_slice())
-            .expect("could not deserialize response");
+    let first = client
+        .register(
+            &origin,
+            options(),
+            DefaultClientDataWithExtra(Extensions {
+                zeta: true,
+                alpha: serde_json::json!({ "b": 2, "a": 1 }),
+            }),
+        )
+        .await
+        .expect("failed to register with options");
+    let second = client
+        .register(
+            &origin,
+            options(),
+            DefaultClientDataWithExtra(ReorderedExtensions {
+                alpha: serde_json::json!({ "a": 1, "b": 2 }),
+                zeta: true,
+            }),
+        )
+        .await
+        .expect("failed to register with options");
+
     assert_eq!(
-        att_obj.auth_data.rp_id_hash(),
-        &sha256(b"www.future.1password.com")
+        first.response.client_data_json,
+        second.response.client_data_json
     );
-
-    let auth_options = webauthn::CredentialRequestOptions {
-        public_key: webauthn::PublicKeyCredentialRequestOptions {
-            rp_id: None,
-            ..good_credential_request_options(cred.raw_id)
-        },
-    };
-    let res = client
-        .authenticate(&origin, auth_options, DefaultClientData)
-        .await
-        .expect("failed to authenticate with freshly created credential");
-    let att_obj = ctap2::AuthenticatorData::from_slice(&res.response.authenticator_data)
-        .expect("could not deserialize response");
-    assert_eq!(att_obj.rp_id_hash(), &sha256(b"www.future.1password.com"));
+    let client_data_json = String::from_utf8(first.response.client_data_json.into()).unwrap();
+    assert!(client_data_json.ends_with(r#""alpha":{"a":1,"b":2},"zeta":true}"#));
 }
 
 #[tokio::test]
-async fn create_and_authenticate_without_cred_params() {
+async fn create_and_discover_credential_with_empty_user_name() {
     let auth = Authenticator::new(
         ctap2::Aaguid::new_empty(),
         MemoryStore::new(),
@@ -268,188 +771,2819 @@ async fn create_and_authenticate_without_cred_params() {
     let mut client = Client::new(auth);
 
     let origin = Url::parse("https://future.1password.com").unwrap();
+    let creation_options = good_credential_creation_options();
+    let user_handle = creation_options.user.id.clone();
     let options = webauthn::CredentialCreationOptions {
         public_key: webauthn::PublicKeyCredentialCreationOptions {
-            pub_key_cred_params: Vec::new(),
-            ..good_credential_creation_options()
+            user: webauthn::PublicKeyCredentialUserEntity {
+                name: String::new(),
+                ..creation_options.user
+            },
+            authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                resident_key: Some(ResidentKeyRequirement::Required),
+                ..Default::default()
+            }),
+            ..creation_options
         },
     };
     let cred = client
         .register(&origin, options, DefaultClientData)
         .await
-        .expect("failed to register with options");
+        .expect("failed to register with an empty user name");
 
-    let credential_id = cred.raw_id;
+    let stored = client
+        .authenticator()
+        .store()
+        .get(&*cred.raw_id)
+        .expect("the credential was not stored");
+    assert_eq!(stored.user_handle, Some(user_handle.clone()));
 
     let auth_options = webauthn::CredentialRequestOptions {
-        public_key: good_credential_request_options(credential_id),
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            allow_credentials: None,
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
     };
-    client
+    let result = client
         .authenticate(&origin, auth_options, DefaultClientData)
         .await
-        .expect("failed to authenticate with freshly created credential");
-}
+        .expect("failed to discover the credential with an empty user name");
 
-#[test]
-fn validate_rp_id() -> Result<(), ParseError> {
-    let client = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER);
+    assert_eq!(result.raw_id, cred.raw_id);
+    assert_eq!(result.response.user_handle, Some(user_handle));
+}
 
-    let example = Url::parse("https://example.com")?.into();
-    let com_tld = client.assert_domain(&example, Some("com"));
-    assert_eq!(com_tld, Err(WebauthnError::InvalidRpId));
+async fn register_with_cred_props(
+    store: impl CredentialStore<PasskeyItem = Passkey> + Send + Sync,
+    resident_key: ResidentKeyRequirement,
+) -> webauthn::CreatedPublicKeyCredential {
+    let auth = Authenticator::new(ctap2::Aaguid::new_empty(), store, uv_mock_with_creation(1));
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                resident_key: Some(resident_key),
+                ..Default::default()
+            }),
+            extensions: Some(webauthn::AuthenticationExtensionsClientInputs {
+                cred_props: Some(true),
+                prf: None,
+                credential_protection_policy: None,
+                enforce_credential_protection_policy: None,
+                appid: None,
+                uvm: None,
+                device_pub_key: None,
+                large_blob: None,
+            }),
+            ..good_credential_creation_options()
+        },
+    };
 
-    let example_dots = Url::parse("https://example...com")?.into();
-    let bunch_of_dots = client.assert_domain(&example_dots, Some("...com"));
-    assert_eq!(bunch_of_dots, Err(WebauthnError::InvalidRpId));
+    client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+}
 
-    let future = Url::parse("https://www.future.1password.com")?.into();
-    let sub_domain_ignored = client.assert_domain(&future, Some("future.1password.com"));
-    assert_eq!(sub_domain_ignored, Ok("future.1password.com"));
+#[tokio::test]
+async fn cred_props_reports_a_discoverable_credential_when_resident_key_is_required() {
+    // Act
+    let cred = register_with_cred_props(MemoryStore::new(), ResidentKeyRequirement::Required).await;
 
-    let use_effective_domain = client.assert_domain(&future, None);
-    assert_eq!(use_effective_domain, Ok("www.future.1password.com"));
+    // Assert
+    let cred_props = cred
+        .client_extension_results
+        .cred_props
+        .expect("credProps was requested");
+    assert_eq!(cred_props.discoverable, Some(true));
+}
 
-    let not_protected = Url::parse("http://example.com")?.into();
-    let not_https = client.assert_domain(&not_protected, Some("example.com"));
-    assert_eq!(not_https, Err(WebauthnError::UnprotectedOrigin));
+/// A store that keeps credentials the way they were requested, unlike the [`MemoryStore`] it wraps
+/// which makes every credential discoverable.
+struct FullStore(MemoryStore);
 
-    let localhost = Url::parse("http://localhost:8080")?.into();
-    let should_still_match = client.assert_domain(&localhost, Some("example.com"));
-    assert_eq!(should_still_match, Err(WebauthnError::OriginRpMissmatch));
+#[async_trait::async_trait]
+impl CredentialStore for FullStore {
+    type PasskeyItem = Passkey;
 
-    let localhost_not_allowed = client.assert_domain(&localhost, Some("localhost"));
-    assert_eq!(
-        localhost_not_allowed,
-        Err(WebauthnError::InsecureLocalhostNotAllowed)
-    );
-    let localhost_not_allowed = client.assert_domain(&localhost, None);
-    assert_eq!(
-        localhost_not_allowed,
-        Err(WebauthnError::InsecureLocalhostNotAllowed)
-    );
+    async fn find_credentials(
+        &self,
+        ids: Option<&[webauthn::PublicKeyCredentialDescriptor]>,
+        rp_id: &str,
+    ) -> Result<Vec<Passkey>, ctap2::StatusCode> {
+        self.0.find_credentials(ids, rp_id).await
+    }
 
-    let client = client.allows_insecure_localhost(true);
-    let skips_http_and_tld_check = client.assert_domain(&localhost, Some("localhost"));
-    assert_eq!(skips_http_and_tld_check, Ok("localhost"));
-    let skips_http_and_tld_check = client.assert_domain(&localhost, None);
-    assert_eq!(skips_http_and_tld_check, Ok("localhost"));
+    async fn save_credential(
+        &mut self,
+        cred: Passkey,
+        _user: ctap2::make_credential::PublicKeyCredentialUserEntity,
+        _rp: ctap2::make_credential::PublicKeyCredentialRpEntity,
+        _options: ctap2::get_assertion::Options,
+    ) -> Result<(), ctap2::StatusCode> {
+        self.0.insert(cred.credential_id.to_vec(), cred);
+        Ok(())
+    }
 
-    Ok(())
-}
+    async fn update_credential(&mut self, cred: Passkey) -> Result<(), ctap2::StatusCode> {
+        self.0.update_credential(cred).await
+    }
 
-struct BrokenTLDProvider {}
-impl public_suffix::EffectiveTLDProvider for BrokenTLDProvider {
-    // Notice that this just returns Err() for every domain regardless.
-    // This is only done to allow the test's assertion to prove that we
-    // are actually using this verifier and not the default one.
-    fn effective_tld_plus_one<'a>(
-        &self,
-        _domain: &'a str,
-    ) -> Result<&'a str, public_suffix::Error> {
-        Err(public_suffix::Error::CannotDeriveETldPlus1)
+    async fn get_info(&self) -> StoreInfo {
+        StoreInfo {
+            discoverability: DiscoverabilitySupport::Full,
+        }
     }
 }
-#[test]
-fn validate_domain_with_private_list_provider() -> Result<(), ParseError> {
-    let my_custom_provider = BrokenTLDProvider {};
-    let client = RpIdVerifier::new(my_custom_provider);
 
-    // Notice that, in this test, this is a legitimate origin/rp_id combination
-    // We assert that this produces an error to prove that we are indeed using our
-    // BrokenTLDProvider which always returns Err() regardless of the TLD.
-    let origin = Url::parse("https://www.future.1password.com")?.into();
-    let rp_id = "future.1password.com";
-    let result = client.assert_domain(&origin, Some(rp_id));
-    assert_eq!(result, Err(WebauthnError::InvalidRpId));
+#[tokio::test]
+async fn cred_props_reports_what_the_store_did_with_a_discouraged_resident_key() {
+    // Act
+    let forced =
+        register_with_cred_props(MemoryStore::new(), ResidentKeyRequirement::Discouraged).await;
+    let full = register_with_cred_props(
+        FullStore(MemoryStore::new()),
+        ResidentKeyRequirement::Discouraged,
+    )
+    .await;
 
-    Ok(())
+    // Assert
+    let discoverable = |cred: webauthn::CreatedPublicKeyCredential| {
+        cred.client_extension_results
+            .cred_props
+            .and_then(|cred_props| cred_props.discoverable)
+    };
+    assert_eq!(discoverable(forced), Some(true));
+    assert_eq!(discoverable(full), Some(false));
 }
 
-fn user_mock_with_uv() -> MockUserValidationMethod {
-    let mut user_mock = MockUserValidationMethod::new();
-    user_mock
-        .expect_is_verification_enabled()
-        .returning(|| Some(true));
-    user_mock.expect_check_user().returning(|_, _, _| {
-        Ok(UserCheck {
-            presence: true,
-            verification: true,
-        })
-    });
-    // Always called by `get_info`
-    user_mock
-        .expect_is_verification_enabled()
-        .returning(|| Some(true));
-    user_mock.expect_is_presence_enabled().returning(|| true);
-    user_mock
-}
+#[tokio::test]
+async fn memory_store_credentials_are_discoverable_without_resident_key() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let creation_options = good_credential_creation_options();
+    let user_handle = creation_options.user.id.clone();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                resident_key: Some(ResidentKeyRequirement::Discouraged),
+                ..Default::default()
+            }),
+            ..creation_options
+        },
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
 
-fn user_mock_without_uv() -> MockUserValidationMethod {
-    let mut user_mock = MockUserValidationMethod::new();
-    user_mock.expect_check_user().returning(|_, _, _| {
-        Ok(UserCheck {
-            presence: true,
-            verification: false,
-        })
-    });
-    // Always called by `get_info`
-    user_mock
-        .expect_is_verification_enabled()
-        .returning(|| Some(true));
-    user_mock.expect_is_presence_enabled().returning(|| true);
-    user_mock
+    // Act
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            allow_credentials: None,
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
+    };
+    let result = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to discover the credential");
+
+    // Assert
+    assert_eq!(result.raw_id, cred.raw_id);
+    assert_eq!(result.response.user_handle, Some(user_handle));
 }
 
 #[tokio::test]
-async fn client_register_triggers_uv_when_uv_is_required() {
+async fn credentials_are_not_discoverable_when_residency_is_not_requested() {
     // Arrange
     let auth = Authenticator::new(
         ctap2::Aaguid::new_empty(),
-        MemoryStore::new(),
-        user_mock_with_uv(),
+        FullStore(MemoryStore::new()),
+        uv_mock_with_creation(2),
     );
     let mut client = Client::new(auth);
     let origin = Url::parse("https://future.1password.com").unwrap();
-    let mut options = webauthn::CredentialCreationOptions {
-        public_key: good_credential_creation_options(),
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            authenticator_selection: None,
+            ..good_credential_creation_options()
+        },
     };
-    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
-        user_verification: UserVerificationRequirement::Required,
-        authenticator_attachment: Default::default(),
-        resident_key: Default::default(),
-        require_resident_key: Default::default(),
-    });
-
-    // Act & Assert
-    client
+    let cred = client
         .register(&origin, options, DefaultClientData)
         .await
         .expect("failed to register with options");
+
+    // Act
+    let listed = client
+        .authenticator()
+        .store()
+        .list_credentials(Some("future.1password.com"))
+        .await
+        .unwrap();
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            allow_credentials: None,
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
+    };
+    let result = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await;
+
+    // Assert
+    let stored = client.authenticator().store().0.get(&*cred.raw_id).unwrap();
+    assert_eq!(stored.user_handle, None);
+    assert!(listed.is_empty());
+    assert_eq!(result.unwrap_err(), WebauthnError::CredentialNotFound);
 }
 
 #[tokio::test]
-async fn client_register_does_not_trigger_uv_when_uv_is_discouraged() {
+async fn result_transforms_are_applied_to_the_returned_credentials() {
     // Arrange
     let auth = Authenticator::new(
         ctap2::Aaguid::new_empty(),
         MemoryStore::new(),
-        user_mock_without_uv(),
+        uv_mock_with_creation(2),
     );
-    let mut client = Client::new(auth);
+    let mut client = Client::new(auth)
+        .map_registration_result(|cred| webauthn::CreatedPublicKeyCredential {
+            authenticator_attachment: None,
+            ..cred
+        })
+        .map_authentication_result(|cred| webauthn::AuthenticatedPublicKeyCredential {
+            id: format!("envelope:{}", cred.id),
+            ..cred
+        });
     let origin = Url::parse("https://future.1password.com").unwrap();
-    let mut options = webauthn::CredentialCreationOptions {
+    let options = webauthn::CredentialCreationOptions {
         public_key: good_credential_creation_options(),
     };
-    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
-        user_verification: UserVerificationRequirement::Discouraged,
-        authenticator_attachment: Default::default(),
-        resident_key: Default::default(),
-        require_resident_key: Default::default(),
-    });
 
-    // Act & Assert
-    client
+    // Act
+    let cred = client
         .register(&origin, options, DefaultClientData)
         .await
         .expect("failed to register with options");
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id.clone()),
+    };
+    let result = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+
+    // Assert
+    assert_eq!(cred.authenticator_attachment, None);
+    assert_eq!(result.id, format!("envelope:{}", cred.id));
+    assert_eq!(result.raw_id, cred.raw_id);
+}
+
+#[tokio::test]
+async fn packed_attestation_is_only_returned_when_direct_attestation_is_requested() {
+    // Arrange
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    auth.set_attestation_statement_provider(passkey_authenticator::PackedAttestation::new(
+        vec![vec![0x30, 0x00].into()],
+        p256::SecretKey::from_slice(&[7; 32]).unwrap(),
+    ));
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let fmt = |cred: &webauthn::CreatedPublicKeyCredential| {
+        let attestation_object: ciborium::Value =
+            ciborium::de::from_reader(cred.response.attestation_object.as_slice()).unwrap();
+        attestation_object
+            .into_map()
+            .unwrap()
+            .into_iter()
+            .find(|(key, _)| key.as_text() == Some("fmt"))
+            .and_then(|(_, value)| value.into_text().ok())
+            .unwrap()
+    };
+
+    // Act
+    let direct = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    attestation: Some(webauthn::AttestationConveyancePreference::Direct),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with direct attestation");
+    let without_attestation = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register without attestation");
+
+    // Assert
+    assert_eq!(fmt(&direct), "packed");
+    assert_eq!(fmt(&without_attestation), "none");
+}
+
+#[tokio::test]
+async fn default_attestation_applies_only_when_the_options_omit_it() {
+    // Arrange
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    auth.set_attestation_statement_provider(passkey_authenticator::SelfAttestation);
+    let mut client = Client::new(auth)
+        .with_default_attestation(webauthn::AttestationConveyancePreference::Direct);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let fmt = |cred: &webauthn::CreatedPublicKeyCredential| {
+        let attestation_object: ciborium::Value =
+            ciborium::de::from_reader(cred.response.attestation_object.as_slice()).unwrap();
+        attestation_object
+            .into_map()
+            .unwrap()
+            .into_iter()
+            .find(|(key, _)| key.as_text() == Some("fmt"))
+            .and_then(|(_, value)| value.into_text().ok())
+            .unwrap()
+    };
+
+    // Act
+    let omitted = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    attestation: None,
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with the default attestation");
+    let explicit_none = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    attestation: Some(webauthn::AttestationConveyancePreference::None),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register without attestation");
+
+    // Assert
+    assert_eq!(fmt(&omitted), "packed");
+    assert_eq!(fmt(&explicit_none), "none");
+}
+
+#[tokio::test]
+async fn enterprise_attestation_is_requested_from_enabled_authenticators() {
+    // Arrange
+    let aaguid = ctap2::Aaguid::from_uuid_str("ee882879-721c-4913-9775-3dfcce97072a").unwrap();
+    let mut auth = Authenticator::new(aaguid, MemoryStore::new(), uv_mock_with_creation(2));
+    auth.set_attestation_statement_provider(passkey_authenticator::SelfAttestation);
+    auth.set_enterprise_attestation_rp_ids(Some(vec!["future.1password.com".into()]));
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let attested_aaguid = |cred: webauthn::CreatedPublicKeyCredential| {
+        ctap2::AuthenticatorData::from_slice(&cred.response.authenticator_data)
+            .unwrap()
+            .attested_credential_data
+            .unwrap()
+            .aaguid
+    };
+
+    // Act
+    let enterprise = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    attestation: Some(webauthn::AttestationConveyancePreference::Enterprise),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with enterprise attestation");
+    let direct = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    attestation: Some(webauthn::AttestationConveyancePreference::Direct),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with direct attestation");
+
+    // Assert
+    assert_eq!(attested_aaguid(enterprise), aaguid);
+    assert_eq!(attested_aaguid(direct), ctap2::Aaguid::new_empty());
+}
+
+#[tokio::test]
+async fn rejected_challenges_abort_the_ceremony_before_prompting() {
+    // Arrange
+    let rejected_challenge = random_vec(32);
+    let rejected = rejected_challenge.clone();
+    // The user is only prompted for the ceremonies with an accepted challenge.
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client =
+        Client::new(auth).with_challenge_validator(move |challenge| challenge != rejected);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with an accepted challenge");
+
+    // Act
+    let registration = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    challenge: rejected_challenge.clone().into(),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await;
+    let authentication = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: webauthn::PublicKeyCredentialRequestOptions {
+                    challenge: rejected_challenge.into(),
+                    ..good_credential_request_options(cred.raw_id.clone())
+                },
+            },
+            DefaultClientData,
+        )
+        .await;
+    let accepted = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(cred.raw_id),
+            },
+            DefaultClientData,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(registration.unwrap_err(), WebauthnError::InvalidChallenge);
+    assert_eq!(authentication.unwrap_err(), WebauthnError::InvalidChallenge);
+    assert!(accepted.is_ok());
+    assert_eq!(client.authenticator().store().len(), 1);
+}
+
+#[tokio::test]
+async fn uvm_extension_outputs_are_passed_through_to_the_relying_party() {
+    // Arrange
+    let mut user_mock = uv_mock_with_creation(2);
+    user_mock
+        .expect_verification_method()
+        .returning(|_| Some(passkey_authenticator::VerificationMethod::PasscodeInternal))
+        .once();
+    let auth = Authenticator::new(ctap2::Aaguid::new_empty(), MemoryStore::new(), user_mock);
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register without uvm");
+
+    // Act
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: webauthn::PublicKeyCredentialRequestOptions {
+                    extensions: Some(webauthn::AuthenticationExtensionsClientInputs {
+                        cred_props: None,
+                        prf: None,
+                        credential_protection_policy: None,
+                        enforce_credential_protection_policy: None,
+                        appid: None,
+                        uvm: Some(true),
+                        device_pub_key: None,
+                        large_blob: None,
+                    }),
+                    ..good_credential_request_options(cred.raw_id.clone())
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with uvm");
+
+    // Assert
+    assert_eq!(cred.client_extension_results.uvm, None);
+    // USER_VERIFY_PASSCODE_INTERNAL, KEY_PROTECTION_SOFTWARE, MATCHER_PROTECTION_SOFTWARE
+    assert_eq!(
+        assertion.client_extension_results.uvm,
+        Some(vec![[0x0004, 0x0001, 0x0001]])
+    );
+}
+
+#[tokio::test]
+async fn device_pub_key_extension_outputs_are_passed_through_to_the_relying_party() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let extensions = || webauthn::AuthenticationExtensionsClientInputs {
+        cred_props: None,
+        prf: None,
+        credential_protection_policy: None,
+        enforce_credential_protection_policy: None,
+        appid: None,
+        uvm: None,
+        device_pub_key: Some(webauthn::AuthenticationExtensionsDevicePublicKeyInputs::default()),
+        large_blob: None,
+    };
+
+    // Act
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    extensions: Some(extensions()),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with devicePubKey");
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: webauthn::PublicKeyCredentialRequestOptions {
+                    extensions: Some(extensions()),
+                    ..good_credential_request_options(cred.raw_id.clone())
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with devicePubKey");
+
+    // Assert
+    let attestation = |output: Option<webauthn::AuthenticationExtensionsDevicePublicKeyOutputs>| {
+        let output = output.expect("missing devicePubKey output");
+        ctap2::DevicePublicKeyAttestation::from_slice(&output.authenticator_output)
+            .expect("could not parse the devicePubKey output")
+    };
+    let created = attestation(cred.client_extension_results.device_pub_key);
+    let asserted = attestation(assertion.client_extension_results.device_pub_key);
+    assert_eq!(created.fmt, "none");
+    assert_eq!(created.dpk, asserted.dpk);
+}
+
+#[tokio::test]
+async fn audit_hook_receives_a_redacted_record_of_each_ceremony() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    let records = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = records.clone();
+    let mut client =
+        Client::new(auth).with_audit_hook(move |record| sink.lock().unwrap().push(record));
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let mistaken_options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            rp: webauthn::PublicKeyCredentialRpEntity {
+                id: Some("1password.ca".into()),
+                name: "1password.ca".into(),
+            },
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+    let mismatch = client
+        .register(&origin, mistaken_options, DefaultClientData)
+        .await;
+
+    // Assert
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    let record = &records[0];
+    assert_eq!(record.operation, CeremonyOperation::Registration);
+    assert_eq!(record.origin, "https://future.1password.com");
+    assert_eq!(record.rp_id.as_deref(), Some("future.1password.com"));
+    assert_eq!(record.algorithm, Some(iana::Algorithm::ES256.to_i64()));
+    assert_eq!(record.user_verified, Some(true));
+    assert_eq!(
+        record.credential_id_hash,
+        Some(encoding::base64url(&sha256(&cred.raw_id)))
+    );
+    assert_eq!(record.outcome, audit::CeremonyOutcome::Success);
+    assert!(record.timestamp > 0);
+
+    let passkey = client.authenticator().store().values().next().unwrap();
+    let private_key = passkey_authenticator::private_key_from_cose_key(&passkey.key).unwrap();
+    let serialized = serde_json::to_string(record).unwrap();
+    assert!(!serialized.contains(&encoding::base64url(&private_key.to_bytes())));
+    assert!(!serialized.contains(&cred.id));
+
+    assert_eq!(
+        mismatch.expect_err("registered for another RP"),
+        WebauthnError::OriginRpMissmatch
+    );
+    assert_eq!(records[1].rp_id, None);
+    assert_eq!(
+        records[1].outcome,
+        audit::CeremonyOutcome::Failure {
+            error: "OriginRpMissmatch".into()
+        }
+    );
+}
+
+#[tokio::test]
+async fn verify_own_assertions_catches_a_corrupted_private_key() {
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(3),
+    );
+    let mut client = Client::new(auth).verify_own_assertions(true);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let credential_id = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+        .raw_id;
+
+    client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(credential_id.clone()),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with an intact credential");
+
+    // Replace the private key while keeping its public coordinates.
+    let passkey = client
+        .authenticator_mut()
+        .store_mut()
+        .get_mut(&*credential_id)
+        .unwrap();
+    for (label, value) in passkey.key.params.iter_mut() {
+        if *label == coset::Label::Int(iana::Ec2KeyParameter::D.to_i64()) {
+            *value = random_vec(32).into();
+        }
+    }
+
+    let err = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(credential_id),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect_err("returned an assertion signed with a corrupted key");
+
+    assert_eq!(err, WebauthnError::InvalidAssertionSignature);
+}
+
+#[tokio::test]
+async fn create_and_authenticate_with_origin_subdomain() {
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+
+    let origin = Url::parse("https://www.future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    let att_obj: ctap2::make_credential::Response =
+        ciborium::de::from_reader(cred.response.attestation_object.as_slice())
+            .expect("could not deserialize response");
+    assert_eq!(
+        att_obj.auth_data.rp_id_hash(),
+        &sha256(b"future.1password.com")
+    );
+
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id),
+    };
+    let res = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+    let att_obj = ctap2::AuthenticatorData::from_slice(&res.response.authenticator_data)
+        .expect("could not deserialize response");
+    assert_eq!(att_obj.rp_id_hash(), &sha256(b"future.1password.com"));
+}
+
+#[tokio::test]
+async fn create_and_authenticate_without_rp_id() {
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+
+    let origin = Url::parse("https://www.future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            rp: webauthn::PublicKeyCredentialRpEntity {
+                id: None,
+                name: "future.1password.com".into(),
+            },
+            ..good_credential_creation_options()
+        },
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    let att_obj: ctap2::make_credential::Response =
+        ciborium::de::from_reader(cred.response.attestation_object.as_slice())
+            .expect("could not deserialize response");
+    assert_eq!(
+        att_obj.auth_data.rp_id_hash(),
+        &sha256(b"www.future.1password.com")
+    );
+
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            rp_id: None,
+            ..good_credential_request_options(cred.raw_id)
+        },
+    };
+    let res = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+    let att_obj = ctap2::AuthenticatorData::from_slice(&res.response.authenticator_data)
+        .expect("could not deserialize response");
+    assert_eq!(att_obj.rp_id_hash(), &sha256(b"www.future.1password.com"));
+}
+
+#[tokio::test]
+async fn authenticate_without_rp_id_derives_the_same_rp_id_as_registration() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        // The failed attempt still informs the user that no credential was found
+        uv_mock_with_creation(3),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://www.future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            rp: webauthn::PublicKeyCredentialRpEntity {
+                id: None,
+                name: "future.1password.com".into(),
+            },
+            ..good_credential_creation_options()
+        },
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+    let auth_options = || webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            rp_id: None,
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
+    };
+
+    // Act
+    let parent_origin = Url::parse("https://future.1password.com").unwrap();
+    let from_parent = client
+        .authenticate(&parent_origin, auth_options(), DefaultClientData)
+        .await;
+    let from_subdomain = client
+        .authenticate(&origin, auth_options(), DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+
+    // Assert
+    // The parent domain derives a different RP ID than the one the credential is bound to.
+    assert_eq!(
+        from_parent.expect_err("authenticated for a different RP ID"),
+        WebauthnError::CredentialNotFound
+    );
+    let registration_data = ctap2::AuthenticatorData::from_slice(&cred.response.authenticator_data)
+        .expect("could not deserialize registration authenticator data");
+    let assertion_data =
+        ctap2::AuthenticatorData::from_slice(&from_subdomain.response.authenticator_data)
+            .expect("could not deserialize assertion authenticator data");
+    assert_eq!(assertion_data.rp_id_hash(), registration_data.rp_id_hash());
+    assert_eq!(
+        assertion_data.rp_id_hash(),
+        &sha256(b"www.future.1password.com")
+    );
+}
+
+#[tokio::test]
+async fn credentials_are_shared_between_related_origins_of_the_rp_id() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let registration_origin = Url::parse("https://1password.ca").unwrap();
+    let authentication_origin = Url::parse("https://1password.eu").unwrap();
+    let mut client = Client::new(auth).with_related_origins(
+        "future.1password.com",
+        [registration_origin.clone(), authentication_origin.clone()],
+    );
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let cred = client
+        .register(&registration_origin, options, DefaultClientData)
+        .await
+        .expect("failed to register from a related origin");
+
+    // Act
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id.clone()),
+    };
+    let res = client
+        .authenticate(&authentication_origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate from another related origin");
+
+    // Assert
+    let auth_data = ctap2::AuthenticatorData::from_slice(&res.response.authenticator_data)
+        .expect("could not deserialize response");
+    assert_eq!(res.raw_id, cred.raw_id);
+    assert_eq!(auth_data.rp_id_hash(), &sha256(b"future.1password.com"));
+    let client_data: CollectedClientData =
+        serde_json::from_slice(&res.response.client_data_json).unwrap();
+    assert_eq!(client_data.origin, "https://1password.eu");
+}
+
+#[test]
+fn related_origins_only_apply_to_their_rp_id() {
+    let verifier = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER).with_related_origins(
+        "future.1password.com",
+        [Url::parse("https://1password.ca").unwrap()],
+    );
+    let related = Url::parse("https://1password.ca").unwrap().into();
+    let unrelated = Url::parse("https://1password.eu").unwrap().into();
+    let other_port = Url::parse("https://1password.ca:8443").unwrap().into();
+
+    assert_eq!(
+        verifier.assert_domain(&related, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&unrelated, Some("future.1password.com")),
+        Err(WebauthnError::OriginRpMissmatch)
+    );
+    assert_eq!(
+        verifier.assert_domain(&other_port, Some("future.1password.com")),
+        Err(WebauthnError::OriginRpMissmatch)
+    );
+    assert_eq!(
+        verifier.assert_domain(&related, Some("other.1password.com")),
+        Err(WebauthnError::OriginRpMissmatch)
+    );
+}
+
+/// Serves the related origins documents of RP IDs without a network.
+struct FakeRelatedOriginsFetcher(HashMap<&'static str, &'static str>);
+
+#[async_trait::async_trait]
+impl RelatedOriginsFetcher for FakeRelatedOriginsFetcher {
+    async fn fetch_related_origins(&self, rp_id: &str) -> Option<Vec<u8>> {
+        self.0
+            .get(rp_id)
+            .map(|document| document.as_bytes().to_vec())
+    }
+}
+
+#[tokio::test]
+async fn fetched_related_origins_are_accepted_for_their_rp_id() {
+    // Arrange
+    let fetcher = FakeRelatedOriginsFetcher(HashMap::from([
+        (
+            "future.1password.com",
+            r#"{"origins": ["https://1password.ca", "not an origin", "https://1password.eu"]}"#,
+        ),
+        ("broken.1password.com", r#"{"related": []}"#),
+    ]));
+
+    // Act
+    let verifier = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+        .fetch_related_origins("future.1password.com", &fetcher)
+        .await
+        .expect("failed to parse the related origins")
+        .fetch_related_origins("unlisted.1password.com", &fetcher)
+        .await
+        .expect("a missing document relates no origin");
+    let broken = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+        .fetch_related_origins("broken.1password.com", &fetcher)
+        .await;
+
+    // Assert
+    let canada = Url::parse("https://1password.ca").unwrap().into();
+    let europe = Url::parse("https://1password.eu").unwrap().into();
+    assert_eq!(
+        verifier.assert_domain(&canada, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&europe, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&canada, Some("unlisted.1password.com")),
+        Err(WebauthnError::OriginRpMissmatch)
+    );
+    assert_eq!(broken.err(), Some(WebauthnError::InvalidRelatedOrigins));
+}
+
+#[test]
+fn related_origins_beyond_the_label_limit_are_rejected() {
+    // Arrange
+    let document = br#"{"origins": [
+        "https://one.com",
+        "https://www.two.com",
+        "https://three.com",
+        "https://four.com",
+        "https://five.com",
+        "https://one.co.uk",
+        "https://six.com"
+    ]}"#;
+
+    // Act
+    let verifier = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+        .with_related_origins_document("future.1password.com", document)
+        .expect("failed to parse the related origins");
+
+    // Assert
+    let fifth_label = Url::parse("https://five.com").unwrap().into();
+    let known_label = Url::parse("https://one.co.uk").unwrap().into();
+    let sixth_label = Url::parse("https://six.com").unwrap().into();
+    assert_eq!(
+        verifier.assert_domain(&fifth_label, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&known_label, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&sixth_label, Some("future.1password.com")),
+        Err(WebauthnError::OriginRpMissmatch)
+    );
+}
+
+#[tokio::test]
+async fn create_and_authenticate_without_cred_params() {
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            pub_key_cred_params: Vec::new(),
+            ..good_credential_creation_options()
+        },
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    let credential_id = cred.raw_id;
+
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(credential_id),
+    };
+    client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+}
+
+#[test]
+fn validate_rp_id() -> Result<(), ParseError> {
+    let client = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER);
+
+    let example = Url::parse("https://example.com")?.into();
+    let com_tld = client.assert_domain(&example, Some("com"));
+    assert_eq!(com_tld, Err(WebauthnError::InvalidRpId));
+
+    let example_dots = Url::parse("https://example...com")?.into();
+    let bunch_of_dots = client.assert_domain(&example_dots, Some("...com"));
+    assert_eq!(bunch_of_dots, Err(WebauthnError::InvalidRpId));
+
+    let future = Url::parse("https://www.future.1password.com")?.into();
+    let sub_domain_ignored = client.assert_domain(&future, Some("future.1password.com"));
+    assert_eq!(sub_domain_ignored, Ok("future.1password.com"));
+
+    let use_effective_domain = client.assert_domain(&future, None);
+    assert_eq!(use_effective_domain, Ok("www.future.1password.com"));
+
+    let not_protected = Url::parse("http://example.com")?.into();
+    let not_https = client.assert_domain(&not_protected, Some("example.com"));
+    assert_eq!(not_https, Err(WebauthnError::UnprotectedOrigin));
+
+    let localhost = Url::parse("http://localhost:8080")?.into();
+    let should_still_match = client.assert_domain(&localhost, Some("example.com"));
+    assert_eq!(should_still_match, Err(WebauthnError::OriginRpMissmatch));
+
+    let localhost_not_allowed = client.assert_domain(&localhost, Some("localhost"));
+    assert_eq!(
+        localhost_not_allowed,
+        Err(WebauthnError::InsecureLocalhostNotAllowed)
+    );
+    let localhost_not_allowed = client.assert_domain(&localhost, None);
+    assert_eq!(
+        localhost_not_allowed,
+        Err(WebauthnError::InsecureLocalhostNotAllowed)
+    );
+
+    let client = client.allows_insecure_localhost(true);
+    let skips_http_and_tld_check = client.assert_domain(&localhost, Some("localhost"));
+    assert_eq!(skips_http_and_tld_check, Ok("localhost"));
+    let skips_http_and_tld_check = client.assert_domain(&localhost, None);
+    assert_eq!(skips_http_and_tld_check, Ok("localhost"));
+
+    Ok(())
+}
+
+#[test]
+fn validate_domain_rejects_origins_that_are_not_web_origins() -> Result<(), ParseError> {
+    let client = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER);
+
+    let hostless = Url::parse("file:///future.1password.com/index.html")?.into();
+    let no_host = client.assert_domain(&hostless, Some("future.1password.com"));
+    assert_eq!(no_host, Err(WebauthnError::InvalidOrigin));
+
+    let data = Url::parse("data:text/html,<h1>future.1password.com</h1>")?.into();
+    let opaque = client.assert_domain(&data, None);
+    assert_eq!(opaque, Err(WebauthnError::InvalidOrigin));
+
+    let websocket = Url::parse("wss://future.1password.com")?.into();
+    let not_web = client.assert_domain(&websocket, Some("future.1password.com"));
+    assert_eq!(not_web, Err(WebauthnError::InvalidOrigin));
+
+    let ip_address = Url::parse("https://127.0.0.1")?.into();
+    let no_domain = client.assert_domain(&ip_address, None);
+    assert_eq!(no_domain, Err(WebauthnError::OriginMissingDomain));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn register_fails_with_invalid_origin_for_a_data_url() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        MockUserValidationMethod::verified_user(0),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("data:text/html,<h1>future.1password.com</h1>").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+
+    // Act
+    let result = client.register(&origin, options, DefaultClientData).await;
+
+    // Assert
+    assert_eq!(
+        result.expect_err("registered from a data URL"),
+        WebauthnError::InvalidOrigin
+    );
+    assert!(client.authenticator().store().is_empty());
+}
+
+struct BrokenTLDProvider {}
+impl public_suffix::EffectiveTLDProvider for BrokenTLDProvider {
+    // Notice that this just returns Err() for every domain regardless.
+    // This is only done to allow the test's assertion to prove that we
+    // are actually using this verifier and not the default one.
+    fn effective_tld_plus_one<'a>(
+        &self,
+        _domain: &'a str,
+    ) -> Result<&'a str, public_suffix::Error> {
+        Err(public_suffix::Error::CannotDeriveETldPlus1)
+    }
+}
+#[test]
+fn validate_domain_with_private_list_provider() -> Result<(), ParseError> {
+    let my_custom_provider = BrokenTLDProvider {};
+    let client = RpIdVerifier::new(my_custom_provider);
+
+    // Notice that, in this test, this is a legitimate origin/rp_id combination
+    // We assert that this produces an error to prove that we are indeed using our
+    // BrokenTLDProvider which always returns Err() regardless of the TLD.
+    let origin = Url::parse("https://www.future.1password.com")?.into();
+    let rp_id = "future.1password.com";
+    let result = client.assert_domain(&origin, Some(rp_id));
+    assert_eq!(result, Err(WebauthnError::InvalidRpId));
+
+    Ok(())
+}
+
+#[test]
+fn exact_origin_rp_id_matches_can_bypass_the_tld_provider() -> Result<(), ParseError> {
+    // Arrange
+    let strict = RpIdVerifier::new(BrokenTLDProvider {});
+    let lenient = RpIdVerifier::new(BrokenTLDProvider {}).allows_exact_origin_rp_id_match(true);
+    let origin = Url::parse("https://future.1password.com")?.into();
+    let subdomain = Url::parse("https://www.future.1password.com")?.into();
+    let rp_id = "future.1password.com";
+
+    // Act & Assert
+    assert_eq!(
+        strict.assert_domain(&origin, Some(rp_id)),
+        Err(WebauthnError::InvalidRpId)
+    );
+    assert_eq!(lenient.assert_domain(&origin, Some(rp_id)), Ok(rp_id));
+    assert_eq!(lenient.assert_domain(&origin, None), Ok(rp_id));
+    assert_eq!(
+        lenient.assert_domain(&subdomain, Some(rp_id)),
+        Err(WebauthnError::InvalidRpId)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn allowed_schemes_bypass_the_https_requirement() -> Result<(), ParseError> {
+    // Arrange
+    let verifier = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+        .allows_schemes(&["https", "chrome-extension"]);
+    let extension = Url::parse("chrome-extension://future.1password.com")?.into();
+    let web = Url::parse("https://future.1password.com")?.into();
+    let http = Url::parse("http://future.1password.com")?.into();
+    let public_suffix = Url::parse("chrome-extension://com")?.into();
+    let rp_id = "future.1password.com";
+
+    // Act & Assert
+    assert_eq!(verifier.assert_domain(&extension, Some(rp_id)), Ok(rp_id));
+    assert_eq!(verifier.assert_domain(&web, Some(rp_id)), Ok(rp_id));
+    assert_eq!(
+        verifier.assert_domain(&http, Some(rp_id)),
+        Err(WebauthnError::UnprotectedOrigin)
+    );
+    assert_eq!(
+        verifier.assert_domain(&public_suffix, None),
+        Err(WebauthnError::InvalidRpId)
+    );
+    assert_eq!(
+        RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER).assert_domain(&extension, Some(rp_id)),
+        Err(WebauthnError::InvalidOrigin)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn android_origins_are_verified_against_their_trusted_rp_ids() -> Result<(), ParseError> {
+    // Arrange
+    // The SHA-256 fingerprint 14:6D:E9:83:C5:73:06:50:D8:EE:B9:95:2F:34:FC:64:16:A0:83:42:E6:1D:BE:A8:8A:04:96:B2:3F:CF:44:E5
+    let fingerprint = [
+        0x14, 0x6d, 0xe9, 0x83, 0xc5, 0x73, 0x06, 0x50, 0xd8, 0xee, 0xb9, 0x95, 0x2f, 0x34, 0xfc,
+        0x64, 0x16, 0xa0, 0x83, 0x42, 0xe6, 0x1d, 0xbe, 0xa8, 0x8a, 0x04, 0x96, 0xb2, 0x3f, 0xcf,
+        0x44, 0xe5,
+    ];
+    let verifier = RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+        .with_android_apk_key_hash("future.1password.com", fingerprint);
+    let origin =
+        Url::parse("android:apk-key-hash:FG3pg8VzBlDY7rmVLzT8ZBagg0LmHb6oigSWsj_PROU")?.into();
+
+    // Act & Assert
+    assert_eq!(
+        verifier.assert_domain(&origin, Some("future.1password.com")),
+        Ok("future.1password.com")
+    );
+    assert_eq!(
+        verifier.assert_domain(&origin, Some("1password.com")),
+        Err(WebauthnError::UntrustedAndroidOrigin)
+    );
+    assert_eq!(
+        RpIdVerifier::new(public_suffix::DEFAULT_PROVIDER)
+            .assert_domain(&origin, Some("future.1password.com")),
+        Err(WebauthnError::UntrustedAndroidOrigin)
+    );
+
+    Ok(())
+}
+
+fn user_mock_with_uv() -> MockUserValidationMethod {
+    let mut user_mock = MockUserValidationMethod::new();
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| Some(true));
+    user_mock.expect_check_user().returning(|_, _, _| {
+        Ok(UserCheck {
+            presence: true,
+            verification: true,
+        })
+    });
+    // Always called by `get_info`
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| Some(true));
+    user_mock.expect_is_presence_enabled().returning(|| true);
+    user_mock
+}
+
+fn user_mock_without_uv() -> MockUserValidationMethod {
+    let mut user_mock = MockUserValidationMethod::new();
+    user_mock.expect_check_user().returning(|_, _, _| {
+        Ok(UserCheck {
+            presence: true,
+            verification: false,
+        })
+    });
+    // Always called by `get_info`
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| Some(true));
+    user_mock.expect_is_presence_enabled().returning(|| true);
+    user_mock
+}
+
+#[tokio::test]
+async fn client_register_triggers_uv_when_uv_is_required() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_mock_with_uv(),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let mut options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
+        user_verification: UserVerificationRequirement::Required,
+        authenticator_attachment: Default::default(),
+        resident_key: Default::default(),
+        require_resident_key: Default::default(),
+    });
+
+    // Act & Assert
+    client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+}
+
+#[tokio::test]
+async fn client_register_does_not_trigger_uv_when_uv_is_discouraged() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_mock_without_uv(),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let mut options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
+        user_verification: UserVerificationRequirement::Discouraged,
+        authenticator_attachment: Default::default(),
+        resident_key: Default::default(),
+        require_resident_key: Default::default(),
+    });
+
+    // Act & Assert
+    client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+}
+
+fn user_mock_with_presence_only(times: usize) -> MockUserValidationMethod {
+    let mut user_mock = MockUserValidationMethod::new();
+    user_mock
+        .expect_check_user()
+        .with(
+            mockall::predicate::always(),
+            mockall::predicate::eq(true),
+            mockall::predicate::eq(false),
+        )
+        .returning(|_, _, _| {
+            Ok(UserCheck {
+                presence: true,
+                verification: false,
+            })
+        })
+        .times(times);
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| None);
+    user_mock.expect_is_presence_enabled().returning(|| true);
+    user_mock
+}
+
+async fn register_without_uv(
+    client: &mut Client<MemoryStore, MockUserValidationMethod, public_suffix::PublicSuffixList>,
+    origin: &Url,
+) -> Bytes {
+    let mut options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
+        user_verification: UserVerificationRequirement::Discouraged,
+        authenticator_attachment: Default::default(),
+        resident_key: Default::default(),
+        require_resident_key: Default::default(),
+    });
+
+    client
+        .register(origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+        .raw_id
+}
+
+#[tokio::test]
+async fn client_authenticate_fails_when_uv_is_required_but_authenticator_only_has_presence() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        // Only the registration prompts the user
+        user_mock_with_presence_only(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let credential_id = register_without_uv(&mut client, &origin).await;
+    let mut auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(credential_id),
+    };
+    auth_options.public_key.user_verification = UserVerificationRequirement::Required;
+
+    // Act
+    let result = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await;
+
+    // Assert
+    assert_eq!(
+        result.expect_err("authenticated without user verification"),
+        WebauthnError::AuthenticatorError(ctap2::Ctap2Error::OperationDenied.into())
+    );
+}
+
+#[tokio::test]
+async fn client_authenticate_falls_back_to_presence_when_uv_is_preferred() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_mock_with_presence_only(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let credential_id = register_without_uv(&mut client, &origin).await;
+    let mut auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(credential_id),
+    };
+    auth_options.public_key.user_verification = UserVerificationRequirement::Preferred;
+
+    // Act
+    let credential = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with presence only");
+
+    // Assert
+    let auth_data = ctap2::AuthenticatorData::from_slice(&credential.response.authenticator_data)
+        .expect("could not parse authenticator data");
+    assert!(auth_data.flags.user_present());
+    assert!(!auth_data.flags.user_verified());
+}
+
+/// A user validation method which always verifies the user, like a device that always asks for
+/// biometrics, even when verification was not requested.
+#[tokio::test]
+async fn authenticate_fails_gracefully_with_a_corrupted_stored_key() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let stored = client
+        .authenticator_mut()
+        .store_mut()
+        .get_mut(&*cred.raw_id)
+        .expect("missing the registered credential");
+    for (_, value) in stored.key.params.iter_mut() {
+        if let ciborium::Value::Bytes(bytes) = value {
+            bytes.truncate(7);
+        }
+    }
+
+    // Act
+    let result = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(cred.raw_id.clone()),
+            },
+            DefaultClientData,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(
+        result.unwrap_err(),
+        WebauthnError::AuthenticatorError(ctap2::Ctap2Error::InvalidCredential.into())
+    );
+    assert_eq!(
+        client.authenticator().store()[&*cred.raw_id].last_used_at,
+        None
+    );
+}
+
+fn user_mock_always_verifying(times: usize) -> MockUserValidationMethod {
+    let mut user_mock = MockUserValidationMethod::new();
+    user_mock
+        .expect_check_user()
+        .with(
+            mockall::predicate::always(),
+            mockall::predicate::eq(true),
+            mockall::predicate::eq(false),
+        )
+        .returning(|_, _, _| {
+            Ok(UserCheck {
+                presence: true,
+                verification: true,
+            })
+        })
+        .times(times);
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| Some(true));
+    user_mock.expect_is_presence_enabled().returning(|| true);
+    user_mock
+}
+
+#[tokio::test]
+async fn uv_flag_reflects_verification_performed_although_discouraged() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_mock_always_verifying(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let mut options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    options.public_key.authenticator_selection = Some(AuthenticatorSelectionCriteria {
+        user_verification: UserVerificationRequirement::Discouraged,
+        authenticator_attachment: Default::default(),
+        resident_key: Default::default(),
+        require_resident_key: Default::default(),
+    });
+
+    // Act
+    let registration = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with uv discouraged");
+    let mut auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(registration.raw_id.clone()),
+    };
+    auth_options.public_key.user_verification = UserVerificationRequirement::Discouraged;
+    let assertion = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with uv discouraged");
+
+    // Assert
+    let registration_data =
+        ctap2::AuthenticatorData::from_slice(&registration.response.authenticator_data)
+            .expect("could not parse authenticator data");
+    let assertion_data =
+        ctap2::AuthenticatorData::from_slice(&assertion.response.authenticator_data)
+            .expect("could not parse authenticator data");
+    assert!(registration_data.flags.user_verified());
+    assert!(assertion_data.flags.user_verified());
+}
+
+/// A user validation method which yields back to the runtime while "prompting" the user, keeping
+/// track of how many prompts were shown at once.
+#[derive(Clone, Default)]
+struct OverlapTrackingUserValidation {
+    in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    max_in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl UserValidationMethod for OverlapTrackingUserValidation {
+    type PasskeyItem = Passkey;
+
+    async fn check_user<'a>(
+        &self,
+        _hint: passkey_authenticator::UIHint<'a, Self::PasskeyItem>,
+        _presence: bool,
+        _verification: bool,
+    ) -> Result<UserCheck, ctap2::Ctap2Error> {
+        use std::sync::atomic::Ordering;
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(UserCheck {
+            presence: true,
+            verification: true,
+        })
+    }
+
+    async fn is_verification_enabled(&self) -> Option<bool> {
+        Some(true)
+    }
+
+    async fn is_presence_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn concurrent_authentications_sharing_a_ceremony_lock_are_serialized() {
+    // Arrange
+    let store = std::sync::Arc::new(tokio::sync::Mutex::new(MemoryStore::new()));
+    let user_validation = OverlapTrackingUserValidation::default();
+    let lock = CeremonyLock::new();
+    let new_client = || {
+        let mut auth = Authenticator::new(
+            ctap2::Aaguid::new_empty(),
+            store.clone(),
+            user_validation.clone(),
+        );
+        auth.set_make_credentials_with_signature_counter(true);
+        Client::new(auth).with_ceremony_lock(lock.clone())
+    };
+    let mut first_client = new_client();
+    let mut second_client = new_client();
+
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let credential_id = first_client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+        .raw_id;
+    let auth_options = || webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(credential_id.clone()),
+    };
+
+    // Act
+    let (first, second) = tokio::join!(
+        first_client.authenticate(&origin, auth_options(), DefaultClientData),
+        second_client.authenticate(&origin, auth_options(), DefaultClientData),
+    );
+
+    // Assert
+    first.expect("first authentication failed");
+    second.expect("second authentication failed");
+    assert_eq!(
+        user_validation
+            .max_in_flight
+            .load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+    let counter = store
+        .lock()
+        .await
+        .get(credential_id.as_slice())
+        .and_then(|passkey| passkey.counter);
+    assert_eq!(counter, Some(2));
+}
+
+#[tokio::test]
+async fn authenticate_reports_the_transports_of_the_used_credential() {
+    // Arrange
+    let transports = vec![
+        webauthn::AuthenticatorTransport::Nfc,
+        webauthn::AuthenticatorTransport::Usb,
+    ];
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    )
+    .transports(transports.clone());
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    // Act
+    let auth_options = webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(cred.raw_id),
+    };
+    let assertion = client
+        .authenticate(&origin, auth_options, DefaultClientData)
+        .await
+        .expect("failed to authenticate with freshly created credential");
+
+    // Assert
+    assert_eq!(cred.response.transports.as_ref(), Some(&transports));
+    assert_eq!(assertion.response.transports, Some(transports));
+}
+
+#[tokio::test]
+async fn register_reports_the_authenticator_transports_in_lexicographical_order() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+
+    // Act
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+    let json = serde_json::to_value(&cred).unwrap();
+    let deserialized: webauthn::CreatedPublicKeyCredential =
+        serde_json::from_value(json.clone()).expect("could not deserialize the registration");
+
+    // Assert
+    assert_eq!(
+        json["response"]["transports"],
+        serde_json::json!(["hybrid", "internal"])
+    );
+    assert_eq!(
+        deserialized.response.transports,
+        Some(vec![
+            webauthn::AuthenticatorTransport::Hybrid,
+            webauthn::AuthenticatorTransport::Internal,
+        ])
+    );
+}
+
+#[tokio::test]
+async fn assertion_signature_covers_the_client_data_hash() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let credential_id = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+        .raw_id;
+
+    // Act
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(credential_id.clone()),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with freshly created credential");
+
+    // Assert
+    let passkey = client.authenticator().store().get(&*credential_id).unwrap();
+    relying_party::verify_signature(
+        &passkey.key,
+        &assertion.response.authenticator_data,
+        &client_data_hash(&assertion.response.client_data_json),
+        &assertion.response.signature,
+    )
+    .expect("the signature does not cover the client data hash");
+}
+
+#[tokio::test]
+async fn eddsa_credentials_are_registered_and_sign_assertions() {
+    use coset::CborSerializable;
+    use ed25519_dalek::pkcs8::DecodePublicKey;
+
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth).verify_own_assertions(true);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::EdDSA,
+            }],
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let credential = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with only EdDSA offered");
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(credential.raw_id.clone()),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with an EdDSA credential");
+
+    // Assert
+    assert_eq!(
+        credential.response.public_key_algorithm,
+        iana::Algorithm::EdDSA.to_i64()
+    );
+    let cose_key = coset::CoseKey::from_slice(&credential.response.cose_public_key().unwrap())
+        .expect("the public key is not a COSE key");
+    assert_eq!(
+        cose_key.kty,
+        coset::RegisteredLabel::Assigned(iana::KeyType::OKP)
+    );
+
+    let public_key =
+        ed25519_dalek::VerifyingKey::from_public_key_der(&credential.response.public_key.unwrap())
+            .expect("the public key is not an Ed25519 SubjectPublicKeyInfo");
+    let signature = ed25519_dalek::Signature::from_slice(&assertion.response.signature)
+        .expect("the signature is not a raw Ed25519 signature");
+    let mut signed_data = assertion.response.authenticator_data.to_vec();
+    signed_data.extend(client_data_hash(&assertion.response.client_data_json));
+    public_key
+        .verify_strict(&signed_data, &signature)
+        .expect("the assertion signature is not valid for the registered public key");
+}
+
+#[tokio::test]
+async fn rs256_credentials_are_registered_and_sign_assertions() {
+    use rsa::{
+        pkcs1v15::{Signature, VerifyingKey},
+        pkcs8::DecodePublicKey,
+        sha2::Sha256,
+        signature::Verifier,
+    };
+
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth).verify_own_assertions(true);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            pub_key_cred_params: vec![webauthn::PublicKeyCredentialParameters {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::RS256,
+            }],
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let credential = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with only RS256 offered");
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: good_credential_request_options(credential.raw_id.clone()),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with an RS256 credential");
+
+    // Assert
+    assert_eq!(
+        credential.response.public_key_algorithm,
+        iana::Algorithm::RS256.to_i64()
+    );
+    let public_key =
+        VerifyingKey::<Sha256>::from_public_key_der(&credential.response.public_key.unwrap())
+            .expect("the public key is not an RSA SubjectPublicKeyInfo");
+    let signature = Signature::try_from(assertion.response.signature.as_slice())
+        .expect("the signature is not an RSA signature");
+    let mut signed_data = assertion.response.authenticator_data.to_vec();
+    signed_data.extend(client_data_hash(&assertion.response.client_data_json));
+    public_key
+        .verify(&signed_data, &signature)
+        .expect("the assertion signature is not valid for the registered public key");
+}
+
+#[tokio::test]
+async fn default_registration_timeout_depends_on_the_resident_key_requirement() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        MockUserValidationMethod::verified_user(0),
+    );
+    let client = Client::new(auth);
+    let options = |resident_key| webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            timeout: None,
+            authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                resident_key: Some(resident_key),
+                ..Default::default()
+            }),
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let required = client
+        .registration_timeout(&options(ResidentKeyRequirement::Required))
+        .await;
+    let discouraged = client
+        .registration_timeout(&options(ResidentKeyRequirement::Discouraged))
+        .await;
+
+    // Assert
+    assert_eq!(required, DEFAULT_DISCOVERABLE_TIMEOUT);
+    assert_eq!(discouraged, DEFAULT_TIMEOUT);
+    assert_ne!(required, discouraged);
+}
+
+#[tokio::test]
+async fn requested_timeouts_are_clamped_regardless_of_discoverability() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        MockUserValidationMethod::verified_user(0),
+    );
+    let client = Client::new(auth);
+    let request = |timeout, allow_credentials| webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            timeout,
+            allow_credentials,
+            ..good_credential_request_options(random_vec(16))
+        },
+    };
+    let allowed = || good_credential_request_options(random_vec(16)).allow_credentials;
+
+    // Act & Assert
+    assert_eq!(
+        client.authentication_timeout(&request(None, None)),
+        DEFAULT_DISCOVERABLE_TIMEOUT
+    );
+    assert_eq!(
+        client.authentication_timeout(&request(None, allowed())),
+        DEFAULT_TIMEOUT
+    );
+    assert_eq!(
+        client.authentication_timeout(&request(Some(1), None)),
+        MIN_TIMEOUT
+    );
+    assert_eq!(
+        client.authentication_timeout(&request(Some(u32::MAX), allowed())),
+        MAX_TIMEOUT
+    );
+    assert_eq!(
+        client.authentication_timeout(&request(Some(60_000), allowed())),
+        60_000
+    );
+}
+
+#[tokio::test]
+async fn recorded_user_validation_calls_of_a_uv_required_registration() {
+    use passkey_authenticator::{RecordingUserValidation, UserValidationCall};
+
+    // Arrange
+    let user_validation = RecordingUserValidation::new(MockUserValidationMethod::verified_user(1));
+    let calls = user_validation.calls();
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_validation,
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                user_verification: UserVerificationRequirement::Required,
+                ..Default::default()
+            }),
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    // Assert
+    // The client reads the capabilities of the authenticator, which checks that it can verify
+    // the user before prompting them.
+    assert_eq!(
+        calls.to_vec(),
+        vec![
+            UserValidationCall::IsVerificationEnabled(Some(true)),
+            UserValidationCall::IsPresenceEnabled(true),
+            UserValidationCall::IsVerificationEnabled(Some(true)),
+            UserValidationCall::CheckUser {
+                hint: "RequestNewCredential",
+                presence: true,
+                verification: true,
+                result: Ok(UserCheck {
+                    presence: true,
+                    verification: true,
+                }),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn registering_an_excluded_credential_fails_after_only_checking_presence() {
+    use passkey_authenticator::{RecordingUserValidation, UserValidationCall};
+
+    // Arrange
+    let mut user_mock = MockUserValidationMethod::new();
+    user_mock
+        .expect_is_verification_enabled()
+        .returning(|| Some(true));
+    user_mock.expect_is_presence_enabled().returning(|| true);
+    user_mock
+        .expect_check_user()
+        .returning(|_, presence, verification| {
+            Ok(UserCheck {
+                presence,
+                verification,
+            })
+        })
+        .times(2);
+    let user_validation = RecordingUserValidation::new(user_mock);
+    let calls = user_validation.calls();
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        user_validation,
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+
+    // Act
+    let result = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    exclude_credentials: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                        ty: webauthn::PublicKeyCredentialType::PublicKey,
+                        id: cred.raw_id,
+                        transports: None,
+                    }]),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(result.unwrap_err(), WebauthnError::InvalidState);
+    let checks: Vec<_> = calls
+        .to_vec()
+        .into_iter()
+        .filter_map(|call| match call {
+            UserValidationCall::CheckUser {
+                hint,
+                presence,
+                verification,
+                ..
+            } => Some((hint, presence, verification)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        checks,
+        vec![
+            ("RequestNewCredential", true, true),
+            ("InformExcludedCredentialFound", true, false),
+        ]
+    );
+}
+
+fn prf_extension(
+    first: &[u8],
+    second: Option<&[u8]>,
+) -> Option<webauthn::AuthenticationExtensionsClientInputs> {
+    Some(webauthn::AuthenticationExtensionsClientInputs {
+        cred_props: None,
+        credential_protection_policy: None,
+        enforce_credential_protection_policy: None,
+        appid: None,
+        prf: Some(webauthn::AuthenticationExtensionsPrfInputs {
+            eval: Some(webauthn::AuthenticationExtensionsPrfValues {
+                first: first.to_vec().into(),
+                second: second.map(|second| second.to_vec().into()),
+            }),
+            eval_by_credential: None,
+        }),
+        uvm: None,
+        device_pub_key: None,
+        large_blob: None,
+    })
+}
+
+#[tokio::test]
+async fn prf_salts_round_trip_through_register_and_authenticate() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(4),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            extensions: prf_extension(b"registration", None),
+            ..good_credential_creation_options()
+        },
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+    let request = |first: &[u8], second: Option<&[u8]>| webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            extensions: prf_extension(first, second),
+            ..good_credential_request_options(cred.raw_id.clone())
+        },
+    };
+
+    // Act
+    let both_salts = client
+        .authenticate(
+            &origin,
+            request(b"first", Some(b"second")),
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with the prf extension")
+        .client_extension_results
+        .prf
+        .and_then(|prf| prf.results)
+        .expect("no prf results");
+    let swapped_salts = client
+        .authenticate(
+            &origin,
+            request(b"second", Some(b"first")),
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with the prf extension")
+        .client_extension_results
+        .prf
+        .and_then(|prf| prf.results)
+        .expect("no prf results");
+    let single_salt = client
+        .authenticate(&origin, request(b"first", None), DefaultClientData)
+        .await
+        .expect("failed to authenticate with the prf extension")
+        .client_extension_results
+        .prf
+        .and_then(|prf| prf.results)
+        .expect("no prf results");
+
+    // Assert
+    assert_eq!(
+        cred.client_extension_results
+            .prf
+            .and_then(|prf| prf.enabled),
+        Some(true)
+    );
+    let second = both_salts.second.expect("no result for the second salt");
+    assert_eq!(both_salts.first.len(), 32);
+    assert_eq!(second.len(), 32);
+    assert_ne!(both_salts.first, second);
+    assert_eq!(swapped_salts.first, second);
+    assert_eq!(swapped_salts.second, Some(both_salts.first.clone()));
+    assert_eq!(single_salt.first, both_salts.first);
+    assert_eq!(single_salt.second, None);
+}
+
+#[tokio::test]
+async fn prf_outputs_are_empty_for_credentials_without_hmac_secret() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let cred = client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options");
+
+    // Act
+    let assertion = client
+        .authenticate(
+            &origin,
+            webauthn::CredentialRequestOptions {
+                public_key: webauthn::PublicKeyCredentialRequestOptions {
+                    extensions: prf_extension(b"first", None),
+                    ..good_credential_request_options(cred.raw_id)
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to authenticate with the prf extension");
+
+    // Assert
+    assert!(cred.client_extension_results.prf.is_none());
+    assert_eq!(
+        assertion.client_extension_results.prf,
+        Some(webauthn::AuthenticationExtensionsPrfOutputs::default())
+    );
+}
+
+async fn register_with_cred_protect(
+    default_policy: webauthn::CredentialProtectionPolicy,
+    requested_policy: webauthn::CredentialProtectionPolicy,
+) -> webauthn::CreatedPublicKeyCredential {
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    auth.set_default_credential_protection_policy(Some(default_policy));
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            extensions: Some(webauthn::AuthenticationExtensionsClientInputs {
+                cred_props: None,
+                prf: None,
+                credential_protection_policy: Some(requested_policy),
+                enforce_credential_protection_policy: None,
+                appid: None,
+                uvm: None,
+                device_pub_key: None,
+                large_blob: None,
+            }),
+            ..good_credential_creation_options()
+        },
+    };
+
+    client
+        .register(&origin, options, DefaultClientData)
+        .await
+        .expect("failed to register with options")
+}
+
+/// The `credProtect` value in the extension outputs of the authenticator data.
+fn cred_protect_in_auth_data(
+    cred: &webauthn::CreatedPublicKeyCredential,
+) -> Option<ciborium::Value> {
+    let auth_data = ctap2::AuthenticatorData::from_slice(&cred.response.authenticator_data)
+        .expect("could not parse the authenticator data");
+    auth_data
+        .extensions
+        .and_then(|extensions| extensions.into_map().ok())
+        .and_then(|outputs| {
+            outputs
+                .into_iter()
+                .find(|(id, _)| id.as_text() == Some("credProtect"))
+        })
+        .map(|(_, value)| value)
+}
+
+#[tokio::test]
+async fn cred_protect_request_raises_the_authenticator_default() {
+    // Act
+    let cred = register_with_cred_protect(
+        webauthn::CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList,
+        webauthn::CredentialProtectionPolicy::UserVerificationRequired,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(
+        cred.client_extension_results.cred_protect,
+        Some(webauthn::CredentialProtectionPolicy::UserVerificationRequired)
+    );
+    assert_eq!(
+        cred_protect_in_auth_data(&cred),
+        Some(ciborium::Value::from(3))
+    );
+}
+
+#[tokio::test]
+async fn cred_protect_request_never_lowers_the_authenticator_default() {
+    // Act
+    let cred = register_with_cred_protect(
+        webauthn::CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList,
+        webauthn::CredentialProtectionPolicy::UserVerificationOptional,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(
+        cred.client_extension_results.cred_protect,
+        Some(webauthn::CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList)
+    );
+    assert_eq!(
+        cred_protect_in_auth_data(&cred),
+        Some(ciborium::Value::from(2))
+    );
+}
+
+fn large_blob_extensions(
+    large_blob: webauthn::AuthenticationExtensionsLargeBlobInputs,
+) -> Option<webauthn::AuthenticationExtensionsClientInputs> {
+    Some(webauthn::AuthenticationExtensionsClientInputs {
+        cred_props: None,
+        prf: None,
+        credential_protection_policy: None,
+        enforce_credential_protection_policy: None,
+        appid: None,
+        uvm: None,
+        device_pub_key: None,
+        large_blob: Some(large_blob),
+    })
+}
+
+#[tokio::test]
+async fn large_blob_is_written_and_read_back_through_the_authenticator() {
+    // Arrange
+    let mut auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(3),
+    );
+    auth.set_max_serialized_large_blob_array(4096);
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    // A random blob cannot be compressed, so the array is written and read in several fragments.
+    let blob = random_vec(2048);
+    let authenticate = |large_blob, credential_id: Bytes| webauthn::CredentialRequestOptions {
+        public_key: webauthn::PublicKeyCredentialRequestOptions {
+            extensions: large_blob_extensions(large_blob),
+            ..good_credential_request_options(credential_id)
+        },
+    };
+
+    // Act
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: webauthn::PublicKeyCredentialCreationOptions {
+                    authenticator_selection: Some(AuthenticatorSelectionCriteria {
+                        resident_key: Some(ResidentKeyRequirement::Required),
+                        ..Default::default()
+                    }),
+                    extensions: large_blob_extensions(
+                        webauthn::AuthenticationExtensionsLargeBlobInputs {
+                            support: Some(LargeBlobSupport::Required),
+                            ..Default::default()
+                        },
+                    ),
+                    ..good_credential_creation_options()
+                },
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with largeBlob");
+    let written = client
+        .authenticate(
+            &origin,
+            authenticate(
+                webauthn::AuthenticationExtensionsLargeBlobInputs {
+                    write: Some(blob.clone().into()),
+                    ..Default::default()
+                },
+                cred.raw_id.clone(),
+            ),
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to write the large blob");
+    let read = client
+        .authenticate(
+            &origin,
+            authenticate(
+                webauthn::AuthenticationExtensionsLargeBlobInputs {
+                    read: Some(true),
+                    ..Default::default()
+                },
+                cred.raw_id.clone(),
+            ),
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to read the large blob");
+    let conflicting = client
+        .authenticate(
+            &origin,
+            authenticate(
+                webauthn::AuthenticationExtensionsLargeBlobInputs {
+                    support: None,
+                    read: Some(true),
+                    write: Some(blob.clone().into()),
+                },
+                cred.raw_id.clone(),
+            ),
+            DefaultClientData,
+        )
+        .await;
+
+    // Assert
+    let supported = cred.client_extension_results.large_blob.unwrap();
+    assert_eq!(supported.supported, Some(true));
+    let written = written.client_extension_results.large_blob.unwrap();
+    assert_eq!(written.written, Some(true));
+    assert_eq!(written.blob, None);
+    let read = read.client_extension_results.large_blob.unwrap();
+    assert_eq!(read.blob, Some(blob.into()));
+    assert_eq!(conflicting.unwrap_err(), WebauthnError::NotSupported);
+}
+
+#[tokio::test]
+async fn large_blob_support_is_only_required_from_capable_authenticators() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        None::<Passkey>,
+        uv_mock_with_creation(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = |support| webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            extensions: large_blob_extensions(webauthn::AuthenticationExtensionsLargeBlobInputs {
+                support: Some(support),
+                ..Default::default()
+            }),
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let required = client
+        .register(
+            &origin,
+            options(LargeBlobSupport::Required),
+            DefaultClientData,
+        )
+        .await;
+    let preferred = client
+        .register(
+            &origin,
+            options(LargeBlobSupport::Preferred),
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with a preferred largeBlob support");
+
+    // Assert
+    assert_eq!(required.unwrap_err(), WebauthnError::NotSupported);
+    assert_eq!(
+        preferred.client_extension_results.large_blob,
+        Some(webauthn::AuthenticationExtensionsLargeBlobOutputs {
+            supported: Some(false),
+            blob: None,
+            written: None,
+        })
+    );
+}
+
+#[tokio::test]
+async fn has_excluded_credential_finds_registered_credentials_without_prompting() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let excluding = |id: Bytes| webauthn::CredentialCreationOptions {
+        public_key: webauthn::PublicKeyCredentialCreationOptions {
+            exclude_credentials: Some(vec![webauthn::PublicKeyCredentialDescriptor {
+                ty: webauthn::PublicKeyCredentialType::PublicKey,
+                id,
+                transports: None,
+            }]),
+            ..good_credential_creation_options()
+        },
+    };
+
+    // Act
+    let registered = client
+        .has_excluded_credential(&origin, &excluding(cred.raw_id))
+        .await;
+    let unknown = client
+        .has_excluded_credential(&origin, &excluding(random_vec(16).into()))
+        .await;
+    let without_exclude_list = client
+        .has_excluded_credential(
+            &origin,
+            &webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+        )
+        .await;
+
+    // Assert
+    assert_eq!(registered, Ok(true));
+    assert_eq!(unknown, Ok(false));
+    assert_eq!(without_exclude_list, Ok(false));
+}
+
+#[tokio::test]
+async fn has_excluded_credential_validates_the_origin() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(0),
+    );
+    let client = Client::new(auth);
+    let origin = Url::parse("https://www.wikipedia.org").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+
+    // Act
+    let result = client.has_excluded_credential(&origin, &options).await;
+
+    // Assert
+    assert_eq!(result, Err(WebauthnError::OriginRpMissmatch));
+}
+
+#[tokio::test]
+async fn signal_all_accepted_credentials_removes_the_other_credentials_of_the_user() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let user_id: Bytes = random_vec(16).into();
+    let mut credential_ids = Vec::new();
+    for _ in 0..2 {
+        let mut options = good_credential_creation_options();
+        options.user.id = user_id.clone();
+        let cred = client
+            .register(
+                &origin,
+                webauthn::CredentialCreationOptions {
+                    public_key: options,
+                },
+                DefaultClientData,
+            )
+            .await
+            .expect("failed to register with options");
+        credential_ids.push(cred.raw_id);
+    }
+    let [accepted, stale] = <[Bytes; 2]>::try_from(credential_ids).unwrap();
+    let options = webauthn::AllAcceptedCredentialsOptions {
+        rp_id: "future.1password.com".into(),
+        user_id: user_id.clone(),
+        all_accepted_credential_ids: vec![accepted.clone()],
+    };
+
+    // Act
+    let other_site = client
+        .signal_all_accepted_credentials(
+            &Url::parse("https://www.wikipedia.org").unwrap(),
+            &options,
+        )
+        .await;
+    let signaled = client
+        .signal_all_accepted_credentials(&origin, &options)
+        .await;
+    client
+        .signal_current_user_details(
+            &origin,
+            &webauthn::CurrentUserDetailsOptions {
+                rp_id: "future.1password.com".into(),
+                user_id: user_id.clone(),
+                name: "wendy@1password.com".into(),
+                display_name: "Wendy Appleseed".into(),
+            },
+        )
+        .await
+        .expect("failed to signal the user details");
+
+    // Assert
+    assert_eq!(other_site, Err(WebauthnError::OriginRpMissmatch));
+    assert_eq!(signaled, Ok(()));
+    let store = client.authenticator().store();
+    assert!(store.contains_key(&*accepted));
+    assert!(!store.contains_key(&*stale));
+    let user = store
+        .user("future.1password.com", &user_id)
+        .expect("missing user details");
+    assert_eq!(user.name.as_deref(), Some("wendy@1password.com"));
+}
+
+#[tokio::test]
+async fn signal_unknown_credential_removes_the_credential() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(1),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let cred = client
+        .register(
+            &origin,
+            webauthn::CredentialCreationOptions {
+                public_key: good_credential_creation_options(),
+            },
+            DefaultClientData,
+        )
+        .await
+        .expect("failed to register with options");
+    let unknown = |credential_id: Bytes| webauthn::UnknownCredentialOptions {
+        rp_id: "future.1password.com".into(),
+        credential_id,
+    };
+
+    // Act
+    let never_stored = client
+        .signal_unknown_credential(&origin, &unknown(random_vec(16).into()))
+        .await;
+    let stored = client
+        .signal_unknown_credential(&origin, &unknown(cred.raw_id.clone()))
+        .await;
+
+    // Assert
+    assert_eq!(never_stored, Ok(()));
+    assert_eq!(stored, Ok(()));
+    assert!(client.authenticator().store().is_empty());
+}
+
+/// A user validation method whose prompt is never answered.
+struct UnansweredUserValidation;
+
+#[async_trait::async_trait]
+impl UserValidationMethod for UnansweredUserValidation {
+    type PasskeyItem = Passkey;
+
+    async fn check_user<'a>(
+        &self,
+        _hint: passkey_authenticator::UIHint<'a, Self::PasskeyItem>,
+        _presence: bool,
+        _verification: bool,
+    ) -> Result<UserCheck, ctap2::Ctap2Error> {
+        std::future::pending().await
+    }
+
+    async fn is_verification_enabled(&self) -> Option<bool> {
+        Some(true)
+    }
+
+    async fn is_presence_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn register_with_signal_is_aborted_while_waiting_on_the_user() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        UnansweredUserValidation,
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    let controller = AbortController::new();
+
+    // Act
+    let (result, ()) = tokio::join!(
+        client.register_with_signal(&origin, options, DefaultClientData, controller.signal()),
+        async { controller.abort() },
+    );
+
+    // Assert
+    assert_eq!(
+        result.expect_err("registration was not aborted"),
+        WebauthnError::Aborted
+    );
+    assert!(client.authenticator().store().is_empty());
+}
+
+#[tokio::test]
+async fn authenticate_with_signal_fails_once_its_controller_aborted() {
+    // Arrange
+    let auth = Authenticator::new(
+        ctap2::Aaguid::new_empty(),
+        MemoryStore::new(),
+        uv_mock_with_creation(2),
+    );
+    let mut client = Client::new(auth);
+    let origin = Url::parse("https://future.1password.com").unwrap();
+    let options = webauthn::CredentialCreationOptions {
+        public_key: good_credential_creation_options(),
+    };
+    // The controller of this signal is dropped without aborting.
+    let never_aborted = AbortController::new().signal();
+    let credential_id = client
+        .register_with_signal(&origin, options, DefaultClientData, never_aborted)
+        .await
+        .expect("failed to register with options")
+        .raw_id;
+    let auth_options = || webauthn::CredentialRequestOptions {
+        public_key: good_credential_request_options(credential_id.clone()),
+    };
+    let controller = AbortController::new();
+    let signal = controller.signal();
+
+    // Act
+    let before_abort = client
+        .authenticate_with_signal(&origin, auth_options(), DefaultClientData, signal.clone())
+        .await;
+    controller.abort();
+    let after_abort = client
+        .authenticate_with_signal(&origin, auth_options(), DefaultClientData, signal)
+        .await;
+
+    // Assert
+    before_abort.expect("failed to authenticate before the abort");
+    assert_eq!(
+        after_abort.expect_err("authenticated after the abort"),
+        WebauthnError::Aborted
+    );
 }
@@ -7,10 +7,14 @@
 mod aaguid;
 mod attestation_fmt;
 mod error;
+mod extensions;
 mod flags;
 
+pub mod client_pin;
+pub mod credential_management;
 pub mod get_assertion;
 pub mod get_info;
+pub mod large_blobs;
 pub mod make_credential;
 
-pub use self::{aaguid::*, attestation_fmt::*, error::*, flags::*};
+pub use self::{aaguid::*, attestation_fmt::*, error::*, extensions::*, flags::*};
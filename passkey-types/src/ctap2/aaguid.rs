@@ -1,3 +1,4 @@
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
 use serde::{Deserialize, Serialize};
 
 /// An Authenticator Attestation GUID is a 128-bit identifier.
@@ -18,12 +19,60 @@ pub struct Aaguid(pub [u8; Self::LEN]);
 impl Aaguid {
     const LEN: usize = 16;
 
+    /// The length of each of the hyphen separated groups of hexadecimal digits of a UUID string.
+    const UUID_GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+
     /// Generate empty AAGUID
     pub const fn new_empty() -> Self {
         Self([0; 16])
     }
+
+    /// Parse an AAGUID from its canonical hyphenated [RFC4122] UUID string, as found in the FIDO
+    /// Metadata Service, e.g. `"ee882879-721c-4913-9775-3dfcce97072a"`. Hexadecimal digits can be
+    /// in either case.
+    ///
+    /// [RFC4122]: https://www.rfc-editor.org/rfc/rfc4122
+    pub fn from_uuid_str(uuid: &str) -> Result<Self, NotAUuid> {
+        let groups: Vec<&str> = uuid.split('-').collect();
+        if groups.len() != Self::UUID_GROUPS.len()
+            || groups
+                .iter()
+                .zip(Self::UUID_GROUPS)
+                .any(|(group, len)| group.len() != len)
+        {
+            return Err(NotAUuid);
+        }
+
+        HEXLOWER_PERMISSIVE
+            .decode(groups.concat().as_bytes())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+            .ok_or(NotAUuid)
+    }
+
+    /// Encode the AAGUID as a lowercase hyphenated [RFC4122] UUID string.
+    ///
+    /// [RFC4122]: https://www.rfc-editor.org/rfc/rfc4122
+    pub fn to_uuid_str(&self) -> String {
+        let mut uuid = String::with_capacity(36);
+        let mut bytes = self.0.as_slice();
+        for (i, len) in Self::UUID_GROUPS.into_iter().enumerate() {
+            if i > 0 {
+                uuid.push('-');
+            }
+            let (group, rest) = bytes.split_at(len / 2);
+            uuid.push_str(&HEXLOWER.encode(group));
+            bytes = rest;
+        }
+        uuid
+    }
 }
 
+/// The string given for parsing is not a hyphenated UUID.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NotAUuid;
+
 impl Default for Aaguid {
     fn default() -> Self {
         Self::new_empty()
@@ -71,9 +120,36 @@ impl<'de> Deserialize<'de> for Aaguid {
     }
 }
 
+/// Serialize and deserialize an [`Aaguid`] as a hyphenated UUID string rather than its 16 bytes,
+/// to be used with `#[serde(with = "passkey_types::ctap2::aaguid_as_uuid_str")]`. This is useful
+/// for configuration files and metadata statements.
+pub mod aaguid_as_uuid_str {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Aaguid;
+
+    /// Serialize the [`Aaguid`] as a hyphenated UUID string.
+    pub fn serialize<S>(aaguid: &Aaguid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&aaguid.to_uuid_str())
+    }
+
+    /// Deserialize an [`Aaguid`] from a hyphenated UUID string.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Aaguid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let uuid = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Aaguid::from_uuid_str(&uuid)
+            .map_err(|_| serde::de::Error::custom(format!("{uuid} is not a hyphenated UUID")))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Aaguid;
+    use super::{Aaguid, NotAUuid};
 
     #[test]
     fn deserialize_byte_str_to_aaguid() {
@@ -111,4 +187,48 @@ mod tests {
 
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn aaguid_uuid_string_round_trip() {
+        let bytes = [
+            0xee, 0x88, 0x28, 0x79, 0x72, 0x1c, 0x49, 0x13, 0x97, 0x75, 0x3d, 0xfc, 0xce, 0x97,
+            0x07, 0x2a,
+        ];
+
+        let aaguid = Aaguid::from_uuid_str("EE882879-721C-4913-9775-3DFCCE97072A")
+            .expect("could not parse the uuid string");
+
+        assert_eq!(aaguid, Aaguid(bytes));
+        assert_eq!(aaguid.to_uuid_str(), "ee882879-721c-4913-9775-3dfcce97072a");
+        assert_eq!(Aaguid::from_uuid_str(&aaguid.to_uuid_str()), Ok(aaguid));
+    }
+
+    #[test]
+    fn invalid_uuid_strings_are_rejected() {
+        for uuid in [
+            "ee882879721c491397753dfcce97072a",
+            "ee882879-721c-4913-9775-3dfcce97072",
+            "ee88287-9721c-4913-9775-3dfcce97072a",
+            "ee882879-721c-4913-9775-3dfcce97072g",
+        ] {
+            assert_eq!(Aaguid::from_uuid_str(uuid), Err(NotAUuid), "{uuid}");
+        }
+    }
+
+    #[test]
+    fn aaguid_serializes_as_uuid_string_when_requested() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Config {
+            #[serde(with = "super::aaguid_as_uuid_str")]
+            aaguid: Aaguid,
+        }
+        let config = Config {
+            aaguid: Aaguid([0xab; 16]),
+        };
+
+        let json = serde_json::to_string(&config).expect("could not serialize");
+
+        assert_eq!(json, r#"{"aaguid":"abababab-abab-abab-abab-abababababab"}"#);
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    }
 }
@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     crypto::sha256,
-    ctap2::{Aaguid, Flags},
+    ctap2::{Aaguid, AuthenticatorExtensionOutputs, Flags},
 };
 
 /// The authenticator data structure encodes contextual bindings made by the authenticator. These
@@ -60,8 +60,16 @@ impl AuthenticatorData {
     ///
     /// The flags will be set to their default values.
     pub fn new(rp_id: &str, counter: Option<u32>) -> Self {
+        Self::from_rp_id_hash(sha256(rp_id.as_bytes()), counter)
+    }
+
+    /// Create a new AuthenticatorData object for the SHA-256 hash of an RP ID and an optional
+    /// counter, such as when re-encoding the authenticator data of another authenticator.
+    ///
+    /// The flags will be set to their default values.
+    pub fn from_rp_id_hash(rp_id_hash: [u8; 32], counter: Option<u32>) -> Self {
         Self {
-            rp_id_hash: sha256(rp_id.as_bytes()),
+            rp_id_hash,
             flags: Flags::default(),
             counter,
             attested_credential_data: None,
@@ -74,12 +82,26 @@ impl AuthenticatorData {
     /// This sets the [`Flags::AT`] value as well.
     pub fn set_attested_credential_data(mut self, acd: AttestedCredentialData) -> Self {
         self.attested_credential_data = Some(acd);
-        self.set_flags(Flags::AT)
+        self.flags |= Flags::AT;
+        self
+    }
+
+    /// Add the authenticator extension outputs, which must be a CBOR map, to the authenticator data.
+    ///
+    /// This sets the [`Flags::ED`] value as well.
+    pub fn set_extensions(mut self, extensions: Value) -> Self {
+        self.extensions = Some(extensions);
+        self.flags |= Flags::ED;
+        self
     }
 
     /// Set additional [`Flags`] to the authenticator data.
+    ///
+    /// The [`Flags::AT`] and [`Flags::ED`] values are ignored since they are only set along with
+    /// the data they announce, see [`Self::set_attested_credential_data`] and
+    /// [`Self::set_extensions`].
     pub fn set_flags(mut self, flags: Flags) -> Self {
-        self.flags |= flags;
+        self.flags |= flags - Flags::AT - Flags::ED;
         self
     }
 
@@ -87,6 +109,40 @@ impl AuthenticatorData {
     pub fn rp_id_hash(&self) -> &[u8] {
         &self.rp_id_hash
     }
+
+    /// Whether the credential is eligible to be backed up, see [`Flags::backup_eligible`].
+    pub fn is_backup_eligible(&self) -> bool {
+        self.flags.backup_eligible()
+    }
+
+    /// Whether the credential is currently backed up, see [`Flags::backed_up`].
+    pub fn is_backed_up(&self) -> bool {
+        self.flags.backed_up()
+    }
+
+    /// Parse the outputs of the known extensions from the extensions of the authenticator data.
+    ///
+    /// Returns `None` when there are no extension outputs, and an error when the outputs of a
+    /// known extension are malformed. Use [`Self::extensions_cbor`] for the other extensions.
+    pub fn extensions(&self) -> coset::Result<Option<AuthenticatorExtensionOutputs>> {
+        self.extensions
+            .as_ref()
+            .map(|extensions| {
+                extensions.deserialized().map_err(|error| {
+                    coset::CoseError::DecodeFailed(ciborium::de::Error::Semantic(
+                        None,
+                        error.to_string(),
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// Get read access to the raw CBOR map of the extension outputs, including those of the
+    /// extensions that are not parsed by [`Self::extensions`].
+    pub fn extensions_cbor(&self) -> Option<&Value> {
+        self.extensions.as_ref()
+    }
 }
 
 impl Serialize for AuthenticatorData {
@@ -165,12 +221,18 @@ impl AuthenticatorData {
     }
 
     /// Encode an authenticator data to its byte representation.
+    ///
+    /// The [`Flags::AT`] and [`Flags::ED`] values are always derived from the presence of the
+    /// attested credential data and of the extension outputs, so that the encoded flags never
+    /// disagree with the encoded data and the result can always be decoded by [`Self::from_slice`].
     pub fn to_vec(&self) -> Vec<u8> {
-        let flags = if self.attested_credential_data.is_some() {
-            self.flags | Flags::AT
-        } else {
-            self.flags
-        };
+        let mut flags = self.flags - Flags::AT - Flags::ED;
+        if self.attested_credential_data.is_some() {
+            flags |= Flags::AT;
+        }
+        if self.extensions.is_some() {
+            flags |= Flags::ED;
+        }
 
         self.rp_id_hash
             .into_iter()
@@ -198,6 +260,25 @@ impl AuthenticatorData {
     }
 }
 
+/// Find the encoded COSE_Key of the credential public key in the attested credential data of the
+/// encoded authenticator data, as it was encoded by the authenticator. Returns `None` when the
+/// authenticator data has no attested credential data or is malformed.
+pub(crate) fn encoded_credential_public_key(auth_data: &[u8]) -> Option<&[u8]> {
+    // rpIdHash (32 bytes) + flags (1 byte) + counter (4 bytes) + AAGUID (16 bytes)
+    let flags = Flags::from(*auth_data.get(32)?);
+    if !flags.contains(Flags::AT) {
+        return None;
+    }
+    let cred_len = u16::from_be_bytes(auth_data.get(53..55)?.try_into().ok()?);
+    let key_and_extensions = auth_data.get(55 + usize::from(cred_len)..)?;
+
+    // The key is followed by the extensions, so it ends where the reader stopped decoding it.
+    let mut reader = key_and_extensions;
+    let key: Value = ciborium::de::from_reader(&mut reader).ok()?;
+    CoseKey::from_cbor_value(key).ok()?;
+    Some(&key_and_extensions[..key_and_extensions.len() - reader.len()])
+}
+
 /// Attested credential data is a variable-length byte array added to the authenticator data when
 /// generating an attestation object for a credential
 ///
@@ -299,7 +380,9 @@ mod test {
     use coset::CoseKeyBuilder;
 
     use super::*;
-    use crate::utils::rand::random_vec;
+    use crate::{
+        ctap2::ExtensionOutput, utils::rand::random_vec, webauthn::CredentialProtectionPolicy,
+    };
 
     #[test]
     fn deserialize_authenticator_data_with_at_and_ed() {
@@ -481,4 +564,160 @@ mod test {
 
         assert_eq!(expected, auth_data);
     }
+
+    #[test]
+    fn at_flag_is_cleared_without_attested_credential_data() {
+        let mut auth_data = AuthenticatorData::new("future.1password.com", Some(0))
+            .set_flags(Flags::UP | Flags::AT);
+        assert!(!auth_data.flags.contains(Flags::AT));
+
+        auth_data.flags |= Flags::AT;
+        let decoded =
+            AuthenticatorData::from_slice(&auth_data.to_vec()).expect("could not deserialize");
+
+        assert!(!decoded.flags.contains(Flags::AT));
+        assert!(decoded.flags.contains(Flags::UP));
+        assert!(decoded.attested_credential_data.is_none());
+    }
+
+    #[test]
+    fn ed_flag_is_cleared_without_extensions() {
+        let mut auth_data = AuthenticatorData::new("future.1password.com", Some(0))
+            .set_flags(Flags::UP | Flags::ED);
+        assert!(!auth_data.flags.contains(Flags::ED));
+
+        auth_data.flags |= Flags::ED;
+        let encoded = auth_data.to_vec();
+        let decoded = AuthenticatorData::from_slice(&encoded).expect("could not deserialize");
+
+        assert_eq!(encoded.len(), 37);
+        assert!(!decoded.flags.contains(Flags::ED));
+        assert!(decoded.extensions.is_none());
+    }
+
+    #[test]
+    fn cred_blob_assertion_auth_data_round_trips() {
+        let cred_blob = random_vec(32);
+        let auth_data = AuthenticatorData::new("future.1password.com", Some(1))
+            .set_flags(Flags::UP | Flags::UV)
+            .set_extensions(cbor!({ "credBlob" => Value::Bytes(cred_blob.clone()) }).unwrap());
+
+        let signed_bytes = auth_data.to_vec();
+        let decoded = AuthenticatorData::from_slice(&signed_bytes).expect("could not deserialize");
+
+        assert_eq!(decoded.flags, auth_data.flags);
+        assert!(decoded.flags.contains(Flags::UP | Flags::UV | Flags::ED));
+        assert!(decoded.attested_credential_data.is_none());
+        assert_eq!(
+            decoded.extensions,
+            Some(cbor!({ "credBlob" => Value::Bytes(cred_blob) }).unwrap())
+        );
+        assert_eq!(decoded.to_vec(), signed_bytes);
+    }
+
+    #[test]
+    fn auth_data_with_attested_credential_data_and_extensions_round_trips() {
+        let rp_id_hash = sha256(b"future.1password.com");
+        let key = CoseKeyBuilder::new_ec2_pub_key(
+            coset::iana::EllipticCurve::P_256,
+            random_vec(32),
+            random_vec(32),
+        )
+        .algorithm(coset::iana::Algorithm::ES256)
+        .build();
+        let acd = AttestedCredentialData::new(Aaguid::new_empty(), random_vec(16), key)
+            .expect("credential ID is too long");
+        let auth_data = AuthenticatorData::from_rp_id_hash(rp_id_hash, Some(7))
+            .set_flags(Flags::UP | Flags::UV | Flags::BE | Flags::BS)
+            .set_attested_credential_data(acd)
+            .set_extensions(cbor!({ "credProtect" => 3, "hmac-secret" => true }).unwrap());
+
+        let encoded = auth_data.to_vec();
+        let decoded = AuthenticatorData::from_slice(&encoded).expect("could not deserialize");
+
+        assert_eq!(&encoded[..32], rp_id_hash);
+        assert_eq!(encoded[32], 0xdd);
+        assert_eq!(encoded[33..37], 7u32.to_be_bytes());
+        assert_eq!(decoded, auth_data);
+        assert_eq!(decoded.to_vec(), encoded);
+    }
+
+    #[test]
+    fn extensions_are_parsed_from_the_authenticator_data() {
+        let rp_id_hash = sha256(b"future.1password.com");
+        let data: Vec<u8> = rp_id_hash
+            .into_iter()
+            // flags UP | ED, counter 1
+            .chain([0x81, 0x00, 0x00, 0x00, 0x01])
+            // {"credProtect": 2, "org.example": true}
+            .chain([
+                0xa2, 0x6b, 0x63, 0x72, 0x65, 0x64, 0x50, 0x72, 0x6f, 0x74, 0x65, 0x63, 0x74, 0x02,
+                0x6b, 0x6f, 0x72, 0x67, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0xf5,
+            ])
+            .collect();
+
+        let auth_data = AuthenticatorData::from_slice(&data).expect("could not deserialize");
+        let extensions = auth_data
+            .extensions()
+            .expect("could not parse the extensions")
+            .expect("missing extensions");
+
+        assert_eq!(
+            extensions,
+            AuthenticatorExtensionOutputs {
+                cred_protect: Some(
+                    CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList
+                ),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            auth_data.extensions_cbor(),
+            Some(&cbor!({ "credProtect" => 2, "org.example" => true }).unwrap())
+        );
+    }
+
+    #[test]
+    fn extensions_of_both_operations_are_parsed() {
+        let cred_blob = random_vec(32);
+        let created = AuthenticatorData::new("future.1password.com", Some(0)).set_extensions(
+            cbor!({ "hmac-secret" => true, "credBlob" => true, "minPinLength" => 6 }).unwrap(),
+        );
+        let asserted = AuthenticatorData::new("future.1password.com", Some(1))
+            .set_extensions(cbor!({ "credBlob" => Value::Bytes(cred_blob.clone()) }).unwrap());
+
+        let created = created
+            .extensions()
+            .expect("could not parse the extensions");
+        let asserted = asserted
+            .extensions()
+            .expect("could not parse the extensions");
+
+        assert_eq!(
+            created,
+            Some(AuthenticatorExtensionOutputs {
+                hmac_secret: Some(ExtensionOutput::Created(true)),
+                cred_blob: Some(ExtensionOutput::Created(true)),
+                min_pin_length: Some(6),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            asserted.and_then(|extensions| extensions.cred_blob),
+            Some(ExtensionOutput::Asserted(cred_blob.into()))
+        );
+        assert!(matches!(
+            AuthenticatorData::new("future.1password.com", None).extensions(),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn malformed_known_extensions_are_rejected() {
+        let auth_data = AuthenticatorData::new("future.1password.com", Some(0))
+            .set_extensions(cbor!({ "credProtect" => 9 }).unwrap());
+
+        assert!(auth_data.extensions().is_err());
+        assert!(auth_data.extensions_cbor().is_some());
+    }
 }
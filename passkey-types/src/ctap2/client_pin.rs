@@ -0,0 +1,283 @@
+//! <https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticatorClientPIN>
+use std::fmt;
+
+use coset::{AsCborValue, CoseKey};
+use serde::{Deserialize, Serialize};
+
+use crate::Bytes;
+
+/// The subcommands of `authenticatorClientPIN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum SubCommand {
+    /// Get the number of PIN attempts left before the authenticator is blocked.
+    GetPinRetries,
+    /// Get the public key agreement key of the authenticator.
+    GetKeyAgreement,
+    /// Set the PIN of an authenticator that does not have one yet.
+    SetPin,
+    /// Change the PIN of the authenticator, knowing the current one.
+    ChangePin,
+    /// Exchange the PIN for a `pinToken`, which authenticates the parameters of the requests
+    /// made on behalf of the verified user.
+    GetPinToken,
+}
+
+impl From<SubCommand> for u8 {
+    fn from(src: SubCommand) -> Self {
+        match src {
+            SubCommand::GetPinRetries => 0x01,
+            SubCommand::GetKeyAgreement => 0x02,
+            SubCommand::SetPin => 0x03,
+            SubCommand::ChangePin => 0x04,
+            SubCommand::GetPinToken => 0x05,
+        }
+    }
+}
+
+impl TryFrom<u8> for SubCommand {
+    type Error = UnknownSubCommand;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(SubCommand::GetPinRetries),
+            0x02 => Ok(SubCommand::GetKeyAgreement),
+            0x03 => Ok(SubCommand::SetPin),
+            0x04 => Ok(SubCommand::ChangePin),
+            0x05 => Ok(SubCommand::GetPinToken),
+            _ => Err(UnknownSubCommand(value)),
+        }
+    }
+}
+
+/// The value of a `subCommand` that is not known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownSubCommand(pub u8);
+
+impl fmt::Display for UnknownSubCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown clientPIN subcommand {:#04x}", self.0)
+    }
+}
+
+/// Request of the `authenticatorClientPIN` command, the parameters that are required depend on
+/// the `sub_command`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "repr::RequestRepr", into = "repr::RequestRepr")]
+pub struct Request {
+    /// The PIN/UV auth protocol chosen by the platform, which must be protocol one.
+    pub pin_protocol: u8,
+
+    /// The subcommand being executed.
+    pub sub_command: SubCommand,
+
+    /// The public key agreement key of the platform, used to compute the shared secret.
+    pub key_agreement: Option<CoseKey>,
+
+    /// The result of authenticating `new_pin_enc`, followed by `pin_hash_enc` when changing the
+    /// PIN, with the shared secret.
+    pub pin_auth: Option<Bytes>,
+
+    /// The new PIN, padded with zeros to at least 64 bytes and encrypted with the shared secret.
+    pub new_pin_enc: Option<Bytes>,
+
+    /// The first 16 bytes of the SHA-256 hash of the current PIN, encrypted with the shared
+    /// secret.
+    pub pin_hash_enc: Option<Bytes>,
+}
+
+/// The response to a clientPIN [`Request`], only the member requested by its subcommand is
+/// present.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "repr::ResponseRepr", into = "repr::ResponseRepr")]
+pub struct Response {
+    /// The public key agreement key of the authenticator.
+    pub key_agreement: Option<CoseKey>,
+
+    /// The `pinToken`, encrypted with the shared secret.
+    pub pin_token: Option<Bytes>,
+
+    /// The number of PIN attempts left before the authenticator is blocked.
+    pub retries: Option<u8>,
+}
+
+mod repr {
+    use ciborium::Value;
+
+    use super::SubCommand;
+    use crate::Bytes;
+
+    serde_workaround! {
+        /// The CBOR representation of a clientPIN [`Request`](super::Request), where the key
+        /// agreement key is a raw COSE key value.
+        #[derive(Clone)]
+        pub struct RequestRepr {
+            #[serde(rename = 0x01)]
+            pub(super) pin_protocol: u8,
+
+            #[serde(rename = 0x02)]
+            pub(super) sub_command: SubCommand,
+
+            #[serde(rename = 0x03, default, skip_serializing_if = Option::is_none)]
+            pub(super) key_agreement: Option<Value>,
+
+            #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none)]
+            pub(super) pin_auth: Option<Bytes>,
+
+            #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none)]
+            pub(super) new_pin_enc: Option<Bytes>,
+
+            #[serde(rename = 0x06, default, skip_serializing_if = Option::is_none)]
+            pub(super) pin_hash_enc: Option<Bytes>,
+        }
+    }
+
+    serde_workaround! {
+        /// The CBOR representation of a clientPIN [`Response`](super::Response), where the key
+        /// agreement key is a raw COSE key value.
+        #[derive(Clone)]
+        pub struct ResponseRepr {
+            #[serde(rename = 0x01, default, skip_serializing_if = Option::is_none)]
+            pub(super) key_agreement: Option<Value>,
+
+            #[serde(rename = 0x02, default, skip_serializing_if = Option::is_none)]
+            pub(super) pin_token: Option<Bytes>,
+
+            #[serde(rename = 0x03, default, skip_serializing_if = Option::is_none)]
+            pub(super) retries: Option<u8>,
+        }
+    }
+}
+
+impl TryFrom<repr::RequestRepr> for Request {
+    type Error = coset::CoseError;
+
+    fn try_from(repr: repr::RequestRepr) -> Result<Self, Self::Error> {
+        Ok(Self {
+            pin_protocol: repr.pin_protocol,
+            sub_command: repr.sub_command,
+            key_agreement: repr
+                .key_agreement
+                .map(CoseKey::from_cbor_value)
+                .transpose()?,
+            pin_auth: repr.pin_auth,
+            new_pin_enc: repr.new_pin_enc,
+            pin_hash_enc: repr.pin_hash_enc,
+        })
+    }
+}
+
+impl From<Request> for repr::RequestRepr {
+    fn from(request: Request) -> Self {
+        Self {
+            pin_protocol: request.pin_protocol,
+            sub_command: request.sub_command,
+            // SAFETY: encoding a COSE key into a CBOR value cannot fail.
+            key_agreement: request
+                .key_agreement
+                .map(|key| key.to_cbor_value().unwrap()),
+            pin_auth: request.pin_auth,
+            new_pin_enc: request.new_pin_enc,
+            pin_hash_enc: request.pin_hash_enc,
+        }
+    }
+}
+
+impl TryFrom<repr::ResponseRepr> for Response {
+    type Error = coset::CoseError;
+
+    fn try_from(repr: repr::ResponseRepr) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key_agreement: repr
+                .key_agreement
+                .map(CoseKey::from_cbor_value)
+                .transpose()?,
+            pin_token: repr.pin_token,
+            retries: repr.retries,
+        })
+    }
+}
+
+impl From<Response> for repr::ResponseRepr {
+    fn from(response: Response) -> Self {
+        Self {
+            // SAFETY: encoding a COSE key into a CBOR value cannot fail.
+            key_agreement: response
+                .key_agreement
+                .map(|key| key.to_cbor_value().unwrap()),
+            pin_token: response.pin_token,
+            retries: response.retries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::{cbor, Value};
+    use coset::{iana, CoseKeyBuilder};
+
+    use super::{Request, Response, SubCommand};
+
+    #[test]
+    fn request_is_encoded_with_integer_keys() {
+        let request = Request {
+            pin_protocol: 1,
+            sub_command: SubCommand::SetPin,
+            key_agreement: Some(
+                CoseKeyBuilder::new_ec2_pub_key(
+                    iana::EllipticCurve::P_256,
+                    vec![1; 32],
+                    vec![2; 32],
+                )
+                .build(),
+            ),
+            pin_auth: Some(vec![3; 16].into()),
+            new_pin_enc: Some(vec![4; 64].into()),
+            pin_hash_enc: None,
+        };
+
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&request, &mut serialized).unwrap();
+        let value: Value = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+        let deserialized: Request = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+
+        let keys: Vec<Value> = value
+            .as_map()
+            .unwrap()
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect();
+        assert_eq!(keys, [1, 2, 3, 4, 5].map(Value::from));
+        assert_eq!(value.as_map().unwrap()[1].1, Value::from(0x03));
+        assert_eq!(deserialized, request);
+    }
+
+    #[test]
+    fn response_only_encodes_present_members() {
+        let response = Response {
+            retries: Some(8),
+            ..Default::default()
+        };
+
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&response, &mut serialized).unwrap();
+        let value: Value = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+
+        assert_eq!(value, cbor!({ 0x03 => 8 }).unwrap());
+    }
+
+    #[test]
+    fn unknown_subcommands_are_rejected() {
+        let serialized = {
+            let mut serialized = Vec::new();
+            ciborium::ser::into_writer(
+                &cbor!({ 0x01 => 1, 0x02 => 0x0a }).unwrap(),
+                &mut serialized,
+            )
+            .unwrap();
+            serialized
+        };
+
+        assert!(ciborium::de::from_reader::<Request, _>(serialized.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! <https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#authenticatorCredentialManagement>
+use std::fmt;
+
+use ciborium::Value;
+use coset::{AsCborValue, CoseKey};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctap2::make_credential::{PublicKeyCredentialRpEntity, PublicKeyCredentialUserEntity},
+    utils::serde::cbor_bytes_opt,
+    webauthn::{CredentialProtectionPolicy, PublicKeyCredentialDescriptor},
+    Bytes,
+};
+
+/// The subcommands of `authenticatorCredentialManagement`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum SubCommand {
+    /// Get the number of discoverable credentials and how many more can be created.
+    GetCredsMetadata,
+    /// Get the first relying party with discoverable credentials, along with their total count.
+    EnumerateRpsBegin,
+    /// Get the next relying party of the enumeration started by [`Self::EnumerateRpsBegin`].
+    EnumerateRpsGetNextRp,
+    /// Get the first discoverable credential of a relying party, along with their total count.
+    EnumerateCredentialsBegin,
+    /// Get the next credential of the enumeration started by
+    /// [`Self::EnumerateCredentialsBegin`].
+    EnumerateCredentialsGetNextCredential,
+    /// Delete a discoverable credential.
+    DeleteCredential,
+    /// Update the name and display name of the user of a discoverable credential.
+    UpdateUserInformation,
+}
+
+impl SubCommand {
+    /// Whether the subcommand must be authorized with a `pinUvAuthParam`, which is the case of
+    /// every subcommand except the ones continuing an enumeration.
+    pub fn requires_pin_uv_auth(self) -> bool {
+        !matches!(
+            self,
+            Self::EnumerateRpsGetNextRp | Self::EnumerateCredentialsGetNextCredential
+        )
+    }
+}
+
+impl From<SubCommand> for u8 {
+    fn from(src: SubCommand) -> Self {
+        match src {
+            SubCommand::GetCredsMetadata => 0x01,
+            SubCommand::EnumerateRpsBegin => 0x02,
+            SubCommand::EnumerateRpsGetNextRp => 0x03,
+            SubCommand::EnumerateCredentialsBegin => 0x04,
+            SubCommand::EnumerateCredentialsGetNextCredential => 0x05,
+            SubCommand::DeleteCredential => 0x06,
+            SubCommand::UpdateUserInformation => 0x07,
+        }
+    }
+}
+
+impl TryFrom<u8> for SubCommand {
+    type Error = UnknownSubCommand;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(SubCommand::GetCredsMetadata),
+            0x02 => Ok(SubCommand::EnumerateRpsBegin),
+            0x03 => Ok(SubCommand::EnumerateRpsGetNextRp),
+            0x04 => Ok(SubCommand::EnumerateCredentialsBegin),
+            0x05 => Ok(SubCommand::EnumerateCredentialsGetNextCredential),
+            0x06 => Ok(SubCommand::DeleteCredential),
+            0x07 => Ok(SubCommand::UpdateUserInformation),
+            _ => Err(UnknownSubCommand(value)),
+        }
+    }
+}
+
+/// The value of a `subCommand` that is not known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownSubCommand(pub u8);
+
+impl fmt::Display for UnknownSubCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown credentialManagement subcommand {:#04x}", self.0)
+    }
+}
+
+serde_workaround! {
+    /// Request of the `authenticatorCredentialManagement` command, the parameters that are
+    /// required depend on the `sub_command`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Request {
+        /// The subcommand being executed.
+        #[serde(rename = 0x01)]
+        pub sub_command: SubCommand,
+
+        /// The parameters of the subcommand.
+        #[serde(rename = 0x02, default, skip_serializing_if = Option::is_none)]
+        pub sub_command_params: Option<SubCommandParams>,
+
+        /// The PIN/UV auth protocol used to compute `pin_uv_auth_param`.
+        #[serde(rename = 0x03, default, skip_serializing_if = Option::is_none)]
+        pub pin_uv_auth_protocol: Option<u8>,
+
+        /// The result of authenticating the message given by [`Request::pin_uv_auth_message`]
+        /// with a PIN/UV auth token.
+        #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+        pub pin_uv_auth_param: Option<Bytes>,
+    }
+}
+
+impl Request {
+    /// The message authenticated by `pin_uv_auth_param`, which is the subcommand byte followed by
+    /// the CBOR encoding of its parameters, when there are any.
+    pub fn pin_uv_auth_message(&self) -> Vec<u8> {
+        let mut message = vec![u8::from(self.sub_command)];
+        if let Some(params) = &self.sub_command_params {
+            // SAFETY: Serializing into a Vec cannot fail.
+            ciborium::ser::into_writer(&params.to_cbor_value(), &mut message).unwrap();
+        }
+        message
+    }
+}
+
+serde_workaround! {
+    /// The parameters of a credential management [`Request`].
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct SubCommandParams {
+        /// The SHA-256 hash of the RP ID whose credentials are enumerated.
+        #[serde(rename = 0x01, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+        pub rp_id_hash: Option<Bytes>,
+
+        /// The credential to delete or whose user is updated.
+        #[serde(rename = 0x02, default, skip_serializing_if = Option::is_none)]
+        pub credential_id: Option<PublicKeyCredentialDescriptor>,
+
+        /// The new details of the user of the credential, whose ID must not change.
+        #[serde(rename = 0x03, default, skip_serializing_if = Option::is_none)]
+        pub user: Option<PublicKeyCredentialUserEntity>,
+    }
+}
+
+impl SubCommandParams {
+    /// The CBOR map of these parameters as authenticated by [`Request::pin_uv_auth_message`]. It
+    /// is built with explicit byte strings, since the serialization of `Bytes` depends on the
+    /// `serialize_bytes_as_base64_string` feature, and its maps are in the CTAP2 canonical order.
+    fn to_cbor_value(&self) -> Value {
+        let text = |text: &str| Value::Text(text.to_owned());
+        let mut params = Vec::new();
+        if let Some(rp_id_hash) = &self.rp_id_hash {
+            params.push((Value::from(0x01), Value::Bytes(rp_id_hash.to_vec())));
+        }
+        if let Some(credential_id) = &self.credential_id {
+            let mut descriptor = vec![
+                (text("id"), Value::Bytes(credential_id.id.to_vec())),
+                // SAFETY: the credential type is encoded as a string.
+                (text("type"), Value::serialized(&credential_id.ty).unwrap()),
+            ];
+            if let Some(transports) = &credential_id.transports {
+                // SAFETY: the transports are encoded as an array of strings.
+                descriptor.push((text("transports"), Value::serialized(transports).unwrap()));
+            }
+            params.push((Value::from(0x02), Value::Map(descriptor)));
+        }
+        if let Some(user) = &self.user {
+            let mut entity = vec![(text("id"), Value::Bytes(user.id.to_vec()))];
+            for (key, value) in [
+                ("icon", &user.icon_url),
+                ("name", &user.name),
+                ("displayName", &user.display_name),
+            ] {
+                if let Some(value) = value {
+                    entity.push((text(key), text(value)));
+                }
+            }
+            params.push((Value::from(0x03), Value::Map(entity)));
+        }
+        Value::Map(params)
+    }
+}
+
+/// The response to a credential management [`Request`], only the members returned by its
+/// subcommand are present.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "repr::ResponseRepr", into = "repr::ResponseRepr")]
+pub struct Response {
+    /// The number of discoverable credentials on the authenticator.
+    pub existing_resident_credentials_count: Option<u32>,
+
+    /// The number of discoverable credentials that can still be created.
+    pub max_possible_remaining_resident_credentials_count: Option<u32>,
+
+    /// A relying party with discoverable credentials.
+    pub rp: Option<PublicKeyCredentialRpEntity>,
+
+    /// The SHA-256 hash of the ID of `rp`.
+    pub rp_id_hash: Option<Bytes>,
+
+    /// The number of relying parties with discoverable credentials.
+    pub total_rps: Option<u32>,
+
+    /// The user of a discoverable credential.
+    pub user: Option<PublicKeyCredentialUserEntity>,
+
+    /// The ID of a discoverable credential.
+    pub credential_id: Option<PublicKeyCredentialDescriptor>,
+
+    /// The public key of the credential.
+    pub public_key: Option<CoseKey>,
+
+    /// The number of discoverable credentials of the relying party.
+    pub total_credentials: Option<u32>,
+
+    /// The `credProtect` policy of the credential.
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+
+    /// The `largeBlobKey` of the credential.
+    pub large_blob_key: Option<Bytes>,
+}
+
+mod repr {
+    use ciborium::Value;
+
+    use crate::{
+        ctap2::make_credential::{PublicKeyCredentialRpEntity, PublicKeyCredentialUserEntity},
+        utils::serde::cbor_bytes_opt,
+        webauthn::PublicKeyCredentialDescriptor,
+        Bytes,
+    };
+
+    serde_workaround! {
+        /// The CBOR representation of a credential management [`Response`](super::Response),
+        /// where the public key is a raw COSE key value and the `credProtect` policy is its CTAP2
+        /// value.
+        #[derive(Clone)]
+        pub struct ResponseRepr {
+            #[serde(rename = 0x01, default, skip_serializing_if = Option::is_none)]
+            pub(super) existing_resident_credentials_count: Option<u32>,
+
+            #[serde(rename = 0x02, default, skip_serializing_if = Option::is_none)]
+            pub(super) max_possible_remaining_resident_credentials_count: Option<u32>,
+
+            #[serde(rename = 0x03, default, skip_serializing_if = Option::is_none)]
+            pub(super) rp: Option<PublicKeyCredentialRpEntity>,
+
+            #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+            pub(super) rp_id_hash: Option<Bytes>,
+
+            #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none)]
+            pub(super) total_rps: Option<u32>,
+
+            #[serde(rename = 0x06, default, skip_serializing_if = Option::is_none)]
+            pub(super) user: Option<PublicKeyCredentialUserEntity>,
+
+            #[serde(rename = 0x07, default, skip_serializing_if = Option::is_none)]
+            pub(super) credential_id: Option<PublicKeyCredentialDescriptor>,
+
+            #[serde(rename = 0x08, default, skip_serializing_if = Option::is_none)]
+            pub(super) public_key: Option<Value>,
+
+            #[serde(rename = 0x09, default, skip_serializing_if = Option::is_none)]
+            pub(super) total_credentials: Option<u32>,
+
+            #[serde(rename = 0x0A, default, skip_serializing_if = Option::is_none)]
+            pub(super) cred_protect: Option<u8>,
+
+            #[serde(rename = 0x0B, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+            pub(super) large_blob_key: Option<Bytes>,
+        }
+    }
+}
+
+impl TryFrom<repr::ResponseRepr> for Response {
+    type Error = coset::CoseError;
+
+    fn try_from(repr: repr::ResponseRepr) -> Result<Self, Self::Error> {
+        Ok(Self {
+            existing_resident_credentials_count: repr.existing_resident_credentials_count,
+            max_possible_remaining_resident_credentials_count: repr
+                .max_possible_remaining_resident_credentials_count,
+            rp: repr.rp,
+            rp_id_hash: repr.rp_id_hash,
+            total_rps: repr.total_rps,
+            user: repr.user,
+            credential_id: repr.credential_id,
+            public_key: repr.public_key.map(CoseKey::from_cbor_value).transpose()?,
+            total_credentials: repr.total_credentials,
+            // Unknown policies are ignored, like in the `credProtect` extension.
+            cred_protect: repr
+                .cred_protect
+                .and_then(CredentialProtectionPolicy::from_ctap_value),
+            large_blob_key: repr.large_blob_key,
+        })
+    }
+}
+
+impl From<Response> for repr::ResponseRepr {
+    fn from(response: Response) -> Self {
+        Self {
+            existing_resident_credentials_count: response.existing_resident_credentials_count,
+            max_possible_remaining_resident_credentials_count: response
+                .max_possible_remaining_resident_credentials_count,
+            rp: response.rp,
+            rp_id_hash: response.rp_id_hash,
+            total_rps: response.total_rps,
+            user: response.user,
+            credential_id: response.credential_id,
+            // SAFETY: encoding a COSE key into a CBOR value cannot fail.
+            public_key: response.public_key.map(|key| key.to_cbor_value().unwrap()),
+            total_credentials: response.total_credentials,
+            cred_protect: response
+                .cred_protect
+                .map(CredentialProtectionPolicy::ctap_value),
+            large_blob_key: response.large_blob_key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::{cbor, Value};
+
+    use super::{Request, Response, SubCommand, SubCommandParams};
+    use crate::{
+        ctap2::make_credential::PublicKeyCredentialUserEntity,
+        webauthn::{
+            CredentialProtectionPolicy, PublicKeyCredentialDescriptor, PublicKeyCredentialType,
+        },
+    };
+
+    #[test]
+    fn auth_message_is_the_subcommand_followed_by_its_parameters() {
+        let request = Request {
+            sub_command: SubCommand::EnumerateCredentialsBegin,
+            sub_command_params: Some(SubCommandParams {
+                rp_id_hash: Some(vec![7; 32].into()),
+                ..Default::default()
+            }),
+            pin_uv_auth_protocol: Some(1),
+            pin_uv_auth_param: Some(vec![1; 16].into()),
+        };
+
+        let message = request.pin_uv_auth_message();
+
+        let mut expected = vec![0x04];
+        ciborium::ser::into_writer(
+            &cbor!({ 0x01 => Value::Bytes(vec![7; 32]) }).unwrap(),
+            &mut expected,
+        )
+        .unwrap();
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn auth_message_encodes_credential_and_user_ids_as_byte_strings() {
+        let request = Request {
+            sub_command: SubCommand::UpdateUserInformation,
+            sub_command_params: Some(SubCommandParams {
+                rp_id_hash: None,
+                credential_id: Some(PublicKeyCredentialDescriptor {
+                    ty: PublicKeyCredentialType::PublicKey,
+                    id: vec![1; 16].into(),
+                    transports: None,
+                }),
+                user: Some(PublicKeyCredentialUserEntity {
+                    id: vec![2; 16].into(),
+                    name: Some("wendy".into()),
+                    display_name: Some("Wendy Appleseed".into()),
+                    icon_url: None,
+                }),
+            }),
+            pin_uv_auth_protocol: Some(1),
+            pin_uv_auth_param: Some(vec![1; 16].into()),
+        };
+
+        let message = request.pin_uv_auth_message();
+
+        let mut expected = vec![0x07];
+        ciborium::ser::into_writer(
+            &cbor!({
+                0x02 => { "id" => Value::Bytes(vec![1; 16]), "type" => "public-key" },
+                0x03 => {
+                    "id" => Value::Bytes(vec![2; 16]),
+                    "name" => "wendy",
+                    "displayName" => "Wendy Appleseed",
+                },
+            })
+            .unwrap(),
+            &mut expected,
+        )
+        .unwrap();
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn response_only_encodes_present_members() {
+        let response = Response {
+            total_credentials: Some(2),
+            cred_protect: Some(CredentialProtectionPolicy::UserVerificationRequired),
+            ..Default::default()
+        };
+
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&response, &mut serialized).unwrap();
+        let value: Value = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+        let deserialized: Response = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+
+        assert_eq!(value, cbor!({ 0x09 => 2, 0x0A => 3 }).unwrap());
+        assert_eq!(deserialized, response);
+    }
+}
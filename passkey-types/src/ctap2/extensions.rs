@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctap2::Aaguid,
+    webauthn::{CredentialProtectionPolicy, UvmEntry},
+    Bytes,
+};
+
+#[cfg(doc)]
+use crate::ctap2::AuthenticatorData;
+
+/// The authenticator extension outputs in the extensions of an [`AuthenticatorData`], as returned
+/// by [`AuthenticatorData::extensions`].
+///
+/// Only the outputs of the extensions known to this library are parsed, the outputs of other
+/// extensions can be read from [`AuthenticatorData::extensions_cbor`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthenticatorExtensionOutputs {
+    /// The output of the [`hmac-secret`] extension.
+    ///
+    /// [`hmac-secret`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension
+    #[serde(
+        rename = "hmac-secret",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hmac_secret: Option<ExtensionOutput>,
+
+    /// The protection policy applied to the new credential by the [`credProtect`] extension.
+    ///
+    /// [`credProtect`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-credProtect-extension
+    #[serde(
+        rename = "credProtect",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::ctap2::make_credential::cred_protect_as_ctap_value"
+    )]
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+
+    /// The output of the [`credBlob`] extension.
+    ///
+    /// [`credBlob`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-credBlob-extension
+    #[serde(rename = "credBlob", default, skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<ExtensionOutput>,
+
+    /// The minimum PIN length of the authenticator reported by the [`minPinLength`] extension.
+    ///
+    /// [`minPinLength`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-minpinlength-extension
+    #[serde(
+        rename = "minPinLength",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_pin_length: Option<u32>,
+
+    /// The user verification methods used for the ceremony, reported by the [`uvm`] extension.
+    ///
+    /// [`uvm`]: https://w3c.github.io/webauthn/#sctn-uvm-extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<Vec<UvmEntry>>,
+
+    /// The CBOR encoded [`DevicePublicKeyAttestation`] produced by the [`devicePubKey`]
+    /// extension.
+    ///
+    /// [`devicePubKey`]: https://github.com/w3c/webauthn/pull/1663
+    #[serde(
+        rename = "devicePubKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub device_pub_key: Option<Bytes>,
+}
+
+/// The output of an extension whose type depends on the operation, such as `hmac-secret` and
+/// `credBlob`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ExtensionOutput {
+    /// Whether the extension was processed when the credential was created.
+    Created(bool),
+    /// The data returned by the extension with an assertion, such as the encrypted
+    /// `hmac-secret` outputs or the stored `credBlob`.
+    Asserted(Bytes),
+}
+
+/// The attestation of a device public key, produced by the [`devicePubKey`] extension, which
+/// binds a key pair that never leaves the device to a credential that may be synced.
+///
+/// [`devicePubKey`]: https://github.com/w3c/webauthn/pull/1663
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DevicePublicKeyAttestation {
+    /// The signature of the client data hash followed by the credential ID, made with the private
+    /// key of the device.
+    pub sig: Bytes,
+
+    /// The AAGUID of the authenticator of the device.
+    pub aaguid: Aaguid,
+
+    /// The CBOR encoded COSE_Key of the public key of the device.
+    pub dpk: Bytes,
+
+    /// Whether the device public key is scoped to the RP ID, with `1`, or to the whole device,
+    /// with `0`.
+    pub scope: u8,
+
+    /// The nonce added to the attestation statement, which is empty without attestation.
+    pub nonce: Bytes,
+
+    /// The format of the attestation statement, such as `"none"`.
+    pub fmt: String,
+
+    /// The attestation statement of the device public key.
+    #[serde(rename = "attStmt")]
+    pub att_stmt: ciborium::Value,
+}
+
+impl DevicePublicKeyAttestation {
+    /// Parse the CBOR encoded device public key attestation of
+    /// [`AuthenticatorExtensionOutputs::device_pub_key`].
+    pub fn from_slice(data: &[u8]) -> coset::Result<Self> {
+        ciborium::de::from_reader(data).map_err(|error| {
+            coset::CoseError::DecodeFailed(ciborium::de::Error::Semantic(None, error.to_string()))
+        })
+    }
+
+    /// The public key of the device, decoded from [`Self::dpk`].
+    pub fn device_public_key(&self) -> coset::Result<coset::CoseKey> {
+        coset::CborSerializable::from_slice(&self.dpk)
+    }
+}
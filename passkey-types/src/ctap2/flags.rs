@@ -22,6 +22,42 @@ bitflags! {
     }
 }
 
+/// The flags of an [`AuthenticatorData`](super::AuthenticatorData), named after the WebAuthn
+/// specification.
+pub type AuthenticatorDataFlags = Flags;
+
+impl Flags {
+    /// Whether the user is present, [`Flags::UP`].
+    pub fn user_present(&self) -> bool {
+        self.contains(Flags::UP)
+    }
+
+    /// Whether the user is verified, [`Flags::UV`].
+    pub fn user_verified(&self) -> bool {
+        self.contains(Flags::UV)
+    }
+
+    /// Whether the credential is eligible to be backed up, [`Flags::BE`].
+    pub fn backup_eligible(&self) -> bool {
+        self.contains(Flags::BE)
+    }
+
+    /// Whether the credential is currently backed up, [`Flags::BS`].
+    pub fn backed_up(&self) -> bool {
+        self.contains(Flags::BS)
+    }
+
+    /// Whether the authenticator data includes attested credential data, [`Flags::AT`].
+    pub fn attested_credential_data_included(&self) -> bool {
+        self.contains(Flags::AT)
+    }
+
+    /// Whether the authenticator data includes extension outputs, [`Flags::ED`].
+    pub fn extension_data_included(&self) -> bool {
+        self.contains(Flags::ED)
+    }
+}
+
 impl Default for Flags {
     fn default() -> Self {
         Flags::BE | Flags::BS
@@ -34,10 +70,41 @@ impl From<Flags> for u8 {
     }
 }
 
-impl TryFrom<u8> for Flags {
-    type Error = ();
+/// Bits which are reserved for future use are retained so that the conversion round-trips.
+impl From<u8> for Flags {
+    fn from(value: u8) -> Self {
+        Flags::from_bits_retain(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Flags;
+
+    #[test]
+    fn flags_round_trip_through_byte() {
+        let byte = 0b1100_0101;
+
+        let flags = Flags::from(byte);
+
+        assert!(flags.user_present());
+        assert!(flags.user_verified());
+        assert!(!flags.backup_eligible());
+        assert!(!flags.backed_up());
+        assert!(flags.attested_credential_data_included());
+        assert!(flags.extension_data_included());
+        assert_eq!(flags, Flags::UP | Flags::UV | Flags::AT | Flags::ED);
+        assert_eq!(u8::from(flags), byte);
+    }
+
+    #[test]
+    fn reserved_bits_are_retained() {
+        let byte = 0b0010_1010;
+
+        let flags = Flags::from(byte);
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Flags::from_bits(value).ok_or(())
+        assert_eq!(flags, Flags::BE | Flags::from_bits_retain(0b0010_0010));
+        assert!(flags.backup_eligible());
+        assert_eq!(u8::from(flags), byte);
     }
 }
@@ -1,8 +1,12 @@
 //! <https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticatorGetAssertion>
+use coset::{AsCborValue, CoseKey};
+use serde::{Deserialize, Serialize};
+
 use crate::{
     ctap2::AuthenticatorData,
     webauthn::{
-        AuthenticationExtensionsClientInputs, PublicKeyCredentialDescriptor,
+        AuthenticationExtensionsDevicePublicKeyInputs, AuthenticationExtensionsPrfInputs,
+        AuthenticationExtensionsPrfValues, PublicKeyCredentialDescriptor,
         PublicKeyCredentialUserEntity,
     },
     Bytes,
@@ -36,7 +40,7 @@ serde_workaround! {
         /// Parameters to influence authenticator operation. These parameters might be authenticator
         /// specific.
         #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none)]
-        pub extensions: Option<AuthenticationExtensionsClientInputs>,
+        pub extensions: Option<ExtensionInputs>,
 
         /// Parameters to influence authenticator operation, see [`Options`] for more details.
         #[serde(rename = 0x05, default)]
@@ -106,5 +110,198 @@ serde_workaround! {
         /// file an enhancement request if this limit impacts your application.
         #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none)]
         pub number_of_credentials: Option<u8>,
+
+        /// The key with which the client decrypts the blob of the asserted credential in the
+        /// large-blob array, present when requested through
+        /// [`ExtensionInputs::large_blob_key`] and the credential was created with one.
+        #[serde(rename = 0x07, default, skip_serializing_if = Option::is_none)]
+        pub large_blob_key: Option<Bytes>,
+
+        /// Extension outputs which are not part of the signed authenticator data, as introduced
+        /// in CTAP 2.2.
+        #[serde(rename = 0x08, default, skip_serializing_if = Option::is_none)]
+        pub unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
+    }
+}
+
+/// The authenticator extension inputs of a [`Request`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionInputs {
+    /// The inputs of the pseudo-random function of credentials created with the `hmac-secret`
+    /// extension, for clients that run alongside the authenticator and therefore do not need to
+    /// encrypt them. Unlike the WebAuthn `prf` inputs, every value MUST already be hashed into a
+    /// 32 byte `hmac-secret` salt by the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prf: Option<AuthenticationExtensionsPrfInputs>,
+
+    /// The encrypted salts of the [`hmac-secret`] extension. The authenticator returns the
+    /// encrypted results with an `"hmac-secret"` entry in the extensions of the authenticator
+    /// data, unless the credential was created without the extension.
+    ///
+    /// [`hmac-secret`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension
+    #[serde(
+        rename = "hmac-secret",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hmac_secret: Option<HmacSecretInput>,
+
+    /// Request the [`largeBlobKey`] of the asserted credential, which is returned in
+    /// [`Response::large_blob_key`] when the credential has one. Only `true` is a valid value.
+    ///
+    /// [`largeBlobKey`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-largeBlobKey-extension
+    #[serde(
+        rename = "largeBlobKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub large_blob_key: Option<bool>,
+
+    /// Request the user verification methods used for the ceremony through the [`uvm`]
+    /// extension. The authenticator reports them with a `"uvm"` entry in the extensions of the
+    /// authenticator data.
+    ///
+    /// [`uvm`]: https://w3c.github.io/webauthn/#sctn-uvm-extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<bool>,
+
+    /// Request a device public key attestation through the [`devicePubKey`] extension. The
+    /// authenticator reports it with a `"devicePubKey"` entry in the extensions of the
+    /// authenticator data.
+    ///
+    /// [`devicePubKey`]: https://github.com/w3c/webauthn/pull/1663
+    #[serde(
+        rename = "devicePubKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub device_pub_key: Option<AuthenticationExtensionsDevicePublicKeyInputs>,
+}
+
+/// The input of the `hmac-secret` extension in an assertion, where the salts are encrypted with
+/// the secret shared between the platform and the authenticator through a PIN/UV auth protocol.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "repr::HmacSecretInputRepr",
+    into = "repr::HmacSecretInputRepr"
+)]
+pub struct HmacSecretInput {
+    /// The public key agreement key of the platform, used to compute the shared secret.
+    pub key_agreement: CoseKey,
+
+    /// One or two 32 byte salts, concatenated and encrypted with the shared secret.
+    pub salt_enc: Bytes,
+
+    /// The result of authenticating `salt_enc` with the shared secret.
+    pub salt_auth: Bytes,
+
+    /// The PIN/UV auth protocol used to encrypt the salts, protocol one when absent.
+    pub pin_uv_auth_protocol: Option<u8>,
+}
+
+mod repr {
+    use ciborium::Value;
+
+    use crate::Bytes;
+
+    serde_workaround! {
+        /// The CBOR representation of a [`HmacSecretInput`](super::HmacSecretInput), where the
+        /// key agreement key is a raw COSE key value.
+        #[derive(Clone)]
+        pub struct HmacSecretInputRepr {
+            #[serde(rename = 0x01)]
+            pub(super) key_agreement: Value,
+
+            #[serde(rename = 0x02)]
+            pub(super) salt_enc: Bytes,
+
+            #[serde(rename = 0x03)]
+            pub(super) salt_auth: Bytes,
+
+            #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none)]
+            pub(super) pin_uv_auth_protocol: Option<u8>,
+        }
+    }
+}
+
+impl TryFrom<repr::HmacSecretInputRepr> for HmacSecretInput {
+    type Error = coset::CoseError;
+
+    fn try_from(repr: repr::HmacSecretInputRepr) -> Result<Self, Self::Error> {
+        Ok(Self {
+            key_agreement: CoseKey::from_cbor_value(repr.key_agreement)?,
+            salt_enc: repr.salt_enc,
+            salt_auth: repr.salt_auth,
+            pin_uv_auth_protocol: repr.pin_uv_auth_protocol,
+        })
+    }
+}
+
+impl From<HmacSecretInput> for repr::HmacSecretInputRepr {
+    fn from(input: HmacSecretInput) -> Self {
+        Self {
+            // SAFETY: encoding a COSE key into a CBOR value cannot fail.
+            key_agreement: input.key_agreement.to_cbor_value().unwrap(),
+            salt_enc: input.salt_enc,
+            salt_auth: input.salt_auth,
+            pin_uv_auth_protocol: input.pin_uv_auth_protocol,
+        }
+    }
+}
+
+/// The unsigned extension outputs of a [`Response`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UnsignedExtensionOutputs {
+    /// The results of the pseudo-random function for the [`ExtensionInputs::prf`] salts selected
+    /// for the asserted credential.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prf: Option<AuthenticationExtensionsPrfValues>,
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::Value;
+    use coset::{iana, CoseKeyBuilder};
+
+    use super::{ExtensionInputs, HmacSecretInput};
+
+    #[test]
+    fn hmac_secret_input_is_encoded_with_integer_keys() {
+        let extensions = ExtensionInputs {
+            prf: None,
+            hmac_secret: Some(HmacSecretInput {
+                key_agreement: CoseKeyBuilder::new_ec2_pub_key(
+                    iana::EllipticCurve::P_256,
+                    vec![1; 32],
+                    vec![2; 32],
+                )
+                .build(),
+                salt_enc: vec![3; 32].into(),
+                salt_auth: vec![4; 16].into(),
+                pin_uv_auth_protocol: None,
+            }),
+            large_blob_key: None,
+            uvm: None,
+            device_pub_key: None,
+        };
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&extensions, &mut serialized)
+            .expect("Could not serialize to cbor");
+
+        let value: Value =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+        let deserialized: ExtensionInputs =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+
+        let (id, input) = value.into_map().unwrap().remove(0);
+        let keys: Vec<_> = input
+            .into_map()
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(id, Value::from("hmac-secret"));
+        assert_eq!(keys, [1, 2, 3].map(Value::from));
+        assert_eq!(deserialized, extensions);
     }
 }
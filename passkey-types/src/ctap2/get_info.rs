@@ -1,9 +1,12 @@
 //! <https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticatorGetInfo>
-use std::{borrow::Cow, num::NonZeroU128};
+use std::{borrow::Cow, collections::BTreeMap, num::NonZeroU128};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{utils::serde::ignore_unknown_opt_vec, webauthn::AuthenticatorTransport};
+use crate::{
+    utils::serde::ignore_unknown_opt_vec,
+    webauthn::{AuthenticatorTransport, PublicKeyCredentialParameters},
+};
 
 use super::Aaguid;
 
@@ -44,6 +47,12 @@ serde_workaround! {
         #[serde(rename = 0x06, default, skip_serializing_if = Option::is_none)]
         pub pin_protocols: Option<Vec<u8>>,
 
+        /// Maximum length, in bytes, of the credential IDs this authenticator accepts. Platforms
+        /// can leave out the credentials of an allow list that are longer, as they cannot belong
+        /// to this authenticator.
+        #[serde(rename = 0x08, default, skip_serializing_if = Option::is_none)]
+        pub max_credential_id_length: Option<usize>,
+
         /// List of supported transports. Values are taken from the [`AuthenticatorTransport`] enum.
         /// The list MUST NOT include duplicate values nor be empty if present.
         /// Platforms MUST tolerate unknown values by ignoring them.
@@ -54,6 +63,42 @@ serde_workaround! {
             deserialize_with = ignore_unknown_opt_vec
         )]
         pub transports: Option<Vec<AuthenticatorTransport>>,
+
+        /// List of supported algorithms for credential generation, in the authenticator's order
+        /// of preference. Platforms MUST tolerate unknown values by ignoring them.
+        #[serde(
+            rename = 0x0A,
+            default,
+            skip_serializing_if = Option::is_none,
+            deserialize_with = ignore_unknown_opt_vec
+        )]
+        pub algorithms: Option<Vec<PublicKeyCredentialParameters>>,
+
+        /// The maximum size, in bytes, of the serialized large-blob array this authenticator can
+        /// store. Only present when the `largeBlobs` option is supported, in which case it MUST be
+        /// at least 1024.
+        #[serde(rename = 0x0B, default, skip_serializing_if = Option::is_none)]
+        pub max_serialized_large_blob_array: Option<usize>,
+
+        /// The minimum length of a PIN, in Unicode code points, which is at least 4. (Optional)
+        #[serde(rename = 0x0D, default, skip_serializing_if = Option::is_none)]
+        pub min_pin_length: Option<u32>,
+
+        /// The user verification modality of the authenticator, as a combination of the
+        /// `USER_VERIFY` flags of the FIDO Registry of Predefined Values, such as `0x0002` for
+        /// fingerprint verification. (Optional)
+        #[serde(rename = 0x12, default, skip_serializing_if = Option::is_none)]
+        pub uv_modality: Option<u32>,
+
+        /// The certifications obtained by the authenticator, which map the name of a
+        /// certification program, such as `FIPS-CMVP-2` or `FIDO`, to the level reached in it.
+        /// (Optional)
+        #[serde(rename = 0x13, default, skip_serializing_if = Option::is_none)]
+        pub certifications: Option<BTreeMap<String, u64>>,
+
+        /// Estimated number of additional discoverable credentials that can be stored. (Optional)
+        #[serde(rename = 0x14, default, skip_serializing_if = Option::is_none)]
+        pub remaining_discoverable_credentials: Option<u32>,
     }
 }
 
@@ -108,6 +153,51 @@ pub struct Options {
     ///  it will return both "uv" and the Client PIN option.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub uv: Option<bool>,
+
+    /// Always Require User Verification:
+    /// If `Some(true)`, it indicates that the device requires some form of user verification for
+    /// all requests, even when the "uv" option was not requested.
+    ///
+    /// If `Some(false)`, it indicates that the device supports always requiring user verification
+    /// but it is currently disabled.
+    ///
+    /// If `None`, it indicates that the device does not support always requiring user verification.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub always_uv: Option<bool>,
+
+    /// Make Credential without User Verification:
+    /// If `Some(true)`, it indicates that the device allows the creation of credentials without
+    /// requiring any form of user verification, even if it is protected by user verification.
+    ///
+    /// If `Some(false)` or `None`, user verification is required to create credentials when the
+    /// device is protected by some form of user verification.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make_cred_uv_not_rqd: Option<bool>,
+
+    /// Large Blobs: Indicates that the device supports the `authenticatorLargeBlobs` command
+    /// and the `largeBlobKey` extension.
+    ///
+    /// If `None`, it indicates that the device does not support storing large blobs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_blobs: Option<bool>,
+
+    /// Credential Management: Indicates that the device supports the
+    /// `authenticatorCredentialManagement` command.
+    ///
+    /// If `None`, it indicates that the device does not support managing its credentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cred_mgmt: Option<bool>,
+
+    /// Enterprise Attestation: Indicates that the device supports enterprise attestation.
+    ///
+    /// If `Some(true)`, enterprise attestation is enabled and can be requested through the
+    /// `enterpriseAttestation` parameter of `authenticatorMakeCredential`.
+    ///
+    /// If `Some(false)`, the device supports enterprise attestation but it is disabled.
+    ///
+    /// If `None`, it indicates that the device does not support enterprise attestation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ep: Option<bool>,
 }
 
 #[must_use]
@@ -124,15 +214,24 @@ impl Default for Options {
             client_pin: None,
             up: true,
             uv: None,
+            always_uv: None,
+            make_cred_uv_not_rqd: None,
+            large_blobs: None,
+            cred_mgmt: None,
+            ep: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use ciborium::cbor;
+    use coset::iana;
 
-    use super::{Aaguid, AuthenticatorTransport, Options, Response};
+    use super::{Aaguid, AuthenticatorTransport, Options, PublicKeyCredentialParameters, Response};
+    use crate::webauthn::PublicKeyCredentialType;
     #[test]
     fn serialization_round_trip() {
         let expected = Response {
@@ -145,11 +244,21 @@ mod tests {
                 ..Default::default()
             }),
             max_msg_size: None,
+            algorithms: Some(vec![PublicKeyCredentialParameters {
+                ty: PublicKeyCredentialType::PublicKey,
+                alg: iana::Algorithm::RS256,
+            }]),
+            max_serialized_large_blob_array: None,
+            min_pin_length: Some(6),
             pin_protocols: Some(vec![1]),
+            max_credential_id_length: Some(128),
             transports: Some(vec![
                 AuthenticatorTransport::Internal,
                 AuthenticatorTransport::Hybrid,
             ]),
+            uv_modality: Some(0x0002),
+            certifications: Some(BTreeMap::from([("FIDO".into(), 2)])),
+            remaining_discoverable_credentials: Some(3),
         };
         let mut serialized = Vec::new();
         ciborium::ser::into_writer(&expected, &mut serialized)
@@ -175,11 +284,18 @@ mod tests {
                 ..Default::default()
             }),
             max_msg_size: None,
+            algorithms: None,
+            max_serialized_large_blob_array: None,
+            min_pin_length: None,
             pin_protocols: Some(vec![1]),
+            max_credential_id_length: Some(128),
             transports: Some(vec![
                 AuthenticatorTransport::Internal,
                 AuthenticatorTransport::Hybrid,
             ]),
+            uv_modality: Some(0x0002),
+            certifications: Some(BTreeMap::from([("FIDO".into(), 2)])),
+            remaining_discoverable_credentials: None,
         };
         let mut serialized = Vec::new();
         ciborium::ser::into_writer(&input, &mut serialized).expect("Could not serialize to cbor");
@@ -200,7 +316,10 @@ mod tests {
             },
             // maxMsgSize should be skipped
             0x06 => vec![1],
-            0x09 => vec!["internal", "hybrid"]
+            0x08 => 128,
+            0x09 => vec!["internal", "hybrid"],
+            0x12 => 0x0002,
+            0x13 => { "FIDO" => 2 }
         })
         .unwrap();
 
@@ -243,8 +362,15 @@ mod tests {
                 ..Default::default()
             }),
             max_msg_size: None,
+            algorithms: None,
+            max_serialized_large_blob_array: None,
+            min_pin_length: None,
             pin_protocols: Some(vec![1]),
+            max_credential_id_length: None,
             transports: Some(vec![AuthenticatorTransport::Hybrid]),
+            uv_modality: None,
+            certifications: None,
+            remaining_discoverable_credentials: None,
         };
 
         assert_eq!(expected, deserialized);
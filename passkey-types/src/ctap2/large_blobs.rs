@@ -0,0 +1,182 @@
+//! <https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#authenticatorLargeBlobs>
+use ciborium::Value;
+
+use crate::{crypto::sha256, utils::serde::cbor_bytes_opt, Bytes};
+
+/// The serialized large-blob array of an authenticator on which nothing was written yet: an
+/// empty CBOR array followed by the first 16 bytes of its SHA-256 hash.
+pub const INITIAL_SERIALIZED_ARRAY: [u8; 17] = [
+    0x80, 0x76, 0xbe, 0x8b, 0x52, 0x8d, 0x00, 0x75, 0xf7, 0xaa, 0xe9, 0x8d, 0x6f, 0xa5, 0x7a, 0x6d,
+    0x3c,
+];
+
+/// The length of the truncated hash that ends a serialized large-blob array.
+pub const CHECKSUM_LENGTH: usize = 16;
+
+serde_workaround! {
+    /// Request to read or write a fragment of the serialized large-blob array, exactly one of
+    /// `get` and `set` must be present.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Request {
+        /// The number of bytes to read from `offset`.
+        #[serde(rename = 0x01, default, skip_serializing_if = Option::is_none)]
+        pub get: Option<usize>,
+
+        /// A fragment to write at `offset`.
+        #[serde(rename = 0x02, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+        pub set: Option<Bytes>,
+
+        /// The byte offset at which to read or write.
+        #[serde(rename = 0x03)]
+        pub offset: usize,
+
+        /// The total length of the array being written, only present with the first fragment of
+        /// a write, which is the one at offset zero.
+        #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none)]
+        pub length: Option<usize>,
+
+        /// The result of authenticating the fragment being written with a PIN/UV auth token.
+        #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+        pub pin_uv_auth_param: Option<Bytes>,
+
+        /// The PIN/UV auth protocol used to compute `pin_uv_auth_param`.
+        #[serde(rename = 0x06, default, skip_serializing_if = Option::is_none)]
+        pub pin_uv_auth_protocol: Option<u8>,
+    }
+}
+
+serde_workaround! {
+    /// The response to a large-blob [`Request`].
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Response {
+        /// The fragment that was read, absent after a write.
+        #[serde(rename = 0x01, default, skip_serializing_if = Option::is_none, serialize_with = cbor_bytes_opt)]
+        pub config: Option<Bytes>,
+    }
+}
+
+serde_workaround! {
+    /// An entry of the large-blob array, which is the blob of one credential encrypted with
+    /// AES-256-GCM under its `largeBlobKey`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct LargeBlobEntry {
+        /// The AES-256-GCM ciphertext of the compressed blob, including the 16 byte tag.
+        #[serde(rename = 0x01)]
+        pub ciphertext: Bytes,
+
+        /// The 12 byte nonce used for the encryption.
+        #[serde(rename = 0x02)]
+        pub nonce: Bytes,
+
+        /// The size of the blob before it was compressed.
+        #[serde(rename = 0x03)]
+        pub orig_size: u64,
+    }
+}
+
+impl LargeBlobEntry {
+    /// The CBOR map of this entry, as it is stored in the large-blob array.
+    fn to_cbor_value(&self) -> Value {
+        Value::Map(vec![
+            (Value::from(0x01), Value::Bytes(self.ciphertext.to_vec())),
+            (Value::from(0x02), Value::Bytes(self.nonce.to_vec())),
+            (Value::from(0x03), Value::from(self.orig_size)),
+        ])
+    }
+}
+
+/// Whether the serialized large-blob array ends with the first 16 bytes of the SHA-256 hash of
+/// its CBOR array, as required before it is stored or read.
+pub fn has_valid_checksum(serialized: &[u8]) -> bool {
+    serialized.len() > CHECKSUM_LENGTH && {
+        let (array, checksum) = serialized.split_at(serialized.len() - CHECKSUM_LENGTH);
+        sha256(array)[..CHECKSUM_LENGTH] == *checksum
+    }
+}
+
+/// Serialize the entries into a large-blob array ended by its truncated hash.
+pub fn serialize_array(entries: &[LargeBlobEntry]) -> Vec<u8> {
+    // The entries are encoded with explicit byte strings, since the serialization of `Bytes`
+    // depends on the `serialize_bytes_as_base64_string` feature.
+    let array = Value::Array(entries.iter().map(LargeBlobEntry::to_cbor_value).collect());
+    let mut serialized = Vec::new();
+    // SAFETY: Serializing into a Vec cannot fail.
+    ciborium::ser::into_writer(&array, &mut serialized).unwrap();
+    let checksum = sha256(&serialized);
+    serialized.extend_from_slice(&checksum[..CHECKSUM_LENGTH]);
+    serialized
+}
+
+/// Parse a serialized large-blob array into its entries. An array whose checksum is invalid is
+/// read as the initial empty array, and entries that are not well formed are skipped, so that a
+/// corrupted array never prevents writing a new one.
+pub fn deserialize_array(serialized: &[u8]) -> Vec<LargeBlobEntry> {
+    if !has_valid_checksum(serialized) {
+        return Vec::new();
+    }
+    let array = &serialized[..serialized.len() - CHECKSUM_LENGTH];
+    ciborium::de::from_reader::<Vec<Value>, _>(array)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|entry| entry.deserialized().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::cbor;
+
+    use super::{
+        deserialize_array, has_valid_checksum, serialize_array, LargeBlobEntry, Request,
+        INITIAL_SERIALIZED_ARRAY,
+    };
+
+    #[test]
+    fn initial_array_is_an_empty_array_with_its_checksum() {
+        assert!(has_valid_checksum(&INITIAL_SERIALIZED_ARRAY));
+        assert_eq!(serialize_array(&[]), INITIAL_SERIALIZED_ARRAY);
+        assert!(deserialize_array(&INITIAL_SERIALIZED_ARRAY).is_empty());
+    }
+
+    #[test]
+    fn entries_round_trip_through_the_serialized_array() {
+        let entry = LargeBlobEntry {
+            ciphertext: vec![1; 32].into(),
+            nonce: vec![2; 12].into(),
+            orig_size: 64,
+        };
+
+        let serialized = serialize_array(&[entry.clone()]);
+        let mut corrupted = serialized.clone();
+        corrupted[1] ^= 1;
+
+        assert_eq!(deserialize_array(&serialized), vec![entry]);
+        assert!(deserialize_array(&corrupted).is_empty());
+    }
+
+    #[test]
+    fn request_is_encoded_with_integer_keys() {
+        let request = Request {
+            get: None,
+            set: Some(vec![0x80].into()),
+            offset: 0,
+            length: Some(17),
+            pin_uv_auth_param: None,
+            pin_uv_auth_protocol: None,
+        };
+
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&request, &mut serialized).unwrap();
+        let value: ciborium::Value = ciborium::de::from_reader(serialized.as_slice()).unwrap();
+
+        assert_eq!(
+            value,
+            cbor!({
+                0x02 => ciborium::Value::Bytes(vec![0x80]),
+                0x03 => 0,
+                0x04 => 17,
+            })
+            .unwrap()
+        );
+    }
+}
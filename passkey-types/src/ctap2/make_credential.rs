@@ -10,6 +10,14 @@ use crate::webauthn::{
     CollectedClientData, PublicKeyCredentialCreationOptions, PublicKeyCredentialDescriptor,
 };
 
+/// The `enterpriseAttestation` value requesting the enterprise attestation the authenticator's
+/// vendor facilitates for the relying parties it was configured with.
+pub const ENTERPRISE_ATTESTATION_VENDOR_FACILITATED: u8 = 1;
+
+/// The `enterpriseAttestation` value requesting an enterprise attestation on behalf of a platform
+/// that is managed to only request it for the relying parties of the enterprise.
+pub const ENTERPRISE_ATTESTATION_PLATFORM_MANAGED: u8 = 2;
+
 serde_workaround! {
     /// While similar in structure to [`PublicKeyCredentialCreationOptions`],
     /// it is not completely identical, namely the presence of the `options` key.
@@ -63,10 +71,10 @@ serde_workaround! {
         #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none)]
         pub exclude_list: Option<Vec<webauthn::PublicKeyCredentialDescriptor>>,
 
-        /// Parameters to influence authenticator operation, as specified in [`webauthn`].
-        /// These parameters might be authenticator specific.
+        /// Parameters to influence authenticator operation, see [`ExtensionInputs`] for the
+        /// supported extensions. These parameters might be authenticator specific.
         #[serde(rename = 0x06, default, skip_serializing_if = Option::is_none)]
-        pub extensions: Option<webauthn::AuthenticationExtensionsClientInputs>,
+        pub extensions: Option<ExtensionInputs>,
 
         /// Parameters to influence authenticator operation, see [`Options`] for more details.
         #[serde(rename = 0x07, default)]
@@ -82,6 +90,20 @@ serde_workaround! {
         /// if ever we hit more than 256 protocol versions, an enhacement request should be filed.
         #[serde(rename = 0x09, default, skip_serializing_if = Option::is_none)]
         pub pin_protocol: Option<u8>,
+
+        /// Requests an enterprise attestation, which may uniquely identify the authenticator, with
+        /// either [`ENTERPRISE_ATTESTATION_VENDOR_FACILITATED`] or
+        /// [`ENTERPRISE_ATTESTATION_PLATFORM_MANAGED`]. Authenticators that do not support
+        /// enterprise attestation reject requests with this parameter.
+        #[serde(rename = 0x0A, default, skip_serializing_if = Option::is_none)]
+        pub enterprise_attestation: Option<u8>,
+
+        /// A list of attestation statement format identifiers, ordered from most preferred to least
+        /// preferred, as introduced in CTAP 2.2. When this list only contains
+        /// [`webauthn::AttestationStatementFormatIdentifiers::None`], the authenticator SHOULD NOT
+        /// produce any attestation and return the empty `none` statement instead.
+        #[serde(rename = 0x0B, default, skip_serializing_if = Option::is_none)]
+        pub attestation_formats_preference: Option<Vec<webauthn::AttestationStatementFormatIdentifiers>>,
     }
 }
 
@@ -170,6 +192,108 @@ impl TryFrom<webauthn::PublicKeyCredentialRpEntity> for PublicKeyCredentialRpEnt
     }
 }
 
+/// The authenticator extension inputs of a [`Request`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionInputs {
+    /// Request that the new credential can evaluate the pseudo-random function of the
+    /// [`hmac-secret`] extension. The authenticator reports whether it did so with an
+    /// `"hmac-secret"` entry in the extensions of the authenticator data.
+    ///
+    /// [`hmac-secret`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension
+    #[serde(
+        rename = "hmac-secret",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hmac_secret: Option<bool>,
+
+    /// The protection policy of the new credential, from the [`credProtect`] extension. The
+    /// authenticator reports the policy it applied with a `"credProtect"` entry in the extensions
+    /// of the authenticator data.
+    ///
+    /// [`credProtect`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-credProtect-extension
+    #[serde(
+        rename = "credProtect",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "cred_protect_as_ctap_value"
+    )]
+    pub cred_protect: Option<webauthn::CredentialProtectionPolicy>,
+
+    /// Request a [`largeBlobKey`] for the new credential, which MUST be discoverable. Unlike the
+    /// other extensions, the key is returned in [`Response::large_blob_key`] rather than in the
+    /// authenticator data.
+    ///
+    /// [`largeBlobKey`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-largeBlobKey-extension
+    #[serde(
+        rename = "largeBlobKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub large_blob_key: Option<bool>,
+
+    /// Request the minimum PIN length of the authenticator through the [`minPinLength`]
+    /// extension. The authenticator only reports it, with a `"minPinLength"` entry in the
+    /// extensions of the authenticator data, to the relying parties it is configured to disclose
+    /// it to.
+    ///
+    /// [`minPinLength`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-minpinlength-extension
+    #[serde(
+        rename = "minPinLength",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_pin_length: Option<bool>,
+
+    /// Request the user verification methods used for the ceremony through the [`uvm`]
+    /// extension. The authenticator reports them with a `"uvm"` entry in the extensions of the
+    /// authenticator data.
+    ///
+    /// [`uvm`]: https://w3c.github.io/webauthn/#sctn-uvm-extension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<bool>,
+
+    /// Request a device public key attestation through the [`devicePubKey`] extension. The
+    /// authenticator reports it with a `"devicePubKey"` entry in the extensions of the
+    /// authenticator data.
+    ///
+    /// [`devicePubKey`]: https://github.com/w3c/webauthn/pull/1663
+    #[serde(
+        rename = "devicePubKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub device_pub_key: Option<webauthn::AuthenticationExtensionsDevicePublicKeyInputs>,
+}
+
+/// Serialize a [`webauthn::CredentialProtectionPolicy`] as its CTAP2 integer value.
+pub(crate) mod cred_protect_as_ctap_value {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::webauthn::CredentialProtectionPolicy;
+
+    pub fn serialize<S: Serializer>(
+        policy: &Option<CredentialProtectionPolicy>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match policy {
+            Some(policy) => serializer.serialize_some(&policy.ctap_value()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<CredentialProtectionPolicy>, D::Error> {
+        Option::<u8>::deserialize(deserializer)?
+            .map(|value| {
+                CredentialProtectionPolicy::from_ctap_value(value)
+                    .ok_or_else(|| D::Error::custom("unknown credential protection policy"))
+            })
+            .transpose()
+    }
+}
+
 /// The options that control how an authenticator will behave.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Options {
@@ -219,10 +343,27 @@ serde_workaround! {
         // the keys
         #[serde(rename = 0x03)]
         pub att_stmt: Value,
+
+        /// Indicates whether an enterprise attestation was returned for this credential. If
+        /// `ep_att` is absent, or present and set to false, then an enterprise attestation was not
+        /// returned. If `ep_att` is present and set to true, then an enterprise attestation was
+        /// returned.
+        #[serde(rename = 0x04, default, skip_serializing_if = Option::is_none)]
+        pub ep_att: Option<bool>,
+
+        /// The 32 byte key with which the client encrypts the blobs of this credential in the
+        /// large-blob array, present when requested through [`ExtensionInputs::large_blob_key`].
+        #[serde(rename = 0x05, default, skip_serializing_if = Option::is_none)]
+        pub large_blob_key: Option<Bytes>,
     }
 }
 
 impl Response {
+    /// Whether the attestation statement of this response is the empty `none` statement.
+    pub fn is_none_attestation(&self) -> bool {
+        self.fmt == "none" && matches!(&self.att_stmt, Value::Map(map) if map.is_empty())
+    }
+
     // Note: Technically the authenticator response should be a CBOR object, but we add this method instead
     // for backwards compatibility with previous versions of this library.
     // [WebAuthn]: Return the attestation object as a CBOR map with the following syntax, filled in with variables initialized by this algorithm
@@ -235,9 +376,9 @@ impl Response {
         // programmer error.
         // TODO: Create strong attestation type definitions, part of CTAP2
         let attestation_object_value = cbor!({
-               // TODO: Follow preference and/or implement AnonCA https://w3c.github.io/webauthn/#anonymization-ca
-               "fmt" => "none",
-                "attStmt" => {},
+               // TODO: implement AnonCA https://w3c.github.io/webauthn/#anonymization-ca
+               "fmt" => self.fmt,
+                "attStmt" => self.att_stmt,
                 // Explicitly define these fields as bytes since specialization is still fairly far
                "authData" => Value::Bytes(self.auth_data.to_vec()),
         })
@@ -246,3 +387,89 @@ impl Response {
         attestation_object.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ciborium::Value;
+
+    use super::{ExtensionInputs, Response};
+    use crate::{ctap2::AuthenticatorData, webauthn::CredentialProtectionPolicy, Bytes};
+
+    /// Serialize the response, returning the keys of the encoded map along with the response
+    /// deserialized from those same bytes.
+    fn round_trip(ep_att: Option<bool>, large_blob_key: Option<Bytes>) -> (Vec<Value>, Response) {
+        let response = Response {
+            auth_data: AuthenticatorData::new("future.1password.com", None),
+            fmt: "none".into(),
+            att_stmt: Value::Map(Vec::new()),
+            ep_att,
+            large_blob_key,
+        };
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&response, &mut serialized)
+            .expect("Could not serialize to cbor");
+
+        let value: Value =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+        let keys = value
+            .into_map()
+            .expect("Response is not a map")
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        let deserialized =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+
+        (keys, deserialized)
+    }
+
+    #[test]
+    fn ep_att_is_emitted_when_enterprise_attestation_is_released() {
+        let (keys, deserialized) = round_trip(Some(true), None);
+
+        assert!(keys.contains(&Value::from(0x04)));
+        assert_eq!(deserialized.ep_att, Some(true));
+    }
+
+    #[test]
+    fn ep_att_is_absent_without_enterprise_attestation() {
+        let (keys, deserialized) = round_trip(None, None);
+
+        assert!(!keys.contains(&Value::from(0x04)));
+        assert_eq!(deserialized.ep_att, None);
+    }
+
+    #[test]
+    fn large_blob_key_is_emitted_when_requested() {
+        let (keys, deserialized) = round_trip(None, Some(vec![1; 32].into()));
+
+        assert!(keys.contains(&Value::from(0x05)));
+        assert_eq!(deserialized.large_blob_key, Some(vec![1; 32].into()));
+    }
+
+    #[test]
+    fn cred_protect_is_encoded_as_its_ctap_value() {
+        let extensions = ExtensionInputs {
+            hmac_secret: None,
+            cred_protect: Some(CredentialProtectionPolicy::UserVerificationRequired),
+            large_blob_key: None,
+            min_pin_length: None,
+            uvm: None,
+            device_pub_key: None,
+        };
+        let mut serialized = Vec::new();
+        ciborium::ser::into_writer(&extensions, &mut serialized)
+            .expect("Could not serialize to cbor");
+
+        let value: Value =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+        let deserialized: ExtensionInputs =
+            ciborium::de::from_reader(serialized.as_slice()).expect("Could not deserialize");
+
+        assert_eq!(
+            value,
+            Value::Map(vec![(Value::from("credProtect"), Value::from(3))])
+        );
+        assert_eq!(deserialized, extensions);
+    }
+}
@@ -39,7 +39,7 @@ pub mod webauthn;
 
 // Re-exports
 pub use self::{
-    passkey::Passkey,
+    passkey::{CredRandom, CredentialExtensions, Passkey},
     utils::{
         bytes::{Bytes, NotBase64Encoded},
         crypto, encoding, rand,
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, time::SystemTime};
 
 use super::u2f::{AuthenticationRequest, RegisterRequest, RegisterResponse};
 use crate::{ctap2::make_credential as ctap2, webauthn, Bytes};
@@ -83,6 +83,86 @@ pub struct Passkey {
     ///
     /// [signCount]: https://w3c.github.io/webauthn/#signature-counter
     pub counter: Option<u32>,
+
+    /// Whether this [`Passkey`] has been disabled, for example while an account is being secured.
+    /// A disabled passkey is kept in storage and can be enabled again, but it cannot be used in
+    /// authentication ceremonies. It still prevents the creation of a new credential when it is
+    /// present in an exclude list.
+    pub disabled: bool,
+
+    /// The transports the managing authenticator supported when this [`Passkey`] was created, which
+    /// are given back to the Relying Party along with the assertions made with it.
+    pub transports: Option<Vec<webauthn::AuthenticatorTransport>>,
+
+    /// The state of the extensions this [`Passkey`] was created with, which stores must persist
+    /// along with it.
+    ///
+    /// # PII considerations
+    /// This value holds secret cryptographic keys, see [`CredentialExtensions`].
+    pub extensions: CredentialExtensions,
+
+    /// When this [`Passkey`] was last used, either to make an assertion or because it was touched
+    /// by its managing authenticator, for example to record that the user viewed it. `None` until
+    /// it is first used.
+    ///
+    /// # PII considerations
+    /// This value reveals when the user last interacted with the Relying Party.
+    pub last_used_at: Option<SystemTime>,
+}
+
+/// The per-credential state of the extensions a [`Passkey`] was created with.
+///
+/// While this struct implements [`Debug`] and [`PartialEq`], they skip the secret
+/// [`Self::cred_random`] and [`Self::large_blob_key`] fields. The [`Debug`] implementation also
+/// skips [`Self::cred_blob`].
+#[derive(Clone, Default)]
+pub struct CredentialExtensions {
+    /// The protection policy of the `credProtect` extension applied to the [`Passkey`] when it
+    /// was created, which restricts its use in assertions without user verification. `None` is
+    /// equivalent to [`webauthn::CredentialProtectionPolicy::UserVerificationOptional`].
+    pub cred_protect: Option<webauthn::CredentialProtectionPolicy>,
+
+    /// The opaque data given with the `credBlob` extension when the [`Passkey`] was created, which
+    /// is returned with its assertions when requested.
+    ///
+    /// # PII considerations
+    /// This value is chosen by the Relying Party and may identify the user.
+    pub cred_blob: Option<Bytes>,
+
+    /// The 32 byte key of a discoverable [`Passkey`] created with the `largeBlobKey` extension,
+    /// with which the client encrypts its blob in the authenticator's large-blob array.
+    ///
+    /// # PII considerations
+    /// This value should be considered secret and never printed out as it is a secret
+    /// cryptographic key.
+    pub large_blob_key: Option<Bytes>,
+
+    /// The random secrets of a [`Passkey`] created with the `hmac-secret` extension, which are
+    /// used to evaluate its pseudo-random function.
+    ///
+    /// # PII considerations
+    /// These values should be considered secret and never printed out as they are secret
+    /// cryptographic keys.
+    pub cred_random: Option<CredRandom>,
+}
+
+impl PartialEq for CredentialExtensions {
+    fn eq(&self, other: &Self) -> bool {
+        self.cred_protect == other.cred_protect && self.cred_blob == other.cred_blob
+    }
+}
+
+/// The `CredRandom` secrets of a [`Passkey`] supporting the `hmac-secret` extension. Two secrets
+/// are kept so that the results of the pseudo-random function differ depending on whether the
+/// user was verified.
+///
+/// <https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-hmac-secret-extension>
+#[derive(Clone)]
+pub struct CredRandom {
+    /// The 32 byte secret used when the user was verified.
+    pub with_uv: Bytes,
+    /// The 32 byte secret used when the user was not verified.
+    pub without_uv: Bytes,
 }
 
 impl Passkey {
@@ -99,6 +179,10 @@ impl Passkey {
             rp_id: app_id.into(),
             user_handle: None,
             counter: Some(0),
+            disabled: false,
+            transports: None,
+            extensions: CredentialExtensions::default(),
+            last_used_at: None,
         }
     }
 
@@ -115,6 +199,10 @@ impl Passkey {
             rp_id: app_id.into(),
             user_handle: None,
             counter: Some(counter),
+            disabled: false,
+            transports: None,
+            extensions: CredentialExtensions::default(),
+            last_used_at: None,
         }
     }
 
@@ -149,7 +237,8 @@ impl Passkey {
     }
 }
 
-/// Custom PartialEq implementation for Passkey which skips the key field due to security reasons.
+/// Custom PartialEq implementation for Passkey which skips the key, as well as the secrets of its extensions, due to
+/// security reasons, as well as last_used_at which changes every time the passkey is used.
 /// See: https://github.com/1Password/passkey-rs/pull/24#discussion_r1633858167
 impl PartialEq for Passkey {
     fn eq(&self, other: &Self) -> bool {
@@ -157,6 +246,9 @@ impl PartialEq for Passkey {
             && self.rp_id == other.rp_id
             && self.user_handle == other.user_handle
             && self.counter == other.counter
+            && self.disabled == other.disabled
+            && self.transports == other.transports
+            && self.extensions == other.extensions
     }
 }
 
@@ -165,7 +257,7 @@ impl From<Passkey> for webauthn::PublicKeyCredentialDescriptor {
         Self {
             ty: webauthn::PublicKeyCredentialType::PublicKey,
             id: value.credential_id,
-            transports: None,
+            transports: value.transports,
         }
     }
 }
@@ -175,16 +267,25 @@ impl From<&Passkey> for webauthn::PublicKeyCredentialDescriptor {
         Self {
             ty: webauthn::PublicKeyCredentialType::PublicKey,
             id: value.credential_id.clone(),
-            transports: None,
+            transports: value.transports.clone(),
         }
     }
 }
 
+impl Debug for CredentialExtensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialExtensions")
+            .field("cred_protect", &self.cred_protect)
+            .finish()
+    }
+}
+
 impl Debug for Passkey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Passkey")
             .field("key_type", &self.key.kty)
             .field("counter", &self.counter)
+            .field("disabled", &self.disabled)
             .finish()
     }
 }
@@ -144,6 +144,18 @@ impl<'de> Deserialize<'de> for Bytes {
                     )
                 })
             }
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Bytes(v.to_vec()))
+            }
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Bytes(v))
+            }
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
                 A: serde::de::SeqAccess<'de>,
@@ -187,4 +199,16 @@ mod tests {
         serde_json::from_str::<HashMap<&str, Bytes>>(json)
             .expect_err("did not give an error as expected.");
     }
+
+    #[test]
+    fn deserialize_cbor_byte_strings() {
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(&ciborium::Value::Bytes(vec![1, 2, 3]), &mut cbor)
+            .expect("failed to serialize");
+
+        let deserialized: Bytes =
+            ciborium::de::from_reader(cbor.as_slice()).expect("failed to deserialize");
+
+        assert_eq!(deserialized, Bytes::from(vec![1, 2, 3]));
+    }
 }
@@ -7,3 +7,23 @@ pub fn sha256(data: &[u8]) -> [u8; 32] {
     // SAFETY: sha256 always gives a 32 byte array
     Sha256::digest(data).into()
 }
+
+/// Compute the hash of the serialized client data of a ceremony, which is the `clientDataHash`
+/// given to the authenticator and covered by its signature along with the authenticator data.
+///
+/// <https://w3c.github.io/webauthn/#collectedclientdata-hash-of-the-serialized-client-data>
+pub fn client_data_hash(client_data_json: &[u8]) -> [u8; 32] {
+    sha256(client_data_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_data_hash, sha256};
+
+    #[test]
+    fn client_data_hash_is_the_sha256_of_the_json() {
+        let client_data_json = br#"{"type":"webauthn.get","challenge":"AAECAwQFBgcICQoLDA0ODw","origin":"https://future.1password.com","crossOrigin":false}"#;
+
+        assert_eq!(client_data_hash(client_data_json), sha256(client_data_json));
+    }
+}
@@ -1,7 +1,14 @@
-//! Random number generator utilities used for tests
+//! Random number generator utilities used for tests and for generating challenges
 
 use rand::RngCore;
 
+use crate::Bytes;
+
+/// The minimum length in bytes of a challenge, as recommended by the WebAuthn specification.
+///
+/// <https://w3c.github.io/webauthn/#sctn-cryptographic-challenges>
+pub const MIN_CHALLENGE_LENGTH: usize = 16;
+
 fn random_fill(buffer: &mut [u8]) {
     let mut random = rand::thread_rng();
     random.fill_bytes(buffer);
@@ -13,3 +20,42 @@ pub fn random_vec(len: usize) -> Vec<u8> {
     random_fill(&mut data);
     data
 }
+
+/// The requested challenge length is shorter than [`MIN_CHALLENGE_LENGTH`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChallengeTooShort;
+
+/// Generate a cryptographically random challenge of `len` bytes for a relying party to include in
+/// the options of a ceremony.
+///
+/// Returns [`ChallengeTooShort`] when `len` is less than [`MIN_CHALLENGE_LENGTH`].
+pub fn generate_challenge(len: usize) -> Result<Bytes, ChallengeTooShort> {
+    if len < MIN_CHALLENGE_LENGTH {
+        return Err(ChallengeTooShort);
+    }
+    Ok(random_vec(len).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_challenges_are_random_and_of_the_requested_length() {
+        let first = generate_challenge(32).expect("valid challenge length");
+        let second = generate_challenge(32).expect("valid challenge length");
+
+        assert_eq!(first.len(), 32);
+        assert_eq!(second.len(), 32);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn short_challenges_are_rejected() {
+        assert_eq!(generate_challenge(15), Err(ChallengeTooShort));
+        assert_eq!(
+            generate_challenge(MIN_CHALLENGE_LENGTH).map(|challenge| challenge.len()),
+            Ok(MIN_CHALLENGE_LENGTH)
+        );
+    }
+}
@@ -40,6 +40,19 @@ where
     }
 }
 
+/// Like [`ignore_unknown`], for optional members where an unknown value is treated as if the
+/// member did not exist.
+pub(crate) fn ignore_unknown_opt<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match Option::<PossiblyUnknown<T>>::deserialize(de)? {
+        Some(PossiblyUnknown::Some(val)) => Some(val),
+        _ => None,
+    })
+}
+
 pub(crate) fn ignore_unknown_opt_vec<'de, D, T>(de: D) -> Result<Option<Vec<T>>, D::Error>
 where
     D: Deserializer<'de>,
@@ -209,3 +222,16 @@ where
     de.deserialize_any(StringOrNum(std::marker::PhantomData))
         .map(Some)
 }
+
+/// Serialize optional [`Bytes`](crate::Bytes) as a byte string whatever the
+/// `serialize_bytes_as_base64_string` feature, for the CTAP2 members that must always be CBOR
+/// byte strings.
+pub(crate) fn cbor_bytes_opt<S>(bytes: &Option<crate::Bytes>, ser: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match bytes {
+        Some(bytes) => ser.serialize_bytes(bytes),
+        None => ser.serialize_none(),
+    }
+}
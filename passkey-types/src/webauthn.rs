@@ -12,9 +12,11 @@ mod assertion;
 mod attestation;
 mod common;
 mod extensions;
+mod json;
+mod signal;
 
 // re-export types
-pub use self::{assertion::*, attestation::*, common::*, extensions::*};
+pub use self::{assertion::*, attestation::*, common::*, extensions::*, json::*, signal::*};
 
 mod sealed {
     pub trait Sealed {}
@@ -48,7 +50,7 @@ pub struct PublicKeyCredential<R: AuthenticatorResponse> {
     /// authenticators.
     ///
     /// > NOTE: This API does not constrain the format or length of this identifier, except that it
-    /// MUST be sufficient for the authenticator to uniquely select a key.
+    /// > MUST be sufficient for the authenticator to uniquely select a key.
     pub id: String,
 
     /// The raw byte containing the credential ID, see [Self::id] for more information.
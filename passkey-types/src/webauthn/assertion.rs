@@ -8,8 +8,8 @@ use crate::{
     utils::serde::{ignore_unknown, ignore_unknown_opt_vec, maybe_stringified},
     webauthn::{
         AttestationConveyancePreference, AttestationStatementFormatIdentifiers,
-        AuthenticationExtensionsClientInputs, PublicKeyCredential, PublicKeyCredentialDescriptor,
-        PublicKeyCredentialHints, UserVerificationRequirement,
+        AuthenticationExtensionsClientInputs, AuthenticatorTransport, PublicKeyCredential,
+        PublicKeyCredentialDescriptor, PublicKeyCredentialHints, UserVerificationRequirement,
     },
     Bytes,
 };
@@ -215,4 +215,10 @@ pub struct AuthenticatorAssertionResponse {
     /// [1]: https://w3c.github.io/webauthn/#sctn-attestation-in-assertions
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub attestation_object: Option<Bytes>,
+
+    /// The transports of the credential used to generate this assertion, when they were stored
+    /// along with it. This is not defined by WebAuthn and complements the
+    /// [`AuthenticatorAttestationResponse::transports`] reported during registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transports: Option<Vec<AuthenticatorTransport>>,
 }
@@ -7,8 +7,10 @@ use std::fmt;
 use typeshare::typeshare;
 
 use crate::{
+    ctap2::encoded_credential_public_key,
     utils::serde::{
-        i64_to_iana, ignore_unknown, ignore_unknown_opt_vec, ignore_unknown_vec, maybe_stringified,
+        i64_to_iana, ignore_unknown, ignore_unknown_opt, ignore_unknown_opt_vec,
+        ignore_unknown_vec, maybe_stringified,
     },
     webauthn::{
         AuthenticationExtensionsClientInputs, AuthenticatorAttachment, AuthenticatorTransport,
@@ -138,9 +140,14 @@ pub struct PublicKeyCredentialCreationOptions {
     /// MUST ignore unknown values, treating an unknown value as if the member does not exist,
     /// therefore acting as the default value.
     ///
-    /// The default value is [`AttestationConveyancePreference::None`]
-    #[serde(default, deserialize_with = "ignore_unknown")]
-    pub attestation: AttestationConveyancePreference,
+    /// When this member is absent, the client uses its own default, which is
+    /// [`AttestationConveyancePreference::None`] unless configured otherwise.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt"
+    )]
+    pub attestation: Option<AttestationConveyancePreference>,
 
     /// The Relying Party MAY use this OPTIONAL member to specify a preference regarding the attestation
     /// statement format used by the authenticator. Values SHOULD be taken from the IANA "WebAuthn
@@ -274,7 +281,7 @@ pub struct PublicKeyCredentialUserEntity {
 /// This type is used to supply additional parameters when creating a new credential.
 ///
 /// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialparameters>
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "typeshare", typeshare)]
 pub struct PublicKeyCredentialParameters {
     /// This member specifies the type of credential to be created. The value SHOULD be a member of
@@ -577,6 +584,17 @@ pub struct AuthenticatorAttestationResponse {
     pub transports: Option<Vec<AuthenticatorTransport>>,
 }
 
+impl AuthenticatorAttestationResponse {
+    /// The credential public key as the COSE_Key bytes encoded by the authenticator in the
+    /// [`AttestedCredentialData`] of [`Self::authenticator_data`], for Relying Parties which store
+    /// the key verbatim rather than as the DER [`Self::public_key`].
+    ///
+    /// Returns `None` when the authenticator data is malformed or has no attested credential data.
+    pub fn cose_public_key(&self) -> Option<Bytes> {
+        encoded_credential_public_key(&self.authenticator_data).map(|key| key.to_vec().into())
+    }
+}
+
 /// The client data represents the contextual bindings of both the Relying Party and the client.
 /// It is a key-value mapping whose keys are strings. Values can be any type that has a valid
 /// encoding in JSON.
@@ -626,6 +644,11 @@ where
     #[serde(default, serialize_with = "truthiness")]
     pub cross_origin: Option<bool>,
 
+    /// This OPTIONAL member contains the fully qualified origin of the top level document, only
+    /// present when the request comes from a cross-origin iframe, see [`Self::cross_origin`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_origin: Option<String>,
+
     /// CollectedClientData can be extended by the user of this library, this accounts for
     /// keys that are unknown to the library, but may be known to the user.
     #[serde(flatten)]
@@ -673,10 +696,17 @@ impl fmt::Display for ClientDataType {
 
 #[cfg(test)]
 mod tests {
+    use coset::{iana::EllipticCurve, CborSerializable, CoseKey, CoseKeyBuilder};
     use serde::{Deserialize, Serialize};
 
-    use super::CredentialCreationOptions;
-    use crate::webauthn::{ClientDataType, CollectedClientData};
+    use super::{
+        iana, AttestationConveyancePreference, AuthenticatorAttestationResponse,
+        CredentialCreationOptions,
+    };
+    use crate::{
+        ctap2::{Aaguid, AttestedCredentialData, AuthenticatorData},
+        webauthn::{ClientDataType, CollectedClientData},
+    };
 
     // Normal client data from Chrome assertion
     const CLIENT_DATA_JSON_STRING: &str = r#"{
@@ -766,6 +796,10 @@ mod tests {
             .expect("Failed to deserialize");
         // there are 10 in the json but we should be ignoring the `alg: -1`
         assert_eq!(deserialized.public_key.pub_key_cred_params.len(), 9);
+        assert_eq!(
+            deserialized.public_key.attestation,
+            Some(AttestationConveyancePreference::Direct)
+        );
     }
 
     #[test]
@@ -805,6 +839,7 @@ mod tests {
         let deserialized = serde_json::from_str::<CredentialCreationOptions>(request)
             .expect("Failed to deserialize");
         assert_eq!(deserialized.public_key.timeout, Some(300_000));
+        assert_eq!(deserialized.public_key.attestation, None);
         assert_eq!(deserialized.public_key.pub_key_cred_params.len(), 2)
     }
 
@@ -847,6 +882,7 @@ mod tests {
             challenge: "ZEvMflZDcwQJmarInnYi88px-6HZcv2Uoxw7-_JOOTg".to_string(),
             origin: "http://localhost:4000".to_owned(),
             cross_origin: Some(false),
+            top_origin: None,
             extra_data: AndroidExtraData {
                 android_package_name: "com.android.chrome".to_string(),
             },
@@ -1010,4 +1046,51 @@ mod tests {
         let client_data_json = serde_json::to_string(&ccd).unwrap();
         assert_eq!(client_data_json, CROSS_ORIGIN_FALSE);
     }
+
+    fn attestation_response(auth_data: &AuthenticatorData) -> AuthenticatorAttestationResponse {
+        AuthenticatorAttestationResponse {
+            client_data_json: Vec::new().into(),
+            authenticator_data: auth_data.to_vec().into(),
+            public_key: None,
+            public_key_algorithm: -7,
+            attestation_object: Vec::new().into(),
+            transports: None,
+        }
+    }
+
+    #[test]
+    fn cose_public_key_is_extracted_from_the_attested_credential_data() {
+        let key = CoseKeyBuilder::new_ec2_pub_key(EllipticCurve::P_256, vec![1; 32], vec![2; 32])
+            .algorithm(iana::Algorithm::ES256)
+            .build();
+        let auth_data = AuthenticatorData::new("future.1password.com", Some(0))
+            .set_attested_credential_data(
+                AttestedCredentialData::new(Aaguid::new_empty(), vec![3; 16], key).unwrap(),
+            )
+            // The extensions follow the key, they must not be extracted along with it.
+            .set_extensions(ciborium::Value::Map(vec![(
+                "credProtect".into(),
+                ciborium::Value::from(2),
+            )]));
+        let response = attestation_response(&auth_data);
+
+        let extracted = response
+            .cose_public_key()
+            .expect("no public key was extracted");
+
+        let parsed = AuthenticatorData::from_slice(&response.authenticator_data)
+            .unwrap()
+            .attested_credential_data
+            .unwrap()
+            .key;
+        assert_eq!(CoseKey::from_slice(&extracted).unwrap(), parsed);
+        assert_eq!(extracted.to_vec(), parsed.to_vec().unwrap());
+    }
+
+    #[test]
+    fn cose_public_key_is_absent_without_attested_credential_data() {
+        let auth_data = AuthenticatorData::new("future.1password.com", Some(0));
+
+        assert_eq!(attestation_response(&auth_data).cose_public_key(), None);
+    }
 }
@@ -5,8 +5,9 @@ use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
 use crate::{
+    encoding,
     utils::serde::{ignore_unknown, ignore_unknown_opt_vec},
-    Bytes,
+    Bytes, NotBase64Encoded,
 };
 
 #[cfg(doc)]
@@ -41,7 +42,7 @@ pub enum PublicKeyCredentialType {
 /// It is recommended to ignore any credential whose type is [`PublicKeyCredentialType::Unknown`]
 ///
 /// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialdescriptor>
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "typeshare", typeshare)]
 pub struct PublicKeyCredentialDescriptor {
     /// This member contains the type of the public key credential the caller is referring to. The
@@ -76,6 +77,20 @@ pub struct PublicKeyCredentialDescriptor {
 }
 
 impl PublicKeyCredentialDescriptor {
+    /// Build a descriptor of a public key credential from its `base64url` encoded credential ID,
+    /// as it is usually stored by a Relying Party.
+    pub fn from_id_base64url(
+        id: &str,
+        transports: Option<Vec<AuthenticatorTransport>>,
+    ) -> Result<Self, NotBase64Encoded> {
+        let id = encoding::try_from_base64url(id).ok_or(NotBase64Encoded)?;
+        Ok(Self {
+            ty: PublicKeyCredentialType::PublicKey,
+            id: id.into(),
+            transports,
+        })
+    }
+
     /// Checks whether [`Self::ty`] is not of value [`PublicKeyCredentialType::Unknown`]. This should
     /// be used for filtering a list of [`PublicKeyCredentialDescriptor`]s that are not of a known type.
     pub fn is_known(&self) -> bool {
@@ -211,3 +226,29 @@ pub enum PublicKeyCredentialHints {
     /// the authenticatorAttachment SHOULD be set to [`AuthenticatorAttachment::CrossPlatform`].
     Hybrid,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthenticatorTransport, PublicKeyCredentialDescriptor, PublicKeyCredentialType};
+
+    #[test]
+    fn descriptor_from_id_base64url() {
+        let descriptor = PublicKeyCredentialDescriptor::from_id_base64url(
+            "AAECA_-_",
+            Some(vec![AuthenticatorTransport::Internal]),
+        )
+        .expect("could not decode the credential id");
+
+        assert_eq!(descriptor.ty, PublicKeyCredentialType::PublicKey);
+        assert_eq!(&*descriptor.id, &[0x00, 0x01, 0x02, 0x03, 0xff, 0xbf]);
+        assert_eq!(
+            descriptor.transports,
+            Some(vec![AuthenticatorTransport::Internal])
+        );
+    }
+
+    #[test]
+    fn descriptor_from_invalid_id_base64url() {
+        assert!(PublicKeyCredentialDescriptor::from_id_base64url("not base64url!", None).is_err());
+    }
+}
@@ -1,12 +1,19 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "typeshare")]
 use typeshare::typeshare;
 
 #[cfg(doc)]
 use crate::webauthn::PublicKeyCredential;
+use crate::{
+    encoding,
+    webauthn::{AttestationConveyancePreference, AttestationStatementFormatIdentifiers},
+    Bytes,
+};
 
 /// This is a dictionary containing the client extension input values for zero or more
-/// [WebAuthn Extensions]. There are currently none supported.
+/// [WebAuthn Extensions].
 ///
 /// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsclientinputs>
 ///
@@ -20,6 +27,54 @@ pub struct AuthenticationExtensionsClientInputs {
     /// See [`CredentialPropertiesOutput`] for more information.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cred_props: Option<bool>,
+
+    /// The inputs of the pseudo-random function extension.
+    ///
+    /// See [`AuthenticationExtensionsPrfInputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prf: Option<AuthenticationExtensionsPrfInputs>,
+
+    /// The protection policy requested for a new credential through the CTAP2 `credProtect`
+    /// extension.
+    ///
+    /// See [`CredentialProtectionPolicy`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_protection_policy: Option<CredentialProtectionPolicy>,
+
+    /// Whether the creation must fail rather than create a credential without the requested
+    /// [`Self::credential_protection_policy`] when the authenticator does not support the
+    /// `credProtect` extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforce_credential_protection_policy: Option<bool>,
+
+    /// The legacy FIDO AppID, such as `https://example.com/app-id.json`, of the credentials
+    /// registered through the U2F API. An assertion uses it instead of the RP ID when none of the
+    /// allowed credentials is scoped to the RP ID.
+    ///
+    /// <https://w3c.github.io/webauthn/#sctn-appid-extension>
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appid: Option<String>,
+
+    /// Boolean to request the user verification methods used by the authenticator for the
+    /// ceremony.
+    ///
+    /// See [`UvmEntry`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<bool>,
+
+    /// The inputs of the device public key extension, which requests a key pair bound to the
+    /// device using a credential that may be synced across devices.
+    ///
+    /// See [`AuthenticationExtensionsDevicePublicKeyInputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_pub_key: Option<AuthenticationExtensionsDevicePublicKeyInputs>,
+
+    /// The inputs of the large blob storage extension, which stores an opaque blob along with a
+    /// credential.
+    ///
+    /// See [`AuthenticationExtensionsLargeBlobInputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<AuthenticationExtensionsLargeBlobInputs>,
 }
 
 /// This is a dictionary containing the client extension output values for zero or more
@@ -37,8 +92,54 @@ pub struct AuthenticatorExtensionsClientOutputs {
     /// See [`CredentialPropertiesOutput`] for more information
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cred_props: Option<CredentialPropertiesOutput>,
+
+    /// The outputs of the pseudo-random function extension.
+    ///
+    /// See [`AuthenticationExtensionsPrfOutputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prf: Option<AuthenticationExtensionsPrfOutputs>,
+
+    /// The protection policy that was applied to a new credential, after merging the relying
+    /// party's request with the authenticator's default policy. This is not defined by WebAuthn,
+    /// which has no client output for the `credProtect` extension.
+    ///
+    /// See [`CredentialProtectionPolicy`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+
+    /// Whether the assertion was made with a credential scoped to the requested
+    /// [`AuthenticationExtensionsClientInputs::appid`] rather than to the RP ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appid: Option<bool>,
+
+    /// The user verification methods used by the authenticator for the ceremony, when they were
+    /// requested with [`AuthenticationExtensionsClientInputs::uvm`].
+    ///
+    /// See [`UvmEntry`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<Vec<UvmEntry>>,
+
+    /// The outputs of the device public key extension.
+    ///
+    /// See [`AuthenticationExtensionsDevicePublicKeyOutputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_pub_key: Option<AuthenticationExtensionsDevicePublicKeyOutputs>,
+
+    /// The outputs of the large blob storage extension.
+    ///
+    /// See [`AuthenticationExtensionsLargeBlobOutputs`] for more information.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<AuthenticationExtensionsLargeBlobOutputs>,
 }
 
+/// A user verification method used by the authenticator, as reported by the [`uvm`] extension.
+/// It holds the `userVerificationMethod`, `keyProtectionType` and `matcherProtectionType` values
+/// of the [FIDO Registry of Predefined Values], in this order.
+///
+/// [`uvm`]: https://w3c.github.io/webauthn/#sctn-uvm-extension
+/// [FIDO Registry of Predefined Values]: https://fidoalliance.org/specs/common-specs/fido-registry-v2.2-ps-20220523.html
+pub type UvmEntry = [u32; 3];
+
 /// This client registration extension facilitates reporting certain credential properties known by
 /// the client to the requesting WebAuthn [Relying Party] upon creation of a [`PublicKeyCredential`]
 /// source as a result of a registration ceremony.
@@ -46,7 +147,7 @@ pub struct AuthenticatorExtensionsClientOutputs {
 /// <https://w3c.github.io/webauthn/#sctn-authenticator-credential-properties-extension>
 ///
 /// [Relying Party]: https://w3c.github.io/webauthn/#relying-party
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "typeshare", typeshare)]
 pub struct CredentialPropertiesOutput {
@@ -86,3 +187,300 @@ pub struct CredentialPropertiesOutput {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub authenticator_display_name: Option<String>,
 }
+
+/// The policies of the CTAP2 [`credProtect`] extension, which control whether a credential can be
+/// used without user verification. The policies are ordered from the least to the most
+/// protective, so that the greater of two policies is the one providing the most assurance.
+///
+/// <https://w3c.github.io/webauthn/#enum-credentialProtectionPolicy>
+///
+/// [`credProtect`]: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-errata-20220621.html#sctn-credProtect-extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub enum CredentialProtectionPolicy {
+    /// The credential can be used with or without user verification.
+    UserVerificationOptional,
+    /// The credential can only be used without user verification when its ID is given in the
+    /// allow list of the request, so that it cannot be discovered without user verification.
+    #[serde(rename = "userVerificationOptionalWithCredentialIDList")]
+    UserVerificationOptionalWithCredentialIdList,
+    /// The credential can only be used with user verification.
+    UserVerificationRequired,
+}
+
+impl CredentialProtectionPolicy {
+    /// The value of this policy in the CTAP2 `credProtect` extension, from `0x01` to `0x03`.
+    pub fn ctap_value(self) -> u8 {
+        match self {
+            Self::UserVerificationOptional => 0x01,
+            Self::UserVerificationOptionalWithCredentialIdList => 0x02,
+            Self::UserVerificationRequired => 0x03,
+        }
+    }
+
+    /// Get the policy from its value in the CTAP2 `credProtect` extension, returning `None` for
+    /// unknown values.
+    pub fn from_ctap_value(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::UserVerificationOptional),
+            0x02 => Some(Self::UserVerificationOptionalWithCredentialIdList),
+            0x03 => Some(Self::UserVerificationRequired),
+            _ => None,
+        }
+    }
+}
+
+/// The inputs of the pseudo-random function (`prf`) extension, which allows a relying party to
+/// evaluate a pseudo-random function tied to a credential, backed by the CTAP2 `hmac-secret`
+/// extension.
+///
+/// <https://w3c.github.io/webauthn/#prf-extension>
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsPrfInputs {
+    /// The inputs to evaluate for any credential that is not listed in
+    /// [`Self::eval_by_credential`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval: Option<AuthenticationExtensionsPrfValues>,
+
+    /// The inputs to evaluate for specific credentials, keyed by their `base64url` encoded
+    /// credential ID. This is only valid during assertions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_by_credential: Option<HashMap<String, AuthenticationExtensionsPrfValues>>,
+}
+
+impl AuthenticationExtensionsPrfInputs {
+    /// Select the inputs to evaluate for the credential with the given ID. The entry of
+    /// [`Self::eval_by_credential`] for this credential takes precedence over [`Self::eval`], and
+    /// `None` is returned when neither is present.
+    pub fn values_for_credential(
+        &self,
+        credential_id: &[u8],
+    ) -> Option<&AuthenticationExtensionsPrfValues> {
+        self.eval_by_credential
+            .as_ref()
+            .and_then(|by_credential| {
+                by_credential.iter().find_map(|(id, values)| {
+                    (encoding::try_from_base64url(id).as_deref() == Some(credential_id))
+                        .then_some(values)
+                })
+            })
+            .or(self.eval.as_ref())
+    }
+}
+
+/// One or two inputs of the pseudo-random function, or its results.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsprfvalues>
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsPrfValues {
+    /// The first input or result.
+    pub first: Bytes,
+
+    /// The optional second input or result.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second: Option<Bytes>,
+}
+
+/// The outputs of the pseudo-random function (`prf`) extension.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsprfoutputs>
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsPrfOutputs {
+    /// Whether the new credential can evaluate the pseudo-random function, this is only present
+    /// during registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// The results of evaluating the pseudo-random function on the requested inputs. This is
+    /// absent when the credential cannot evaluate it or no inputs were given for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<AuthenticationExtensionsPrfValues>,
+}
+
+/// The inputs of the device public key (`devicePubKey`) extension, which lets a relying party
+/// recognize the device on which a credential, that may be synced, is used through a key pair
+/// that never leaves this device.
+///
+/// <https://github.com/w3c/webauthn/pull/1663>
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsDevicePublicKeyInputs {
+    /// The attestation requested for the device public key.
+    #[serde(default)]
+    pub attestation: AttestationConveyancePreference,
+
+    /// The attestation statement formats preferred for the device public key, from the most to
+    /// the least preferred.
+    #[serde(default)]
+    pub attestation_formats: Vec<AttestationStatementFormatIdentifiers>,
+}
+
+/// The outputs of the device public key (`devicePubKey`) extension.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsDevicePublicKeyOutputs {
+    /// The CBOR encoded device public key attestation produced by the authenticator, which is
+    /// also found in the extensions of the authenticator data.
+    ///
+    /// See [`DevicePublicKeyAttestation`](crate::ctap2::DevicePublicKeyAttestation)
+    /// for its content.
+    pub authenticator_output: Bytes,
+}
+
+/// The inputs of the large blob storage (`largeBlob`) extension, which lets a relying party store
+/// an opaque blob along with a credential, backed by the CTAP2 `largeBlobKey` extension and the
+/// authenticator's large-blob array.
+///
+/// <https://w3c.github.io/webauthn/#sctn-large-blob-extension>
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsLargeBlobInputs {
+    /// Whether the new credential must or should be able to store a large blob, this is only
+    /// valid during registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub support: Option<LargeBlobSupport>,
+
+    /// Whether to read the large blob of the asserted credential, this is only valid during
+    /// assertions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+
+    /// The large blob to store for the asserted credential, replacing its previous one. This is
+    /// only valid during assertions and cannot be combined with [`Self::read`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write: Option<Bytes>,
+}
+
+/// Whether a new credential must be able to store a large blob.
+///
+/// <https://w3c.github.io/webauthn/#enumdef-largeblobsupport>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub enum LargeBlobSupport {
+    /// The credential must be created by an authenticator able to store large blobs.
+    Required,
+    /// The credential should be able to store large blobs, but may be created without.
+    Preferred,
+}
+
+/// The outputs of the large blob storage (`largeBlob`) extension.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionslargebloboutputs>
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsLargeBlobOutputs {
+    /// Whether the new credential can store a large blob, this is only present during
+    /// registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported: Option<bool>,
+
+    /// The large blob of the asserted credential, when it was read and the credential has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<Bytes>,
+
+    /// Whether the requested large blob was stored for the asserted credential.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AuthenticationExtensionsClientInputs, AuthenticationExtensionsPrfInputs,
+        AuthenticationExtensionsPrfValues, CredentialProtectionPolicy,
+    };
+
+    fn values(first: &[u8]) -> AuthenticationExtensionsPrfValues {
+        AuthenticationExtensionsPrfValues {
+            first: first.to_vec().into(),
+            second: None,
+        }
+    }
+
+    #[test]
+    fn prf_values_are_selected_by_credential() {
+        let inputs: AuthenticationExtensionsPrfInputs = serde_json::from_str(
+            r#"{
+                "eval": { "first": "AAAA" },
+                "evalByCredential": {
+                    "AQID": { "first": "AQEB", "second": "AgIC" },
+                    "BAUG": { "first": "BAQE" }
+                }
+            }"#,
+        )
+        .expect("could not deserialize the prf inputs");
+
+        assert_eq!(
+            inputs.values_for_credential(&[1, 2, 3]),
+            Some(&AuthenticationExtensionsPrfValues {
+                first: vec![1, 1, 1].into(),
+                second: Some(vec![2, 2, 2].into()),
+            })
+        );
+        assert_eq!(
+            inputs.values_for_credential(&[4, 5, 6]),
+            Some(&values(&[4, 4, 4]))
+        );
+        assert_eq!(
+            inputs.values_for_credential(&[7, 8, 9]),
+            Some(&values(&[0, 0, 0]))
+        );
+    }
+
+    #[test]
+    fn prf_values_are_absent_without_eval_for_unlisted_credentials() {
+        let inputs = AuthenticationExtensionsPrfInputs {
+            eval: None,
+            eval_by_credential: Some([("AQID".to_owned(), values(&[1]))].into()),
+        };
+
+        assert_eq!(inputs.values_for_credential(&[7, 8, 9]), None);
+    }
+
+    #[test]
+    fn credential_protection_policy_is_parsed_from_its_webauthn_name() {
+        let inputs: AuthenticationExtensionsClientInputs = serde_json::from_str(
+            r#"{ "credentialProtectionPolicy": "userVerificationOptionalWithCredentialIDList" }"#,
+        )
+        .expect("could not deserialize the extension inputs");
+
+        assert_eq!(
+            inputs.credential_protection_policy,
+            Some(CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList)
+        );
+        assert!(
+            CredentialProtectionPolicy::UserVerificationRequired
+                > CredentialProtectionPolicy::UserVerificationOptionalWithCredentialIdList
+        );
+        assert_eq!(
+            CredentialProtectionPolicy::from_ctap_value(0x02)
+                .map(CredentialProtectionPolicy::ctap_value),
+            Some(0x02)
+        );
+    }
+
+    #[test]
+    fn enforce_credential_protection_policy_is_parsed() {
+        let inputs: AuthenticationExtensionsClientInputs = serde_json::from_str(
+            r#"{
+                "credentialProtectionPolicy": "userVerificationRequired",
+                "enforceCredentialProtectionPolicy": true
+            }"#,
+        )
+        .expect("could not deserialize the extension inputs");
+
+        assert_eq!(inputs.enforce_credential_protection_policy, Some(true));
+    }
+}
@@ -0,0 +1,871 @@
+//! The JSON representations of the options given to `create()` and `get()`, which Relying Parties
+//! send to their clients to be parsed with [`parseCreationOptionsFromJSON`] and
+//! [`parseRequestOptionsFromJSON`], and of the credentials they return, as produced by
+//! `PublicKeyCredential.toJSON()`.
+//!
+//! Binary members are `base64url` encoded strings in these types. They convert to and from the
+//! options taken by the client, where binary members are [`Bytes`].
+//!
+//! [`parseCreationOptionsFromJSON`]: https://w3c.github.io/webauthn/#sctn-parseCreationOptionsFromJSON
+//! [`parseRequestOptionsFromJSON`]: https://w3c.github.io/webauthn/#sctn-parseRequestOptionsFromJSON
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "typeshare")]
+use typeshare::typeshare;
+
+use crate::{
+    encoding,
+    utils::serde::{
+        ignore_unknown, ignore_unknown_opt, ignore_unknown_opt_vec, ignore_unknown_vec,
+    },
+    webauthn::{
+        AttestationConveyancePreference, AttestationStatementFormatIdentifiers,
+        AuthenticatedPublicKeyCredential, AuthenticationExtensionsClientInputs,
+        AuthenticatorAttachment, AuthenticatorExtensionsClientOutputs,
+        AuthenticatorSelectionCriteria, AuthenticatorTransport, CreatedPublicKeyCredential,
+        CredentialCreationOptions, CredentialPropertiesOutput, CredentialProtectionPolicy,
+        CredentialRequestOptions, PublicKeyCredentialCreationOptions,
+        PublicKeyCredentialDescriptor, PublicKeyCredentialHints, PublicKeyCredentialParameters,
+        PublicKeyCredentialRequestOptions, PublicKeyCredentialRpEntity, PublicKeyCredentialType,
+        PublicKeyCredentialUserEntity, UserVerificationRequirement, UvmEntry,
+    },
+    Bytes, NotBase64Encoded,
+};
+
+#[cfg(doc)]
+use crate::webauthn::{
+    AuthenticationExtensionsDevicePublicKeyOutputs, AuthenticationExtensionsLargeBlobOutputs,
+    AuthenticationExtensionsPrfOutputs, AuthenticationExtensionsPrfValues,
+    AuthenticatorAssertionResponse, AuthenticatorAttestationResponse, PublicKeyCredential,
+};
+
+/// The JSON representation of [`PublicKeyCredentialCreationOptions`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialcreationoptionsjson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct PublicKeyCredentialCreationOptionsJson {
+    /// See [`PublicKeyCredentialCreationOptions::rp`].
+    pub rp: PublicKeyCredentialRpEntity,
+
+    /// See [`PublicKeyCredentialCreationOptions::user`].
+    pub user: PublicKeyCredentialUserEntityJson,
+
+    /// The `base64url` encoded [`PublicKeyCredentialCreationOptions::challenge`].
+    pub challenge: String,
+
+    /// See [`PublicKeyCredentialCreationOptions::pub_key_cred_params`].
+    #[serde(deserialize_with = "ignore_unknown_vec")]
+    pub pub_key_cred_params: Vec<PublicKeyCredentialParameters>,
+
+    /// See [`PublicKeyCredentialCreationOptions::timeout`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+
+    /// See [`PublicKeyCredentialCreationOptions::exclude_credentials`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub exclude_credentials: Option<Vec<PublicKeyCredentialDescriptorJson>>,
+
+    /// See [`PublicKeyCredentialCreationOptions::authenticator_selection`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticator_selection: Option<AuthenticatorSelectionCriteria>,
+
+    /// See [`PublicKeyCredentialCreationOptions::hints`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub hints: Option<Vec<PublicKeyCredentialHints>>,
+
+    /// See [`PublicKeyCredentialCreationOptions::attestation`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt"
+    )]
+    pub attestation: Option<AttestationConveyancePreference>,
+
+    /// See [`PublicKeyCredentialCreationOptions::attestation_formats`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub attestation_formats: Option<Vec<AttestationStatementFormatIdentifiers>>,
+
+    /// See [`PublicKeyCredentialCreationOptions::extensions`], the inputs are used as they are.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown"
+    )]
+    pub extensions: Option<AuthenticationExtensionsClientInputs>,
+}
+
+/// The JSON representation of [`PublicKeyCredentialRequestOptions`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialrequestoptionsjson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct PublicKeyCredentialRequestOptionsJson {
+    /// The `base64url` encoded [`PublicKeyCredentialRequestOptions::challenge`].
+    pub challenge: String,
+
+    /// See [`PublicKeyCredentialRequestOptions::timeout`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+
+    /// See [`PublicKeyCredentialRequestOptions::rp_id`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rp_id: Option<String>,
+
+    /// See [`PublicKeyCredentialRequestOptions::allow_credentials`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub allow_credentials: Option<Vec<PublicKeyCredentialDescriptorJson>>,
+
+    /// See [`PublicKeyCredentialRequestOptions::user_verification`].
+    #[serde(default, deserialize_with = "ignore_unknown")]
+    pub user_verification: UserVerificationRequirement,
+
+    /// See [`PublicKeyCredentialRequestOptions::hints`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub hints: Option<Vec<PublicKeyCredentialHints>>,
+
+    /// See [`PublicKeyCredentialRequestOptions::extensions`], the inputs are used as they are.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown"
+    )]
+    pub extensions: Option<AuthenticationExtensionsClientInputs>,
+}
+
+/// The JSON representation of [`PublicKeyCredentialUserEntity`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialuserentityjson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct PublicKeyCredentialUserEntityJson {
+    /// The `base64url` encoded [`PublicKeyCredentialUserEntity::id`].
+    pub id: String,
+
+    /// See [`PublicKeyCredentialUserEntity::name`].
+    pub name: String,
+
+    /// See [`PublicKeyCredentialUserEntity::display_name`].
+    pub display_name: String,
+}
+
+/// The JSON representation of [`PublicKeyCredentialDescriptor`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-publickeycredentialdescriptorjson>
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct PublicKeyCredentialDescriptorJson {
+    /// The `base64url` encoded [`PublicKeyCredentialDescriptor::id`].
+    pub id: String,
+
+    /// See [`PublicKeyCredentialDescriptor::ty`].
+    #[serde(rename = "type", deserialize_with = "ignore_unknown")]
+    pub ty: PublicKeyCredentialType,
+
+    /// See [`PublicKeyCredentialDescriptor::transports`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown_opt_vec"
+    )]
+    pub transports: Option<Vec<AuthenticatorTransport>>,
+}
+
+/// Decode a binary member, which must be `base64url` encoded.
+fn decode(value: &str) -> Result<Bytes, NotBase64Encoded> {
+    encoding::try_from_base64url(value)
+        .map(Bytes::from)
+        .ok_or(NotBase64Encoded)
+}
+
+impl TryFrom<PublicKeyCredentialCreationOptionsJson> for PublicKeyCredentialCreationOptions {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialCreationOptionsJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            rp: json.rp,
+            user: json.user.try_into()?,
+            challenge: decode(&json.challenge)?,
+            pub_key_cred_params: json.pub_key_cred_params,
+            timeout: json.timeout,
+            exclude_credentials: json
+                .exclude_credentials
+                .map(|list| list.into_iter().map(TryInto::try_into).collect())
+                .transpose()?,
+            authenticator_selection: json.authenticator_selection,
+            hints: json.hints,
+            attestation: json.attestation,
+            attestation_formats: json.attestation_formats,
+            extensions: json.extensions,
+        })
+    }
+}
+
+impl From<PublicKeyCredentialCreationOptions> for PublicKeyCredentialCreationOptionsJson {
+    fn from(options: PublicKeyCredentialCreationOptions) -> Self {
+        Self {
+            rp: options.rp,
+            user: options.user.into(),
+            challenge: options.challenge.into(),
+            pub_key_cred_params: options.pub_key_cred_params,
+            timeout: options.timeout,
+            exclude_credentials: options
+                .exclude_credentials
+                .map(|list| list.into_iter().map(Into::into).collect()),
+            authenticator_selection: options.authenticator_selection,
+            hints: options.hints,
+            attestation: options.attestation,
+            attestation_formats: options.attestation_formats,
+            extensions: options.extensions,
+        }
+    }
+}
+
+impl TryFrom<PublicKeyCredentialCreationOptionsJson> for CredentialCreationOptions {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialCreationOptionsJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            public_key: json.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<PublicKeyCredentialRequestOptionsJson> for PublicKeyCredentialRequestOptions {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialRequestOptionsJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            challenge: decode(&json.challenge)?,
+            timeout: json.timeout,
+            rp_id: json.rp_id,
+            allow_credentials: json
+                .allow_credentials
+                .map(|list| list.into_iter().map(TryInto::try_into).collect())
+                .transpose()?,
+            user_verification: json.user_verification,
+            hints: json.hints,
+            attestation: AttestationConveyancePreference::default(),
+            attestation_formats: None,
+            extensions: json.extensions,
+        })
+    }
+}
+
+/// The attestation members of the request options are not part of their JSON representation, and
+/// are dropped.
+impl From<PublicKeyCredentialRequestOptions> for PublicKeyCredentialRequestOptionsJson {
+    fn from(options: PublicKeyCredentialRequestOptions) -> Self {
+        Self {
+            challenge: options.challenge.into(),
+            timeout: options.timeout,
+            rp_id: options.rp_id,
+            allow_credentials: options
+                .allow_credentials
+                .map(|list| list.into_iter().map(Into::into).collect()),
+            user_verification: options.user_verification,
+            hints: options.hints,
+            extensions: options.extensions,
+        }
+    }
+}
+
+impl TryFrom<PublicKeyCredentialRequestOptionsJson> for CredentialRequestOptions {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialRequestOptionsJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            public_key: json.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<PublicKeyCredentialUserEntityJson> for PublicKeyCredentialUserEntity {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialUserEntityJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: decode(&json.id)?,
+            name: json.name,
+            display_name: json.display_name,
+        })
+    }
+}
+
+impl From<PublicKeyCredentialUserEntity> for PublicKeyCredentialUserEntityJson {
+    fn from(user: PublicKeyCredentialUserEntity) -> Self {
+        Self {
+            id: user.id.into(),
+            name: user.name,
+            display_name: user.display_name,
+        }
+    }
+}
+
+impl TryFrom<PublicKeyCredentialDescriptorJson> for PublicKeyCredentialDescriptor {
+    type Error = NotBase64Encoded;
+
+    fn try_from(json: PublicKeyCredentialDescriptorJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            ty: json.ty,
+            id: decode(&json.id)?,
+            transports: json.transports,
+        })
+    }
+}
+
+impl From<PublicKeyCredentialDescriptor> for PublicKeyCredentialDescriptorJson {
+    fn from(descriptor: PublicKeyCredentialDescriptor) -> Self {
+        Self {
+            id: descriptor.id.into(),
+            ty: descriptor.ty,
+            transports: descriptor.transports,
+        }
+    }
+}
+
+/// The JSON representation of a [`CreatedPublicKeyCredential`], as produced by the browser's
+/// `PublicKeyCredential.toJSON()`, see [`CreatedPublicKeyCredential::to_json`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-registrationresponsejson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct RegistrationResponseJson {
+    /// See [`PublicKeyCredential::id`].
+    pub id: String,
+
+    /// The `base64url` encoded [`PublicKeyCredential::raw_id`].
+    pub raw_id: String,
+
+    /// See [`PublicKeyCredential::response`].
+    pub response: AuthenticatorAttestationResponseJson,
+
+    /// See [`PublicKeyCredential::authenticator_attachment`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown"
+    )]
+    pub authenticator_attachment: Option<AuthenticatorAttachment>,
+
+    /// See [`PublicKeyCredential::client_extension_results`].
+    #[serde(default)]
+    pub client_extension_results: AuthenticationExtensionsClientOutputsJson,
+
+    /// See [`PublicKeyCredential::ty`].
+    #[serde(rename = "type")]
+    pub ty: PublicKeyCredentialType,
+}
+
+/// The JSON representation of [`AuthenticatorAttestationResponse`], where binary members are
+/// `base64url` encoded strings.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticatorattestationresponsejson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticatorAttestationResponseJson {
+    /// The `base64url` encoded [`AuthenticatorAttestationResponse::client_data_json`].
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+
+    /// The `base64url` encoded [`AuthenticatorAttestationResponse::authenticator_data`].
+    pub authenticator_data: String,
+
+    /// See [`AuthenticatorAttestationResponse::transports`], which is empty when unknown.
+    #[serde(default, deserialize_with = "ignore_unknown_vec")]
+    pub transports: Vec<AuthenticatorTransport>,
+
+    /// The `base64url` encoded [`AuthenticatorAttestationResponse::public_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+
+    /// See [`AuthenticatorAttestationResponse::public_key_algorithm`].
+    #[cfg(feature = "typeshare")]
+    #[typeshare(serialized_as = "I54")] // because i64 fails for js
+    pub public_key_algorithm: i64,
+
+    /// See [`AuthenticatorAttestationResponse::public_key_algorithm`].
+    #[cfg(not(feature = "typeshare"))]
+    pub public_key_algorithm: i64,
+
+    /// The `base64url` encoded [`AuthenticatorAttestationResponse::attestation_object`].
+    pub attestation_object: String,
+}
+
+/// The JSON representation of an [`AuthenticatedPublicKeyCredential`], as produced by the
+/// browser's `PublicKeyCredential.toJSON()`, see [`AuthenticatedPublicKeyCredential::to_json`].
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationresponsejson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationResponseJson {
+    /// See [`PublicKeyCredential::id`].
+    pub id: String,
+
+    /// The `base64url` encoded [`PublicKeyCredential::raw_id`].
+    pub raw_id: String,
+
+    /// See [`PublicKeyCredential::response`].
+    pub response: AuthenticatorAssertionResponseJson,
+
+    /// See [`PublicKeyCredential::authenticator_attachment`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "ignore_unknown"
+    )]
+    pub authenticator_attachment: Option<AuthenticatorAttachment>,
+
+    /// See [`PublicKeyCredential::client_extension_results`].
+    #[serde(default)]
+    pub client_extension_results: AuthenticationExtensionsClientOutputsJson,
+
+    /// See [`PublicKeyCredential::ty`].
+    #[serde(rename = "type")]
+    pub ty: PublicKeyCredentialType,
+}
+
+/// The JSON representation of [`AuthenticatorAssertionResponse`], where binary members are
+/// `base64url` encoded strings.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticatorassertionresponsejson>
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticatorAssertionResponseJson {
+    /// The `base64url` encoded [`AuthenticatorAssertionResponse::client_data_json`].
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+
+    /// The `base64url` encoded [`AuthenticatorAssertionResponse::authenticator_data`].
+    pub authenticator_data: String,
+
+    /// The `base64url` encoded [`AuthenticatorAssertionResponse::signature`].
+    pub signature: String,
+
+    /// The `base64url` encoded [`AuthenticatorAssertionResponse::user_handle`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_handle: Option<String>,
+
+    /// The `base64url` encoded [`AuthenticatorAssertionResponse::attestation_object`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_object: Option<String>,
+}
+
+/// The JSON representation of [`AuthenticatorExtensionsClientOutputs`], where the results of the
+/// `prf` extension are `base64url` encoded strings.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-authenticationextensionsclientoutputsjson>
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsClientOutputsJson {
+    /// See [`AuthenticatorExtensionsClientOutputs::cred_props`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cred_props: Option<CredentialPropertiesOutput>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::prf`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prf: Option<AuthenticationExtensionsPrfOutputsJson>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::cred_protect`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::appid`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appid: Option<bool>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::uvm`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvm: Option<Vec<UvmEntry>>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::device_pub_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_pub_key: Option<AuthenticationExtensionsDevicePublicKeyOutputsJson>,
+
+    /// See [`AuthenticatorExtensionsClientOutputs::large_blob`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_blob: Option<AuthenticationExtensionsLargeBlobOutputsJson>,
+}
+
+/// The JSON representation of [`AuthenticationExtensionsDevicePublicKeyOutputs`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsDevicePublicKeyOutputsJson {
+    /// The `base64url` encoded
+    /// [`AuthenticationExtensionsDevicePublicKeyOutputs::authenticator_output`].
+    pub authenticator_output: String,
+}
+
+/// The JSON representation of [`AuthenticationExtensionsLargeBlobOutputs`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsLargeBlobOutputsJson {
+    /// See [`AuthenticationExtensionsLargeBlobOutputs::supported`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supported: Option<bool>,
+
+    /// The `base64url` encoded [`AuthenticationExtensionsLargeBlobOutputs::blob`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+
+    /// See [`AuthenticationExtensionsLargeBlobOutputs::written`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written: Option<bool>,
+}
+
+/// The JSON representation of [`AuthenticationExtensionsPrfOutputs`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsPrfOutputsJson {
+    /// See [`AuthenticationExtensionsPrfOutputs::enabled`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// The `base64url` encoded [`AuthenticationExtensionsPrfOutputs::results`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<AuthenticationExtensionsPrfValuesJson>,
+}
+
+/// The JSON representation of [`AuthenticationExtensionsPrfValues`].
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AuthenticationExtensionsPrfValuesJson {
+    /// The `base64url` encoded [`AuthenticationExtensionsPrfValues::first`].
+    pub first: String,
+
+    /// The `base64url` encoded [`AuthenticationExtensionsPrfValues::second`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second: Option<String>,
+}
+
+impl CreatedPublicKeyCredential {
+    /// The JSON representation of this credential, like the browser's
+    /// `PublicKeyCredential.toJSON()`, to be sent to a Relying Party expecting it.
+    pub fn to_json(&self) -> RegistrationResponseJson {
+        RegistrationResponseJson {
+            id: self.id.clone(),
+            raw_id: encoding::base64url(&self.raw_id),
+            response: AuthenticatorAttestationResponseJson {
+                client_data_json: encoding::base64url(&self.response.client_data_json),
+                authenticator_data: encoding::base64url(&self.response.authenticator_data),
+                transports: self.response.transports.clone().unwrap_or_default(),
+                public_key: self
+                    .response
+                    .public_key
+                    .as_deref()
+                    .map(|bytes| encoding::base64url(bytes)),
+                public_key_algorithm: self.response.public_key_algorithm,
+                attestation_object: encoding::base64url(&self.response.attestation_object),
+            },
+            authenticator_attachment: self.authenticator_attachment,
+            client_extension_results: (&self.client_extension_results).into(),
+            ty: self.ty,
+        }
+    }
+}
+
+impl AuthenticatedPublicKeyCredential {
+    /// The JSON representation of this credential, like the browser's
+    /// `PublicKeyCredential.toJSON()`, to be sent to a Relying Party expecting it.
+    pub fn to_json(&self) -> AuthenticationResponseJson {
+        AuthenticationResponseJson {
+            id: self.id.clone(),
+            raw_id: encoding::base64url(&self.raw_id),
+            response: AuthenticatorAssertionResponseJson {
+                client_data_json: encoding::base64url(&self.response.client_data_json),
+                authenticator_data: encoding::base64url(&self.response.authenticator_data),
+                signature: encoding::base64url(&self.response.signature),
+                user_handle: self
+                    .response
+                    .user_handle
+                    .as_deref()
+                    .map(|bytes| encoding::base64url(bytes)),
+                attestation_object: self
+                    .response
+                    .attestation_object
+                    .as_deref()
+                    .map(|bytes| encoding::base64url(bytes)),
+            },
+            authenticator_attachment: self.authenticator_attachment,
+            client_extension_results: (&self.client_extension_results).into(),
+            ty: self.ty,
+        }
+    }
+}
+
+impl From<&AuthenticatorExtensionsClientOutputs> for AuthenticationExtensionsClientOutputsJson {
+    fn from(outputs: &AuthenticatorExtensionsClientOutputs) -> Self {
+        Self {
+            cred_props: outputs.cred_props.clone(),
+            prf: outputs
+                .prf
+                .as_ref()
+                .map(|prf| AuthenticationExtensionsPrfOutputsJson {
+                    enabled: prf.enabled,
+                    results: prf.results.as_ref().map(|results| {
+                        AuthenticationExtensionsPrfValuesJson {
+                            first: encoding::base64url(&results.first),
+                            second: results
+                                .second
+                                .as_deref()
+                                .map(|bytes| encoding::base64url(bytes)),
+                        }
+                    }),
+                }),
+            cred_protect: outputs.cred_protect,
+            appid: outputs.appid,
+            uvm: outputs.uvm.clone(),
+            device_pub_key: outputs.device_pub_key.as_ref().map(|device_pub_key| {
+                AuthenticationExtensionsDevicePublicKeyOutputsJson {
+                    authenticator_output: encoding::base64url(&device_pub_key.authenticator_output),
+                }
+            }),
+            large_blob: outputs.large_blob.as_ref().map(|large_blob| {
+                AuthenticationExtensionsLargeBlobOutputsJson {
+                    supported: large_blob.supported,
+                    blob: large_blob
+                        .blob
+                        .as_deref()
+                        .map(|bytes| encoding::base64url(bytes)),
+                    written: large_blob.written,
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{PublicKeyCredentialCreationOptionsJson, PublicKeyCredentialRequestOptionsJson};
+    use crate::webauthn::{
+        AttestationConveyancePreference, AuthenticatedPublicKeyCredential,
+        AuthenticationExtensionsPrfOutputs, AuthenticationExtensionsPrfValues,
+        AuthenticatorAssertionResponse, AuthenticatorAttachment, AuthenticatorAttestationResponse,
+        AuthenticatorExtensionsClientOutputs, AuthenticatorTransport, CreatedPublicKeyCredential,
+        CredentialCreationOptions, CredentialPropertiesOutput, CredentialRequestOptions,
+        PublicKeyCredentialCreationOptions, PublicKeyCredentialRequestOptions,
+        PublicKeyCredentialType,
+    };
+
+    /// Registration options in the shape webauthn.io gives to `parseCreationOptionsFromJSON`.
+    const CREATION_OPTIONS: &str = r#"{
+        "challenge": "h1t3ir2zlUXKk9hT8mYtaZ-lM0WwA2hJ1mJnh9hM6PCW5kqV1UD_9GbtK0prLfkCkYz1cf0adRLSbqnSLiiREA",
+        "rp": { "name": "webauthn.io", "id": "webauthn.io" },
+        "user": {
+            "id": "YzNkZjAxYWQtNjEzNi00ZTJkLWE2NWYtMGE2ZTMzYzk0YjJm",
+            "name": "wendy",
+            "displayName": "wendy"
+        },
+        "pubKeyCredParams": [
+            { "type": "public-key", "alg": -8 },
+            { "type": "public-key", "alg": -7 },
+            { "type": "public-key", "alg": -257 }
+        ],
+        "timeout": 60000,
+        "excludeCredentials": [
+            {
+                "id": "Q_RmaR3E4Ncl1-r44nqRKtobmm8kr-kZ7ZrxcL-0EJY",
+                "type": "public-key",
+                "transports": ["internal", "hybrid"]
+            }
+        ],
+        "authenticatorSelection": {
+            "residentKey": "preferred",
+            "requireResidentKey": false,
+            "userVerification": "preferred"
+        },
+        "hints": ["client-device"],
+        "attestation": "none",
+        "extensions": { "credProps": true }
+    }"#;
+
+    /// Authentication options in the shape webauthn.io gives to `parseRequestOptionsFromJSON`.
+    const REQUEST_OPTIONS: &str = r#"{
+        "challenge": "cmKbRMz7Bj6dGmnB0J3bO8wWCIYUinGrxDc6rc2Kv3jJAm8XUhGR0tddhbLESl7RpA9PVnS-9-8xXzNYlROB7A",
+        "timeout": 60000,
+        "rpId": "webauthn.io",
+        "allowCredentials": [
+            { "id": "Q_RmaR3E4Ncl1-r44nqRKtobmm8kr-kZ7ZrxcL-0EJY", "type": "public-key" }
+        ],
+        "userVerification": "preferred"
+    }"#;
+
+    #[test]
+    fn creation_options_round_trip_through_the_binary_options() {
+        let original: serde_json::Value = serde_json::from_str(CREATION_OPTIONS).unwrap();
+
+        let json: PublicKeyCredentialCreationOptionsJson =
+            serde_json::from_str(CREATION_OPTIONS).unwrap();
+        let options = PublicKeyCredentialCreationOptions::try_from(json).unwrap();
+        let round_trip =
+            serde_json::to_value(PublicKeyCredentialCreationOptionsJson::from(options)).unwrap();
+
+        assert_eq!(round_trip, original);
+    }
+
+    #[test]
+    fn creation_options_decode_their_binary_members() {
+        let json: PublicKeyCredentialCreationOptionsJson =
+            serde_json::from_str(CREATION_OPTIONS).unwrap();
+
+        let CredentialCreationOptions { public_key } = json.try_into().unwrap();
+
+        assert_eq!(public_key.challenge.len(), 64);
+        assert_eq!(
+            public_key.user.id.as_slice(),
+            b"c3df01ad-6136-4e2d-a65f-0a6e33c94b2f"
+        );
+        assert_eq!(public_key.exclude_credentials.unwrap()[0].id.len(), 32);
+        assert_eq!(
+            public_key.attestation,
+            Some(AttestationConveyancePreference::None)
+        );
+    }
+
+    #[test]
+    fn request_options_round_trip_through_the_binary_options() {
+        let original: serde_json::Value = serde_json::from_str(REQUEST_OPTIONS).unwrap();
+
+        let json: PublicKeyCredentialRequestOptionsJson =
+            serde_json::from_str(REQUEST_OPTIONS).unwrap();
+        let CredentialRequestOptions { public_key } = json.try_into().unwrap();
+        assert_eq!(public_key.rp_id.as_deref(), Some("webauthn.io"));
+        let round_trip =
+            serde_json::to_value(PublicKeyCredentialRequestOptionsJson::from(public_key)).unwrap();
+
+        assert_eq!(round_trip, original);
+    }
+
+    #[test]
+    fn binary_members_must_be_base64url_encoded() {
+        let json: PublicKeyCredentialRequestOptionsJson =
+            serde_json::from_str(&REQUEST_OPTIONS.replace("cmKbRMz7", "cmKb+Mz7")).unwrap();
+
+        assert!(PublicKeyCredentialRequestOptions::try_from(json).is_err());
+    }
+
+    #[test]
+    fn registration_response_json_matches_the_browser() {
+        let credential = CreatedPublicKeyCredential {
+            id: "AQID".into(),
+            raw_id: vec![1, 2, 3].into(),
+            ty: PublicKeyCredentialType::PublicKey,
+            response: AuthenticatorAttestationResponse {
+                client_data_json: b"{}".to_vec().into(),
+                authenticator_data: vec![4, 5, 6].into(),
+                public_key: Some(vec![7, 8, 9].into()),
+                public_key_algorithm: -7,
+                attestation_object: vec![0xfb, 0xff].into(),
+                transports: Some(vec![AuthenticatorTransport::Internal]),
+            },
+            authenticator_attachment: Some(AuthenticatorAttachment::Platform),
+            client_extension_results: AuthenticatorExtensionsClientOutputs {
+                cred_props: Some(CredentialPropertiesOutput {
+                    discoverable: Some(true),
+                    authenticator_display_name: None,
+                }),
+                ..Default::default()
+            },
+        };
+
+        let json = serde_json::to_value(credential.to_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "id": "AQID",
+                "rawId": "AQID",
+                "response": {
+                    "clientDataJSON": "e30",
+                    "authenticatorData": "BAUG",
+                    "transports": ["internal"],
+                    "publicKey": "BwgJ",
+                    "publicKeyAlgorithm": -7,
+                    "attestationObject": "-_8"
+                },
+                "authenticatorAttachment": "platform",
+                "clientExtensionResults": { "credProps": { "rk": true } },
+                "type": "public-key"
+            })
+        );
+    }
+
+    #[test]
+    fn authentication_response_json_matches_the_browser() {
+        let credential = AuthenticatedPublicKeyCredential {
+            id: "AQID".into(),
+            raw_id: vec![1, 2, 3].into(),
+            ty: PublicKeyCredentialType::PublicKey,
+            response: AuthenticatorAssertionResponse {
+                client_data_json: b"{}".to_vec().into(),
+                authenticator_data: vec![4, 5, 6].into(),
+                signature: vec![0xfb, 0xff].into(),
+                user_handle: Some(vec![7, 8, 9].into()),
+                attestation_object: None,
+                transports: Some(vec![AuthenticatorTransport::Internal]),
+            },
+            authenticator_attachment: None,
+            client_extension_results: AuthenticatorExtensionsClientOutputs {
+                prf: Some(AuthenticationExtensionsPrfOutputs {
+                    enabled: None,
+                    results: Some(AuthenticationExtensionsPrfValues {
+                        first: vec![1; 4].into(),
+                        second: None,
+                    }),
+                }),
+                ..Default::default()
+            },
+        };
+
+        let json = serde_json::to_value(credential.to_json()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "id": "AQID",
+                "rawId": "AQID",
+                "response": {
+                    "clientDataJSON": "e30",
+                    "authenticatorData": "BAUG",
+                    "signature": "-_8",
+                    "userHandle": "BwgJ"
+                },
+                "clientExtensionResults": { "prf": { "results": { "first": "AQEBAQ" } } },
+                "type": "public-key"
+            })
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Options of the Signal API, which lets a Relying Party tell the authenticator about the state of
+//! its credentials so that they stay consistent with the server.
+//!
+//! <https://w3c.github.io/webauthn/#sctn-signal-methods>
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "typeshare")]
+use typeshare::typeshare;
+
+use crate::Bytes;
+
+#[cfg(doc)]
+use crate::webauthn::PublicKeyCredentialUserEntity;
+
+/// Signals that a credential is not recognized by the Relying Party, for example after an
+/// assertion with a credential it deleted. The authenticator should remove it.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-unknowncredentialoptions>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct UnknownCredentialOptions {
+    /// The RP ID of the credential.
+    pub rp_id: String,
+
+    /// The ID of the credential the Relying Party does not recognize.
+    pub credential_id: Bytes,
+}
+
+/// Signals the complete list of the credentials of a user that the Relying Party accepts. The
+/// authenticator should remove the credentials of the user that are not listed.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-allacceptedcredentialsoptions>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct AllAcceptedCredentialsOptions {
+    /// The RP ID of the credentials.
+    pub rp_id: String,
+
+    /// The user handle of the user whose credentials are listed.
+    pub user_id: Bytes,
+
+    /// The IDs of every credential of the user that the Relying Party accepts.
+    pub all_accepted_credential_ids: Vec<Bytes>,
+}
+
+/// Signals the current name and display name of a user, which the authenticator should update in
+/// the credentials of the user.
+///
+/// <https://w3c.github.io/webauthn/#dictdef-currentuserdetailsoptions>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "typeshare", typeshare)]
+pub struct CurrentUserDetailsOptions {
+    /// The RP ID of the credentials of the user.
+    pub rp_id: String,
+
+    /// The user handle of the user.
+    pub user_id: Bytes,
+
+    /// The current name of the user, see [`PublicKeyCredentialUserEntity::name`].
+    pub name: String,
+
+    /// The current display name of the user, see
+    /// [`PublicKeyCredentialUserEntity::display_name`].
+    pub display_name: String,
+}
@@ -2,7 +2,7 @@
 use passkey::{
     authenticator::{Authenticator, UIHint, UserCheck, UserValidationMethod},
     client::{Client, WebauthnError},
-    types::{crypto::sha256, ctap2::*, rand::random_vec, webauthn::*, Bytes, Passkey},
+    types::{crypto::client_data_hash, ctap2::*, rand::random_vec, webauthn::*, Bytes, Passkey},
 };
 
 use coset::iana;
@@ -70,7 +70,7 @@ async fn client_setup(
             exclude_credentials: None,
             authenticator_selection: None,
             hints: None,
-            attestation: AttestationConveyancePreference::None,
+            attestation: Some(AttestationConveyancePreference::None),
             attestation_formats: None,
             extensions: None,
         },
@@ -131,6 +131,8 @@ async fn authenticator_setup(
         options: make_credential::Options::default(),
         pin_auth: None,
         pin_protocol: None,
+        enterprise_attestation: None,
+        attestation_formats_preference: None,
     };
 
     let credential: make_credential::Response =
@@ -200,7 +202,7 @@ async fn main() -> Result<(), WebauthnError> {
     println!("Webauthn credential auth'ed:\n\n{:?}\n\n", authed_cred);
 
     // Generate the client_data_hash from the created_cred response
-    let client_data_hash = sha256(&created_cred.response.client_data_json).to_vec();
+    let client_data_hash = client_data_hash(&created_cred.response.client_data_json).to_vec();
 
     // Authenticator Version
     let authenticator_result = authenticator_setup(
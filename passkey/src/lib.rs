@@ -136,7 +136,7 @@
 //!         exclude_credentials: None,
 //!         authenticator_selection: None,
 //!         hints: None,
-//!         attestation: AttestationConveyancePreference::None,
+//!         attestation: Some(AttestationConveyancePreference::None),
 //!         attestation_formats: None,
 //!         extensions: None,
 //!     },
@@ -240,6 +240,8 @@
 //!     options: make_credential::Options::default(),
 //!     pin_auth: None,
 //!     pin_protocol: None,
+//!     enterprise_attestation: None,
+//!     attestation_formats_preference: None,
 //! };
 //!
 //! let credential: make_credential::Response =